@@ -158,6 +158,10 @@ impl Calls for RpcClient {
         .await
     }
 
+    async fn player_detailed_info(&self, login: &str) -> Result<PlayerDetailedInfo> {
+        self.call_method("GetDetailedPlayerInfo", args!(login)).await
+    }
+
     async fn map(&self, file_name: &str) -> Result<MapInfo> {
         self.call_method("GetMapInfo", args!(file_name)).await
     }
@@ -271,6 +275,15 @@ impl Calls for RpcClient {
         .await
     }
 
+    async fn send_manialink_to_logins(&self, ml: &str, logins: Vec<&str>) -> Result<()> {
+        // 0 = do not auto-hide, false = do not hide on click
+        self.call_method_unit(
+            "SendDisplayManialinkPageToLogin",
+            args!(logins.join(","), escape_xml(ml), 0, false),
+        )
+        .await
+    }
+
     async fn force_spectator(&self, player_login: &str) -> Result<()> {
         // This value is documented as "spectator but keep selectable",
         // which probably means that you can switch back to a playing slot,
@@ -149,6 +149,14 @@ pub trait Calls {
     ///     GetPlayerList
     async fn players(&self) -> Vec<PlayerInfo>;
 
+    /// Get detailed information about the connected player with the given login.
+    ///
+    /// Faults if there is no such player.
+    ///
+    /// Calls method:
+    ///     GetDetailedPlayerInfo
+    async fn player_detailed_info(&self, login: &str) -> Result<PlayerDetailedInfo>;
+
     /// Fetch information about the map with the given file name.
     ///
     /// Faults if there is no map with that file name.
@@ -287,6 +295,16 @@ pub trait Calls {
     ///     SendDisplayManialinkPageToId
     async fn send_manialink_to(&self, ml: &str, player_uid: i32) -> Result<()>;
 
+    /// Send a Manialink to the specified player logins.
+    ///
+    /// Faults if none of the players are connected.
+    ///
+    /// To remove a single Manialink, send an empty one with the same ID (`<manialink id="...">`).
+    ///
+    /// Calls method:
+    ///     SendDisplayManialinkPageToLogin
+    async fn send_manialink_to_logins(&self, ml: &str, logins: Vec<&str>) -> Result<()>;
+
     /// Moves a player to spectator, and removes their player slot,
     /// effectively making place for another player.
     ///
@@ -529,6 +529,20 @@ impl PlayerInfo {
     }
 }
 
+/// Detailed information for a connected player.
+///
+/// Only includes the fields that are not part of `PlayerInfo`,
+/// and that are used by the controller.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlayerDetailedInfo {
+    /// Player-unique login.
+    pub login: String,
+
+    /// The language of the player's game client, f.e. `"en"` or `"de"`.
+    pub language: String,
+}
+
 /// Signals what slot a player occupies, and whether they are spectating or not.
 #[derive(Debug, PartialEq, Eq)]
 pub enum PlayerSlot {
@@ -18,6 +18,7 @@ pub struct MapFileHeader {
     pub millis_gold: i32,
     pub millis_author: i32,
     pub nb_laps: Option<i32>,
+    pub nb_cps: i32,
     pub author_login: String,
     pub author_display_name: DisplayString,
 }
@@ -209,7 +210,7 @@ pub fn parse_map_file<P: AsRef<Path>>(path: P) -> anyhow::Result<MapFileHeader>
 
     let _ = read_bytes!(4); // skip bool 0
 
-    let _nb_cps = read_i32!();
+    let nb_cps = read_i32!();
     let nb_laps = Some(read_i32!()).filter(|_| is_multi_lap);
 
     // === "String" chunk ===
@@ -266,6 +267,7 @@ pub fn parse_map_file<P: AsRef<Path>>(path: P) -> anyhow::Result<MapFileHeader>
         millis_gold,
         millis_author,
         nb_laps,
+        nb_cps,
         author_login,
         author_display_name,
     })
@@ -1,9 +1,12 @@
+use std::convert::TryFrom;
 use std::default::Default;
-use std::str::FromStr;
 
 use lazy_static::lazy_static;
 
-use crate::chat::{BadCommandContext, CommandContext, CommandEnum, CommandReference};
+use crate::chat::{
+    ArgKind, BadCommandContext, CommandArgs, CommandContext, CommandEnum, CommandSpec,
+};
+use crate::database::{AnnouncementTrigger, TimeWindow};
 use crate::server::ModeScript;
 
 /// Chat commands that can only be executed by admins.
@@ -16,15 +19,22 @@ pub enum AdminCommand<'a> {
 
     /// List the server's maps and their UIDs.
     /// For each map, it should say whether it is in the playlist
-    /// or not.
+    /// or not. The list can be filtered and sorted with a `ListQuery`.
     ///
-    /// Usage: `/maps`
-    ListMaps,
+    /// Usage: `/maps [query]`
+    ListMaps { query: Option<&'a str> },
 
     /// List the connected players with login and display names.
+    /// The list can be filtered and sorted with a `ListQuery`.
     ///
-    /// Usage: `/players`
-    ListPlayers,
+    /// Usage: `/players [query]`
+    ListPlayers { query: Option<&'a str> },
+
+    /// Find players by their login, or by their current or past display names,
+    /// and list their aliases, when they were seen, and how many records they have.
+    ///
+    /// Usage: `/whois <name>`
+    WhoIs { name: &'a str },
 
     /// Add the map with the given UID to the playlist.
     ///
@@ -41,6 +51,27 @@ pub enum AdminCommand<'a> {
     /// Usage: `/playlist remove <uid>`
     PlaylistRemove { uid: &'a str },
 
+    /// List every playlist map's remove votes, and whether it should be
+    /// removed from the playlist, without removing anything.
+    ///
+    /// Usage: `/playlist curate`
+    PlaylistCurate,
+
+    /// List the map pools, and the number of maps in each of them.
+    ///
+    /// Usage: `/pools`
+    ListPools,
+
+    /// Store the current playlist as a map pool with the given name.
+    ///
+    /// Usage: `/pool create <name>`
+    CreatePool { name: &'a str },
+
+    /// Replace the playlist with the maps of the given pool.
+    ///
+    /// Usage: `/pool switch <name>`
+    SwitchPool { name: &'a str },
+
     /// Import a map from `trackmania.exchange`.
     /// The ID is either its ID on the website (a number),
     /// or its UID (from inside the map file).
@@ -111,6 +142,22 @@ pub enum AdminCommand<'a> {
     /// Usage: `/bounce <login/nick>`
     MovePlayerToSpectator { login_or_display_name: &'a str },
 
+    /// Prevent a player from chatting for some time.
+    ///
+    /// The duration is a number, followed by `s`, `m`, `h` or `d`
+    /// for seconds, minutes, hours or days.
+    ///
+    /// Usage: `/mute <login/nick> <duration>`
+    MutePlayer {
+        login_or_display_name: &'a str,
+        secs: u64,
+    },
+
+    /// Allow a muted player to chat again.
+    ///
+    /// Usage: `/unmute <login/nick>`
+    UnmutePlayer { login_or_display_name: &'a str },
+
     /// Change the game mode for the next map.
     ///
     /// The argument must be the file name of the mode script relative to `/UserData/Scripts/Modes`.
@@ -136,6 +183,61 @@ pub enum AdminCommand<'a> {
     ///
     /// Usage: `/settings save <name>`
     SaveSettings { file_name: &'a str },
+
+    /// List flagged records that await review, recent top records,
+    /// and recently removed records.
+    ///
+    /// Usage: `/records review`
+    ReviewRecords,
+
+    /// Store a flagged record, if it improves the player's personal best.
+    ///
+    /// Usage: `/record approve <id>`
+    ApproveRecord { id: i32 },
+
+    /// Discard a flagged record.
+    ///
+    /// Usage: `/record reject <id>`
+    RejectRecord { id: i32 },
+
+    /// Remove a player's record on a map, and restore their previous
+    /// personal best on that map, if any. On multi-lap maps, this is
+    /// either their full-race record, or their flying lap record.
+    ///
+    /// Usage: `/record remove <uid> <login>`, `/record remove lap <uid> <login>`
+    RemoveRecord {
+        map_uid: &'a str,
+        login: &'a str,
+        flying_lap: bool,
+    },
+
+    /// List the scheduled announcements, with their IDs and triggers.
+    ///
+    /// Usage: `/announcements`
+    ListAnnouncements,
+
+    /// Add an announcement that is displayed whenever its trigger occurs.
+    ///
+    /// Usage:
+    /// - `/announce join <message>`
+    /// - `/announce map <message>`
+    /// - `/announce maps <count> <message>`
+    /// - `/announce every <duration> <message>`
+    AddAnnouncement {
+        trigger: AnnouncementTrigger,
+        message: &'a str,
+    },
+
+    /// Only display an announcement between two times of day (in UTC),
+    /// or at any time if both are omitted.
+    ///
+    /// Usage: `/announce window <id> [from] [until]`
+    SetAnnouncementWindow { id: i32, window: Option<TimeWindow> },
+
+    /// Remove an announcement.
+    ///
+    /// Usage: `/announce remove <id>`
+    RemoveAnnouncement { id: i32 },
 }
 
 lazy_static! {
@@ -143,8 +245,15 @@ lazy_static! {
         use AdminCommand::*;
         vec![
             EditConfig,
-            ListMaps,
-            ListPlayers,
+            ListMaps {
+                query: Default::default(),
+            },
+            ListPlayers {
+                query: Default::default(),
+            },
+            WhoIs {
+                name: Default::default(),
+            },
             PlaylistAdd {
                 uid: Default::default(),
             },
@@ -152,6 +261,14 @@ lazy_static! {
             PlaylistRemove {
                 uid: Default::default(),
             },
+            PlaylistCurate,
+            ListPools,
+            CreatePool {
+                name: Default::default(),
+            },
+            SwitchPool {
+                name: Default::default(),
+            },
             ImportMap {
                 id: Default::default(),
             },
@@ -178,6 +295,13 @@ lazy_static! {
             MovePlayerToSpectator {
                 login_or_display_name: Default::default(),
             },
+            MutePlayer {
+                login_or_display_name: Default::default(),
+                secs: Default::default(),
+            },
+            UnmutePlayer {
+                login_or_display_name: Default::default(),
+            },
             ChangeMode {
                 script_name: Default::default(),
             },
@@ -187,121 +311,374 @@ lazy_static! {
             SaveSettings {
                 file_name: Default::default(),
             },
+            ReviewRecords,
+            ApproveRecord {
+                id: Default::default(),
+            },
+            RejectRecord {
+                id: Default::default(),
+            },
+            RemoveRecord {
+                map_uid: Default::default(),
+                login: Default::default(),
+                flying_lap: false,
+            },
+            RemoveRecord {
+                map_uid: Default::default(),
+                login: Default::default(),
+                flying_lap: true,
+            },
+            ListAnnouncements,
+            AddAnnouncement {
+                trigger: AnnouncementTrigger::Join,
+                message: Default::default(),
+            },
+            AddAnnouncement {
+                trigger: AnnouncementTrigger::MapStart,
+                message: Default::default(),
+            },
+            AddAnnouncement {
+                trigger: AnnouncementTrigger::EveryNbMaps {
+                    nb_maps: Default::default(),
+                },
+                message: Default::default(),
+            },
+            AddAnnouncement {
+                trigger: AnnouncementTrigger::Interval {
+                    secs: Default::default(),
+                },
+                message: Default::default(),
+            },
+            SetAnnouncementWindow {
+                id: Default::default(),
+                window: Default::default(),
+            },
+            RemoveAnnouncement {
+                id: Default::default(),
+            },
         ]
     };
 }
 
 impl<'a> CommandEnum<'a> for AdminCommand<'a> {
-    fn all() -> &'static Vec<Self> {
+    fn all() -> &'a Vec<Self> {
         &ADMIN_COMMANDS
     }
 
-    fn parse(chat_message: &'a str) -> Option<Self> {
-        use AdminCommand::*;
-
-        let parts: Vec<&str> = chat_message.split_whitespace().collect();
-
-        match &parts[..] {
-            ["/blacklist", "add", login] => Some(BlacklistAdd { login: *login }),
-            ["/blacklist", "remove", login] => Some(BlacklistRemove { login: *login }),
-            ["/blacklist", "clear"] => Some(BlacklistClear),
-            ["/bounce", name] => Some(MovePlayerToSpectator {
-                login_or_display_name: *name,
-            }),
-            ["/config"] => Some(EditConfig),
-            ["/import", "map", id] => Some(ImportMap { id: *id }),
-            ["/kick", name] => Some(KickPlayer {
-                login_or_display_name: *name,
-            }),
-            ["/maps"] => Some(ListMaps),
-            ["/mode", name] => Some(ChangeMode { script_name: *name }),
-            ["/pause"] => Some(TogglePause),
-            ["/players"] => Some(ListPlayers),
-            ["/playlist", "add", "all"] => Some(PlaylistAddAll),
-            ["/playlist", "add", uid] => Some(PlaylistAdd { uid: *uid }),
-            ["/playlist", "remove", uid] => Some(PlaylistRemove { uid: *uid }),
-            ["/queue", uid] => Some(ForceQueue { uid: *uid }),
-            ["/restart"] => Some(RestartCurrentMap),
-            ["/settings", "load", name] => Some(LoadSettings { file_name: *name }),
-            ["/settings", "save", name] => Some(SaveSettings { file_name: *name }),
-            ["/skip"] => Some(SkipCurrentMap),
-            ["/warmup", "add", secs] => match u64::from_str(secs) {
-                Ok(secs) => Some(ExtendWarmup { secs }),
-                Err(_) => None,
-            },
-            ["/warmup", "skip"] => Some(SkipWarmup),
-            _ => None,
-        }
-    }
-
-    fn check(&self, ctxt: CommandContext) -> Result<(), BadCommandContext> {
+    fn spec(&self) -> CommandSpec {
         use crate::config::PlayerRole;
         use AdminCommand::*;
-        use BadCommandContext::*;
+        use ArgKind::*;
         use ModeScript::*;
 
-        match self {
-            _ if ctxt.player_role < PlayerRole::Admin => Err(NoPermission),
-
-            ExtendWarmup { .. } | SkipWarmup if !ctxt.warmup.available => Err(InOtherModes),
-            ExtendWarmup { .. } | SkipWarmup if !ctxt.warmup.active => Err(DuringWarmup),
-
-            TogglePause if !ctxt.pause.available => Err(InOtherModes),
-
-            ForceQueue { .. } | SkipCurrentMap | RestartCurrentMap | PlaylistAddAll
-                if *ctxt.mode != TimeAttack =>
-            {
-                Err(InMode(TimeAttack))
+        let spec = match self {
+            EditConfig => CommandSpec::new("/config", "Open the config editor").details(
+                "Edit every config section, except for the connection settings \
+                 and the admin whitelists. Changes are listed for review, \
+                 and only applied once confirmed.",
+            ),
+            ListMaps { .. } => {
+                CommandSpec::new("/maps", "List maps in- and outside of the playlist")
+                    .optional_arg("query", Text)
+                    .details(
+                        "Only lists maps whose name or author contains every word \
+                         of the query, f.e. '/maps tech'. Use 'author:<name>' to only \
+                         match the author, and 'sort:<name|author|uid|added>' \
+                         to change the order.",
+                    )
             }
-
-            _ => Ok(()),
-        }
-    }
-
-    fn reference(&self) -> CommandReference {
-        use AdminCommand::*;
-        match self {
-            EditConfig => ("/config", "Open the config editor").into(),
-            ListMaps => ("/maps", "List maps in- and outside of the playlist").into(),
-            ListPlayers => ("/players", "List connected players' logins and names").into(),
-            PlaylistAdd { .. } => ("/playlist add <uid>", "Add a map to the playlist").into(),
-            PlaylistAddAll => ("/playlist add all", "Add all maps to the playlist").into(),
+            ListPlayers { .. } => {
+                CommandSpec::new("/players", "List connected players' logins and names")
+                    .optional_arg("query", Text)
+                    .details(
+                        "Only lists players whose login or name contains every word \
+                         of the query. Use 'sort:<name|login>' to change the order.",
+                    )
+            }
+            WhoIs { .. } => CommandSpec::new("/whois", "Look up players by current or past names")
+                .arg("name", Text)
+                .details(
+                    "Finds players by their login, or by their current or past names, \
+                     and lists their aliases, when they were seen, \
+                     and how many records they have.",
+                ),
+            PlaylistAdd { .. } => CommandSpec::new("/playlist add", "Add a map to the playlist")
+                .alias("/pl add")
+                .arg("uid", Word),
+            PlaylistAddAll => CommandSpec::new("/playlist add all", "Add all maps to the playlist")
+                .alias("/pl add all")
+                .mode(TimeAttack),
             PlaylistRemove { .. } => {
-                ("/playlist remove <uid>", "Remove a map from the playlist").into()
+                CommandSpec::new("/playlist remove", "Remove a map from the playlist")
+                    .alias("/pl remove")
+                    .arg("uid", Word)
             }
-            ImportMap { .. } => (
-                "/import map <id/uid>",
-                "Import the TMX map with the given id",
-            )
-                .into(),
-            SkipCurrentMap => ("/skip", "Start the next map immediately").into(),
-            RestartCurrentMap => ("/restart", "Restart the current map after this race").into(),
-            ForceQueue { .. } => ("/queue <uid>", "Select the next map").into(),
+            PlaylistCurate => {
+                CommandSpec::new("/playlist curate", "List maps that players want removed")
+                    .alias("/pl curate")
+            }
+            ListPools => CommandSpec::new("/pools", "List map pools"),
+            CreatePool { .. } => {
+                CommandSpec::new("/pool create", "Store the playlist as a map pool")
+                    .arg("name", Word)
+            }
+            SwitchPool { .. } => {
+                CommandSpec::new("/pool switch", "Replace the playlist with a map pool")
+                    .arg("name", Word)
+            }
+            ImportMap { .. } => {
+                CommandSpec::new("/import map", "Import the TMX map with the given id")
+                    .arg("id/uid", Word)
+                    .details(
+                        "The ID is either the map's ID on trackmania.exchange, \
+                         or its UID. The map is added to the playlist, \
+                         and queued as the next map.",
+                    )
+            }
+            SkipCurrentMap => {
+                CommandSpec::new("/skip", "Start the next map immediately").mode(TimeAttack)
+            }
+            RestartCurrentMap => {
+                CommandSpec::new("/restart", "Restart the current map after this race")
+                    .alias("/res")
+                    .mode(TimeAttack)
+            }
+            ForceQueue { .. } => CommandSpec::new("/queue", "Select the next map")
+                .arg("uid", Word)
+                .mode(TimeAttack)
+                .details("Running this command several times queues all maps in order."),
             BlacklistAdd { .. } => {
-                ("/blacklist add <login>", "Add a player to the blacklist").into()
+                CommandSpec::new("/blacklist add", "Add a player to the blacklist")
+                    .arg("login", Word)
             }
-            BlacklistRemove { .. } => (
-                "/blacklist remove <login>",
-                "Remove a player from the blacklist",
-            )
-                .into(),
-            BlacklistClear => ("/blacklist clear", "Remove all players from the blacklist").into(),
-            TogglePause => ("/pause", "Pause or unpause the current match").into(),
+            BlacklistRemove { .. } => {
+                CommandSpec::new("/blacklist remove", "Remove a player from the blacklist")
+                    .arg("login", Word)
+            }
+            BlacklistClear => {
+                CommandSpec::new("/blacklist clear", "Remove all players from the blacklist")
+            }
+            TogglePause => CommandSpec::new("/pause", "Pause or unpause the current match"),
             ExtendWarmup { .. } => {
-                ("/warmup add <seconds>", "Extend the current warmup round").into()
+                CommandSpec::new("/warmup add", "Extend the current warmup round")
+                    .arg("seconds", Number)
+            }
+            SkipWarmup => CommandSpec::new("/warmup skip", "End the current warmup section"),
+            KickPlayer { .. } => {
+                CommandSpec::new("/kick", "Kick a player from the server").arg("login/nick", Player)
             }
-            SkipWarmup => ("/warmup skip", "End the current warmup section").into(),
-            KickPlayer { .. } => ("/kick <login/nick>", "Kick a player from the server").into(),
             MovePlayerToSpectator { .. } => {
-                ("/bounce <login/nick>", "Force a player to spectate").into()
+                CommandSpec::new("/bounce", "Force a player to spectate")
+                    .alias("/spec")
+                    .arg("login/nick", Player)
             }
-            ChangeMode { .. } => ("/mode <name>", "Change the game mode for the next map").into(),
-            LoadSettings { .. } => ("/settings load <name>", "Load a match settings file").into(),
-            SaveSettings { .. } => (
-                "/settings save <name>",
+            MutePlayer { .. } => CommandSpec::new(
+                "/mute",
+                "Prevent a player from chatting, f.e. for 10m or 2h",
+            )
+            .arg("login/nick", Player)
+            .arg("duration", Duration),
+            UnmutePlayer { .. } => CommandSpec::new("/unmute", "Allow a muted player to chat")
+                .arg("login/nick", Player),
+            ChangeMode { .. } => CommandSpec::new("/mode", "Change the game mode for the next map")
+                .arg("name", Word)
+                .details("The name is that of a default mode, f.e. 'TimeAttack' or 'Rounds'."),
+            LoadSettings { .. } => CommandSpec::new("/settings load", "Load a match settings file")
+                .arg("name", Word)
+                .details(
+                    "The name is a file name relative to /UserData/Maps/MatchSettings. \
+                     The .txt suffix can be omitted. A mode change only takes place \
+                     on the next map.",
+                ),
+            SaveSettings { .. } => CommandSpec::new(
+                "/settings save",
                 "Save the current match settings to a file",
             )
-                .into(),
+            .arg("name", Word)
+            .details(
+                "The name is a file name relative to /UserData/Maps/MatchSettings. \
+                 The .txt suffix can be omitted.",
+            ),
+            ReviewRecords => {
+                CommandSpec::new("/records review", "Review flagged and recent records")
+            }
+            ApproveRecord { .. } => {
+                CommandSpec::new("/record approve", "Store a flagged record").arg("id", Number)
+            }
+            RejectRecord { .. } => {
+                CommandSpec::new("/record reject", "Discard a flagged record").arg("id", Number)
+            }
+            RemoveRecord {
+                flying_lap: false, ..
+            } => CommandSpec::new("/record remove", "Remove a player's record on a map")
+                .arg("uid", Word)
+                .arg("login", Word)
+                .details("The player's previous personal best on that map is restored, if any."),
+            RemoveRecord {
+                flying_lap: true, ..
+            } => CommandSpec::new(
+                "/record remove lap",
+                "Remove a player's flying lap record on a map",
+            )
+            .arg("uid", Word)
+            .arg("login", Word)
+            .details("The player's previous flying lap record on that map is restored, if any."),
+            ListAnnouncements => CommandSpec::new("/announcements", "List scheduled announcements"),
+            AddAnnouncement { trigger, .. } => {
+                let spec = match trigger {
+                    AnnouncementTrigger::Join => {
+                        CommandSpec::new("/announce join", "Tell players when they join")
+                    }
+                    AnnouncementTrigger::MapStart => {
+                        CommandSpec::new("/announce map", "Announce at the start of every map")
+                    }
+                    AnnouncementTrigger::EveryNbMaps { .. } => CommandSpec::new(
+                        "/announce maps",
+                        "Announce at the start of every n-th map",
+                    )
+                    .arg("count", Number),
+                    AnnouncementTrigger::Interval { .. } => {
+                        CommandSpec::new("/announce every", "Announce periodically, f.e. every 10m")
+                            .arg("duration", Duration)
+                    }
+                };
+                spec.arg("message", Text).details(
+                    "Announcements with the same trigger take turns, \
+                     so that several tips are displayed one after another.",
+                )
+            }
+            SetAnnouncementWindow { .. } => CommandSpec::new(
+                "/announce window",
+                "Only display an announcement at certain times of day",
+            )
+            .arg("id", Number)
+            .optional_arg("from", Time)
+            .optional_arg("until", Time)
+            .details(
+                "Times are in UTC. A window that ends before it starts extends past midnight. \
+                 Omit both times to display the announcement at any time again.",
+            ),
+            RemoveAnnouncement { .. } => {
+                CommandSpec::new("/announce remove", "Remove an announcement").arg("id", Number)
+            }
+        };
+        spec.min_role(PlayerRole::Admin)
+    }
+
+    fn with_args(&self, args: &CommandArgs<'a>) -> Option<Self> {
+        use AdminCommand::*;
+
+        let cmd = match self {
+            ListMaps { .. } => ListMaps {
+                query: args.opt_text("query"),
+            },
+            ListPlayers { .. } => ListPlayers {
+                query: args.opt_text("query"),
+            },
+            WhoIs { .. } => WhoIs {
+                name: args.text("name"),
+            },
+            PlaylistAdd { .. } => PlaylistAdd {
+                uid: args.text("uid"),
+            },
+            PlaylistRemove { .. } => PlaylistRemove {
+                uid: args.text("uid"),
+            },
+            CreatePool { .. } => CreatePool {
+                name: args.text("name"),
+            },
+            SwitchPool { .. } => SwitchPool {
+                name: args.text("name"),
+            },
+            ImportMap { .. } => ImportMap {
+                id: args.text("id/uid"),
+            },
+            ForceQueue { .. } => ForceQueue {
+                uid: args.text("uid"),
+            },
+            BlacklistAdd { .. } => BlacklistAdd {
+                login: args.text("login"),
+            },
+            BlacklistRemove { .. } => BlacklistRemove {
+                login: args.text("login"),
+            },
+            ExtendWarmup { .. } => ExtendWarmup {
+                secs: u64::try_from(args.number("seconds")).ok()?,
+            },
+            KickPlayer { .. } => KickPlayer {
+                login_or_display_name: args.text("login/nick"),
+            },
+            MovePlayerToSpectator { .. } => MovePlayerToSpectator {
+                login_or_display_name: args.text("login/nick"),
+            },
+            MutePlayer { .. } => MutePlayer {
+                login_or_display_name: args.text("login/nick"),
+                secs: args.duration_secs("duration"),
+            },
+            UnmutePlayer { .. } => UnmutePlayer {
+                login_or_display_name: args.text("login/nick"),
+            },
+            ChangeMode { .. } => ChangeMode {
+                script_name: args.text("name"),
+            },
+            LoadSettings { .. } => LoadSettings {
+                file_name: args.text("name"),
+            },
+            SaveSettings { .. } => SaveSettings {
+                file_name: args.text("name"),
+            },
+            ApproveRecord { .. } => ApproveRecord {
+                id: i32::try_from(args.number("id")).ok()?,
+            },
+            RejectRecord { .. } => RejectRecord {
+                id: i32::try_from(args.number("id")).ok()?,
+            },
+            RemoveRecord { flying_lap, .. } => RemoveRecord {
+                map_uid: args.text("uid"),
+                login: args.text("login"),
+                flying_lap: *flying_lap,
+            },
+            AddAnnouncement { trigger, .. } => AddAnnouncement {
+                trigger: match trigger {
+                    AnnouncementTrigger::EveryNbMaps { .. } => AnnouncementTrigger::EveryNbMaps {
+                        nb_maps: i32::try_from(args.number("count"))
+                            .ok()
+                            .filter(|n| *n > 0)?,
+                    },
+                    AnnouncementTrigger::Interval { .. } => AnnouncementTrigger::Interval {
+                        secs: i32::try_from(args.duration_secs("duration")).ok()?,
+                    },
+                    trigger => *trigger,
+                },
+                message: args.text("message"),
+            },
+            SetAnnouncementWindow { .. } => SetAnnouncementWindow {
+                id: i32::try_from(args.number("id")).ok()?,
+                window: match (args.opt_time("from"), args.opt_time("until")) {
+                    (Some(start), Some(end)) => Some(TimeWindow { start, end }),
+                    (None, None) => None,
+                    _ => return None,
+                },
+            },
+            RemoveAnnouncement { .. } => RemoveAnnouncement {
+                id: i32::try_from(args.number("id")).ok()?,
+            },
+            cmd => *cmd,
+        };
+        Some(cmd)
+    }
+
+    fn check(&self, ctxt: CommandContext) -> Result<(), BadCommandContext> {
+        use AdminCommand::*;
+        use BadCommandContext::*;
+
+        match self {
+            ExtendWarmup { .. } | SkipWarmup if !ctxt.warmup.available => Err(InOtherModes),
+            ExtendWarmup { .. } | SkipWarmup if !ctxt.warmup.active => Err(DuringWarmup),
+
+            TogglePause if !ctxt.pause.available => Err(InOtherModes),
+
+            _ => Ok(()),
         }
     }
 }
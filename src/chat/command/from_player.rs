@@ -1,47 +1,155 @@
 use lazy_static::lazy_static;
 
-use crate::chat::{BadCommandContext, CommandContext, CommandEnum, CommandReference};
+use crate::chat::{ArgKind, CommandArgs, CommandEnum, CommandSpec};
+use crate::server::ModeScript;
 
 /// Chat commands for all players.
 #[derive(Debug, Copy, Clone)]
-pub enum PlayerCommand {
+pub enum PlayerCommand<'a> {
     /// Print information about server & controller.
     ///
     /// Usage: `/info`
     Info,
+
+    /// Show your personal best and map rank on the current map,
+    /// or on the map with the given UID.
+    ///
+    /// Usage: `/pb [uid]`
+    PersonalBest { map_uid: Option<&'a str> },
+
+    /// List the top records on the current map,
+    /// or on the map with the given UID.
+    ///
+    /// Usage: `/top [uid]`
+    TopRecords { map_uid: Option<&'a str> },
+
+    /// Show your server rank, with your number of wins and losses.
+    ///
+    /// Usage: `/rank`
+    ServerRank,
+
+    /// List the playlist maps that you have not finished yet.
+    ///
+    /// Usage: `/recs`
+    MissingRecords,
+
+    /// Compare your sector times on the current map to those of the
+    /// top record, and to the best sector times of all players.
+    ///
+    /// Usage: `/sectors`
+    Sectors,
+
+    /// Show your language and the available languages, or select the
+    /// language of chat messages and widgets. Use `auto` to select the
+    /// language of your game client.
+    ///
+    /// Usage: `/language [code]`
+    Language { code: Option<&'a str> },
+
+    /// Start a vote to skip the current map.
+    ///
+    /// Usage: `/vote skip`
+    VoteSkip,
+
+    /// Start a vote to extend the time limit of the current map.
+    ///
+    /// Usage: `/vote extend`
+    VoteExtend,
 }
 
 lazy_static! {
-    static ref PLAYER_COMMANDS: Vec<PlayerCommand> = {
+    static ref PLAYER_COMMANDS: Vec<PlayerCommand<'static>> = {
         use PlayerCommand::*;
-        vec![Info]
+        vec![
+            Info,
+            PersonalBest {
+                map_uid: Default::default(),
+            },
+            TopRecords {
+                map_uid: Default::default(),
+            },
+            ServerRank,
+            MissingRecords,
+            Sectors,
+            Language {
+                code: Default::default(),
+            },
+            VoteSkip,
+            VoteExtend,
+        ]
     };
 }
 
-impl CommandEnum<'_> for PlayerCommand {
-    fn all() -> &'static Vec<Self> {
+impl<'a> CommandEnum<'a> for PlayerCommand<'a> {
+    fn all() -> &'a Vec<Self> {
         &PLAYER_COMMANDS
     }
 
-    fn parse(chat_message: &str) -> Option<Self> {
+    fn spec(&self) -> CommandSpec {
+        use ArgKind::*;
+        use ModeScript::*;
         use PlayerCommand::*;
 
-        let parts: Vec<&str> = chat_message.split_whitespace().collect();
-
-        match &parts[..] {
-            ["/info"] => Some(Info),
-            _ => None,
+        match self {
+            Info => CommandSpec::new("/info", "Display server & controller information"),
+            PersonalBest { .. } => CommandSpec::new("/pb", "Show your record on the current map")
+                .optional_arg("uid", Word)
+                .details(
+                    "Shows your personal best and map rank on the current map, \
+                     or on the map with the given UID, and how far you are behind \
+                     the top record.",
+                ),
+            TopRecords { .. } => {
+                CommandSpec::new("/top", "List the top records on the current map")
+                    .optional_arg("uid", Word)
+            }
+            ServerRank => CommandSpec::new("/rank", "Show your server rank"),
+            MissingRecords => CommandSpec::new("/recs", "List the maps you have not finished yet"),
+            Sectors => CommandSpec::new("/sectors", "Find the sectors you can improve in")
+                .alias("/sec")
+                .details(
+                    "Compares your sector times on the current map to those of \
+                     the top record, and to the best sector times of all players.",
+                ),
+            Language { .. } => CommandSpec::new("/language", "Select your language")
+                .alias("/lang")
+                .optional_arg("code", Word)
+                .details(
+                    "Lists the available languages if no code is given. \
+                     Use 'auto' to select the language of your game client.",
+                ),
+            VoteSkip => CommandSpec::new("/vote skip", "Start a vote to skip the current map")
+                .mode(TimeAttack),
+            VoteExtend => CommandSpec::new("/vote extend", "Start a vote to extend the time limit")
+                .mode(TimeAttack),
         }
     }
 
-    fn check(&self, _ctxt: CommandContext) -> Result<(), BadCommandContext> {
-        Ok(())
-    }
-
-    fn reference(&self) -> CommandReference {
+    fn with_args(&self, args: &CommandArgs<'a>) -> Option<Self> {
         use PlayerCommand::*;
-        match self {
-            Info => ("/info", "Display server & controller information").into(),
-        }
+
+        let cmd = match self {
+            PersonalBest { .. } => PersonalBest {
+                map_uid: args.opt_text("uid"),
+            },
+            TopRecords { .. } => TopRecords {
+                map_uid: args.opt_text("uid"),
+            },
+            Language { .. } => Language {
+                code: args.opt_text("code"),
+            },
+            cmd => *cmd,
+        };
+        Some(cmd)
     }
 }
+
+/// The kinds of votes that players can start during a race.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RaceVoteKind {
+    /// End the current map immediately.
+    Skip,
+
+    /// Extend the time limit of the current map.
+    Extend,
+}
@@ -3,7 +3,7 @@ use std::default::Default;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
-use crate::chat::{BadCommandContext, CommandContext, CommandEnum, CommandReference};
+use crate::chat::{ArgKind, CommandArgs, CommandEnum, CommandSpec};
 
 /// Chat commands that can only be executed by super admins.
 #[derive(Debug, Clone, Copy)]
@@ -13,6 +13,38 @@ pub enum SuperAdminCommand<'a> {
     ///
     /// Usage: see `DangerousCommand`
     Prepare(DangerousCommand<'a>),
+
+    /// List all roles, the commands they grant, and the players
+    /// they are assigned to.
+    ///
+    /// Usage: `/roles`
+    ListRoles,
+
+    /// Assign a role to a player, or allow a role to execute a command.
+    ///
+    /// Usage: `/role grant <role> <login>`, or `/role grant <role> </command>`
+    GrantRole {
+        role_name: &'a str,
+        grantee: RoleGrantee<'a>,
+    },
+
+    /// Take a role from a player, or disallow a role to execute a command.
+    ///
+    /// Usage: `/role revoke <role> <login>`, or `/role revoke <role> </command>`
+    RevokeRole {
+        role_name: &'a str,
+        grantee: RoleGrantee<'a>,
+    },
+}
+
+/// The target of `/role grant` and `/role revoke`.
+#[derive(Debug, Clone, Copy)]
+pub enum RoleGrantee<'a> {
+    /// The login of a player.
+    Player(&'a str),
+
+    /// The name of a command, f.e. `/playlist add`.
+    Command(&'a str),
 }
 
 /// Destructive chat commands that can only be executed by super admins,
@@ -47,53 +79,102 @@ lazy_static! {
                 login: Default::default(),
             }),
             Prepare(Shutdown),
+            ListRoles,
+            GrantRole {
+                role_name: Default::default(),
+                grantee: RoleGrantee::Player(Default::default()),
+            },
+            RevokeRole {
+                role_name: Default::default(),
+                grantee: RoleGrantee::Player(Default::default()),
+            },
         ]
     };
 }
 
 impl<'a> CommandEnum<'a> for SuperAdminCommand<'a> {
-    fn all() -> &'static Vec<Self> {
+    fn all() -> &'a Vec<Self> {
         &SUPER_ADMIN_COMMANDS
     }
 
-    fn parse(chat_message: &'a str) -> Option<Self> {
+    fn spec(&self) -> CommandSpec {
+        use crate::config::PlayerRole;
+        use ArgKind::*;
         use DangerousCommand::*;
         use SuperAdminCommand::*;
 
-        let parts: Vec<&str> = chat_message.split_whitespace().collect();
-
-        match &parts[..] {
-            ["/delete", "map", uid] => Some(Prepare(DeleteMap { uid })),
-            ["/delete", "player", login] => Some(Prepare(DeletePlayer { login })),
-            ["/shutdown"] => Some(Prepare(Shutdown)),
-            _ => None,
-        }
-    }
-
-    fn check(&self, ctxt: CommandContext) -> Result<(), BadCommandContext> {
-        use crate::config::PlayerRole;
-        use BadCommandContext::*;
-
-        match self {
-            _ if ctxt.player_role < PlayerRole::SuperAdmin => Err(NoPermission),
-            _ => Ok(()),
-        }
+        let spec = match self {
+            Prepare(DeleteMap { .. }) => {
+                CommandSpec::new("/delete map", "Delete a map from the database")
+                    .arg("uid", Word)
+                    .details("Only maps that are not in the playlist can be deleted.")
+            }
+            Prepare(DeletePlayer { .. }) => {
+                CommandSpec::new("/delete player", "Delete a player from the database")
+                    .arg("login", Word)
+                    .details("Only blacklisted players can be deleted.")
+            }
+            Prepare(Shutdown) => CommandSpec::new("/shutdown", "Shutdown the server"),
+            ListRoles => CommandSpec::new("/roles", "List roles, their commands and players"),
+            GrantRole { .. } => CommandSpec::new(
+                "/role grant",
+                "Give a role to a player, or a command to a role",
+            )
+            .arg("role", Word)
+            .arg("login/cmd", Text)
+            .details(
+                "Either give the role to the player with the given login, \
+                 or allow players with the role to execute the given admin command, \
+                 f.e. '/role grant moderator /kick'.",
+            ),
+            RevokeRole { .. } => CommandSpec::new(
+                "/role revoke",
+                "Take a role from a player, or a command from a role",
+            )
+            .arg("role", Word)
+            .arg("login/cmd", Text)
+            .details(
+                "Either take the role from the player with the given login, \
+                 or disallow players with the role to execute the given admin command.",
+            ),
+        };
+        spec.min_role(PlayerRole::SuperAdmin)
     }
 
-    fn reference(&self) -> CommandReference {
+    fn with_args(&self, args: &CommandArgs<'a>) -> Option<Self> {
         use DangerousCommand::*;
         use SuperAdminCommand::*;
 
-        match self {
-            Prepare(DeleteMap { .. }) => {
-                ("/delete map <uid>", "Delete a map from the database").into()
-            }
-            Prepare(DeletePlayer { .. }) => (
-                "/delete player <login>",
-                "Delete a player from the database",
-            )
-                .into(),
-            Prepare(Shutdown) => ("/shutdown", "Shutdown the server").into(),
-        }
+        let cmd = match self {
+            Prepare(DeleteMap { .. }) => Prepare(DeleteMap {
+                uid: args.text("uid"),
+            }),
+            Prepare(DeletePlayer { .. }) => Prepare(DeletePlayer {
+                login: args.text("login"),
+            }),
+            GrantRole { .. } => GrantRole {
+                role_name: args.text("role"),
+                grantee: parse_grantee(args.text("login/cmd"))?,
+            },
+            RevokeRole { .. } => RevokeRole {
+                role_name: args.text("role"),
+                grantee: parse_grantee(args.text("login/cmd"))?,
+            },
+            cmd => *cmd,
+        };
+        Some(cmd)
+    }
+}
+
+/// Parse the text after `/role grant <role>` or `/role revoke <role>`,
+/// which is either a single login, or a command name that can have
+/// several words.
+fn parse_grantee(text: &str) -> Option<RoleGrantee> {
+    if text.starts_with('/') {
+        return Some(RoleGrantee::Command(text));
+    }
+    match text.split_whitespace().count() {
+        1 => Some(RoleGrantee::Player(text)),
+        _ => None,
     }
 }
@@ -7,28 +7,45 @@ pub use from_admin::*;
 pub use from_player::*;
 pub use from_super_admin::*;
 pub use output::*;
+pub use query::*;
+pub use spec::*;
 
 use crate::config::PlayerRole;
+use crate::constants::MAX_COMMAND_SUGGESTIONS;
+use crate::controller::edit_distance;
 use crate::server::{ModeScript, PauseStatus, PlayerInfo, WarmupStatus};
 
 mod from_admin;
 mod from_player;
 mod from_super_admin;
 mod output;
+mod query;
+mod spec;
 
 /// A parsed and validated command that should be executed.
 #[derive(Debug, Copy, Clone)]
 pub enum Command<'a> {
-    Help,
-    Player(PlayerCommand),
+    /// List all commands, or explain the command with the given name.
+    Help {
+        topic: Option<&'a str>,
+    },
+    Player(PlayerCommand<'a>),
     Admin(AdminCommand<'a>),
     SuperAdmin(SuperAdminCommand<'a>),
 }
 
 /// Reasons why a command should not/cannot be considered for execution.
 pub enum CommandDeniedError {
-    /// There is no such command.
-    NoSuchCommand,
+    /// There is no such command. Lists the names of similar commands
+    /// that the player can execute.
+    NoSuchCommand { suggestions: Vec<String> },
+
+    /// The command with the given name exists, but its arguments are invalid.
+    InvalidArguments {
+        name: &'static str,
+        usage: String,
+        problem: ArgError,
+    },
 
     /// The command cannot be executed in this context.
     NotAvailable(BadCommandContext),
@@ -44,6 +61,11 @@ pub struct CommandContext<'a> {
     pub cmd: &'a str,
     pub player: &'a PlayerInfo,
     pub player_role: PlayerRole,
+
+    /// The names of commands that the player may execute due to their roles,
+    /// regardless of their `player_role`.
+    pub granted_commands: &'a [String],
+
     pub mode: &'a ModeScript,
     pub warmup: &'a WarmupStatus,
     pub pause: &'a PauseStatus,
@@ -65,38 +87,81 @@ pub enum BadCommandContext {
     NoPermission,
 }
 
-/// A tuple of a command's example usage and explanation.
-#[derive(Debug)]
-pub(in crate::chat) struct CommandReference<'a> {
-    usage: &'a str,
-    doc: &'a str,
-}
-
-impl<'a> From<(&'a str, &'a str)> for CommandReference<'a> {
-    fn from(tuple: (&'a str, &'a str)) -> Self {
-        CommandReference {
-            usage: tuple.0,
-            doc: tuple.1,
-        }
-    }
-}
+/// The names that list all commands, or explain a command when
+/// followed by its name.
+const HELP_NAMES: [&str; 3] = ["/", "/h", "/help"];
 
 /// Commands can be grouped in arbitrary sets, to not have one huge `enum` for all commands.
-pub(in crate::chat) trait CommandEnum<'a>: Sized {
+pub(in crate::chat) trait CommandEnum<'a>: Sized + Copy + 'a {
     /// All possible command variants in this set.
     ///
-    /// This list is just there to build a reference list.
-    /// Command parameters should just be dummy/default values.
-    fn all() -> &'static Vec<Self>;
-
-    /// Check if a chat message matches any command variant.
-    fn parse(chat_message: &'a str) -> Option<Self>;
+    /// This list is used to match chat messages against the specs of each variant,
+    /// and to build a reference list. Command parameters should just be
+    /// dummy/default values.
+    fn all() -> &'a Vec<Self>;
+
+    /// The definition of this command variant, which declares its name, aliases,
+    /// arguments, and which roles and modes it requires.
+    fn spec(&self) -> CommandSpec;
+
+    /// Fill in this command variant with arguments that were parsed according
+    /// to its spec, or return `None` if they are invalid for this command.
+    fn with_args(&self, args: &CommandArgs<'a>) -> Option<Self>;
+
+    /// Check if this command variant can be executed in the given context,
+    /// apart from the requirements declared in its spec.
+    fn check(&self, _ctxt: CommandContext) -> Result<(), BadCommandContext> {
+        Ok(())
+    }
 
     /// Check if this command variant can be executed in the given context.
-    fn check(&self, ctxt: CommandContext) -> Result<(), BadCommandContext>;
+    fn check_all(&self, ctxt: CommandContext) -> Result<(), BadCommandContext> {
+        self.spec().check(&ctxt)?;
+        self.check(ctxt)
+    }
 
-    /// Return the reference of this command variant.
-    fn reference(&self) -> CommandReference;
+    /// Check if a chat message matches any command variant.
+    ///
+    /// Returns `Ok(None)` if the message does not start with the name of any
+    /// command in this set, and an error with the matching spec if its
+    /// arguments are invalid.
+    fn parse(
+        chat_message: &'a str,
+        tokens: &[Token<'a>],
+    ) -> Result<Option<Self>, (CommandSpec, ArgError)> {
+        let candidates: Vec<(&Self, CommandSpec, usize)> = Self::all()
+            .iter()
+            .filter_map(|cmd| {
+                let spec = cmd.spec();
+                spec.matched_name_len(tokens)
+                    .map(|name_len| (cmd, spec, name_len))
+            })
+            .collect();
+
+        // Prefer the longest name, f.e. `/playlist add all` over `/playlist add <uid>`.
+        let max_name_len = match candidates.iter().map(|(_, _, len)| *len).max() {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+
+        let mut first_err = None;
+        for (cmd, spec, name_len) in candidates {
+            if name_len < max_name_len {
+                continue;
+            }
+            let err = match spec.parse_args(chat_message, &tokens[name_len..]) {
+                Ok(args) => match cmd.with_args(&args) {
+                    Some(cmd) => return Ok(Some(cmd)),
+                    None => ArgError::Invalid,
+                },
+                Err(err) => err,
+            };
+            if first_err.is_none() {
+                first_err = Some((spec, err));
+            }
+        }
+        Err(first_err.expect("no matching command"))
+    }
 }
 
 impl Command<'_> {
@@ -105,16 +170,43 @@ impl Command<'_> {
     /// Returns an error variant if the command is unknown, was provided
     /// wrong arguments, or when it cannot be executed in this context.
     pub fn try_from(ctxt: CommandContext) -> Result<Command, CommandDeniedError> {
-        match ctxt.cmd.trim() {
-            "/" | "/h" | "/help" => return Ok(Command::Help),
+        use CommandDeniedError::*;
+
+        let tokens = tokenize(ctxt.cmd);
+
+        match tokens.first() {
+            Some(first) if !first.quoted && HELP_NAMES.contains(&first.text) => {
+                let topic = match tokens.get(1) {
+                    Some(token) => ctxt.cmd[token.start..].trim(),
+                    None => return Ok(Command::Help { topic: None }),
+                };
+                if command_spec(topic).is_some() {
+                    return Ok(Command::Help { topic: Some(topic) });
+                }
+                let topic = normalize_command_name(topic);
+                let suggestions = suggestions(ctxt, &tokenize(&topic));
+                return Err(NoSuchCommand { suggestions });
+            }
             _ => {}
         }
 
         macro_rules! try_command_set {
             ($cmd_type:ty, $cmd_variant:ident) => {
-                if let Some(cmd) = <$cmd_type>::parse(ctxt.cmd) {
-                    let _ = cmd.check(ctxt).map_err(CommandDeniedError::NotAvailable)?;
-                    return Ok(Command::$cmd_variant(cmd));
+                match <$cmd_type>::parse(ctxt.cmd, &tokens) {
+                    Ok(Some(cmd)) => {
+                        let _ = cmd.check_all(ctxt).map_err(NotAvailable)?;
+                        return Ok(Command::$cmd_variant(cmd));
+                    }
+                    Ok(None) => {}
+                    Err((spec, problem)) => {
+                        // Don't explain the usage to players that cannot execute the command.
+                        let _ = spec.check(&ctxt).map_err(NotAvailable)?;
+                        return Err(InvalidArguments {
+                            name: spec.name,
+                            usage: spec.usage(),
+                            problem,
+                        });
+                    }
                 }
             };
         }
@@ -123,23 +215,147 @@ impl Command<'_> {
         try_command_set!(AdminCommand, Admin);
         try_command_set!(SuperAdminCommand, SuperAdmin);
 
-        Err(CommandDeniedError::NoSuchCommand)
+        let suggestions = suggestions(ctxt, &tokens);
+        Err(NoSuchCommand { suggestions })
+    }
+}
+
+/// The names of all commands that can be granted by roles,
+/// f.e. `/playlist add`.
+pub fn grantable_commands() -> Vec<String> {
+    let mut names: Vec<String> = AdminCommand::all()
+        .iter()
+        .map(|cmd| cmd.spec().name.to_string())
+        .chain(
+            SuperAdminCommand::all()
+                .iter()
+                .map(|cmd| cmd.spec().name.to_string()),
+        )
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Find the spec of the command with the given name or alias.
+/// The leading slash of the name can be omitted.
+pub fn command_spec(name: &str) -> Option<CommandSpec> {
+    let name = normalize_command_name(name);
+
+    macro_rules! find_in_command_set {
+        ($cmd_type:ty) => {
+            let spec = <$cmd_type>::all()
+                .iter()
+                .map(|cmd| cmd.spec())
+                .find(|spec| spec.names().any(|n| n == name));
+            if spec.is_some() {
+                return spec;
+            }
+        };
+    }
+
+    find_in_command_set!(PlayerCommand);
+    find_in_command_set!(AdminCommand);
+    find_in_command_set!(SuperAdminCommand);
+    None
+}
+
+/// Collapse whitespace in a command name, and add the leading slash if it is missing.
+fn normalize_command_name(name: &str) -> String {
+    let name = name.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if name.starts_with('/') {
+        name
+    } else {
+        format!("/{}", name)
     }
 }
 
+/// The names of commands that the player can execute, and that are similar
+/// to the one they tried, from most to least similar.
+fn suggestions(ctxt: CommandContext, tokens: &[Token]) -> Vec<String> {
+    let mut scored: Vec<(usize, &'static str)> = Vec::new();
+
+    macro_rules! add_command_set {
+        ($cmd_type:ty) => {
+            for cmd in <$cmd_type>::all().iter() {
+                if cmd.check_all(ctxt).is_err() {
+                    continue;
+                }
+                let spec = cmd.spec();
+                let score = spec
+                    .names()
+                    .filter_map(|name| similarity(name, tokens))
+                    .min();
+                if let Some(score) = score {
+                    scored.push((score, spec.name));
+                }
+            }
+        };
+    }
+
+    add_command_set!(PlayerCommand);
+    add_command_set!(AdminCommand);
+    add_command_set!(SuperAdminCommand);
+
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_COMMAND_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// How similar the start of a message is to a command name. Lower is better,
+/// and `None` means that they are not similar.
+///
+/// Names are similar if they are only a few typos apart, or if
+/// the message is the start of the name, f.e. `/playlist`.
+fn similarity(name: &str, tokens: &[Token]) -> Option<usize> {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    let tried: Vec<&str> = tokens
+        .iter()
+        .take(words.len())
+        .map(|token| token.text)
+        .collect();
+    if tried.is_empty() {
+        return None;
+    }
+
+    let max_typos = (name.chars().count() / 4).max(1);
+    let typos = edit_distance(&tried.join(" "), name);
+    if typos <= max_typos {
+        return Some(typos);
+    }
+    if tried.len() < words.len() && words.starts_with(&tried) {
+        return Some(max_typos + words.len() - tried.len());
+    }
+    None
+}
+
 impl CommandContext<'_> {
+    /// Check whether the player has at least the given role, or whether
+    /// any of their roles grant the command with the given name.
+    pub(in crate::chat) fn has_permission(&self, min_role: PlayerRole, cmd_name: &str) -> bool {
+        if self.player_role >= min_role {
+            return true;
+        }
+        self.granted_commands
+            .iter()
+            .any(|granted| granted == cmd_name)
+    }
+
     /// The command reference for the given context, in tabular form.
     pub(in crate::chat) fn reference(&self) -> String {
         use BadCommandContext::*;
 
-        let mut cmds = HashMap::<Option<BadCommandContext>, Vec<CommandReference>>::new();
+        let mut cmds = HashMap::<Option<BadCommandContext>, Vec<CommandSpec>>::new();
 
         macro_rules! add_command_set {
             ($cmd_type:ty) => {
                 for cmd in <$cmd_type>::all().iter() {
-                    cmds.entry(cmd.check(*self).err())
+                    cmds.entry(cmd.check_all(*self).err())
                         .or_insert_with(Vec::new)
-                        .push(cmd.reference());
+                        .push(cmd.spec());
                 }
             };
         }
@@ -155,12 +371,15 @@ impl CommandContext<'_> {
         table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
         table.set_titles(row!["Command", "Action"]);
 
-        table.add_row(row!["/help", "Display this list"]);
+        table.add_row(row![
+            "/help [command]",
+            "Display this list, or explain a command"
+        ]);
         table.add_row(row!["", ""]);
 
         for maybe_err in sections.iter() {
-            let refs = cmds.get_mut(maybe_err).unwrap();
-            refs.sort_by_key(|r| r.usage);
+            let specs = cmds.get_mut(maybe_err).unwrap();
+            specs.sort_by_key(|spec| spec.usage());
 
             if let Some(err) = maybe_err {
                 let notice = match err {
@@ -175,11 +394,72 @@ impl CommandContext<'_> {
                 table.add_row(row!["", notice]);
             }
 
-            for r in refs {
-                table.add_row(row![r.usage, r.doc]);
+            for spec in specs {
+                table.add_row(row![spec.usage(), spec.doc]);
             }
         }
 
         table.to_string()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_commands_without_arguments() {
+        let names = grantable_commands();
+        assert!(names.contains(&"/playlist add".to_string()));
+        assert!(names.contains(&"/playlist add all".to_string()));
+        assert!(names.contains(&"/record remove".to_string()));
+        assert!(names.contains(&"/role grant".to_string()));
+        assert!(!names.iter().any(|name| name.contains('<')));
+    }
+
+    macro_rules! assert_parses_examples {
+        ($cmd_type:ty) => {
+            for cmd in <$cmd_type>::all().iter() {
+                let spec = cmd.spec();
+                let example = spec.example();
+                let tokens = tokenize(&example);
+                let parsed = <$cmd_type>::parse(&example, &tokens)
+                    .unwrap_or_else(|(_, err)| panic!("failed to parse '{}': {}", &example, err))
+                    .unwrap_or_else(|| panic!("no match for '{}'", &example));
+                assert_eq!(spec.name, parsed.spec().name, "parsed {:?}", parsed);
+            }
+        };
+    }
+
+    #[test]
+    fn parses_every_command() {
+        assert_parses_examples!(PlayerCommand);
+        assert_parses_examples!(AdminCommand);
+        assert_parses_examples!(SuperAdminCommand);
+    }
+
+    #[test]
+    fn finds_commands_by_name_or_alias() {
+        assert_eq!(
+            Some("/playlist add"),
+            command_spec("playlist  add").map(|spec| spec.name)
+        );
+        assert_eq!(
+            Some("/playlist add"),
+            command_spec("/pl add").map(|spec| spec.name)
+        );
+        assert!(command_spec("/playlist").is_none());
+    }
+
+    #[test]
+    fn rates_similar_names() {
+        assert_eq!(
+            Some(1),
+            similarity("/playlist add", &tokenize("/playlist ad x"))
+        );
+        assert_eq!(Some(1), similarity("/pb", &tokenize("/pd")));
+        assert!(similarity("/pb", &tokenize("/rank")).is_none());
+        assert!(similarity("/playlist add", &tokenize("/playlist")).is_some());
+        assert!(similarity("/playlist add", &tokenize("/pool")).is_none());
+    }
+}
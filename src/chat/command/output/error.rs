@@ -8,6 +8,11 @@ pub enum CommandErrorOutput<'a> {
     /// The reason why a command was not executed.
     CommandError(CommandContext<'a>, CommandDeniedError),
 
+    /// The reason why a player cannot start a vote.
+    ///
+    /// Output for `/vote skip`, `/vote extend`
+    CannotStartVote(RaceVoteError),
+
     /// Feedback for commands that affect the playlist.
     ///
     /// Output for `/playlist add`, `/playlist remove`, `/import map`
@@ -18,6 +23,17 @@ pub enum CommandErrorOutput<'a> {
     /// Output for `/delete player`, `/kick`, `/bounce`
     UnknownPlayer,
 
+    /// The specified name does not match any player's login, current
+    /// or past display names, or it matches several players equally well.
+    ///
+    /// Output for `/kick`, `/bounce`, `/mute`, `/unmute`, `/whois`
+    NoMatchingPlayer,
+
+    /// The specified player is not muted.
+    ///
+    /// Output for `/unmute`
+    PlayerNotMuted,
+
     /// The specified login does not match any blacklisted player.
     ///
     /// Output for `/blacklist remove`
@@ -29,9 +45,18 @@ pub enum CommandErrorOutput<'a> {
     /// Output for `/delete player`
     CannotDeleteWhitelistedPlayer,
 
+    /// The specified command name does not match any command that
+    /// can be granted to a role.
+    ///
+    /// Output for `/role grant`, `/role revoke`
+    UnknownCommandName {
+        tried: &'a str,
+        options: Vec<String>,
+    },
+
     /// The specified map UID does not match any map.
     ///
-    /// Output for `/delete map`, `/queue`
+    /// Output for `/delete map`, `/queue`, `/pb`, `/top`
     UnknownMap,
 
     /// Tell a super admin that prior to deleting a map,
@@ -40,6 +65,27 @@ pub enum CommandErrorOutput<'a> {
     /// Output for `/delete map`
     CannotDeletePlaylistMap,
 
+    /// Tell a player that there are no records on a map.
+    ///
+    /// Output for `/sectors`, `/top`
+    NoRecordsOnMap,
+
+    /// The specified ID or login & map UID do not match any record.
+    ///
+    /// Output for `/record approve`, `/record reject`, `/record remove`
+    UnknownRecord,
+
+    /// Tell an admin that an approved record was discarded, since it is
+    /// not better than the player's personal best.
+    ///
+    /// Output for `/record approve`
+    FlaggedRecordNotImproved,
+
+    /// The specified ID does not match any announcement.
+    ///
+    /// Output for `/announce window`, `/announce remove`
+    UnknownAnnouncement,
+
     /// Tell an admin that the current game mode does not support pauses.
     ///
     /// Output for `/pause`
@@ -77,6 +123,25 @@ pub enum CommandErrorOutput<'a> {
     CannotSaveMatchSettings { msg: &'a str },
 }
 
+/// Possible errors when trying to start a vote during a race.
+pub enum RaceVoteError {
+    /// Votes can only be started during a race.
+    NotDuringRace,
+
+    /// Spectators cannot start votes.
+    NotPlaying,
+
+    /// There is another vote that has not ended yet.
+    VoteInProgress,
+
+    /// Another vote ended only recently.
+    OnCooldown { secs_left: i64 },
+
+    /// The maximum number of votes of this kind were started on this map,
+    /// or this kind of vote is disabled.
+    LimitReached,
+}
+
 /// Possible errors when issuing a command that changes the playlist.
 pub enum PlaylistCommandError {
     /// A generic error message that indicates that the map
@@ -100,6 +165,9 @@ pub enum PlaylistCommandError {
     /// Tried to remove a map that was already removed from the playlist.
     MapAlreadyRemoved,
 
+    /// Tried to switch to a map pool that does not exist.
+    UnknownPool,
+
     /// Command failed for a reason not covered by any other variant.
     MapImportFailed(Box<dyn std::error::Error + Send>),
 }
@@ -129,6 +197,8 @@ impl Display for CommandErrorOutput<'_> {
                 write!(f, "This map was already removed from the playlist.")
             }
 
+            InvalidPlaylistCommand(UnknownPool) => write!(f, "No map pool with this name!"),
+
             InvalidPlaylistCommand(MapImportFailed(err)) => {
                 write!(f, "Failed to import map: {:?}", err)
             }
@@ -138,6 +208,22 @@ impl Display for CommandErrorOutput<'_> {
                 write!(f, "Add at least one other map to remove this one.")
             }
 
+            CannotStartVote(err) => match err {
+                RaceVoteError::NotDuringRace => {
+                    writeln!(f, "Votes can only be started during a race.")
+                }
+                RaceVoteError::NotPlaying => writeln!(f, "Spectators cannot start votes."),
+                RaceVoteError::VoteInProgress => writeln!(f, "There already is an ongoing vote."),
+                RaceVoteError::OnCooldown { secs_left } => writeln!(
+                    f,
+                    "Please wait {} seconds before starting another vote.",
+                    secs_left
+                ),
+                RaceVoteError::LimitReached => {
+                    writeln!(f, "You cannot start another vote of this kind on this map.")
+                }
+            },
+
             CommandError(ctxt, NotAvailable(err)) => {
                 match err {
                     BadCommandContext::DuringWarmup => {
@@ -159,14 +245,41 @@ impl Display for CommandErrorOutput<'_> {
                 writeln!(f)?;
                 write!(f, "{}", ctxt.reference())
             }
-            CommandError(ctxt, NoSuchCommand) => {
+            CommandError(ctxt, NoSuchCommand { suggestions }) => {
                 writeln!(f, "'{}' is not a valid command", ctxt.cmd)?;
+                if !suggestions.is_empty() {
+                    writeln!(f, "Did you mean {}?", suggestions.join(", or "))?;
+                }
                 writeln!(f)?;
                 write!(f, "{}", ctxt.reference())
             }
 
+            CommandError(
+                ctxt,
+                InvalidArguments {
+                    name,
+                    usage,
+                    problem,
+                },
+            ) => {
+                writeln!(f, "'{}' is not a valid command", ctxt.cmd)?;
+                writeln!(f, "{}", problem)?;
+                writeln!(f)?;
+                writeln!(f, "Usage: {}", usage)?;
+                writeln!(f, "Type '/help {}' for details.", name)
+            }
+
             UnknownPlayer => writeln!(f, "There is no player with that login!"),
 
+            PlayerNotMuted => writeln!(f, "That player is not muted!"),
+
+            NoMatchingPlayer => {
+                writeln!(
+                    f,
+                    "There is no single player with a matching login or name!"
+                )
+            }
+
             UnknownBlacklistPlayer => {
                 writeln!(f, "There is no blacklisted player with that login!")
             }
@@ -176,6 +289,15 @@ impl Display for CommandErrorOutput<'_> {
                 "Only blacklisted players can be removed from the database!"
             ),
 
+            UnknownCommandName { tried, options } => {
+                writeln!(f, "There is no command '{}' that roles can grant!", tried)?;
+                writeln!(f, "Commands:")?;
+                for option in options {
+                    writeln!(f, "{}", option)?;
+                }
+                Ok(())
+            }
+
             UnknownMap => writeln!(f, "There is no map with that UID!"),
 
             CannotDeletePlaylistMap => writeln!(
@@ -183,6 +305,17 @@ impl Display for CommandErrorOutput<'_> {
                 "Only maps outside of the playlist can be removed from the database!"
             ),
 
+            NoRecordsOnMap => writeln!(f, "There are no records on this map yet."),
+
+            UnknownRecord => writeln!(f, "There is no such record!"),
+
+            FlaggedRecordNotImproved => writeln!(
+                f,
+                "This record is not better than the player's personal best, and was discarded."
+            ),
+
+            UnknownAnnouncement => writeln!(f, "There is no such announcement!"),
+
             CannotPause => writeln!(f, "This game mode does not support pausing!"),
 
             NotInWarmup => writeln!(f, "This command works only during warmup."),
@@ -40,3 +40,54 @@ pub(super) fn truncate(text: &str, columns: usize) -> String {
         text.to_string()
     }
 }
+
+/// Lists the lines that were removed (`-`) from, or added (`+`) to a text,
+/// in the order in which they appear.
+pub(super) fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(('-', old[i]));
+            i += 1;
+        } else {
+            diff.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diffs_lines() {
+        let old = "a = 1\nb = 2\nc = 3";
+        let new = "a = 1\nb = 4\nc = 3\nd = 5";
+        assert_eq!(
+            vec![('-', "b = 2"), ('+', "b = 4"), ('+', "d = 5")],
+            diff_lines(old, new)
+        );
+        assert!(diff_lines(old, old).is_empty());
+    }
+}
@@ -4,10 +4,14 @@ use prettytable::format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR;
 use prettytable::{cell, row, Table};
 use semver::Version;
 
-use crate::chat::command::output::truncate;
-use crate::chat::CommandContext;
+use crate::chat::command::output::{diff_lines, truncate};
+use crate::chat::{CommandContext, CommandSpec, ListQuery, Page};
 use crate::config::TimeAttackConfig;
-use crate::database::{Map, Player};
+use crate::controller::{CurationVerdict, SectorAnalysis};
+use crate::database::{
+    Announcement, AnnouncementTrigger, FlaggedRecord, Map, MapPool, Player, PlayerAlias, Record,
+    RecordRemoval, Role,
+};
 use crate::server::{PlayerInfo, ServerBuildInfo, ServerNetStats};
 
 /// Outputs for successful commands that list some result.
@@ -17,6 +21,11 @@ pub enum CommandResultOutput<'a> {
     /// Output for: `/help`
     CommandReference(CommandContext<'a>),
 
+    /// Explain a command in detail.
+    ///
+    /// Output for: `/help <command>`
+    CommandHelp(CommandSpec),
+
     /// List the current config, so that an admin can edit it.
     ///
     /// Output for: `/config`
@@ -30,19 +39,116 @@ pub enum CommandResultOutput<'a> {
         error_msg: &'a str,
     },
 
-    /// List all maps in the database, and group maps in- and outside
-    /// of the playlist.
+    /// List the changes of a submitted config, so that an admin can
+    /// review them before they are applied.
+    ///
+    /// Output for: `/config`, after submitting a valid config
+    ConfigChanges {
+        curr_repr: &'a str,
+        new_repr: &'a str,
+    },
+
+    /// List a page of the maps in the database that match a query,
+    /// and whether they are in the playlist. Playlist maps are listed first.
     ///
     /// Output for `/maps`
     MapList {
-        in_playlist: Vec<&'a Map>,
-        not_in_playlist: Vec<&'a Map>,
+        maps: Page<(&'a Map, bool)>,
+        query: &'a ListQuery,
+    },
+
+    /// Lists the remove votes of every playlist map, and whether it
+    /// should be removed from the playlist.
+    ///
+    /// Output for `/playlist curate`
+    CurationReport(Vec<CurationVerdict>),
+
+    /// Lists map pools, and the number of maps in each of them.
+    ///
+    /// Output for `/pools`
+    PoolList {
+        pools: Vec<&'a MapPool>,
+        active_pool: Option<&'a str>,
     },
 
-    /// Lists logins and display names of connected players.
+    /// Lists a page of the logins and display names of connected players
+    /// that match a query.
     ///
     /// Output for `/players`
-    PlayerList(Vec<&'a PlayerInfo>),
+    PlayerList {
+        players: Page<&'a PlayerInfo>,
+        query: &'a ListQuery,
+    },
+
+    /// Lists players that match a name, with all of their aliases,
+    /// when they were seen, and how many records they have.
+    ///
+    /// Output for `/whois`
+    PlayerProfiles(Vec<PlayerProfile>),
+
+    /// Lists all roles, the commands they grant, and the players
+    /// they are assigned to.
+    ///
+    /// Output for `/roles`, `/role grant`, `/role revoke`
+    RoleList(Vec<Role>),
+
+    /// Lists flagged records that await review, recent top records,
+    /// and records that were removed by admins.
+    ///
+    /// Output for `/records review`
+    RecordReview {
+        flagged: Vec<FlaggedRecord>,
+        recent: Vec<Record>,
+        removals: Vec<RecordRemoval>,
+    },
+
+    /// Compares a player's sector times to those of the top record,
+    /// and to the ideal run.
+    ///
+    /// Output for `/sectors`
+    SectorReport {
+        map_name: &'a str,
+        analysis: SectorAnalysis,
+    },
+
+    /// Shows a player's personal best on a map, and compares it
+    /// to the top record.
+    ///
+    /// Output for `/pb`
+    PersonalBestReport {
+        map_name: &'a str,
+        pb: Option<Record>,
+        top_record: Option<Record>,
+    },
+
+    /// Lists the top records on a map.
+    ///
+    /// Output for `/top`
+    TopRecordList {
+        map_name: &'a str,
+        records: Vec<Record>,
+        player_login: &'a str,
+    },
+
+    /// Shows a player's server rank, and their seasonal rank
+    /// if there is an active season.
+    ///
+    /// Output for `/rank`
+    ServerRankReport {
+        server_rank: Option<RankSummary>,
+        season_rank: Option<(&'a str, Option<RankSummary>)>,
+    },
+
+    /// Lists playlist maps that a player has not set a record on.
+    ///
+    /// Output for `/recs`
+    MissingRecordList(Vec<&'a Map>),
+
+    /// Lists the scheduled announcements, with their IDs, triggers
+    /// and time windows.
+    ///
+    /// Output for `/announcements`, `/announce *`
+    AnnouncementList(Vec<Announcement>),
 
     /// Information about server & controller.
     ///
@@ -50,6 +156,24 @@ pub enum CommandResultOutput<'a> {
     ControllerInfo(Box<ControllerInfo>),
 }
 
+pub struct PlayerProfile {
+    pub login: String,
+
+    /// Every display name of this player, from most to least recent.
+    pub aliases: Vec<PlayerAlias>,
+
+    /// The number of maps this player has set a record on.
+    pub nb_records: i64,
+}
+
+/// A player's position in the server ranking, or in a seasonal ranking.
+pub struct RankSummary {
+    pub pos: usize,
+    pub max_pos: usize,
+    pub nb_wins: usize,
+    pub nb_losses: usize,
+}
+
 pub struct ControllerInfo {
     pub controller_version: Version,
     pub most_recent_controller_version: Version,
@@ -66,6 +190,8 @@ impl Display for CommandResultOutput<'_> {
         match self {
             CommandReference(ctxt) => write!(f, "{}", ctxt.reference()),
 
+            CommandHelp(spec) => write!(f, "{}", spec.help()),
+
             CurrentConfig { repr } => write!(f, "{}", repr),
 
             InvalidConfig {
@@ -80,10 +206,24 @@ impl Display for CommandResultOutput<'_> {
                 write!(f, "{}", tried_repr)
             }
 
-            MapList {
-                in_playlist,
-                not_in_playlist,
+            ConfigChanges {
+                curr_repr,
+                new_repr,
             } => {
+                writeln!(f, "# Apply to confirm these changes:")?;
+                for (change, line) in diff_lines(curr_repr, new_repr) {
+                    writeln!(f, "# {} {}", change, line)?;
+                }
+                writeln!(f)?;
+                write!(f, "{}", new_repr)
+            }
+
+            MapList { maps, query } => {
+                if maps.nb_items == 0 {
+                    return write_no_matches(f, "maps", query);
+                }
+                write_page_header(f, maps, query)?;
+
                 let mut table = Table::new();
                 table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
                 table.set_titles(row!["Name", "Author", "UID", "Link"]);
@@ -99,28 +239,75 @@ impl Display for CommandResultOutput<'_> {
                     ]);
                 };
 
-                for map in in_playlist.iter() {
+                let mut prev_in_playlist = true;
+                for (idx, (map, in_playlist)) in maps.items.iter().enumerate() {
+                    if prev_in_playlist && !in_playlist {
+                        if idx > 0 {
+                            table.add_row(row!["", "", "", ""]);
+                        }
+                        table.add_row(row!["not in playlist".to_uppercase(), "", "", ""]);
+                    }
+                    prev_in_playlist = *in_playlist;
                     add_row(&mut table, map);
                 }
 
-                if !not_in_playlist.is_empty() {
-                    table.add_row(row!["", "", "", ""]);
-                    table.add_row(row!["not in playlist".to_uppercase(), "", "", ""]);
+                write!(f, "{}", table.to_string())
+            }
 
-                    for map in not_in_playlist.iter() {
-                        add_row(&mut table, map);
-                    }
+            CurationReport(verdicts) => {
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row![
+                    "Name", "UID", "Voters", "Removes", "Players", "Ratio", "Days", "Remove?"
+                ]);
+
+                for verdict in verdicts {
+                    table.add_row(row![
+                        truncate(&verdict.map.name.plain(), 20),
+                        &verdict.map.uid,
+                        verdict.nb_voters,
+                        verdict.nb_remove_votes,
+                        verdict.nb_players,
+                        format!("{:.0}%", verdict.remove_ratio() * 100.0),
+                        verdict.days_in_playlist,
+                        if verdict.should_remove { "yes" } else { "no" },
+                    ]);
                 }
 
                 write!(f, "{}", table.to_string())
             }
 
-            PlayerList(players) => {
+            PoolList { pools, active_pool } => {
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Name", "Maps", "Active"]);
+
+                for pool in pools {
+                    table.add_row(row![
+                        truncate(&pool.name, 30),
+                        pool.map_uids.len(),
+                        if Some(pool.name.as_str()) == *active_pool {
+                            "yes"
+                        } else {
+                            ""
+                        },
+                    ]);
+                }
+
+                write!(f, "{}", table.to_string())
+            }
+
+            PlayerList { players, query } => {
+                if players.nb_items == 0 {
+                    return write_no_matches(f, "players", query);
+                }
+                write_page_header(f, players, query)?;
+
                 let mut table = Table::new();
                 table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
                 table.set_titles(row!["Nickname", "Login"]);
 
-                for player in players {
+                for player in players.items.iter() {
                     table.add_row(row![
                         truncate(&player.display_name.plain(), 30),
                         &player.login
@@ -130,6 +317,281 @@ impl Display for CommandResultOutput<'_> {
                 write!(f, "{}", table.to_string())
             }
 
+            RoleList(roles) => {
+                if roles.is_empty() {
+                    return write!(f, "There are no roles.");
+                }
+
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Role", "Commands", "Players"]);
+
+                for role in roles {
+                    table.add_row(row![
+                        truncate(&role.name, 20),
+                        role.commands.join("\n"),
+                        role.player_logins.join("\n"),
+                    ]);
+                }
+
+                write!(f, "{}", table.to_string())
+            }
+
+            PlayerProfiles(profiles) => {
+                for profile in profiles {
+                    let first_seen = profile.aliases.iter().map(|a| a.first_seen).min();
+                    let last_seen = profile.aliases.iter().map(|a| a.last_seen).max();
+                    let fmt_date = |date: Option<chrono::NaiveDateTime>| {
+                        date.map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default()
+                    };
+
+                    writeln!(f, "{}", profile.login.to_uppercase())?;
+                    writeln!(f, "First seen: {}", fmt_date(first_seen))?;
+                    writeln!(f, "Last seen:  {}", fmt_date(last_seen))?;
+                    writeln!(f, "Records:    {}", profile.nb_records)?;
+
+                    let mut table = Table::new();
+                    table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                    table.set_titles(row!["Nickname", "First seen", "Last seen"]);
+                    for alias in &profile.aliases {
+                        table.add_row(row![
+                            truncate(&alias.display_name.plain(), 30),
+                            alias.first_seen.format("%Y-%m-%d"),
+                            alias.last_seen.format("%Y-%m-%d"),
+                        ]);
+                    }
+                    writeln!(f, "{}", table.to_string())?;
+                }
+                Ok(())
+            }
+
+            SectorReport { map_name, analysis } => {
+                writeln!(f, "Sectors of {}", map_name)?;
+                writeln!(f)?;
+
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Sector", "PB", "Your Best", "Top", "Ideal"]);
+
+                let fmt_opt = |sectors: &Option<Vec<i32>>, idx: usize| {
+                    sectors
+                        .as_ref()
+                        .map(|sectors| fmt_secs(sectors[idx]))
+                        .unwrap_or_else(|| "-".to_string())
+                };
+                for idx in 0..analysis.ideal.len() {
+                    table.add_row(row![
+                        idx + 1,
+                        fmt_opt(&analysis.player_pb, idx),
+                        fmt_opt(&analysis.player_best, idx),
+                        fmt_secs(analysis.top_record[idx]),
+                        fmt_secs(analysis.ideal[idx]),
+                    ]);
+                }
+                writeln!(f, "{}", table.to_string())?;
+
+                writeln!(f, "Ideal run: {}", fmt_secs(analysis.ideal_millis()))?;
+                if let Some(millis) = analysis.player_best_millis() {
+                    writeln!(f, "Your best possible run: {}", fmt_secs(millis))?;
+                }
+                if let Some((idx, loss)) = analysis.max_loss(&analysis.top_record) {
+                    writeln!(
+                        f,
+                        "You lose the most time against the top record in sector {} (+{})",
+                        idx + 1,
+                        fmt_secs(loss)
+                    )?;
+                }
+                if let Some((idx, loss)) = analysis.max_loss(&analysis.ideal) {
+                    writeln!(
+                        f,
+                        "You lose the most time against the ideal run in sector {} (+{})",
+                        idx + 1,
+                        fmt_secs(loss)
+                    )?;
+                }
+                Ok(())
+            }
+
+            RecordReview {
+                flagged,
+                recent,
+                removals,
+            } => {
+                let fmt_splits = |cp_millis: &[i32]| {
+                    cp_millis
+                        .iter()
+                        .map(|millis| fmt_secs(*millis))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                };
+
+                writeln!(f, "FLAGGED RECORDS")?;
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["ID", "Login", "Map UID", "Time", "Reason", "Splits"]);
+                for rec in flagged {
+                    table.add_row(row![
+                        rec.id,
+                        &rec.player_login,
+                        &rec.map_uid,
+                        fmt_secs(rec.millis),
+                        truncate(&rec.reason, 40),
+                        fmt_splits(&rec.cp_millis),
+                    ]);
+                }
+                writeln!(f, "{}", table.to_string())?;
+
+                writeln!(f, "RECENT TOP RECORDS")?;
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Login", "Map UID", "Time", "Date", "Splits"]);
+                for rec in recent {
+                    table.add_row(row![
+                        &rec.player_login,
+                        &rec.map_uid,
+                        fmt_secs(rec.millis),
+                        rec.timestamp.format("%Y-%m-%d %H:%M"),
+                        fmt_splits(&rec.cp_millis),
+                    ]);
+                }
+                writeln!(f, "{}", table.to_string())?;
+
+                writeln!(f, "REMOVED RECORDS")?;
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row![
+                    "Admin", "Login", "Map UID", "Time", "Restored", "Date"
+                ]);
+                for removal in removals {
+                    table.add_row(row![
+                        &removal.admin_login,
+                        &removal.player_login,
+                        &removal.map_uid,
+                        fmt_secs(removal.millis),
+                        removal.restored_millis.map(fmt_secs).unwrap_or_default(),
+                        removal.removed_at.format("%Y-%m-%d %H:%M"),
+                    ]);
+                }
+                write!(f, "{}", table.to_string())
+            }
+
+            PersonalBestReport {
+                map_name,
+                pb,
+                top_record,
+            } => {
+                writeln!(f, "Personal best on {}", map_name)?;
+                writeln!(f)?;
+
+                let pb = match pb {
+                    Some(pb) => pb,
+                    None => return writeln!(f, "You have not finished this map yet."),
+                };
+                writeln!(f, "Time: {}", fmt_secs(pb.millis))?;
+                writeln!(f, "Rank: {}/{}", pb.map_rank, pb.max_map_rank)?;
+                writeln!(f, "Date: {}", pb.timestamp.format("%Y-%m-%d %H:%M"))?;
+
+                if let Some(top) = top_record.as_ref().filter(|top| top.map_rank < pb.map_rank) {
+                    writeln!(
+                        f,
+                        "Top record: {} by {} (+{})",
+                        fmt_secs(top.millis),
+                        top.player_display_name.plain(),
+                        fmt_secs(pb.millis - top.millis)
+                    )?;
+                }
+                Ok(())
+            }
+
+            TopRecordList {
+                map_name,
+                records,
+                player_login,
+            } => {
+                writeln!(f, "Top records on {}", map_name)?;
+                writeln!(f)?;
+
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Rank", "Time", "Nickname", "Date", ""]);
+                for rec in records {
+                    table.add_row(row![
+                        rec.map_rank,
+                        fmt_secs(rec.millis),
+                        truncate(&rec.player_display_name.plain(), 30),
+                        rec.timestamp.format("%Y-%m-%d"),
+                        if rec.player_login == *player_login {
+                            "you"
+                        } else {
+                            ""
+                        },
+                    ]);
+                }
+                write!(f, "{}", table.to_string())
+            }
+
+            ServerRankReport {
+                server_rank,
+                season_rank,
+            } => {
+                let write_rank = |f: &mut Formatter<'_>, rank: &Option<RankSummary>| match rank {
+                    Some(rank) => {
+                        writeln!(f, "Rank:   {}/{}", rank.pos, rank.max_pos)?;
+                        writeln!(f, "Wins:   {}", rank.nb_wins)?;
+                        writeln!(f, "Losses: {}", rank.nb_losses)
+                    }
+                    None => writeln!(f, "You are not ranked yet."),
+                };
+
+                writeln!(f, "SERVER RANKING")?;
+                write_rank(f, server_rank)?;
+
+                if let Some((season_name, season_rank)) = season_rank {
+                    writeln!(f)?;
+                    writeln!(f, "{}", season_name.to_uppercase())?;
+                    write_rank(f, season_rank)?;
+                }
+                Ok(())
+            }
+
+            MissingRecordList(maps) => {
+                if maps.is_empty() {
+                    return write!(f, "You have finished every map in the playlist!");
+                }
+
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Name", "Author", "UID"]);
+                for map in maps {
+                    table.add_row(row![
+                        truncate(&map.name.plain(), 30),
+                        truncate(&map.author_display_name.plain(), 20),
+                        &map.uid,
+                    ]);
+                }
+                write!(f, "{}", table.to_string())
+            }
+
+            AnnouncementList(announcements) => {
+                if announcements.is_empty() {
+                    return write!(f, "There are no announcements.");
+                }
+
+                let mut table = Table::new();
+                table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["ID", "When", "Message"]);
+                for announcement in announcements {
+                    table.add_row(row![
+                        announcement.id,
+                        fmt_announcement_schedule(announcement),
+                        truncate(&announcement.message, 40),
+                    ]);
+                }
+                write!(f, "{}", table.to_string())
+            }
+
             ControllerInfo(info) => {
                 writeln!(
                     f,
@@ -159,3 +621,61 @@ impl Display for CommandResultOutput<'_> {
         }
     }
 }
+
+/// Format milliseconds as seconds, f.e. `12.345`.
+fn fmt_secs(millis: i32) -> String {
+    format!("{:.3}", millis as f64 / 1000.0)
+}
+
+/// Describe when an announcement is displayed, f.e. `every 3 maps (18:00-23:00 UTC)`.
+fn fmt_announcement_schedule(announcement: &Announcement) -> String {
+    use AnnouncementTrigger::*;
+
+    let trigger = match announcement.trigger {
+        Join => "on join".to_string(),
+        MapStart => "on map start".to_string(),
+        EveryNbMaps { nb_maps } => format!("every {} maps", nb_maps),
+        Interval { secs } if secs % 3600 == 0 => format!("every {}h", secs / 3600),
+        Interval { secs } if secs % 60 == 0 => format!("every {}m", secs / 60),
+        Interval { secs } => format!("every {}s", secs),
+    };
+    match announcement.window {
+        Some(window) => format!(
+            "{} ({}-{} UTC)",
+            trigger,
+            window.start.format("%H:%M"),
+            window.end.format("%H:%M")
+        ),
+        None => trigger,
+    }
+}
+
+/// Write a line like `Page 2/3 - 55 results - query: tech sort:added`
+/// above a paged list.
+fn write_page_header<T>(
+    f: &mut Formatter<'_>,
+    page: &Page<T>,
+    query: &ListQuery,
+) -> std::fmt::Result {
+    write!(
+        f,
+        "Page {}/{} - {} results",
+        page.index + 1,
+        page.nb_pages,
+        page.nb_items
+    )?;
+    if !query.is_empty() {
+        write!(f, " - query: {}", query)?;
+    }
+    writeln!(f)?;
+    writeln!(f)
+}
+
+/// Explain that a paged list is empty.
+fn write_no_matches(f: &mut Formatter<'_>, items: &str, query: &ListQuery) -> std::fmt::Result {
+    if query.is_empty() {
+        write!(f, "There are no {}.", items)
+    } else {
+        write!(f, "There are no {} that match '{}'.", items, query)
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt::{Display, Formatter};
+
+use crate::chat::tokenize;
+use crate::database::Map;
+use crate::server::PlayerInfo;
+
+/// A search query that filters and sorts the lists of `/maps` and `/players`,
+/// f.e. `/maps tech author:xyz sort:added`.
+///
+/// - Free terms have to be contained in a map's name or in its author's
+///   login or name, or in a player's login or name. Use double quotes
+///   for terms that contain spaces.
+/// - `author:<term>` only keeps maps whose author's login or name contains
+///   the term. For players, it is treated like a free term.
+/// - `sort:<key>` orders by `name` (the default), `author`, `uid`, `login`,
+///   or `added` (newest first).
+///
+/// Matching is case-insensitive, and ignores formatting in names.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ListQuery {
+    terms: Vec<String>,
+    author: Option<String>,
+    order: Option<ListOrder>,
+}
+
+/// The orders that can be selected with `sort:<key>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListOrder {
+    /// Sort by map or player name.
+    Name,
+
+    /// Sort maps by their author's name, and players by name.
+    Author,
+
+    /// Sort maps by UID, and players by login.
+    Id,
+
+    /// Sort maps by the date they were added, newest first,
+    /// and players by name.
+    Added,
+}
+
+impl ListOrder {
+    fn from_key(key: &str) -> Option<ListOrder> {
+        use ListOrder::*;
+        match key {
+            "name" => Some(Name),
+            "author" => Some(Author),
+            "uid" | "login" => Some(Id),
+            "added" => Some(Added),
+            _ => None,
+        }
+    }
+
+    fn key(self) -> &'static str {
+        use ListOrder::*;
+        match self {
+            Name => "name",
+            Author => "author",
+            Id => "uid",
+            Added => "added",
+        }
+    }
+}
+
+impl ListQuery {
+    /// Parse a query. Keys with unknown values, like `sort:xyz`,
+    /// are kept as free terms.
+    pub fn parse(query: &str) -> ListQuery {
+        const AUTHOR_KEY: &str = "author:";
+        const SORT_KEY: &str = "sort:";
+
+        let mut parsed = ListQuery::default();
+        for token in tokenize(query) {
+            let term = token.text.to_lowercase();
+            if term.is_empty() {
+                continue;
+            }
+            if !token.quoted && term.starts_with(AUTHOR_KEY) && term.len() > AUTHOR_KEY.len() {
+                parsed.author = Some(term[AUTHOR_KEY.len()..].to_string());
+                continue;
+            }
+            if !token.quoted && term.starts_with(SORT_KEY) {
+                if let Some(order) = ListOrder::from_key(&term[SORT_KEY.len()..]) {
+                    parsed.order = Some(order);
+                    continue;
+                }
+            }
+            parsed.terms.push(term);
+        }
+        parsed
+    }
+
+    /// `True` if this query neither filters nor sorts.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.author.is_none() && self.order.is_none()
+    }
+
+    pub fn matches_map(&self, map: &Map) -> bool {
+        let name = map.name.plain().to_lowercase();
+        let author_name = map.author_display_name.plain().to_lowercase();
+        let author_login = map.author_login.to_lowercase();
+        self.matches(
+            &[&name, &author_name, &author_login],
+            &[&author_name, &author_login],
+        )
+    }
+
+    pub fn matches_player(&self, player: &PlayerInfo) -> bool {
+        let name = player.display_name.plain().to_lowercase();
+        let login = player.login.to_lowercase();
+        self.matches(&[&name, &login], &[&name, &login])
+    }
+
+    /// Sort maps in the query's order, or by name if it has none.
+    pub fn sort_maps(&self, maps: &mut [&Map]) {
+        match self.order.unwrap_or(ListOrder::Name) {
+            ListOrder::Name => maps.sort_by_key(|map| map.name.plain().to_lowercase()),
+            ListOrder::Author => {
+                maps.sort_by_key(|map| map.author_display_name.plain().to_lowercase())
+            }
+            ListOrder::Id => maps.sort_by(|a, b| a.uid.cmp(&b.uid)),
+            ListOrder::Added => maps.sort_by(|a, b| b.added_since.cmp(&a.added_since)),
+        }
+    }
+
+    /// Sort players in the query's order, or by name if it has none.
+    pub fn sort_players(&self, players: &mut [&PlayerInfo]) {
+        match self.order.unwrap_or(ListOrder::Name) {
+            ListOrder::Id => players.sort_by(|a, b| a.login.cmp(&b.login)),
+            _ => players.sort_by_key(|player| player.display_name.plain().to_lowercase()),
+        }
+    }
+
+    /// Check that every free term is contained in any of the `fields`,
+    /// and that the author term is contained in any of the `author_fields`.
+    fn matches(&self, fields: &[&str], author_fields: &[&str]) -> bool {
+        let contains = |fields: &[&str], term: &str| fields.iter().any(|f| f.contains(term));
+        self.terms.iter().all(|term| contains(fields, term))
+            && self
+                .author
+                .as_ref()
+                .map(|term| contains(author_fields, term))
+                .unwrap_or(true)
+    }
+}
+
+impl Display for ListQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts: Vec<String> = self
+            .terms
+            .iter()
+            .map(|term| {
+                if term.contains(char::is_whitespace) {
+                    format!("\"{}\"", term)
+                } else {
+                    term.clone()
+                }
+            })
+            .collect();
+        if let Some(author) = &self.author {
+            parts.push(format!("author:{}", author));
+        }
+        if let Some(order) = self.order {
+            parts.push(format!("sort:{}", order.key()));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// One page of a longer list.
+#[derive(Debug)]
+pub struct Page<T> {
+    /// The items on this page.
+    pub items: Vec<T>,
+
+    /// The zero-based index of this page.
+    pub index: usize,
+
+    /// The number of pages, which is at least one.
+    pub nb_pages: usize,
+
+    /// The number of items on all pages.
+    pub nb_items: usize,
+}
+
+impl<T> Page<T> {
+    /// Split `items` into pages of `page_size` items, and select the page
+    /// at `index`, or the last page if there are fewer pages.
+    pub fn of(items: Vec<T>, index: usize, page_size: usize) -> Page<T> {
+        let nb_items = items.len();
+        let nb_pages = ((nb_items + page_size - 1) / page_size).max(1);
+        let index = index.min(nb_pages - 1);
+        let items = items
+            .into_iter()
+            .skip(index * page_size)
+            .take(page_size)
+            .collect();
+        Page {
+            items,
+            index,
+            nb_pages,
+            nb_items,
+        }
+    }
+
+    /// The index of the previous page, if any.
+    pub fn prev_index(&self) -> Option<usize> {
+        self.index.checked_sub(1)
+    }
+
+    /// The index of the next page, if any.
+    pub fn next_index(&self) -> Option<usize> {
+        Some(self.index + 1).filter(|idx| *idx < self.nb_pages)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_queries() {
+        let query = ListQuery::parse("Tech AUTHOR:xyz sort:added \"ice run\"");
+        assert_eq!(vec!["tech", "ice run"], query.terms);
+        assert_eq!(Some("xyz".to_string()), query.author);
+        assert_eq!(Some(ListOrder::Added), query.order);
+        assert_eq!("tech \"ice run\" author:xyz sort:added", query.to_string());
+
+        let query = ListQuery::parse("sort:xyz author:");
+        assert_eq!(vec!["sort:xyz", "author:"], query.terms);
+        assert!(query.order.is_none());
+        assert!(query.author.is_none());
+
+        assert!(ListQuery::parse("  ").is_empty());
+    }
+
+    #[test]
+    fn matches_terms_and_author() {
+        let query = ListQuery::parse("tech author:xyz");
+        assert!(query.matches(&["tech map", "xyz"], &["xyz"]));
+        assert!(!query.matches(&["tech map", "xyz"], &["abc"]));
+        assert!(!query.matches(&["dirt map", "xyz"], &["xyz"]));
+        assert!(ListQuery::parse("").matches(&["any"], &["any"]));
+    }
+
+    #[test]
+    fn splits_pages() {
+        let page = Page::of((0..55).collect(), 1, 25);
+        assert_eq!((25..50).collect::<Vec<_>>(), page.items);
+        assert_eq!(3, page.nb_pages);
+        assert_eq!(Some(0), page.prev_index());
+        assert_eq!(Some(2), page.next_index());
+
+        let page = Page::of((0..55).collect(), 9, 25);
+        assert_eq!(2, page.index);
+        assert_eq!(5, page.items.len());
+        assert_eq!(None, page.next_index());
+
+        let page = Page::<u8>::of(vec![], 0, 25);
+        assert_eq!(1, page.nb_pages);
+        assert_eq!(None, page.prev_index());
+        assert_eq!(None, page.next_index());
+    }
+}
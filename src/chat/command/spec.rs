@@ -0,0 +1,584 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use chrono::NaiveTime;
+
+use crate::chat::{BadCommandContext, CommandContext};
+use crate::config::PlayerRole;
+use crate::constants::MAX_COMMAND_DURATION_SECS;
+use crate::server::ModeScript;
+
+/// The declarative definition of a chat command, from which its parser,
+/// its entry in the command reference, and its detailed help are derived.
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    /// The words that invoke this command, f.e. `/playlist add`.
+    pub(in crate::chat) name: &'static str,
+
+    /// Alternative names of this command, f.e. `/pl add`.
+    aliases: Vec<&'static str>,
+
+    /// The arguments that follow the command name.
+    args: Vec<ArgSpec>,
+
+    /// A short explanation, listed in the command reference.
+    pub(in crate::chat) doc: &'static str,
+
+    /// A longer explanation, shown with `/help <command>`.
+    details: Option<&'static str>,
+
+    /// The role required to execute this command, unless one of
+    /// a player's roles grants it.
+    min_role: PlayerRole,
+
+    /// The only game mode this command can be used in,
+    /// or `None` if it can be used in any mode.
+    mode: Option<ModeScript>,
+}
+
+#[derive(Debug, Clone)]
+struct ArgSpec {
+    name: &'static str,
+    kind: ArgKind,
+    optional: bool,
+}
+
+/// The types of command arguments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(in crate::chat) enum ArgKind {
+    /// A single word, or text in double quotes.
+    Word,
+
+    /// A login or display name. Names with spaces can be put in double quotes.
+    Player,
+
+    /// A whole number.
+    Number,
+
+    /// A duration like `30s`, `10m`, `2h` or `1d`.
+    Duration,
+
+    /// A time of day in UTC, like `18:00`.
+    Time,
+
+    /// The rest of the message, which can contain spaces.
+    /// Only used for the last argument.
+    Text,
+}
+
+/// Reasons why the arguments of a command are invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgError {
+    /// A required argument is missing.
+    Missing(&'static str),
+
+    /// An argument is not a whole number.
+    NotANumber(&'static str),
+
+    /// An argument is not a duration.
+    NotADuration(&'static str),
+
+    /// An argument is not a time of day.
+    NotATime(&'static str),
+
+    /// There are more words than the command has arguments.
+    TooMany,
+
+    /// The arguments have the right types, but are invalid for this command.
+    Invalid,
+}
+
+/// A word of a chat message, or a text in double quotes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(in crate::chat) struct Token<'a> {
+    /// The word, or the text in between quotes.
+    pub text: &'a str,
+
+    /// The byte offset of this token in the message, including
+    /// the opening quote.
+    pub start: usize,
+
+    /// `True` if this token was quoted.
+    pub quoted: bool,
+}
+
+/// The arguments of a command, parsed according to its spec.
+pub(in crate::chat) struct CommandArgs<'a>(Vec<(&'static str, ArgValue<'a>)>);
+
+enum ArgValue<'a> {
+    Text(&'a str),
+    Number(i64),
+    Duration(u64),
+    Time(NaiveTime),
+}
+
+impl CommandSpec {
+    pub(in crate::chat) fn new(name: &'static str, doc: &'static str) -> Self {
+        CommandSpec {
+            name,
+            aliases: vec![],
+            args: vec![],
+            doc,
+            details: None,
+            min_role: PlayerRole::Player,
+            mode: None,
+        }
+    }
+
+    pub(in crate::chat) fn alias(mut self, alias: &'static str) -> Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    pub(in crate::chat) fn arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: false,
+        });
+        self
+    }
+
+    /// Add an argument that can be omitted. Optional arguments
+    /// have to follow all required arguments.
+    pub(in crate::chat) fn optional_arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: true,
+        });
+        self
+    }
+
+    pub(in crate::chat) fn details(mut self, details: &'static str) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub(in crate::chat) fn min_role(mut self, role: PlayerRole) -> Self {
+        self.min_role = role;
+        self
+    }
+
+    pub(in crate::chat) fn mode(mut self, mode: ModeScript) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// The command name, followed by its arguments,
+    /// f.e. `/pb [uid]` or `/mute <login/nick> <duration>`.
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.to_string();
+        for arg in &self.args {
+            if arg.optional {
+                usage.push_str(&format!(" [{}]", arg.name));
+            } else {
+                usage.push_str(&format!(" <{}>", arg.name));
+            }
+        }
+        usage
+    }
+
+    /// The command name, followed by all of its aliases.
+    pub(in crate::chat) fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        std::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+
+    /// Check whether the player is permitted to execute this command,
+    /// and whether it can be executed in the current game mode.
+    pub(in crate::chat) fn check(&self, ctxt: &CommandContext) -> Result<(), BadCommandContext> {
+        if !ctxt.has_permission(self.min_role, self.name) {
+            return Err(BadCommandContext::NoPermission);
+        }
+        match &self.mode {
+            Some(mode) if mode != ctxt.mode => Err(BadCommandContext::InMode(mode.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    /// The number of tokens that match this command's name or one of its aliases,
+    /// or `None` if the tokens start with neither.
+    pub(in crate::chat) fn matched_name_len(&self, tokens: &[Token]) -> Option<usize> {
+        self.names()
+            .filter_map(|name| {
+                let words: Vec<&str> = name.split_whitespace().collect();
+                let is_match = tokens.len() >= words.len()
+                    && tokens
+                        .iter()
+                        .zip(words.iter())
+                        .all(|(token, word)| !token.quoted && token.text == *word);
+                if is_match {
+                    Some(words.len())
+                } else {
+                    None
+                }
+            })
+            .max()
+    }
+
+    /// Parse the tokens that follow the command name.
+    ///
+    /// # Arguments
+    /// `msg` - The chat message that was tokenized
+    /// `tokens` - The tokens after the command name
+    pub(in crate::chat) fn parse_args<'a>(
+        &self,
+        msg: &'a str,
+        tokens: &[Token<'a>],
+    ) -> Result<CommandArgs<'a>, ArgError> {
+        let mut values = Vec::with_capacity(self.args.len());
+        let mut idx = 0;
+
+        for arg in &self.args {
+            let token = match tokens.get(idx) {
+                Some(token) => token,
+                None if arg.optional => break,
+                None => return Err(ArgError::Missing(arg.name)),
+            };
+            idx += 1;
+
+            let value = match arg.kind {
+                ArgKind::Word | ArgKind::Player => ArgValue::Text(token.text),
+                ArgKind::Text if idx == tokens.len() => ArgValue::Text(token.text),
+                ArgKind::Text => {
+                    idx = tokens.len();
+                    ArgValue::Text(msg[token.start..].trim_end())
+                }
+                ArgKind::Number => match i64::from_str(token.text) {
+                    Ok(number) => ArgValue::Number(number),
+                    Err(_) => return Err(ArgError::NotANumber(arg.name)),
+                },
+                ArgKind::Duration => match parse_duration_secs(token.text) {
+                    Some(secs) => ArgValue::Duration(secs),
+                    None => return Err(ArgError::NotADuration(arg.name)),
+                },
+                ArgKind::Time => match NaiveTime::parse_from_str(token.text, "%H:%M") {
+                    Ok(time) => ArgValue::Time(time),
+                    Err(_) => return Err(ArgError::NotATime(arg.name)),
+                },
+            };
+            values.push((arg.name, value));
+        }
+
+        if idx < tokens.len() {
+            return Err(ArgError::TooMany);
+        }
+        Ok(CommandArgs(values))
+    }
+
+    /// A message with example arguments, that should be parsed as this command.
+    #[cfg(test)]
+    pub(in crate::chat) fn example(&self) -> String {
+        let mut example = self.name.to_string();
+        for arg in &self.args {
+            let value = match arg.kind {
+                ArgKind::Word | ArgKind::Text => "abc",
+                ArgKind::Player => "\"Some Player\"",
+                ArgKind::Number => "42",
+                ArgKind::Duration => "10m",
+                ArgKind::Time => "18:00",
+            };
+            example.push(' ');
+            example.push_str(value);
+        }
+        example
+    }
+
+    /// The detailed help for this command, shown with `/help <command>`.
+    pub(in crate::chat) fn help(&self) -> String {
+        let mut help = String::new();
+        help.push_str(&format!("{}\n", self.usage()));
+        help.push_str(&format!("{}\n", self.doc));
+
+        if let Some(details) = self.details {
+            help.push('\n');
+            help.push_str(&format!("{}\n", details));
+        }
+
+        if !self.args.is_empty() {
+            help.push('\n');
+            help.push_str("Arguments:\n");
+            for arg in &self.args {
+                let kind = match arg.kind {
+                    ArgKind::Word => "a word, or text in double quotes",
+                    ArgKind::Player => "a login or nickname, in double quotes if it has spaces",
+                    ArgKind::Number => "a whole number",
+                    ArgKind::Duration => "a duration like 30s, 10m, 2h or 1d",
+                    ArgKind::Time => "a time of day in UTC, like 18:00",
+                    ArgKind::Text => "any text",
+                };
+                let optional = if arg.optional { ", can be omitted" } else { "" };
+                help.push_str(&format!("  {}: {}{}\n", arg.name, kind, optional));
+            }
+        }
+
+        if !self.aliases.is_empty() {
+            help.push('\n');
+            help.push_str(&format!("Aliases: {}\n", self.aliases.join(", ")));
+        }
+
+        match self.min_role {
+            PlayerRole::Player => {}
+            PlayerRole::Admin => help.push_str("\nRequires: admin\n"),
+            PlayerRole::SuperAdmin => help.push_str("\nRequires: super admin\n"),
+        }
+        if let Some(mode) = &self.mode {
+            help.push_str(&format!("Only in {} mode\n", mode.name()));
+        }
+        help
+    }
+}
+
+impl<'a> CommandArgs<'a> {
+    fn get(&self, name: &str) -> Option<&ArgValue<'a>> {
+        self.0
+            .iter()
+            .find(|(arg_name, _)| *arg_name == name)
+            .map(|(_, value)| value)
+    }
+
+    /// The value of a `Word`, `Player` or `Text` argument.
+    ///
+    /// # Panics
+    /// Panics if the command spec has no such required argument.
+    pub(in crate::chat) fn text(&self, name: &str) -> &'a str {
+        self.opt_text(name)
+            .unwrap_or_else(|| panic!("missing argument <{}>", name))
+    }
+
+    /// The value of an optional `Word`, `Player` or `Text` argument,
+    /// or `None` if it was omitted.
+    pub(in crate::chat) fn opt_text(&self, name: &str) -> Option<&'a str> {
+        match self.get(name) {
+            Some(ArgValue::Text(text)) => Some(*text),
+            Some(_) => panic!("argument <{}> is not a text", name),
+            None => None,
+        }
+    }
+
+    /// The value of a `Number` argument.
+    ///
+    /// # Panics
+    /// Panics if the command spec has no such required argument.
+    pub(in crate::chat) fn number(&self, name: &str) -> i64 {
+        match self.get(name) {
+            Some(ArgValue::Number(number)) => *number,
+            _ => panic!("missing number argument <{}>", name),
+        }
+    }
+
+    /// The value of a `Duration` argument in seconds.
+    ///
+    /// # Panics
+    /// Panics if the command spec has no such required argument.
+    pub(in crate::chat) fn duration_secs(&self, name: &str) -> u64 {
+        match self.get(name) {
+            Some(ArgValue::Duration(secs)) => *secs,
+            _ => panic!("missing duration argument <{}>", name),
+        }
+    }
+
+    /// The value of an optional `Time` argument, or `None` if it was omitted.
+    pub(in crate::chat) fn opt_time(&self, name: &str) -> Option<NaiveTime> {
+        match self.get(name) {
+            Some(ArgValue::Time(time)) => Some(*time),
+            Some(_) => panic!("argument <{}> is not a time", name),
+            None => None,
+        }
+    }
+}
+
+impl Display for ArgError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use ArgError::*;
+
+        match self {
+            Missing(name) => write!(f, "The <{}> argument is missing.", name),
+            NotANumber(name) => write!(f, "The <{}> argument must be a number.", name),
+            NotADuration(name) => write!(
+                f,
+                "The <{}> argument must be a duration like 30s, 10m, 2h or 1d, of at most a year.",
+                name
+            ),
+            NotATime(name) => write!(
+                f,
+                "The <{}> argument must be a time of day like 18:00.",
+                name
+            ),
+            TooMany => write!(f, "There are too many arguments."),
+            Invalid => write!(f, "The arguments are invalid."),
+        }
+    }
+}
+
+/// Split a chat message into words, and texts in double quotes.
+///
+/// A quote that is not closed extends until the end of the message.
+pub(in crate::chat) fn tokenize(msg: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    loop {
+        let rest = &msg[pos..];
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            return tokens;
+        }
+        let start = pos + (rest.len() - trimmed.len());
+
+        if trimmed.starts_with('"') {
+            let quoted = &trimmed[1..];
+            let len = quoted.find('"').unwrap_or_else(|| quoted.len());
+            tokens.push(Token {
+                text: &quoted[..len],
+                start,
+                quoted: true,
+            });
+            pos = (start + len + 2).min(msg.len());
+        } else {
+            let len = trimmed
+                .find(char::is_whitespace)
+                .unwrap_or_else(|| trimmed.len());
+            tokens.push(Token {
+                text: &trimmed[..len],
+                start,
+                quoted: false,
+            });
+            pos = start + len;
+        }
+    }
+}
+
+/// Parse durations like `30s`, `10m`, `2h` or `1d` into seconds.
+/// Durations longer than `MAX_COMMAND_DURATION_SECS` are rejected.
+fn parse_duration_secs(duration: &str) -> Option<u64> {
+    let unit_idx = duration.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = duration.split_at(unit_idx);
+    let amount = u64::from_str(amount).ok()?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    amount
+        .checked_mul(unit_secs)
+        .filter(|secs| *secs > 0 && *secs <= MAX_COMMAND_DURATION_SECS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts<'a>(tokens: &[Token<'a>]) -> Vec<&'a str> {
+        tokens.iter().map(|token| token.text).collect()
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(Some(30), parse_duration_secs("30s"));
+        assert_eq!(Some(600), parse_duration_secs("10m"));
+        assert_eq!(Some(7200), parse_duration_secs("2h"));
+        assert_eq!(Some(86400), parse_duration_secs("1d"));
+        assert_eq!(None, parse_duration_secs("30"));
+        assert_eq!(None, parse_duration_secs("m"));
+        assert_eq!(None, parse_duration_secs("0m"));
+        assert_eq!(None, parse_duration_secs("5 min"));
+        assert_eq!(Some(365 * 86400), parse_duration_secs("365d"));
+        assert_eq!(None, parse_duration_secs("366d"));
+        assert_eq!(None, parse_duration_secs("100000000d"));
+        assert_eq!(None, parse_duration_secs("9999999999999999s"));
+    }
+
+    #[test]
+    fn tokenizes_quotes() {
+        assert_eq!(
+            vec!["/kick", "Speedy Gonzales"],
+            texts(&tokenize("  /kick \"Speedy Gonzales\" "))
+        );
+        assert_eq!(
+            vec!["/mute", "Speedy G", "10m"],
+            texts(&tokenize("/mute \"Speedy G\" 10m"))
+        );
+        assert_eq!(vec!["/kick", "open"], texts(&tokenize("/kick \"open")));
+        assert_eq!(vec!["/kick", ""], texts(&tokenize("/kick \"\"")));
+        assert!(tokenize("   ").is_empty());
+    }
+
+    #[test]
+    fn parses_typed_args() {
+        let spec = CommandSpec::new("/mute", "")
+            .arg("login/nick", ArgKind::Player)
+            .arg("duration", ArgKind::Duration);
+
+        let msg = "/mute \"Speedy G\" 2h";
+        let tokens = tokenize(msg);
+        assert_eq!(Some(1), spec.matched_name_len(&tokens));
+        let args = spec.parse_args(msg, &tokens[1..]).unwrap();
+        assert_eq!("Speedy G", args.text("login/nick"));
+        assert_eq!(7200, args.duration_secs("duration"));
+
+        let msg = "/mute someone soon";
+        let tokens = tokenize(msg);
+        assert_eq!(
+            Err(ArgError::NotADuration("duration")),
+            spec.parse_args(msg, &tokens[1..]).map(|_| ())
+        );
+
+        let msg = "/mute someone";
+        let tokens = tokenize(msg);
+        assert_eq!(
+            Err(ArgError::Missing("duration")),
+            spec.parse_args(msg, &tokens[1..]).map(|_| ())
+        );
+
+        let msg = "/mute someone 2h 3h";
+        let tokens = tokenize(msg);
+        assert_eq!(
+            Err(ArgError::TooMany),
+            spec.parse_args(msg, &tokens[1..]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn parses_optional_and_text_args() {
+        let spec = CommandSpec::new("/top", "").optional_arg("uid", ArgKind::Word);
+        let tokens = tokenize("/top");
+        let args = spec.parse_args("/top", &tokens[1..]).unwrap();
+        assert_eq!(None, args.opt_text("uid"));
+
+        let spec = CommandSpec::new("/whois", "").arg("name", ArgKind::Text);
+        let msg = "/whois  Speedy  Gonzales ";
+        let tokens = tokenize(msg);
+        let args = spec.parse_args(msg, &tokens[1..]).unwrap();
+        assert_eq!("Speedy  Gonzales", args.text("name"));
+    }
+
+    #[test]
+    fn parses_times() {
+        let spec = CommandSpec::new("/window", "").optional_arg("from", ArgKind::Time);
+        let msg = "/window 18:30";
+        let tokens = tokenize(msg);
+        let args = spec.parse_args(msg, &tokens[1..]).unwrap();
+        assert_eq!(Some(NaiveTime::from_hms(18, 30, 0)), args.opt_time("from"));
+
+        let msg = "/window 25:00";
+        let tokens = tokenize(msg);
+        assert_eq!(
+            Err(ArgError::NotATime("from")),
+            spec.parse_args(msg, &tokens[1..]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn matches_aliases() {
+        let spec = CommandSpec::new("/playlist add", "")
+            .alias("/pl add")
+            .arg("uid", ArgKind::Word);
+        assert_eq!(Some(2), spec.matched_name_len(&tokenize("/pl add x")));
+        assert_eq!(Some(2), spec.matched_name_len(&tokenize("/playlist add x")));
+        assert_eq!(None, spec.matched_name_len(&tokenize("/playlist")));
+        assert_eq!(None, spec.matched_name_len(&tokenize("\"/pl\" add")));
+        assert_eq!("/playlist add <uid>", spec.usage());
+    }
+}
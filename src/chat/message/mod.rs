@@ -1,5 +1,3 @@
-use std::fmt::Formatter;
-
 pub use to_player::*;
 pub use to_server::*;
 
@@ -16,33 +14,25 @@ pub(self) fn fmt_time(millis: usize) -> String {
     format!("{:02}:{:02}:{:03}", mins, secs, millis)
 }
 
-/// Either `"no <word>"`, `"one <word>"` or `"<amount> <word>s"`.
-pub(self) fn pluralize(word: &str, amount: usize) -> String {
-    let prefix = match amount {
-        0 => "no".to_string(),
-        1 => "one".to_string(),
-        n => n.to_string(),
-    };
-    let suffix = if amount == 1 { "" } else { "s" };
-    format!("{} {}{}", prefix, word, suffix)
-}
-
-pub(self) fn write_start_message(f: &mut Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{}{}🔊 ", RESET, NOTICE)
+/// The prefix of every message, that resets any formatting.
+pub(self) fn start_message() -> String {
+    format!("{}{}🔊 ", RESET, NOTICE)
 }
 
-pub(self) fn write_and_reset<T>(f: &mut Formatter<'_>, text: T) -> std::fmt::Result
+/// Format a text that has formatting of its own, like a display name or
+/// map name, so that it does not affect the rest of the message.
+pub(self) fn reset<T>(text: T) -> String
 where
     T: std::fmt::Display,
 {
-    write!(f, "{}{}{}{}", RESET, text, RESET, NOTICE)
+    format!("{}{}{}{}", RESET, text, RESET, NOTICE)
 }
 
-pub(self) fn write_highlighted<T>(f: &mut Formatter<'_>, text: T) -> std::fmt::Result
+pub(self) fn highlight<T>(text: T) -> String
 where
     T: std::fmt::Display,
 {
-    write!(f, "{}{}{}{}{}", RESET, HIGHLIGHT, text, RESET, NOTICE)
+    format!("{}{}{}{}{}", RESET, HIGHLIGHT, text, RESET, NOTICE)
 }
 
 const HIGHLIGHT: &str = "$fff$o";
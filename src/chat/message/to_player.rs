@@ -2,7 +2,9 @@ use std::fmt::Display;
 
 use serde::export::Formatter;
 
-use crate::chat::message::{pluralize, write_start_message};
+use crate::chat::message::{fmt_time, highlight, reset, start_message};
+use crate::constants::DEFAULT_LANGUAGE;
+use crate::locale::{language_name, supported_languages, translate, translate_count};
 
 /// Chat messages from the controller to a specific player.
 ///
@@ -11,35 +13,235 @@ use crate::chat::message::{pluralize, write_start_message};
 pub enum PlayerMessage {
     /// Remind a player to change their preferences to influence the queue.
     PreferenceReminder { nb_active_preferences: usize },
+
+    /// Recommend an admin to remove a map from the playlist,
+    /// since too many players voted to remove it.
+    CurationRecommendation {
+        map_name: String,
+        map_uid: String,
+        remove_ratio: f32,
+    },
+
+    /// Tell an admin that a player had many incoherent runs recently.
+    FrequentIncoherences {
+        player_name: String,
+        player_login: String,
+        nb_incoherences: i64,
+    },
+
+    /// Tell an admin that another admin has approved or rejected a flagged record.
+    FlaggedRecordReviewed {
+        admin_name: String,
+        player_name: String,
+        map_name: String,
+        millis: usize,
+        approved: bool,
+    },
+
+    /// Tell a player that their run was rejected, since it is invalid.
+    RunRejected { reason: String },
+
+    /// Tell a player that their run has to be reviewed by an admin,
+    /// before it counts as their personal best.
+    RunFlagged { reason: String },
+
+    /// Tell a player that they improved their flying lap record.
+    FlyingLapPb { millis: usize, map_rank: usize },
+
+    /// Warn an idle player that they will be moved to a spectator slot.
+    AfkWarning { secs_left: i64 },
+
+    /// Tell a super admin that changes to the config file were not applied.
+    ConfigRejected { reason: String },
+
+    /// Tell a muted player that their chat message was not delivered.
+    ChatMuted { secs_left: i64 },
+
+    /// Tell a player that they were muted for sending too many messages.
+    ChatFlooding { mute_secs: i64 },
+
+    /// Tell a player that their chat message was not delivered,
+    /// since it contained a blocked word.
+    ChatBlockedWord { word: String },
+
+    /// Tell a player which language they selected, and which languages
+    /// are available.
+    LanguageInfo { language: String },
+
+    /// Tell a player that they selected another language.
+    LanguageChanged { language: String },
+
+    /// Tell a player that the language they tried to select is not available.
+    UnknownLanguage { code: String },
+
+    /// Tell a player an announcement that was added by an admin.
+    /// The text is displayed as is, in every language.
+    Announcement { text: String },
 }
 
-impl Display for PlayerMessage {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl PlayerMessage {
+    /// Render this message in the given language.
+    pub fn localize(&self, lang: &str) -> String {
         use PlayerMessage::*;
 
-        write_start_message(f)?;
+        let tr = |key: &str, args: &[(&str, &dyn Display)]| translate(lang, key, args);
 
-        match self {
+        let text = match self {
             PreferenceReminder {
                 nb_active_preferences: 0,
-            } => {
-                write!(f, "Don't like this map? ")?;
-                write!(f, "Make sure to set your preferences in the map list.")
-            }
+            } => tr("chat.no_preferences", &[]),
 
             PreferenceReminder {
                 nb_active_preferences: nb,
-            } => {
-                write!(
-                    f,
-                    "You are influencing the map queue with {}. ",
-                    pluralize("preference", *nb)
-                )?;
-                write!(
-                    f,
-                    "Make sure to change them to your liking by bringing up the map list."
-                )
+            } => tr(
+                "chat.preference_reminder",
+                &[(
+                    "preferences",
+                    &translate_count(lang, "units.preference", *nb),
+                )],
+            ),
+
+            CurationRecommendation {
+                map_name,
+                map_uid,
+                remove_ratio,
+            } => tr(
+                "chat.curation_recommendation",
+                &[
+                    ("ratio", &highlight(format!("{:.0}%", remove_ratio * 100.0))),
+                    ("map", &reset(map_name)),
+                    (
+                        "command",
+                        &highlight(format!("/playlist remove {}", map_uid)),
+                    ),
+                ],
+            ),
+
+            FrequentIncoherences {
+                player_name,
+                player_login,
+                nb_incoherences,
+            } => tr(
+                "chat.frequent_incoherences",
+                &[
+                    ("name", &reset(player_name)),
+                    ("login", player_login),
+                    (
+                        "runs",
+                        &highlight(translate_count(
+                            lang,
+                            "units.incoherent_run",
+                            *nb_incoherences as usize,
+                        )),
+                    ),
+                ],
+            ),
+
+            FlaggedRecordReviewed {
+                admin_name,
+                player_name,
+                map_name,
+                millis,
+                approved,
+            } => tr(
+                if *approved {
+                    "chat.flagged_record_approved"
+                } else {
+                    "chat.flagged_record_rejected"
+                },
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("time", &highlight(fmt_time(*millis))),
+                    ("name", &reset(player_name)),
+                    ("map", &reset(map_name)),
+                ],
+            ),
+
+            RunRejected { reason } => tr("chat.run_rejected", &[("reason", reason)]),
+
+            RunFlagged { reason } => tr("chat.run_flagged", &[("reason", reason)]),
+
+            FlyingLapPb { millis, map_rank } => tr(
+                "chat.flying_lap_pb",
+                &[
+                    ("time", &highlight(fmt_time(*millis))),
+                    ("rank", &highlight(map_rank)),
+                ],
+            ),
+
+            AfkWarning { secs_left } => {
+                let secs = translate_count(lang, "units.second", *secs_left as usize);
+                tr("chat.afk_warning", &[("duration", &highlight(secs))])
             }
-        }
+
+            ConfigRejected { reason } => tr("chat.config_rejected", &[("reason", reason)]),
+
+            ChatMuted { secs_left } => tr(
+                "chat.chat_muted",
+                &[("duration", &highlight(fmt_secs(lang, *secs_left)))],
+            ),
+
+            ChatFlooding { mute_secs } => tr(
+                "chat.chat_flooding",
+                &[("duration", &highlight(fmt_secs(lang, *mute_secs)))],
+            ),
+
+            ChatBlockedWord { word } => tr(
+                "chat.chat_blocked_word",
+                &[("word", &highlight(format!("\"{}\"", word)))],
+            ),
+
+            LanguageInfo { language } => tr(
+                "chat.language_info",
+                &[
+                    ("language", &highlight(fmt_language(language))),
+                    ("languages", &fmt_languages()),
+                    ("command", &highlight("/language <code>")),
+                ],
+            ),
+
+            LanguageChanged { language } => tr(
+                "chat.language_changed",
+                &[("language", &highlight(fmt_language(language)))],
+            ),
+
+            UnknownLanguage { code } => tr(
+                "chat.unknown_language",
+                &[("code", &highlight(code)), ("languages", &fmt_languages())],
+            ),
+
+            Announcement { text } => text.clone(),
+        };
+
+        format!("{}{}", start_message(), text)
     }
 }
+
+/// Messages are displayed in the `DEFAULT_LANGUAGE`, f.e. in logs.
+impl Display for PlayerMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.localize(DEFAULT_LANGUAGE))
+    }
+}
+
+/// Format a number of seconds as minutes if it is large enough.
+fn fmt_secs(lang: &str, secs: i64) -> String {
+    if secs >= 120 {
+        translate_count(lang, "units.minute", (secs / 60) as usize)
+    } else {
+        translate_count(lang, "units.second", secs as usize)
+    }
+}
+
+/// Format a language code with its name, f.e. `Deutsch (de)`.
+fn fmt_language(code: &str) -> String {
+    match language_name(code) {
+        Some(name) => format!("{} ({})", name, code),
+        None => code.to_string(),
+    }
+}
+
+/// List the codes of all supported languages, f.e. `de, en, fr`.
+fn fmt_languages() -> String {
+    supported_languages().join(", ")
+}
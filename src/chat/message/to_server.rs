@@ -3,8 +3,11 @@ use std::fmt::Display;
 use chrono::Duration;
 use serde::export::Formatter;
 
-use crate::chat::message::{fmt_time, write_and_reset, write_highlighted, write_start_message};
+use crate::chat::message::{fmt_time, highlight, reset, start_message};
+use crate::chat::RaceVoteKind;
+use crate::constants::DEFAULT_LANGUAGE;
 use crate::database::Map;
+use crate::locale::{translate, translate_count};
 use crate::server::ModeScript;
 
 /// Chat announcements from the controller to all players.
@@ -45,6 +48,37 @@ pub enum ServerMessage<'a> {
     /// A map was removed from the playlist.
     RemovedMap { admin_name: &'a str, map: &'a Map },
 
+    /// Tell players that an admin stored the playlist as a map pool.
+    CreatedPool {
+        admin_name: &'a str,
+        pool_name: &'a str,
+    },
+
+    /// Tell players that an admin replaced the playlist with a map pool.
+    SwitchedPool {
+        admin_name: &'a str,
+        pool_name: &'a str,
+    },
+
+    /// Tell players that the playlist was replaced with a scheduled map pool.
+    ScheduledPool { pool_name: &'a str },
+
+    /// Tell players that a map was removed from the playlist,
+    /// because too many players voted to remove it.
+    CuratedMap { map: &'a Map },
+
+    /// Tell players that a player started a vote during the race.
+    RaceVoteStarted {
+        player_name: &'a str,
+        kind: RaceVoteKind,
+    },
+
+    /// Tell players that enough players voted in favour of a race vote.
+    RaceVotePassed { kind: RaceVoteKind },
+
+    /// Tell players that not enough players voted in favour of a race vote.
+    RaceVoteFailed { kind: RaceVoteKind },
+
     /// Announce the next map after the vote.
     NextMap { map: &'a Map },
 
@@ -72,6 +106,13 @@ pub enum ServerMessage<'a> {
         player_name: &'a str,
     },
 
+    /// Tell players that an admin has removed a player's record.
+    RecordRemoved {
+        admin_name: &'a str,
+        player_name: &'a str,
+        map_name: &'a str,
+    },
+
     /// Tell players that an admin has forced a restart of the current map.
     ForceRestart { admin_name: &'a str },
 
@@ -102,12 +143,28 @@ pub enum ServerMessage<'a> {
         player_name: &'a str,
     },
 
+    /// Tell players that an admin muted a player.
+    PlayerMuted {
+        admin_name: &'a str,
+        player_name: &'a str,
+    },
+
+    /// Tell players that an admin unmuted a player.
+    PlayerUnmuted {
+        admin_name: &'a str,
+        player_name: &'a str,
+    },
+
     /// Tell players that an admin move a player to spectator
     PlayerMovedToSpectator {
         admin_name: &'a str,
         player_name: &'a str,
     },
 
+    /// Tell players that an idle player was moved to spectator,
+    /// to make room for other players.
+    IdlePlayerMovedToSpectator { player_name: &'a str },
+
     /// Tell players that an admin changed the game mode for the next map.
     ModeChanging {
         admin_name: &'a str,
@@ -125,6 +182,10 @@ pub enum ServerMessage<'a> {
         admin_name: &'a str,
         settings_name: &'a str,
     },
+
+    /// An announcement that was added by an admin.
+    /// The text is displayed as is, in every language.
+    Announcement { text: &'a str },
 }
 
 /// A player improved their rank, and took one of the top spots.
@@ -133,243 +194,310 @@ pub struct TopRankMessage<'a> {
     pub rank: usize,
 }
 
-impl Display for ServerMessage<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl ServerMessage<'_> {
+    /// Render this message in the given language, or return an empty string
+    /// if there is nothing to announce.
+    pub fn localize(&self, lang: &str) -> String {
         use ServerMessage::*;
 
-        match self {
-            NewTopRanks(top_ranks) if top_ranks.is_empty() => return Ok(()),
-            _ => {}
-        }
+        let tr = |key: &str, args: &[(&str, &dyn Display)]| translate(lang, key, args);
 
-        write_start_message(f)?;
+        let text = match self {
+            NewTopRanks(top_ranks) if top_ranks.is_empty() => return String::new(),
 
-        match self {
-            Joining { display_name } => {
-                write_and_reset(f, display_name)?;
-                write!(f, " joined.")
-            }
+            Joining { display_name } => tr("chat.joining", &[("name", &reset(display_name))]),
 
-            Leaving { display_name } => {
-                write_and_reset(f, display_name)?;
-                write!(f, " left.")
-            }
+            Leaving { display_name } => tr("chat.leaving", &[("name", &reset(display_name))]),
 
-            NewTopRanks(top_ranks) => {
-                for tr in top_ranks {
-                    write_and_reset(f, tr.display_name)?;
-                    write!(f, " reaches rank ")?;
-                    write_highlighted(f, tr.rank)?;
-                    write!(f, "!")?;
-                }
-                Ok(())
-            }
+            NewTopRanks(top_ranks) => top_ranks
+                .iter()
+                .map(|top_rank| {
+                    translate(
+                        lang,
+                        "chat.new_top_rank",
+                        &[
+                            ("name", &reset(top_rank.display_name)),
+                            ("rank", &highlight(top_rank.rank)),
+                        ],
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
 
             TopRecord {
                 player_display_name: display_name,
                 new_map_rank: new_record_rank,
                 millis,
-            } => {
-                write_and_reset(f, display_name)?;
-                write!(f, " sets the ")?;
-                write_highlighted(f, format!("{}.", new_record_rank))?;
-                write!(f, " record! ")?;
-                write_highlighted(f, fmt_time(*millis))
-            }
+            } => tr(
+                "chat.top_record",
+                &[
+                    ("name", &reset(display_name)),
+                    ("rank", &highlight(format!("{}.", new_record_rank))),
+                    ("time", &highlight(fmt_time(*millis))),
+                ],
+            ),
 
             TopRecordImproved {
                 player_display_name: display_name,
                 map_rank: record_rank,
                 millis,
-            } => {
-                write_and_reset(f, display_name)?;
-                write!(f, " improved the ")?;
-                write_highlighted(f, format!("{}.", record_rank))?;
-                write!(f, " record! ")?;
-                write_highlighted(f, fmt_time(*millis))
+            } => tr(
+                "chat.top_record_improved",
+                &[
+                    ("name", &reset(display_name)),
+                    ("rank", &highlight(format!("{}.", record_rank))),
+                    ("time", &highlight(fmt_time(*millis))),
+                ],
+            ),
+
+            NewMap { admin_name, map } => tr(
+                "chat.new_map",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("map", &reset(&map.name.formatted)),
+                    ("author", &reset(&map.author_display_name.formatted)),
+                ],
+            ),
+
+            AddedMap { admin_name, map } => tr(
+                "chat.added_map",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("map", &reset(&map.name.formatted)),
+                ],
+            ),
+
+            RemovedMap { admin_name, map } => tr(
+                "chat.removed_map",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("map", &reset(&map.name.formatted)),
+                ],
+            ),
+
+            CreatedPool {
+                admin_name,
+                pool_name,
+            } => tr(
+                "chat.created_pool",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("pool", &highlight(pool_name)),
+                ],
+            ),
+
+            SwitchedPool {
+                admin_name,
+                pool_name,
+            } => tr(
+                "chat.switched_pool",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("pool", &highlight(pool_name)),
+                ],
+            ),
+
+            ScheduledPool { pool_name } => {
+                tr("chat.scheduled_pool", &[("pool", &highlight(pool_name))])
             }
 
-            NewMap { admin_name, map } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " imported ")?;
-                write_and_reset(f, &map.name.formatted)?;
-                write!(f, " by ")?;
-                write_and_reset(f, &map.author_display_name.formatted)
-            }
+            CuratedMap { map } => tr("chat.curated_map", &[("map", &reset(&map.name.formatted))]),
 
-            AddedMap { admin_name, map } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " added ")?;
-                write_and_reset(f, &map.name.formatted)?;
-                write!(f, " to the playlist.")
-            }
+            RaceVoteStarted {
+                player_name,
+                kind: RaceVoteKind::Skip,
+            } => tr("chat.skip_vote_started", &[("name", &reset(player_name))]),
 
-            RemovedMap { admin_name, map } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " removed ")?;
-                write_and_reset(f, &map.name.formatted)?;
-                write!(f, " from the playlist.")
-            }
+            RaceVoteStarted {
+                player_name,
+                kind: RaceVoteKind::Extend,
+            } => tr("chat.extend_vote_started", &[("name", &reset(player_name))]),
 
-            NextMap { map } => {
-                write!(f, "Next map will be ")?;
-                write_and_reset(f, &map.name.formatted)?;
-                write!(f, " by ")?;
-                write_and_reset(f, &map.author_display_name.formatted)
-            }
+            RaceVotePassed {
+                kind: RaceVoteKind::Skip,
+            } => tr("chat.skip_vote_passed", &[]),
 
-            VoteNow { threshold, .. } if *threshold > 1f32 => {
-                write!(f, "This map will not be restarted.")
-            }
+            RaceVotePassed {
+                kind: RaceVoteKind::Extend,
+            } => tr("chat.extend_vote_passed", &[]),
+
+            RaceVoteFailed {
+                kind: RaceVoteKind::Skip,
+            } => tr("chat.skip_vote_failed", &[]),
+
+            RaceVoteFailed {
+                kind: RaceVoteKind::Extend,
+            } => tr("chat.extend_vote_failed", &[]),
+
+            NextMap { map } => tr(
+                "chat.next_map",
+                &[
+                    ("map", &reset(&map.name.formatted)),
+                    ("author", &reset(&map.author_display_name.formatted)),
+                ],
+            ),
+
+            VoteNow { threshold, .. } if *threshold > 1f32 => tr("chat.no_restart", &[]),
 
             VoteNow { duration, .. } => {
-                write!(f, "Vote for a restart in the next ")?;
-                write_highlighted(f, format!("{} seconds", duration.num_seconds()))?;
-                write!(f, "!")
+                let secs = translate_count(lang, "units.second", duration.num_seconds() as usize);
+                tr("chat.restart_vote", &[("duration", &highlight(secs))])
             }
 
             CurrentMapSkipped { admin_name } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " skipped the current map!")
+                tr("chat.map_skipped", &[("admin", &reset(admin_name))])
             }
 
             MapDeleted {
                 admin_name,
                 map_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " deleted map ")?;
-                write_and_reset(f, map_name)?;
-                write!(f, " and all of its records!")
-            }
+            } => tr(
+                "chat.map_deleted",
+                &[("admin", &reset(admin_name)), ("map", &reset(map_name))],
+            ),
 
             PlayerBlacklisted {
                 admin_name,
                 player_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " blacklisted player ")?;
-                write_and_reset(f, player_name)?;
-                write!(f, "!")
-            }
+            } => tr(
+                "chat.player_blacklisted",
+                &[("admin", &reset(admin_name)), ("name", &reset(player_name))],
+            ),
+
+            RecordRemoved {
+                admin_name,
+                player_name,
+                map_name,
+            } => tr(
+                "chat.record_removed",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("name", &reset(player_name)),
+                    ("map", &reset(map_name)),
+                ],
+            ),
 
             PlayerUnblacklisted {
                 admin_name,
                 player_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " un-blacklisted player ")?;
-                write_and_reset(f, player_name)?;
-                write!(f, "!")
-            }
+            } => tr(
+                "chat.player_unblacklisted",
+                &[("admin", &reset(admin_name)), ("name", &reset(player_name))],
+            ),
 
             ForceRestart { admin_name } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " forced a map restart!")
+                tr("chat.force_restart", &[("admin", &reset(admin_name))])
             }
 
             ForceQueued {
                 admin_name,
                 map_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " queued map ")?;
-                write_and_reset(f, map_name)?;
-                write!(f, "!")
-            }
+            } => tr(
+                "chat.force_queued",
+                &[("admin", &reset(admin_name)), ("map", &reset(map_name))],
+            ),
 
             TimeLimitChanged { admin_name } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " changed the time limit settings!")
+                tr("chat.time_limit_changed", &[("admin", &reset(admin_name))])
             }
 
-            MatchPaused { admin_name } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " paused the match!")
-            }
+            MatchPaused { admin_name } => tr("chat.match_paused", &[("admin", &reset(admin_name))]),
 
             MatchUnpaused { admin_name } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " unpaused the match!")
+                tr("chat.match_unpaused", &[("admin", &reset(admin_name))])
             }
 
             WarmupRoundExtended { admin_name, secs } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " extended the warmup by ")?;
-                write_highlighted(f, format!("{} seconds", secs))?;
-                write!(f, "!")
+                let secs = translate_count(lang, "units.second", *secs as usize);
+                tr(
+                    "chat.warmup_extended",
+                    &[
+                        ("admin", &reset(admin_name)),
+                        ("duration", &highlight(secs)),
+                    ],
+                )
             }
 
             WarmupSkipped { admin_name } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " skipped the warmup!")
+                tr("chat.warmup_skipped", &[("admin", &reset(admin_name))])
             }
 
-            ModeChanging { admin_name, mode } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " changed the game mode to ")?;
-                write_highlighted(f, mode.name())?;
-                write!(f, "!")
-            }
+            ModeChanging { admin_name, mode } => tr(
+                "chat.mode_changing",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("mode", &highlight(mode.name())),
+                ],
+            ),
 
             LoadedMatchSettings {
                 admin_name,
                 settings_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " loaded the ")?;
-                write_highlighted(f, settings_name)?;
-                write!(f, " match settings!")
-            }
+            } => tr(
+                "chat.loaded_match_settings",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("settings", &highlight(settings_name)),
+                ],
+            ),
 
             SavedMatchSettings {
                 admin_name,
                 settings_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " saved the current match settings in ")?;
-                write_highlighted(f, settings_name)?;
-                write!(f, "!")
-            }
+            } => tr(
+                "chat.saved_match_settings",
+                &[
+                    ("admin", &reset(admin_name)),
+                    ("settings", &highlight(settings_name)),
+                ],
+            ),
 
             PlayerKicked {
                 admin_name,
                 player_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " kicked ")?;
-                write_and_reset(f, player_name)?;
-                write!(f, ".")
+            } => tr(
+                "chat.player_kicked",
+                &[("admin", &reset(admin_name)), ("name", &reset(player_name))],
+            ),
+
+            PlayerMuted {
+                admin_name,
+                player_name,
+            } => tr(
+                "chat.player_muted",
+                &[("admin", &reset(admin_name)), ("name", &reset(player_name))],
+            ),
+
+            PlayerUnmuted {
+                admin_name,
+                player_name,
+            } => tr(
+                "chat.player_unmuted",
+                &[("admin", &reset(admin_name)), ("name", &reset(player_name))],
+            ),
+
+            IdlePlayerMovedToSpectator { player_name } => {
+                tr("chat.idle_player_moved", &[("name", &reset(player_name))])
             }
 
             PlayerMovedToSpectator {
                 admin_name,
                 player_name,
-            } => {
-                write!(f, "Admin ")?;
-                write_and_reset(f, admin_name)?;
-                write!(f, " moved ")?;
-                write_and_reset(f, player_name)?;
-                write!(f, " to spectator.")
-            }
-        }
+            } => tr(
+                "chat.player_moved_to_spectator",
+                &[("admin", &reset(admin_name)), ("name", &reset(player_name))],
+            ),
+
+            Announcement { text } => text.to_string(),
+        };
+
+        format!("{}{}", start_message(), text)
+    }
+}
+
+/// Messages are displayed in the `DEFAULT_LANGUAGE`, f.e. in logs.
+impl Display for ServerMessage<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.localize(DEFAULT_LANGUAGE))
     }
 }
@@ -1,13 +1,16 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::SystemTime;
 
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use toml_edit::{decorated, Document, Item, Table};
 
-use crate::constants::{CONFIG_ENV_VAR, VERSION, VOTE_DURATION_RATIO};
+use crate::constants::{CONFIG_ENV_VAR, VOTE_DURATION_RATIO};
 
 /// Controller config.
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Config {
     /// The address of the game server's XML-RPC port, f.e. "127.0.0.1:5000".
     ///
@@ -40,6 +43,42 @@ pub struct Config {
     /// List of player logins that can execute admin commands.
     pub admin_whitelist: Vec<String>,
 
+    /// The records that decide map ranks on multi-lap maps.
+    #[serde(default)]
+    pub ranked_records: RankedRecords,
+
+    /// The formula that is used to calculate the server ranking.
+    #[serde(default)]
+    pub server_ranking: RankingStrategy,
+
+    /// Periods that have their own server ranking, in addition to the all-time ranking.
+    #[serde(default)]
+    pub seasons: SeasonConfig,
+
+    /// Decides when maps are removed from the playlist due to player votes.
+    #[serde(default)]
+    pub curation: CurationConfig,
+
+    /// Decides when idle players are moved to spectator slots.
+    #[serde(default)]
+    pub afk: AfkConfig,
+
+    /// Decides when admins are notified about players with incoherent runs.
+    #[serde(default)]
+    pub incoherence: IncoherenceConfig,
+
+    /// Decides which records are implausible, and have to be reviewed by admins.
+    #[serde(default)]
+    pub record_checks: RecordCheckConfig,
+
+    /// Decides which chat messages are censored or blocked.
+    #[serde(default)]
+    pub chat: ChatConfig,
+
+    /// Activates map pools at given times of the week.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pool_calendar: Vec<PoolActivation>,
+
     /// Controller config for the TimeAttack mode.
     pub timeattack: TimeAttackConfig,
 }
@@ -53,30 +92,100 @@ impl Config {
     /// - when the file cannot be parsed
     pub fn load() -> Config {
         let f = Self::expect_path();
-        let f_str = std::fs::read_to_string(f).expect("failed to read config file");
-        let cfg: Config = toml::from_str(&f_str).expect("failed to parse config file");
-        cfg.timeattack.check().expect("invalid config file");
-        cfg
+        Self::read(&f).expect("invalid config file")
+    }
+
+    /// Read the config file listed in the `STEWARD_CONFIG` environment variable,
+    /// or return an error if it is missing, or not a valid config.
+    pub fn reload() -> Result<Config, ConfigError> {
+        let f = Self::path().ok_or(ConfigError::MissingFile)?;
+        Self::read(&f)
+    }
+
+    fn read(f: &Path) -> Result<Config, ConfigError> {
+        let f_str = std::fs::read_to_string(f)?;
+        let cfg: Config = toml::from_str(&f_str)?;
+        cfg.public().check()?;
+        Ok(cfg)
+    }
+
+    /// The parts of this config that admins can edit in-game.
+    pub fn public(&self) -> PublicConfig {
+        PublicConfig {
+            ranked_records: self.ranked_records,
+            server_ranking: self.server_ranking.clone(),
+            seasons: self.seasons.clone(),
+            curation: self.curation.clone(),
+            afk: self.afk,
+            incoherence: self.incoherence,
+            record_checks: self.record_checks,
+            chat: self.chat.clone(),
+            pool_calendar: self.pool_calendar.clone(),
+            timeattack: self.timeattack,
+        }
+    }
+
+    /// Replace the parts of this config that admins can edit in-game.
+    pub fn set_public(&mut self, public: PublicConfig) {
+        self.ranked_records = public.ranked_records;
+        self.server_ranking = public.server_ranking;
+        self.seasons = public.seasons;
+        self.curation = public.curation;
+        self.afk = public.afk;
+        self.incoherence = public.incoherence;
+        self.record_checks = public.record_checks;
+        self.chat = public.chat;
+        self.pool_calendar = public.pool_calendar;
+        self.timeattack = public.timeattack;
+    }
+
+    /// The last time the config file listed in the `STEWARD_CONFIG` environment
+    /// variable was modified, or `None` if it cannot be determined.
+    pub fn modified_time() -> Option<SystemTime> {
+        let f = Self::path()?;
+        std::fs::metadata(f).and_then(|meta| meta.modified()).ok()
+    }
+
+    /// Check that replacing this config with the given one does not change
+    /// any of the fields that are only used when starting the controller.
+    pub fn check_reload(&self, new_cfg: &Config) -> Result<(), ConfigError> {
+        use ConfigError::RequiresRestart;
+
+        if self.rpc_address != new_cfg.rpc_address {
+            return Err(RequiresRestart("rpc_address"));
+        }
+        if self.rpc_login != new_cfg.rpc_login {
+            return Err(RequiresRestart("rpc_login"));
+        }
+        if self.rpc_password != new_cfg.rpc_password {
+            return Err(RequiresRestart("rpc_password"));
+        }
+        if self.postgres_connection != new_cfg.postgres_connection {
+            return Err(RequiresRestart("postgres_connection"));
+        }
+        Ok(())
     }
 
     /// Overwrite the config file listed in the `STEWARD_CONFIG` environment variable.
     ///
+    /// Only changed values are replaced, so that the comments and the formatting
+    /// of the file are preserved.
+    ///
     /// # Panics
     /// - when `STEWARD_CONFIG` is not set
+    /// - when the file cannot be parsed
     /// - when the file cannot be overwritten
     pub fn save(&self) {
-        let mut config_str = toml::to_string(&self).expect("failed to compose config file");
+        let f = Self::expect_path();
+        let f_str = std::fs::read_to_string(&f).expect("failed to read config file");
+        let mut doc: Document = f_str.parse().expect("failed to parse config file");
 
-        // Since all comments are removed from a previous config file,
-        // we can at least add a link to the default config.
-        let reference_link = format!(
-            "# Reference: https://github.com/timwie/steward/blob/v{}/config/steward.toml\n",
-            VERSION.to_string()
-        );
-        config_str.insert_str(0, &reference_link);
+        let new_str = toml::to_string(&self).expect("failed to compose config file");
+        let new_doc: Document = new_str.parse().expect("failed to compose config file");
 
-        let f = Self::expect_path();
-        std::fs::write(f, config_str).expect("failed to overwrite config file");
+        merge_toml(doc.as_table_mut(), new_doc.as_table());
+
+        std::fs::write(f, doc.to_string()).expect("failed to overwrite config file");
     }
 
     fn path() -> Option<PathBuf> {
@@ -91,6 +200,37 @@ impl Config {
             .unwrap_or_else(|| panic!("cannot locate config: use the '{}' env var", CONFIG_ENV_VAR))
     }
 
+    /// Returns the most recent pool activation at or before the given time,
+    /// or `None` if there is none within the last week.
+    pub fn scheduled_pool_at(&self, time: &NaiveDateTime) -> Option<(NaiveDateTime, &str)> {
+        (0..=7)
+            .flat_map(|days| self.pool_activations_on(time.date() - Duration::days(days)))
+            .filter(|(at, _)| at <= time)
+            .max_by_key(|(at, _)| *at)
+    }
+
+    /// Returns the next activation after the given time that changes the
+    /// scheduled pool, or `None` if there is none within the next week.
+    pub fn next_pool_switch(&self, time: &NaiveDateTime) -> Option<(NaiveDateTime, &str)> {
+        let curr_pool = self.scheduled_pool_at(time).map(|(_, pool)| pool);
+        let mut upcoming: Vec<(NaiveDateTime, &str)> = (0..=7)
+            .flat_map(|days| self.pool_activations_on(time.date() + Duration::days(days)))
+            .filter(|(at, _)| at > time)
+            .collect();
+        upcoming.sort_by_key(|(at, _)| *at);
+        upcoming
+            .into_iter()
+            .find(|(_, pool)| Some(*pool) != curr_pool)
+    }
+
+    fn pool_activations_on(&self, date: NaiveDate) -> Vec<(NaiveDateTime, &str)> {
+        self.pool_calendar
+            .iter()
+            .filter(|a| a.weekdays.is_empty() || a.weekdays.contains(&date.weekday()))
+            .map(|a| (date.and_time(a.at), a.pool.as_str()))
+            .collect()
+    }
+
     pub fn role_of(&self, player_login: &str) -> PlayerRole {
         if self
             .super_admin_whitelist
@@ -110,6 +250,136 @@ impl Config {
     }
 }
 
+/// The parts of the controller config that admins can edit in-game.
+///
+/// This excludes the connection settings, which are secret, and the
+/// admin whitelists, so that admins cannot change their own permissions.
+/// See `Config` for the documentation of each section.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PublicConfig {
+    #[serde(default)]
+    pub ranked_records: RankedRecords,
+
+    #[serde(default)]
+    pub server_ranking: RankingStrategy,
+
+    #[serde(default)]
+    pub seasons: SeasonConfig,
+
+    #[serde(default)]
+    pub curation: CurationConfig,
+
+    #[serde(default)]
+    pub afk: AfkConfig,
+
+    #[serde(default)]
+    pub incoherence: IncoherenceConfig,
+
+    #[serde(default)]
+    pub record_checks: RecordCheckConfig,
+
+    #[serde(default)]
+    pub chat: ChatConfig,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pool_calendar: Vec<PoolActivation>,
+
+    pub timeattack: TimeAttackConfig,
+}
+
+impl PublicConfig {
+    fn check(&self) -> Result<(), ConfigError> {
+        self.timeattack.check()?;
+        self.server_ranking.check()?;
+        self.seasons.check()?;
+        self.curation.check()?;
+        self.afk.check()?;
+        self.record_checks.check()?;
+        self.chat.check()?;
+        Ok(())
+    }
+}
+
+impl ToString for PublicConfig {
+    fn to_string(&self) -> String {
+        toml::to_string(&self).expect("failed to serialize PublicConfig")
+    }
+}
+
+impl FromStr for PublicConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cfg: PublicConfig = toml::from_str(s)?;
+        cfg.check()?;
+        Ok(cfg)
+    }
+}
+
+/// Update the values in `doc` to the ones in `new_doc`, but keep the comments
+/// and the formatting of values that did not change.
+fn merge_toml(doc: &mut Table, new_doc: &Table) {
+    let removed_keys: Vec<String> = doc
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new_doc.contains_key(key))
+        .collect();
+    for key in removed_keys {
+        doc.remove(&key);
+    }
+
+    for (key, new_item) in new_doc.iter() {
+        match (doc.entry(key), new_item) {
+            (Item::Table(table), Item::Table(new_table)) => merge_toml(table, new_table),
+            (Item::Value(value), Item::Value(new_value)) => {
+                if value.to_string().trim() != new_value.to_string().trim() {
+                    let prefix = value.decor().prefix().to_string();
+                    let suffix = value.decor().suffix().to_string();
+                    *value = decorated(new_value.clone(), &prefix, &suffix);
+                }
+            }
+            (item, new_item) => *item = new_item.clone(),
+        }
+    }
+}
+
+/// Failed checks when reading the controller config.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("cannot locate config file")]
+    MissingFile,
+
+    #[error("failed to read config file")]
+    ReadError(#[from] std::io::Error),
+
+    #[error("bad config format: {0}")]
+    ParseError(#[from] toml::de::Error),
+
+    #[error("'{0}' cannot be changed without restarting the controller")]
+    RequiresRestart(&'static str),
+
+    #[error("bad 'timeattack' config: {0}")]
+    TimeAttack(#[from] TimeAttackConfigError),
+
+    #[error("bad 'server_ranking' config: {0}")]
+    RankingStrategy(#[from] RankingStrategyError),
+
+    #[error("bad 'seasons' config: {0}")]
+    Seasons(#[from] SeasonConfigError),
+
+    #[error("bad 'curation' config: {0}")]
+    Curation(#[from] CurationConfigError),
+
+    #[error("bad 'afk' config: {0}")]
+    Afk(#[from] AfkConfigError),
+
+    #[error("bad 'record_checks' config: {0}")]
+    RecordChecks(#[from] RecordCheckConfigError),
+
+    #[error("bad 'chat' config: {0}")]
+    Chat(#[from] ChatConfigError),
+}
+
 /// Player permission level.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
 pub enum PlayerRole {
@@ -118,8 +388,485 @@ pub enum PlayerRole {
     SuperAdmin,
 }
 
+/// Decides which records are ranked on multi-lap maps, in the server ranking
+/// as well as in seasonal rankings.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankedRecords {
+    /// Rank records of full races, with all laps.
+    Race,
+
+    /// Rank the best flying lap of each player, which is the best lap
+    /// of any run, except for the first lap from a standing start.
+    FlyingLap,
+}
+
+impl Default for RankedRecords {
+    fn default() -> Self {
+        RankedRecords::Race
+    }
+}
+
+/// Decides how players are ranked against each other in the server ranking.
+///
+/// Only maps in the current playlist are taken into account with any strategy.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum RankingStrategy {
+    /// Players earn a "win" on each map, for every player that has a worse
+    /// personal best (or none at all). More wins are better.
+    Wins,
+
+    /// Players are ranked by their average map rank, where less is better.
+    ///
+    /// On maps without a record of the player, they are assigned the map's
+    /// worst rank plus this penalty.
+    AverageMapRank { missing_rank_penalty: u32 },
+
+    /// Players earn points for their rank on each map. The first value
+    /// are the points for the first rank, the second value for the second
+    /// rank and so on. Ranks past the end of the table earn no points.
+    PointsPerRank { points: Vec<u32> },
+
+    /// Players earn points for the best medal they have achieved on each map.
+    ///
+    /// Gold, silver and bronze times are read from the map files.
+    Medals {
+        author_points: u32,
+        gold_points: u32,
+        silver_points: u32,
+        bronze_points: u32,
+    },
+}
+
+impl Default for RankingStrategy {
+    fn default() -> Self {
+        RankingStrategy::Wins
+    }
+}
+
+impl RankingStrategy {
+    fn check(&self) -> Result<(), RankingStrategyError> {
+        use RankingStrategyError::*;
+
+        match self {
+            RankingStrategy::PointsPerRank { points } => {
+                if points.is_empty() {
+                    return Err(PointsCannotBeEmpty);
+                }
+                if points.windows(2).any(|w| w[0] < w[1]) {
+                    return Err(PointsMustDecrease);
+                }
+            }
+            RankingStrategy::Medals {
+                author_points,
+                gold_points,
+                silver_points,
+                bronze_points,
+            } => {
+                if author_points < gold_points
+                    || gold_points < silver_points
+                    || silver_points < bronze_points
+                {
+                    return Err(MedalPointsMustDecrease);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Failed checks for the server ranking strategy.
+#[derive(Error, Debug)]
+pub enum RankingStrategyError {
+    #[error("'points' must not be empty")]
+    PointsCannotBeEmpty,
+
+    #[error("'points' must not increase for worse ranks")]
+    PointsMustDecrease,
+
+    #[error("medal points must not increase for worse medals")]
+    MedalPointsMustDecrease,
+}
+
+/// Decides the periods in which records count towards a seasonal ranking.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "rotation", rename_all = "snake_case")]
+pub enum SeasonConfig {
+    /// There is no seasonal ranking.
+    Disabled,
+
+    /// A new season starts every Monday.
+    Weekly,
+
+    /// A new season starts on the first day of every month.
+    Monthly,
+
+    /// Seasons are defined by admins. There is no seasonal ranking
+    /// outside of these periods.
+    Custom { periods: Vec<SeasonPeriod> },
+}
+
+impl Default for SeasonConfig {
+    fn default() -> Self {
+        SeasonConfig::Disabled
+    }
+}
+
+/// A named period of days.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SeasonPeriod {
+    pub name: String,
+
+    /// The first day of this season.
+    pub start: NaiveDate,
+
+    /// The last day of this season.
+    pub end: NaiveDate,
+}
+
+impl SeasonPeriod {
+    /// The first moment of this season.
+    pub fn start_time(&self) -> NaiveDateTime {
+        self.start.and_hms(0, 0, 0)
+    }
+
+    /// The first moment after this season.
+    pub fn end_time(&self) -> NaiveDateTime {
+        (self.end + Duration::days(1)).and_hms(0, 0, 0)
+    }
+}
+
+impl SeasonConfig {
+    /// Returns the season at the given time, or `None` if there is
+    /// no active season.
+    pub fn period_at(&self, time: &NaiveDateTime) -> Option<SeasonPeriod> {
+        let today = time.date();
+        match self {
+            SeasonConfig::Disabled => None,
+            SeasonConfig::Weekly => {
+                let start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+                let end = start + Duration::days(6);
+                let week = start.iso_week();
+                Some(SeasonPeriod {
+                    name: format!("Week {} {}", week.week(), week.year()),
+                    start,
+                    end,
+                })
+            }
+            SeasonConfig::Monthly => {
+                let start = NaiveDate::from_ymd(today.year(), today.month(), 1);
+                let next_start = if today.month() == 12 {
+                    NaiveDate::from_ymd(today.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd(today.year(), today.month() + 1, 1)
+                };
+                Some(SeasonPeriod {
+                    name: start.format("%B %Y").to_string(),
+                    start,
+                    end: next_start - Duration::days(1),
+                })
+            }
+            SeasonConfig::Custom { periods } => periods
+                .iter()
+                .find(|p| p.start <= today && today <= p.end)
+                .cloned(),
+        }
+    }
+
+    fn check(&self) -> Result<(), SeasonConfigError> {
+        use SeasonConfigError::*;
+
+        if let SeasonConfig::Custom { periods } = self {
+            if let Some(p) = periods.iter().find(|p| p.start > p.end) {
+                return Err(SeasonEndsBeforeStart(p.name.clone()));
+            }
+            let mut sorted: Vec<&SeasonPeriod> = periods.iter().collect();
+            sorted.sort_by_key(|p| p.start);
+            if let Some(w) = sorted.windows(2).find(|w| w[0].end >= w[1].start) {
+                return Err(SeasonsOverlap(w[0].name.clone(), w[1].name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Failed checks for the season config.
+#[derive(Error, Debug)]
+pub enum SeasonConfigError {
+    #[error("season '{0}' must not end before it starts")]
+    SeasonEndsBeforeStart(String),
+
+    #[error("seasons '{0}' and '{1}' must not overlap")]
+    SeasonsOverlap(String, String),
+}
+
+/// Decides when maps should be removed from the playlist, based on
+/// the number of players that voted to remove them.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CurationConfig {
+    /// What to do with maps that meet the thresholds below.
+    pub action: CurationAction,
+
+    /// The minimum number of players that have set any preference for a map.
+    pub min_voters: u32,
+
+    /// The minimum percentage of players that have played a map,
+    /// and voted to remove it.
+    pub min_remove_ratio: f32,
+
+    /// The minimum number of days since a map was added.
+    pub min_days_in_playlist: u32,
+}
+
+impl Default for CurationConfig {
+    fn default() -> Self {
+        CurationConfig {
+            action: CurationAction::Disabled,
+            min_voters: 10,
+            min_remove_ratio: 0.5,
+            min_days_in_playlist: 14,
+        }
+    }
+}
+
+impl CurationConfig {
+    fn check(&self) -> Result<(), CurationConfigError> {
+        use CurationConfigError::*;
+
+        if self.min_remove_ratio <= 0.0 || self.min_remove_ratio > 1.0 {
+            return Err(RemoveRatioOutOfRange);
+        }
+        Ok(())
+    }
+}
+
+/// The action that is taken after a map was played, if it should
+/// be removed from the playlist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurationAction {
+    /// Maps are never evaluated.
+    Disabled,
+
+    /// Tell connected admins that a map should be removed.
+    Recommend,
+
+    /// Remove the map from the playlist.
+    Remove,
+}
+
+/// Failed checks for the curation config.
+#[derive(Error, Debug)]
+pub enum CurationConfigError {
+    #[error("'min_remove_ratio' must be > 0 and <= 1")]
+    RemoveRatioOutOfRange,
+}
+
+/// Decides when players are considered idle, and are moved to spectator
+/// slots to make room for other players.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AfkConfig {
+    /// Players are only moved if this is `true`. Otherwise, players
+    /// lose their player slot as soon as they start spectating.
+    pub enabled: bool,
+
+    /// Players that have not started a run or crossed a checkpoint for
+    /// this number of seconds are considered idle.
+    pub idle_secs: u32,
+
+    /// Players that spectate while keeping their player slot for this
+    /// number of seconds are considered idle.
+    pub spectator_secs: u32,
+
+    /// Idle players are warned this number of seconds before they
+    /// are moved to a spectator slot.
+    pub warning_secs: u32,
+}
+
+impl Default for AfkConfig {
+    fn default() -> Self {
+        AfkConfig {
+            enabled: true,
+            idle_secs: 300,
+            spectator_secs: 180,
+            warning_secs: 30,
+        }
+    }
+}
+
+impl AfkConfig {
+    fn check(&self) -> Result<(), AfkConfigError> {
+        use AfkConfigError::*;
+
+        if self.warning_secs >= self.idle_secs || self.warning_secs >= self.spectator_secs {
+            return Err(WarningNotBeforeTimeout);
+        }
+        Ok(())
+    }
+}
+
+/// Failed checks for the AFK config.
+#[derive(Error, Debug)]
+pub enum AfkConfigError {
+    #[error("'warning_secs' must be < 'idle_secs' and < 'spectator_secs'")]
+    WarningNotBeforeTimeout,
+}
+
+/// Decides when admins are notified about players with incoherent runs.
+///
+/// The server reports a run as incoherent if the player's client is out of sync
+/// with the server, which can be caused by connection issues, but also by cheating.
+/// Such runs are never stored as records.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct IncoherenceConfig {
+    /// Connected admins are notified when a player has more incoherent runs
+    /// than this within an hour. Use `0` to disable notifications.
+    pub max_per_hour: u32,
+}
+
+impl Default for IncoherenceConfig {
+    fn default() -> Self {
+        IncoherenceConfig { max_per_hour: 3 }
+    }
+}
+
+/// Decides which records are implausible, and have to be reviewed by admins
+/// before they are stored.
+///
+/// Runs with decreasing checkpoint times are always rejected. Records with
+/// a checkpoint count that does not match the map are flagged as well.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RecordCheckConfig {
+    /// Records are only flagged if this is `true`.
+    pub enabled: bool,
+
+    /// Flag records that are faster than the author time by this percentage.
+    pub max_author_improvement: f32,
+
+    /// Flag records that are faster than the top record by this percentage.
+    pub max_top_record_improvement: f32,
+}
+
+impl Default for RecordCheckConfig {
+    fn default() -> Self {
+        RecordCheckConfig {
+            enabled: true,
+            max_author_improvement: 0.3,
+            max_top_record_improvement: 0.1,
+        }
+    }
+}
+
+impl RecordCheckConfig {
+    fn check(&self) -> Result<(), RecordCheckConfigError> {
+        use RecordCheckConfigError::*;
+
+        if self.max_author_improvement <= 0.0 || self.max_author_improvement >= 1.0 {
+            return Err(AuthorImprovementOutOfRange);
+        }
+        if self.max_top_record_improvement <= 0.0 || self.max_top_record_improvement >= 1.0 {
+            return Err(TopRecordImprovementOutOfRange);
+        }
+        Ok(())
+    }
+}
+
+/// Failed checks for the record check config.
+#[derive(Error, Debug)]
+pub enum RecordCheckConfigError {
+    #[error("'max_author_improvement' must be > 0 and < 1")]
+    AuthorImprovementOutOfRange,
+
+    #[error("'max_top_record_improvement' must be > 0 and < 1")]
+    TopRecordImprovementOutOfRange,
+}
+
+/// Decides which chat messages are censored or blocked, and how many messages
+/// players can send before they are muted.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ChatConfig {
+    /// Players that send more than this number of messages within
+    /// `flood_window_secs` are muted. Use `0` to disable.
+    pub flood_max_messages: u32,
+
+    /// The number of seconds in which messages are counted for the flood limit.
+    pub flood_window_secs: u32,
+
+    /// The number of seconds that players are muted for after exceeding
+    /// the flood limit.
+    pub flood_mute_secs: u32,
+
+    /// Words that are replaced with asterisks in chat messages.
+    /// Letter case is ignored.
+    pub censored_words: Vec<String>,
+
+    /// Chat messages that contain any of these words are not delivered.
+    /// Letter case is ignored.
+    pub blocked_words: Vec<String>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        ChatConfig {
+            flood_max_messages: 5,
+            flood_window_secs: 10,
+            flood_mute_secs: 60,
+            censored_words: vec![],
+            blocked_words: vec![],
+        }
+    }
+}
+
+impl ChatConfig {
+    fn check(&self) -> Result<(), ChatConfigError> {
+        use ChatConfigError::*;
+
+        if self.flood_max_messages > 0 && self.flood_window_secs == 0 {
+            return Err(FloodWindowCannotBeZero);
+        }
+        if self
+            .censored_words
+            .iter()
+            .chain(self.blocked_words.iter())
+            .any(|word| word.trim().is_empty())
+        {
+            return Err(EmptyWord);
+        }
+        Ok(())
+    }
+}
+
+/// Failed checks for the chat config.
+#[derive(Error, Debug)]
+pub enum ChatConfigError {
+    #[error("'flood_window_secs' must be > 0 if 'flood_max_messages' is > 0")]
+    FloodWindowCannotBeZero,
+
+    #[error("'censored_words' and 'blocked_words' must not contain empty words")]
+    EmptyWord,
+}
+
+/// Switches the playlist to a map pool at a given time.
+///
+/// The switch takes effect after the map that is played at that time.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PoolActivation {
+    /// The name of the map pool.
+    pub pool: String,
+
+    /// The days of the week on which this pool is activated,
+    /// or every day if empty.
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+
+    /// The time of day at which this pool is activated.
+    pub at: NaiveTime,
+}
+
 /// Controller config for the TimeAttack mode.
-#[derive(Clone, Copy, Deserialize, Serialize)]
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
 pub struct TimeAttackConfig {
     /// Decides the time limit of a map.
     ///
@@ -141,6 +888,184 @@ pub struct TimeAttackConfig {
     ///
     /// Votes during the outro will be open for two thirds of this value.
     pub outro_duration_secs: u32,
+
+    /// The threshold percentage of players (not including spectators)
+    /// that have to vote in favour of a restart to cause replaying a map.
+    #[serde(default = "default_min_restart_vote_ratio")]
+    pub min_restart_vote_ratio: f32,
+
+    /// This percentage is added to the restart vote threshold for
+    /// each subsequent restart of the same map.
+    #[serde(default = "default_min_restart_vote_ratio_step")]
+    pub min_restart_vote_ratio_step: f32,
+
+    /// Adjusts the time limit to the records on a map, and to the
+    /// players on the server.
+    #[serde(default)]
+    pub time_limit: TimeLimitPolicy,
+
+    /// Weights that decide which maps are queued first.
+    #[serde(default)]
+    pub queue: QueueWeights,
+
+    /// Decides when players can vote to skip a map, or to extend its time limit.
+    #[serde(default)]
+    pub votes: RaceVoteConfig,
+}
+
+/// Decides how the time limit of a map is adjusted, before it is limited by
+/// `time_limit_min_secs` and `time_limit_max_secs`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct TimeLimitPolicy {
+    /// The record time that `time_limit_factor` is applied to.
+    pub reference: TimeLimitReference,
+
+    /// The percentile of records that is used with the `record_percentile` reference,
+    /// where `0` is the top record and `1` the worst record.
+    pub percentile: f32,
+
+    /// The time limit is extended by this percentage for every connected
+    /// player that has not finished the map yet.
+    pub scale_per_unfinished_player: f32,
+
+    /// The time limit is at least the median of the most recent finishes
+    /// on the map, multiplied with this factor. Use `0` to disable.
+    pub recent_median_factor: f32,
+}
+
+impl Default for TimeLimitPolicy {
+    fn default() -> Self {
+        TimeLimitPolicy {
+            reference: TimeLimitReference::TopRecord,
+            percentile: 0.5,
+            scale_per_unfinished_player: 0.0,
+            recent_median_factor: 0.0,
+        }
+    }
+}
+
+/// The record time that the time limit factor is applied to.
+///
+/// The author time is used if there are no records on a map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeLimitReference {
+    /// Use the top record.
+    TopRecord,
+
+    /// Use the record at the configured percentile.
+    RecordPercentile,
+}
+
+fn default_min_restart_vote_ratio() -> f32 {
+    0.5
+}
+
+fn default_min_restart_vote_ratio_step() -> f32 {
+    0.25
+}
+
+/// Decides when players can vote to skip the current map, or to extend
+/// its time limit during a race.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RaceVoteConfig {
+    /// The percentage of players (not including spectators) that have
+    /// to vote in favour of skipping the current map.
+    pub skip_ratio: f32,
+
+    /// The percentage of players (not including spectators) that have
+    /// to vote in favour of extending the time limit.
+    pub extend_ratio: f32,
+
+    /// The number of seconds that are added to the time limit,
+    /// if players voted to extend it.
+    pub extend_secs: u32,
+
+    /// The number of seconds players can vote after a vote was started.
+    pub vote_duration_secs: u32,
+
+    /// The number of seconds after a vote ended, until players
+    /// can start another vote.
+    pub cooldown_secs: u32,
+
+    /// The maximum number of skip votes per map. Use `0` to disable skip votes.
+    pub max_skip_votes_per_map: u32,
+
+    /// The maximum number of extend votes per map. Use `0` to disable extend votes.
+    pub max_extend_votes_per_map: u32,
+}
+
+impl Default for RaceVoteConfig {
+    fn default() -> Self {
+        RaceVoteConfig {
+            skip_ratio: 0.5,
+            extend_ratio: 0.5,
+            extend_secs: 300,
+            vote_duration_secs: 30,
+            cooldown_secs: 120,
+            max_skip_votes_per_map: 1,
+            max_extend_votes_per_map: 2,
+        }
+    }
+}
+
+/// Weights that are used to score maps in the queue.
+/// The map with the highest score will be queued next.
+///
+/// Preferences and histories are only considered for players
+/// that are currently playing on the server.
+///
+/// Weights that are missing from the config have their default value.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QueueWeights {
+    /// Added for every player that picked a map.
+    pub pick: i32,
+
+    /// Added for every player that vetoed a map.
+    pub veto: i32,
+
+    /// Added for every player that voted to remove a map.
+    pub remove: i32,
+
+    /// Added for every player that has no record on a map,
+    /// and did not set a preference for it.
+    pub auto_pick: i32,
+
+    /// Added for every player that has a record on a map,
+    /// and did not set a preference for it.
+    pub no_preference: i32,
+
+    /// Added for every time a map was not queued.
+    pub skip_count: i32,
+
+    /// Added for every map that a player has played since they
+    /// last played a map.
+    pub nb_maps_since: i32,
+
+    /// Added for every day a map has been on the server,
+    /// up to a maximum of 30 days.
+    /// Use a negative value to prefer recently added maps.
+    pub map_age: i32,
+
+    /// Added for every record that has been set on a map within the last seven days.
+    pub record_activity: i32,
+}
+
+impl Default for QueueWeights {
+    fn default() -> Self {
+        QueueWeights {
+            pick: 1,
+            veto: -1,
+            remove: -1,
+            auto_pick: 1,
+            no_preference: -1,
+            skip_count: 1,
+            nb_maps_since: 0,
+            map_age: 0,
+            record_activity: 0,
+        }
+    }
 }
 
 impl TimeAttackConfig {
@@ -163,6 +1088,30 @@ impl TimeAttackConfig {
         if self.time_limit_min_secs >= self.time_limit_max_secs {
             return Err(TimeLimitMinGreaterThanMax);
         }
+        if self.time_limit.percentile < 0.0 || self.time_limit.percentile > 1.0 {
+            return Err(TimeLimitPercentileOutOfRange);
+        }
+        if self.time_limit.scale_per_unfinished_player < 0.0 {
+            return Err(TimeLimitScaleNegative);
+        }
+        if self.time_limit.recent_median_factor < 0.0 {
+            return Err(TimeLimitMedianFactorNegative);
+        }
+        if self.votes.skip_ratio <= 0.0 || self.votes.skip_ratio > 1.0 {
+            return Err(SkipVoteRatioOutOfRange);
+        }
+        if self.votes.extend_ratio <= 0.0 || self.votes.extend_ratio > 1.0 {
+            return Err(ExtendVoteRatioOutOfRange);
+        }
+        if self.votes.vote_duration_secs == 0 {
+            return Err(VoteDurationCannotBeZero);
+        }
+        if self.min_restart_vote_ratio <= 0.0 || self.min_restart_vote_ratio > 1.0 {
+            return Err(RestartVoteRatioOutOfRange);
+        }
+        if self.min_restart_vote_ratio_step < 0.0 {
+            return Err(RestartVoteRatioStepNegative);
+        }
         Ok(())
     }
 }
@@ -197,4 +1146,84 @@ pub enum TimeAttackConfigError {
 
     #[error("'time_limit_max_secs' must be > 'time_limit_min_secs'")]
     TimeLimitMinGreaterThanMax,
+
+    #[error("'percentile' must be >= 0 and <= 1")]
+    TimeLimitPercentileOutOfRange,
+
+    #[error("'scale_per_unfinished_player' must be >= 0")]
+    TimeLimitScaleNegative,
+
+    #[error("'recent_median_factor' must be >= 0")]
+    TimeLimitMedianFactorNegative,
+
+    #[error("'min_restart_vote_ratio' must be > 0 and <= 1")]
+    RestartVoteRatioOutOfRange,
+
+    #[error("'min_restart_vote_ratio_step' must be >= 0")]
+    RestartVoteRatioStepNegative,
+
+    #[error("'skip_ratio' must be > 0 and <= 1")]
+    SkipVoteRatioOutOfRange,
+
+    #[error("'extend_ratio' must be > 0 and <= 1")]
+    ExtendVoteRatioOutOfRange,
+
+    #[error("'vote_duration_secs' must be > 0")]
+    VoteDurationCannotBeZero,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_comments() {
+        let mut doc: Document = r#"
+# A comment about 'a'.
+a = 1 # a trailing comment
+
+# A comment about 'b'.
+b = "unchanged"
+
+[section]
+# A comment about 'c'.
+c = [1, 2]
+d = true
+"#
+        .parse()
+        .unwrap();
+
+        let new_doc: Document = r#"
+a = 2
+b = "unchanged"
+
+[section]
+c = [1, 2, 3]
+e = false
+"#
+        .parse()
+        .unwrap();
+
+        merge_toml(doc.as_table_mut(), new_doc.as_table());
+        let merged = doc.to_string();
+
+        assert!(merged.contains("# A comment about 'a'."));
+        assert!(merged.contains("# a trailing comment"));
+        assert!(merged.contains("# A comment about 'b'."));
+        assert!(merged.contains("# A comment about 'c'."));
+
+        let merged: toml::Value = toml::from_str(&merged).unwrap();
+        let expected: toml::Value = new_doc.to_string().parse().unwrap();
+        assert_eq!(expected, merged);
+    }
+
+    #[test]
+    fn partial_queue_weights() {
+        let weights: QueueWeights = toml::from_str("pick = 5").unwrap();
+        let expected = QueueWeights {
+            pick: 5,
+            ..QueueWeights::default()
+        };
+        assert_eq!(expected, weights);
+    }
 }
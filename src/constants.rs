@@ -38,6 +38,11 @@ pub const BLACKLIST_FILE: &str = "blacklist.txt";
 
 pub const CONFIG_ENV_VAR: &str = "STEWARD_CONFIG";
 
+/// The language of chat messages and widgets for players whose game client
+/// uses a language without translations. Texts that are missing in other
+/// languages are also taken from this language.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
 /// The time (in percentage of the total outro duration) during which players
 /// can still vote for a restart after the race ends. The next map will be
 /// decided after this duration.
@@ -46,11 +51,17 @@ pub const CONFIG_ENV_VAR: &str = "STEWARD_CONFIG";
 /// enough to be able to display the next map for a good duration within the outro.
 pub const VOTE_DURATION_RATIO: f32 = 0.66;
 
-/// Require this percentage of players for the first restart vote.
-pub const DEFAULT_MIN_RESTART_VOTE_RATIO: f32 = 0.5;
+/// When scoring maps in the queue, the age of a map is only considered
+/// up to this number of days.
+pub const MAX_QUEUE_MAP_AGE_DAYS: i64 = 30;
+
+/// When scoring maps in the queue, only records set within
+/// this number of days count towards a map's record activity.
+pub const QUEUE_RECORD_ACTIVITY_DAYS: i64 = 7;
 
-/// Add this percentage to the restart vote threshold for subsequent restarts.
-pub const MIN_RESTART_VOTE_RATIO_STEP: f32 = 0.25;
+/// When adjusting the time limit of a map, only this number of the
+/// most recent finishes count towards the median of recent runs.
+pub const TIME_LIMIT_RECENT_RUNS: usize = 50;
 
 /// Limits the amount of top server ranks displayed.
 ///
@@ -58,6 +69,10 @@ pub const MIN_RESTART_VOTE_RATIO_STEP: f32 = 0.25;
 /// with limited vertical space.
 pub const MAX_DISPLAYED_SERVER_RANKS: usize = 10;
 
+/// Limits the amount of past seasons, whose final standings are
+/// displayed in the server ranking menu.
+pub const MAX_DISPLAYED_ARCHIVED_SEASONS: usize = 3;
+
 /// Limits the amount of top map ranks displayed.
 ///
 /// This should be as low as necessary to display it in a widget
@@ -70,6 +85,19 @@ pub const MAX_DISPLAYED_MAP_RANKS: usize = 10;
 /// with limited vertical space.
 pub const MAX_DISPLAYED_RACE_RANKS: usize = 10;
 
+/// Limits the amount of players listed by the `/whois` command.
+pub const MAX_DISPLAYED_WHOIS_MATCHES: usize = 5;
+
+/// Limits the amount of records listed by the `/top` command.
+pub const MAX_DISPLAYED_TOP_RECORDS: usize = 25;
+
+/// The amount of rows on each page of the `/maps` and `/players` lists.
+pub const LIST_PAGE_SIZE: usize = 25;
+
+/// Limits the amount of similar commands that are suggested
+/// when a player enters an unknown command.
+pub const MAX_COMMAND_SUGGESTIONS: usize = 3;
+
 /// Limits the amount of next maps in the queue displayed
 /// during the outro.
 ///
@@ -99,6 +127,25 @@ pub const MAX_ANNOUNCED_RANK: usize = 10;
 /// Setting this too high might pollute the chat.
 pub const MAX_NB_ANNOUNCED_RANKS: usize = 3;
 
+/// The longest duration that can be passed to chat commands, f.e. to `/mute`.
+pub const MAX_COMMAND_DURATION_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// The number of seconds in between checks for idle players.
+pub const AFK_CHECK_INTERVAL_SECS: u64 = 5;
+
+/// The number of seconds in between checks for announcements whose interval elapsed.
+pub const ANNOUNCEMENT_CHECK_INTERVAL_SECS: u64 = 10;
+
+/// The number of seconds in between checks for changes to the config file.
+pub const CONFIG_RELOAD_INTERVAL_SECS: u64 = 5;
+
+/// The milliseconds in between updates of the live race ranking widget.
+/// The widget is only re-sent if the ranking changed in the meantime.
+pub const LIVE_RANKING_REFRESH_MILLIS: u64 = 1000;
+
+/// The milliseconds that a player's checkpoint split is displayed for.
+pub const CHECKPOINT_SPLIT_DISPLAY_MILLIS: i64 = 3000;
+
 /// The milliseconds into a player's run after which temporary widgets
 /// are hidden.
 ///
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::config::AfkConfig;
+use crate::controller::{LiveConfig, LivePlayers};
+use crate::event::{PlayerDiff, PlayerTransition};
+use crate::server::{Calls, Server};
+
+#[derive(Default)]
+struct AfkState {
+    /// The last time players (with player slots, and not spectating)
+    /// started a run, or crossed a checkpoint.
+    last_active: HashMap<i32, NaiveDateTime>,
+
+    /// The time at which spectators that still have a player slot
+    /// started spectating.
+    spectating_since: HashMap<i32, NaiveDateTime>,
+
+    /// The UIDs of idle players that have been warned, and the time
+    /// at which they were warned.
+    warned_at: HashMap<i32, NaiveDateTime>,
+
+    /// `True` if idle players are checked, which is the case during the race,
+    /// but not during the outro.
+    in_race: bool,
+}
+
+/// An action to take for an idle player.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AfkAction {
+    /// Warn the player that they will be moved to a spectator slot.
+    Warn { secs_left: i64 },
+
+    /// Move the player to a spectator slot.
+    MoveToSpectator,
+}
+
+/// This controller tracks player activity, to move idle players
+/// to spectator slots when the server is full.
+#[derive(Clone)]
+pub struct AfkController {
+    state: Arc<RwLock<AfkState>>,
+    server: Server,
+    live_config: Arc<dyn LiveConfig>,
+    live_players: Arc<dyn LivePlayers>,
+}
+
+impl AfkController {
+    pub async fn init(
+        server: &Server,
+        live_config: &Arc<dyn LiveConfig>,
+        live_players: &Arc<dyn LivePlayers>,
+    ) -> Self {
+        let mut state = AfkState::default();
+        state.in_race = true;
+
+        let now = Utc::now().naive_utc();
+        for info in live_players.info_all().await {
+            if !info.has_player_slot() {
+                continue;
+            }
+            if info.is_spectator() {
+                state.spectating_since.insert(info.uid, now);
+            } else {
+                state.last_active.insert(info.uid, now);
+            }
+        }
+
+        AfkController {
+            state: Arc::new(RwLock::new(state)),
+            server: server.clone(),
+            live_config: live_config.clone(),
+            live_players: live_players.clone(),
+        }
+    }
+
+    /// Signal that a player has started a run, or crossed a checkpoint.
+    pub async fn touch(&self, player_login: &str) {
+        let player_uid = match self.live_players.uid(player_login).await {
+            Some(uid) => uid,
+            None => return,
+        };
+        let mut afk_state = self.state.write().await;
+        if let Some(last_active) = afk_state.last_active.get_mut(&player_uid) {
+            *last_active = Utc::now().naive_utc();
+            afk_state.warned_at.remove(&player_uid);
+        }
+    }
+
+    /// Start or stop tracking a player, depending on whether they occupy
+    /// a player slot, and whether they are spectating.
+    pub async fn update_for_player(&self, diff: &PlayerDiff) {
+        use PlayerTransition::*;
+
+        let uid = diff.info.uid;
+        let now = Utc::now().naive_utc();
+        let mut afk_state = self.state.write().await;
+
+        afk_state.warned_at.remove(&uid);
+        afk_state.last_active.remove(&uid);
+        afk_state.spectating_since.remove(&uid);
+
+        match diff.transition {
+            AddPlayer | MoveToPlayer => {
+                afk_state.last_active.insert(uid, now);
+            }
+            AddSpectator | MoveToSpectator => {
+                afk_state.spectating_since.insert(uid, now);
+            }
+            AddPureSpectator | MoveToPureSpectator | RemovePlayer | RemoveSpectator
+            | RemovePureSpectator => {}
+        }
+    }
+
+    /// Stop checking for idle players until the next race.
+    pub async fn pause(&self) {
+        self.state.write().await.in_race = false;
+    }
+
+    /// Continue checking for idle players, and consider all players
+    /// active at the start of the race.
+    pub async fn resume(&self) {
+        let now = Utc::now().naive_utc();
+        let mut afk_state = self.state.write().await;
+        afk_state.in_race = true;
+        afk_state.warned_at.clear();
+        afk_state.last_active.values_mut().for_each(|t| *t = now);
+    }
+
+    /// Returns the logins of idle players, and the actions to take for them.
+    /// Returns nothing if the server has free player slots.
+    pub async fn check(&self) -> Vec<(String, AfkAction)> {
+        let config = self.live_config.lock().await.afk;
+        if !config.enabled {
+            return vec![];
+        }
+
+        let max_players = self.server.server_options().await.current_max_players;
+        let players_state = self.live_players.lock().await;
+        let nb_player_slots = players_state
+            .info_all()
+            .iter()
+            .filter(|info| info.has_player_slot())
+            .count();
+        if (nb_player_slots as i32) < max_players {
+            return vec![];
+        }
+
+        let mut afk_state = self.state.write().await;
+        if !afk_state.in_race {
+            return vec![];
+        }
+
+        let now = Utc::now().naive_utc();
+        let actions = idle_actions(&afk_state, &config, now);
+        for (uid, action) in actions.iter() {
+            if let AfkAction::Warn { .. } = action {
+                afk_state.warned_at.insert(*uid, now);
+            }
+        }
+
+        actions
+            .into_iter()
+            .filter_map(|(uid, action)| {
+                players_state
+                    .login(uid)
+                    .map(|login| (login.to_string(), action))
+            })
+            .collect()
+    }
+}
+
+/// Decide which players should be warned, and which should be moved
+/// to a spectator slot.
+///
+/// Players are always warned first, and only moved once they have been
+/// idle for long enough, and were warned at least `warning_secs` ago.
+/// This includes players that were already idle for too long when the
+/// server became full.
+fn idle_actions(
+    afk_state: &AfkState,
+    config: &AfkConfig,
+    now: NaiveDateTime,
+) -> Vec<(i32, AfkAction)> {
+    let players = afk_state
+        .last_active
+        .iter()
+        .map(|(uid, since)| (*uid, *since, config.idle_secs));
+    let spectators = afk_state
+        .spectating_since
+        .iter()
+        .map(|(uid, since)| (*uid, *since, config.spectator_secs));
+
+    players
+        .chain(spectators)
+        .filter_map(|(uid, since, timeout_secs)| {
+            let warning = Duration::seconds(config.warning_secs as i64);
+            let timeout_at = since + Duration::seconds(timeout_secs as i64);
+            match afk_state.warned_at.get(&uid) {
+                Some(warned_at) if now >= timeout_at && now >= *warned_at + warning => {
+                    Some((uid, AfkAction::MoveToSpectator))
+                }
+                Some(_) => None,
+                None if now >= timeout_at - warning => {
+                    let secs_left = std::cmp::max(timeout_at - now, warning).num_seconds();
+                    Some((uid, AfkAction::Warn { secs_left }))
+                }
+                None => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn warns_before_moving() {
+        let config = AfkConfig::default();
+        let now = Utc::now().naive_utc();
+
+        let mut state = AfkState::default();
+        state.last_active.insert(1, now);
+        state
+            .last_active
+            .insert(2, now - Duration::seconds(config.idle_secs as i64 - 10));
+        state
+            .last_active
+            .insert(3, now - Duration::seconds(config.idle_secs as i64));
+        state
+            .spectating_since
+            .insert(4, now - Duration::seconds(config.spectator_secs as i64));
+
+        let warning_secs = config.warning_secs as i64;
+
+        // Players that are already idle for too long are warned as well.
+        let mut actions = idle_actions(&state, &config, now);
+        actions.sort_by_key(|(uid, _)| *uid);
+        assert_eq!(
+            vec![
+                (
+                    2,
+                    AfkAction::Warn {
+                        secs_left: warning_secs
+                    }
+                ),
+                (
+                    3,
+                    AfkAction::Warn {
+                        secs_left: warning_secs
+                    }
+                ),
+                (
+                    4,
+                    AfkAction::Warn {
+                        secs_left: warning_secs
+                    }
+                ),
+            ],
+            actions
+        );
+
+        // Players are only warned once.
+        state.warned_at.insert(2, now);
+        state.warned_at.insert(3, now);
+        state.warned_at.insert(4, now);
+        let actions = idle_actions(&state, &config, now);
+        assert!(actions.is_empty());
+
+        // Players are moved once the warning has passed.
+        let later = now + Duration::seconds(warning_secs);
+        let mut actions = idle_actions(&state, &config, later);
+        actions.sort_by_key(|(uid, _)| *uid);
+        assert_eq!(
+            vec![
+                (2, AfkAction::MoveToSpectator),
+                (3, AfkAction::MoveToSpectator),
+                (4, AfkAction::MoveToSpectator),
+            ],
+            actions
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, NaiveTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::database::{
+    Announcement, AnnouncementQueries, AnnouncementTrigger, DatabaseClient, TimeWindow,
+};
+
+#[derive(Default)]
+struct AnnouncementState {
+    /// All announcements, ordered by ID.
+    announcements: Vec<Announcement>,
+
+    /// The ID of the announcement that was displayed most recently
+    /// for each trigger.
+    last_shown: HashMap<AnnouncementTrigger, i32>,
+
+    /// The time at which each interval (in seconds) last elapsed.
+    last_interval_at: HashMap<i32, NaiveDateTime>,
+
+    /// The number of maps that started since the controller started.
+    nb_maps: i32,
+}
+
+impl AnnouncementState {
+    /// Pick the announcement that should be displayed next for the given trigger,
+    /// skipping those whose time window does not contain the given time.
+    ///
+    /// Announcements that share a trigger take turns, in the order of their IDs.
+    fn next(&mut self, trigger: AnnouncementTrigger, time: NaiveTime) -> Option<String> {
+        let candidates: Vec<&Announcement> = self
+            .announcements
+            .iter()
+            .filter(|a| a.trigger == trigger)
+            .filter(|a| a.window.map(|w| w.contains(time)).unwrap_or(true))
+            .collect();
+
+        let last_id = self.last_shown.get(&trigger).copied().unwrap_or(0);
+        let next = candidates
+            .iter()
+            .find(|a| a.id > last_id)
+            .or_else(|| candidates.first())?;
+
+        self.last_shown.insert(trigger, next.id);
+        Some(next.message.clone())
+    }
+
+    /// The distinct triggers of all announcements that match the predicate.
+    fn triggers<F>(&self, predicate: F) -> Vec<AnnouncementTrigger>
+    where
+        F: Fn(&AnnouncementTrigger) -> bool,
+    {
+        let mut triggers = Vec::new();
+        for announcement in &self.announcements {
+            if predicate(&announcement.trigger) && !triggers.contains(&announcement.trigger) {
+                triggers.push(announcement.trigger);
+            }
+        }
+        triggers
+    }
+
+    /// Count the started map, and return the announcements for the start of that map.
+    fn on_map_start(&mut self, now: NaiveDateTime) -> Vec<String> {
+        use AnnouncementTrigger::*;
+
+        self.nb_maps += 1;
+        let nb_maps = self.nb_maps;
+
+        self.triggers(|trigger| match trigger {
+            MapStart => true,
+            EveryNbMaps { nb_maps: n } => nb_maps % n == 0,
+            _ => false,
+        })
+        .into_iter()
+        .filter_map(|trigger| self.next(trigger, now.time()))
+        .collect()
+    }
+
+    /// Return the announcements of every interval that elapsed since it last did.
+    fn due(&mut self, now: NaiveDateTime) -> Vec<String> {
+        let intervals =
+            self.triggers(|trigger| matches!(trigger, AnnouncementTrigger::Interval { .. }));
+
+        let mut messages = Vec::new();
+        for trigger in intervals {
+            let secs = match trigger {
+                AnnouncementTrigger::Interval { secs } => secs,
+                _ => continue,
+            };
+            let last_at = *self.last_interval_at.entry(secs).or_insert(now);
+            if now - last_at < Duration::seconds(secs as i64) {
+                continue;
+            }
+            self.last_interval_at.insert(secs, now);
+            if let Some(msg) = self.next(trigger, now.time()) {
+                messages.push(msg);
+            }
+        }
+        messages
+    }
+}
+
+/// This controller picks the announcements that are displayed in the chat,
+/// and lets admins add and remove them at runtime.
+#[derive(Clone)]
+pub struct AnnouncementController {
+    state: Arc<RwLock<AnnouncementState>>,
+    db: DatabaseClient,
+}
+
+impl AnnouncementController {
+    pub async fn init(db: &DatabaseClient) -> Self {
+        let controller = AnnouncementController {
+            state: Arc::new(RwLock::new(AnnouncementState::default())),
+            db: db.clone(),
+        };
+        controller.reload().await;
+        controller
+    }
+
+    async fn reload(&self) {
+        let announcements = self
+            .db
+            .announcements()
+            .await
+            .expect("failed to load announcements");
+        self.state.write().await.announcements = announcements;
+    }
+
+    /// List all announcements, ordered by ID.
+    pub async fn list(&self) -> Vec<Announcement> {
+        self.state.read().await.announcements.clone()
+    }
+
+    /// Add an announcement.
+    pub async fn add(&self, message: &str, trigger: AnnouncementTrigger) {
+        self.db
+            .add_announcement(message, trigger)
+            .await
+            .expect("failed to add announcement");
+        self.reload().await;
+    }
+
+    /// Restrict an announcement to a time window, or remove its window with `None`.
+    ///
+    /// Returns `false` if there is no announcement with that ID.
+    pub async fn set_window(&self, id: i32, window: Option<TimeWindow>) -> bool {
+        let updated = self
+            .db
+            .set_announcement_window(id, window)
+            .await
+            .expect("failed to set announcement window");
+        if updated {
+            self.reload().await;
+        }
+        updated
+    }
+
+    /// Remove an announcement.
+    ///
+    /// Returns `false` if there is no announcement with that ID.
+    pub async fn remove(&self, id: i32) -> bool {
+        let removed = self
+            .db
+            .remove_announcement(id)
+            .await
+            .expect("failed to remove announcement");
+        if removed {
+            self.reload().await;
+        }
+        removed
+    }
+
+    /// The announcement for a player that joined, if any.
+    pub async fn on_join(&self) -> Option<String> {
+        let now = Utc::now().naive_utc();
+        self.state
+            .write()
+            .await
+            .next(AnnouncementTrigger::Join, now.time())
+    }
+
+    /// The announcements for the start of a map.
+    pub async fn on_map_start(&self) -> Vec<String> {
+        let now = Utc::now().naive_utc();
+        self.state.write().await.on_map_start(now)
+    }
+
+    /// The announcements whose interval elapsed since they were last displayed.
+    pub async fn due(&self) -> Vec<String> {
+        let now = Utc::now().naive_utc();
+        self.state.write().await.due(now)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn announcement(id: i32, trigger: AnnouncementTrigger) -> Announcement {
+        Announcement {
+            id,
+            message: format!("msg {}", id),
+            trigger,
+            window: None,
+        }
+    }
+
+    fn at(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2020, 1, 1).and_hms(h, m, s)
+    }
+
+    fn state(announcements: Vec<Announcement>) -> AnnouncementState {
+        AnnouncementState {
+            announcements,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn announcements_take_turns() {
+        use AnnouncementTrigger::*;
+
+        let mut state = state(vec![
+            announcement(1, Join),
+            announcement(2, MapStart),
+            announcement(3, Join),
+        ]);
+        let time = at(12, 0, 0).time();
+        assert_eq!(Some("msg 1".to_string()), state.next(Join, time));
+        assert_eq!(Some("msg 3".to_string()), state.next(Join, time));
+        assert_eq!(Some("msg 1".to_string()), state.next(Join, time));
+        assert_eq!(None, state.next(Interval { secs: 60 }, time));
+    }
+
+    #[test]
+    fn skips_announcements_outside_window() {
+        use AnnouncementTrigger::*;
+
+        let mut late = announcement(2, Join);
+        late.window = Some(TimeWindow {
+            start: NaiveTime::from_hms(22, 0, 0),
+            end: NaiveTime::from_hms(2, 0, 0),
+        });
+        let mut state = state(vec![announcement(1, Join), late]);
+
+        let noon = at(12, 0, 0).time();
+        assert_eq!(Some("msg 1".to_string()), state.next(Join, noon));
+        assert_eq!(Some("msg 1".to_string()), state.next(Join, noon));
+
+        let night = at(23, 0, 0).time();
+        assert_eq!(Some("msg 2".to_string()), state.next(Join, night));
+    }
+
+    #[test]
+    fn announces_every_nb_maps() {
+        use AnnouncementTrigger::*;
+
+        let mut state = state(vec![
+            announcement(1, MapStart),
+            announcement(2, EveryNbMaps { nb_maps: 2 }),
+        ]);
+        let now = at(12, 0, 0);
+        assert_eq!(vec!["msg 1"], state.on_map_start(now));
+        assert_eq!(vec!["msg 1", "msg 2"], state.on_map_start(now));
+        assert_eq!(vec!["msg 1"], state.on_map_start(now));
+    }
+
+    #[test]
+    fn announces_after_interval() {
+        use AnnouncementTrigger::*;
+
+        let mut state = state(vec![
+            announcement(1, Interval { secs: 600 }),
+            announcement(2, Interval { secs: 600 }),
+        ]);
+        assert!(state.due(at(12, 0, 0)).is_empty());
+        assert!(state.due(at(12, 9, 59)).is_empty());
+        assert_eq!(vec!["msg 1"], state.due(at(12, 10, 0)));
+        assert!(state.due(at(12, 15, 0)).is_empty());
+        assert_eq!(vec!["msg 2"], state.due(at(12, 20, 0)));
+    }
+}
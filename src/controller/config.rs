@@ -1,11 +1,13 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use async_trait::async_trait;
 use chrono::Duration;
-use tokio::sync::{RwLock, RwLockReadGuard};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 
-use crate::config::{Config, TimeAttackConfig};
+use crate::config::{Config, ConfigError, PublicConfig, TimeAttackConfig};
 use crate::event::ConfigDiff;
 use crate::server::{Calls, ModeOptions, ModeScript, Server};
 
@@ -45,6 +47,12 @@ pub trait LiveConfig: Send + Sync {
 #[derive(Clone)]
 pub struct ConfigController {
     state: Arc<RwLock<Config>>,
+    modified_time: Arc<Mutex<Option<SystemTime>>>,
+
+    /// The representation of the config that each admin was shown the
+    /// changes of, and that they may confirm.
+    reviewed: Arc<Mutex<HashMap<String, String>>>,
+
     server: Server,
 }
 
@@ -52,6 +60,8 @@ impl ConfigController {
     pub async fn init(server: &Server, config: Config) -> Self {
         let controller = ConfigController {
             state: Arc::new(RwLock::new(config)),
+            modified_time: Arc::new(Mutex::new(Config::modified_time())),
+            reviewed: Arc::new(Mutex::new(HashMap::new())),
             server: server.clone(),
         };
         {
@@ -61,49 +71,90 @@ impl ConfigController {
         controller
     }
 
-    /// Update the public parts of the controller config.
-    pub async fn set_mode_config(&self, new_cfg: TimeAttackConfig) -> Vec<ConfigDiff> {
-        use ConfigDiff::*;
+    /// Remember that the given admin reviewed the changes of this config.
+    pub async fn set_reviewed(&self, login: &str, cfg: &PublicConfig) {
+        self.reviewed
+            .lock()
+            .await
+            .insert(login.to_string(), cfg.to_string());
+    }
+
+    /// Returns `true` if the given config is the one whose changes the admin
+    /// reviewed last. Any config needs to be reviewed again after this check.
+    pub async fn take_reviewed(&self, login: &str, cfg: &PublicConfig) -> bool {
+        self.reviewed.lock().await.remove(login) == Some(cfg.to_string())
+    }
 
-        let mut diffs = Vec::new();
+    /// Update the parts of the controller config that admins can edit in-game.
+    pub async fn set_public_config(&self, new_cfg: PublicConfig) -> Vec<ConfigDiff> {
+        let mut modified_time = self.modified_time.lock().await;
         let mut cfg = self.state.write().await;
 
-        if cfg.timeattack.outro_duration_secs != new_cfg.outro_duration_secs {
-            diffs.push(NewOutroDuration {
-                secs: new_cfg.outro_duration_secs,
-            });
+        let mut new_full_cfg = cfg.clone();
+        new_full_cfg.set_public(new_cfg);
 
-            cfg.timeattack.outro_duration_secs = new_cfg.outro_duration_secs;
-            self.set_mode_options(&cfg).await;
-        }
+        let diffs = config_diffs(&cfg, &new_full_cfg);
+        let new_outro_duration =
+            cfg.timeattack.outro_duration_secs != new_full_cfg.timeattack.outro_duration_secs;
 
-        if cfg.timeattack.time_limit_factor != new_cfg.time_limit_factor
-            || cfg.timeattack.time_limit_max_secs != new_cfg.time_limit_max_secs
-            || cfg.timeattack.time_limit_min_secs != new_cfg.time_limit_min_secs
-        {
-            diffs.push(NewTimeLimit {
-                time_limit_factor: new_cfg.time_limit_factor,
-                time_limit_max_secs: new_cfg.time_limit_max_secs,
-                time_limit_min_secs: new_cfg.time_limit_min_secs,
-            });
-
-            cfg.timeattack.time_limit_factor = new_cfg.time_limit_factor;
-            cfg.timeattack.time_limit_max_secs = new_cfg.time_limit_max_secs;
-            cfg.timeattack.time_limit_min_secs = new_cfg.time_limit_min_secs;
+        *cfg = new_full_cfg;
+
+        if new_outro_duration {
+            self.set_mode_options(&cfg).await;
         }
 
         if !diffs.is_empty() {
             (*cfg).save(); // write to file
+
+            // Do not reload our own changes.
+            *modified_time = Config::modified_time();
         }
 
         diffs
     }
 
+    /// Replace the controller config with the contents of the config file,
+    /// if it was modified since it was last read or written.
+    ///
+    /// The config is only replaced if all of its sections are valid, and none
+    /// of the fields were changed that require restarting the controller.
+    pub async fn reload(&self) -> Result<Vec<ConfigDiff>, ConfigError> {
+        let mut modified_time = self.modified_time.lock().await;
+        let new_modified_time = Config::modified_time();
+        if new_modified_time == *modified_time {
+            return Ok(Vec::new());
+        }
+
+        // Only try once per modification, so that errors are not repeated.
+        *modified_time = new_modified_time;
+
+        let new_cfg = Config::reload()?;
+        let mut cfg = self.state.write().await;
+        cfg.check_reload(&new_cfg)?;
+
+        let diffs = config_diffs(&cfg, &new_cfg);
+        let new_outro_duration =
+            cfg.timeattack.outro_duration_secs != new_cfg.timeattack.outro_duration_secs;
+
+        *cfg = new_cfg;
+
+        if new_outro_duration {
+            self.set_mode_options(&cfg).await;
+        }
+
+        Ok(diffs)
+    }
+
     pub async fn mode_config(&self) -> TimeAttackConfig {
         let config = self.state.read().await;
         config.timeattack
     }
 
+    pub async fn public_config(&self) -> PublicConfig {
+        let config = self.state.read().await;
+        config.public()
+    }
+
     async fn set_mode_options(&self, config: &Config) {
         let mode_options = self.server.mode_options().await;
 
@@ -152,3 +203,150 @@ impl LiveConfig for ConfigController {
         self.server.user_data_dir().await.join("Maps")
     }
 }
+
+/// Compare the sections of two controller configs.
+fn config_diffs(old_cfg: &Config, new_cfg: &Config) -> Vec<ConfigDiff> {
+    use ConfigDiff::*;
+
+    let mut diffs = mode_config_diffs(&old_cfg.timeattack, &new_cfg.timeattack);
+
+    if old_cfg.super_admin_whitelist != new_cfg.super_admin_whitelist
+        || old_cfg.admin_whitelist != new_cfg.admin_whitelist
+    {
+        diffs.push(NewAdmins);
+    }
+
+    if old_cfg.server_ranking != new_cfg.server_ranking
+        || old_cfg.ranked_records != new_cfg.ranked_records
+    {
+        diffs.push(NewServerRankingConfig);
+    }
+
+    if old_cfg.seasons != new_cfg.seasons {
+        diffs.push(NewSeasonConfig);
+    }
+
+    if old_cfg.curation != new_cfg.curation {
+        diffs.push(NewCurationConfig);
+    }
+
+    if old_cfg.afk != new_cfg.afk {
+        diffs.push(NewAfkConfig);
+    }
+
+    if old_cfg.incoherence != new_cfg.incoherence || old_cfg.record_checks != new_cfg.record_checks
+    {
+        diffs.push(NewRecordCheckConfig);
+    }
+
+    if old_cfg.chat != new_cfg.chat {
+        diffs.push(NewChatConfig);
+    }
+
+    if old_cfg.pool_calendar != new_cfg.pool_calendar {
+        diffs.push(NewPoolCalendar);
+    }
+
+    diffs
+}
+
+/// Compare the sections of two TimeAttack mode configs.
+fn mode_config_diffs(old_cfg: &TimeAttackConfig, new_cfg: &TimeAttackConfig) -> Vec<ConfigDiff> {
+    use ConfigDiff::*;
+
+    let mut diffs = Vec::new();
+
+    if old_cfg.outro_duration_secs != new_cfg.outro_duration_secs {
+        diffs.push(NewOutroDuration {
+            secs: new_cfg.outro_duration_secs,
+        });
+    }
+
+    if old_cfg.time_limit_factor != new_cfg.time_limit_factor
+        || old_cfg.time_limit_max_secs != new_cfg.time_limit_max_secs
+        || old_cfg.time_limit_min_secs != new_cfg.time_limit_min_secs
+        || old_cfg.time_limit != new_cfg.time_limit
+    {
+        diffs.push(NewTimeLimit {
+            time_limit_factor: new_cfg.time_limit_factor,
+            time_limit_max_secs: new_cfg.time_limit_max_secs,
+            time_limit_min_secs: new_cfg.time_limit_min_secs,
+        });
+    }
+
+    if old_cfg.queue != new_cfg.queue
+        || old_cfg.min_restart_vote_ratio != new_cfg.min_restart_vote_ratio
+        || old_cfg.min_restart_vote_ratio_step != new_cfg.min_restart_vote_ratio_step
+    {
+        diffs.push(NewQueueConfig);
+    }
+
+    if old_cfg.votes != new_cfg.votes {
+        diffs.push(NewRaceVoteConfig);
+    }
+
+    diffs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        rpc_address = "127.0.0.1:5000"
+        rpc_login = "SuperAdmin"
+        rpc_password = "SuperAdmin"
+        postgres_connection = "host=127.0.0.1 user=postgres"
+        super_admin_whitelist = ["login1"]
+        admin_whitelist = []
+
+        [timeattack]
+        time_limit_factor = 10
+        time_limit_max_secs = 600
+        time_limit_min_secs = 120
+        outro_duration_secs = 30
+    "#;
+
+    fn config(toml_str: &str) -> Config {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn diffs_changed_sections() {
+        let old_cfg = config(CONFIG);
+        assert!(config_diffs(&old_cfg, &config(CONFIG)).is_empty());
+
+        let new_cfg = config(&CONFIG.replace(
+            "admin_whitelist = []",
+            "admin_whitelist = [\"login2\"]\n[afk]\nenabled = false\nidle_secs = 60\nspectator_secs = 60\nwarning_secs = 10",
+        ));
+        let diffs = config_diffs(&old_cfg, &new_cfg);
+        assert_eq!(2, diffs.len());
+        assert!(matches!(diffs[0], ConfigDiff::NewAdmins));
+        assert!(matches!(diffs[1], ConfigDiff::NewAfkConfig));
+
+        let new_cfg =
+            config(&CONFIG.replace("outro_duration_secs = 30", "outro_duration_secs = 60"));
+        let diffs = config_diffs(&old_cfg, &new_cfg);
+        assert_eq!(1, diffs.len());
+        assert!(matches!(
+            diffs[0],
+            ConfigDiff::NewOutroDuration { secs: 60 }
+        ));
+    }
+
+    #[test]
+    fn rejects_restart_only_changes() {
+        let old_cfg = config(CONFIG);
+        assert!(old_cfg.check_reload(&config(CONFIG)).is_ok());
+
+        let new_cfg = config(&CONFIG.replace("[\"login1\"]", "[\"login2\"]"));
+        assert!(old_cfg.check_reload(&new_cfg).is_ok());
+
+        let new_cfg = config(&CONFIG.replace("127.0.0.1:5000", "127.0.0.1:5001"));
+        assert!(matches!(
+            old_cfg.check_reload(&new_cfg),
+            Err(ConfigError::RequiresRestart("rpc_address"))
+        ));
+    }
+}
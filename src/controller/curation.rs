@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{NaiveDateTime, Utc};
+use futures::future::join_all;
+use tokio::sync::Mutex;
+
+use crate::config::{CurationAction, CurationConfig};
+use crate::controller::{LiveConfig, LivePlaylist};
+use crate::database::timeattack::{PreferenceValue, TimeAttackQueries};
+use crate::database::{DatabaseClient, Map};
+
+/// The outcome of evaluating a map against the curation thresholds.
+#[derive(Debug, Clone)]
+pub struct CurationVerdict {
+    pub map: Map,
+
+    /// The number of players that have set any preference for this map.
+    pub nb_voters: i64,
+
+    /// The number of players that voted to remove this map.
+    pub nb_remove_votes: i64,
+
+    /// The number of players that have played this map.
+    pub nb_players: i64,
+
+    /// The number of days since this map was added.
+    pub days_in_playlist: i64,
+
+    /// `True` if this map meets all thresholds, and should be removed.
+    pub should_remove: bool,
+}
+
+impl CurationVerdict {
+    /// The percentage of players that voted to remove this map.
+    pub fn remove_ratio(&self) -> f32 {
+        let nb_players = self.nb_players.max(self.nb_voters);
+        if nb_players == 0 {
+            return 0.0;
+        }
+        self.nb_remove_votes as f32 / nb_players as f32
+    }
+}
+
+/// Evaluate a map with the given vote counts and play statistics.
+fn evaluate(
+    map: Map,
+    counts: &[(PreferenceValue, i64)],
+    nb_players: i64,
+    config: &CurationConfig,
+    now: NaiveDateTime,
+) -> CurationVerdict {
+    let nb_voters = counts.iter().map(|(_, count)| count).sum();
+    let nb_remove_votes = counts
+        .iter()
+        .filter(|(value, _)| *value == PreferenceValue::Remove)
+        .map(|(_, count)| count)
+        .sum();
+    let days_in_playlist = now.signed_duration_since(map.added_since).num_days();
+
+    let mut verdict = CurationVerdict {
+        map,
+        nb_voters,
+        nb_remove_votes,
+        nb_players,
+        days_in_playlist,
+        should_remove: false,
+    };
+    verdict.should_remove = verdict.nb_voters >= config.min_voters as i64
+        && verdict.days_in_playlist >= config.min_days_in_playlist as i64
+        && verdict.remove_ratio() >= config.min_remove_ratio;
+    verdict
+}
+
+/// This controller decides which maps should be removed from the playlist,
+/// based on how many players voted to remove them.
+#[derive(Clone)]
+pub struct CurationController {
+    db: DatabaseClient,
+    live_config: Arc<dyn LiveConfig>,
+    live_playlist: Arc<dyn LivePlaylist>,
+
+    /// The UIDs of maps that admins were recommended to remove.
+    recommended: Arc<Mutex<HashSet<String>>>,
+}
+
+impl CurationController {
+    pub fn init(
+        db: &DatabaseClient,
+        live_config: &Arc<dyn LiveConfig>,
+        live_playlist: &Arc<dyn LivePlaylist>,
+    ) -> Self {
+        CurationController {
+            db: db.clone(),
+            live_config: live_config.clone(),
+            live_playlist: live_playlist.clone(),
+            recommended: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// The action that should be taken for maps that meet the curation thresholds.
+    pub async fn action(&self) -> CurationAction {
+        self.live_config.lock().await.curation.action
+    }
+
+    /// Evaluate the specified map, or return `None` if curation is disabled,
+    /// or if that map is not in the playlist.
+    pub async fn evaluate(&self, map_uid: &str) -> Option<CurationVerdict> {
+        if self.action().await == CurationAction::Disabled {
+            return None;
+        }
+        let map = self.live_playlist.map(map_uid).await?;
+        Some(self.evaluate_map(map).await)
+    }
+
+    /// Returns `true` if the verdict recommends removing its map, and the
+    /// previous verdict for that map did not. Admins only need to be told
+    /// about recommendations when they change.
+    pub async fn is_new_recommendation(&self, verdict: &CurationVerdict) -> bool {
+        let mut recommended = self.recommended.lock().await;
+        if verdict.should_remove {
+            recommended.insert(verdict.map.uid.clone())
+        } else {
+            recommended.remove(&verdict.map.uid);
+            false
+        }
+    }
+
+    /// Evaluate every map in the playlist, regardless of whether curation
+    /// is enabled.
+    pub async fn report(&self) -> Vec<CurationVerdict> {
+        let maps = self.live_playlist.lock().await.maps.clone();
+        let mut verdicts = join_all(maps.into_iter().map(|map| self.evaluate_map(map))).await;
+        verdicts.sort_by(|a, b| {
+            b.should_remove
+                .cmp(&a.should_remove)
+                .then(b.nb_remove_votes.cmp(&a.nb_remove_votes))
+        });
+        verdicts
+    }
+
+    async fn evaluate_map(&self, map: Map) -> CurationVerdict {
+        let counts = self
+            .db
+            .count_map_preferences(&map.uid)
+            .await
+            .expect("failed to load map preferences");
+        let nb_players = self
+            .db
+            .nb_players_played(&map.uid)
+            .await
+            .expect("failed to load number of players");
+        let config = self.live_config.lock().await.curation.clone();
+        evaluate(map, &counts, nb_players, &config, Utc::now().naive_utc())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use crate::database::mock_map;
+
+    use super::*;
+
+    fn config() -> CurationConfig {
+        CurationConfig {
+            action: CurationAction::Remove,
+            min_voters: 4,
+            min_remove_ratio: 0.5,
+            min_days_in_playlist: 7,
+        }
+    }
+
+    #[test]
+    fn removes_unpopular_map() {
+        use PreferenceValue::*;
+
+        let now = Utc::now().naive_utc();
+        let map = mock_map("uid", now - Duration::days(10));
+        let counts = vec![(Pick, 1), (Veto, 1), (Remove, 3)];
+        let verdict = evaluate(map, &counts, 6, &config(), now);
+        assert_eq!(5, verdict.nb_voters);
+        assert_eq!(3, verdict.nb_remove_votes);
+        assert!(verdict.should_remove);
+    }
+
+    #[test]
+    fn keeps_map_below_thresholds() {
+        use PreferenceValue::*;
+
+        let now = Utc::now().naive_utc();
+
+        // too few voters
+        let counts = vec![(Pick, 0), (Veto, 0), (Remove, 3)];
+        let map = mock_map("uid", now - Duration::days(10));
+        let verdict = evaluate(map, &counts, 3, &config(), now);
+        assert!(!verdict.should_remove);
+
+        // too recent
+        let counts = vec![(Pick, 0), (Veto, 0), (Remove, 5)];
+        let map = mock_map("uid", now - Duration::days(2));
+        let verdict = evaluate(map, &counts, 5, &config(), now);
+        assert!(!verdict.should_remove);
+
+        // too many players that did not vote to remove it
+        let counts = vec![(Pick, 2), (Veto, 0), (Remove, 4)];
+        let map = mock_map("uid", now - Duration::days(10));
+        let verdict = evaluate(map, &counts, 20, &config(), now);
+        assert!(!verdict.should_remove);
+    }
+}
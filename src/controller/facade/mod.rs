@@ -1,7 +1,13 @@
 use std::sync::Arc;
 
+use tokio::time::Duration;
+
 use crate::chat::ServerMessage;
 use crate::config::Config;
+use crate::constants::{
+    AFK_CHECK_INTERVAL_SECS, ANNOUNCEMENT_CHECK_INTERVAL_SECS, CONFIG_RELOAD_INTERVAL_SECS,
+    DEFAULT_LANGUAGE, LIVE_RANKING_REFRESH_MILLIS,
+};
 use crate::controller::*;
 use crate::database::DatabaseClient;
 use crate::server::{Calls, Server};
@@ -20,13 +26,18 @@ pub struct Controller {
     db: DatabaseClient,
     config: ConfigController,
     playlist: PlaylistController,
+    curation: CurationController,
     players: PlayerController,
+    afk: AfkController,
+    moderation: ModerationController,
+    announcements: AnnouncementController,
     prefs: PreferenceController,
     queue: QueueController,
     schedule: ScheduleController,
     ranking: ServerRankController,
     records: RecordController,
     race: RaceController,
+    votes: VoteController,
     widget: WidgetController,
 }
 
@@ -50,20 +61,36 @@ impl Controller {
         let playlist = PlaylistController::init(&server, &db, &live_config).await;
         let live_playlist = Arc::new(playlist.clone()) as Arc<dyn LivePlaylist>;
 
+        let curation = CurationController::init(&db, &live_config, &live_playlist);
+
         let players = PlayerController::init(&server, &db).await;
         let live_players = Arc::new(players.clone()) as Arc<dyn LivePlayers>;
 
+        let afk = AfkController::init(&server, &live_config, &live_players).await;
+
+        let moderation = ModerationController::init(&live_config);
+
+        let announcements = AnnouncementController::init(&db).await;
+
         let prefs = PreferenceController::init(&server, &db, &live_playlist, &live_players).await;
         let live_prefs = Arc::new(prefs.clone()) as Arc<dyn LivePreferences>;
 
-        let queue =
-            QueueController::init(&server, &live_players, &live_playlist, &live_prefs).await;
+        let queue = QueueController::init(
+            &server,
+            &db,
+            &live_config,
+            &live_players,
+            &live_playlist,
+            &live_prefs,
+        )
+        .await;
         let live_queue = Arc::new(queue.clone()) as Arc<dyn LiveQueue>;
 
-        let ranking = ServerRankController::init(&server, &db, &live_players).await;
+        let ranking = ServerRankController::init(&server, &db, &live_config, &live_players).await;
         let live_server_ranking = Arc::new(ranking.clone()) as Arc<dyn LiveServerRanking>;
 
-        let records = RecordController::init(&db, &live_playlist, &live_players).await;
+        let records =
+            RecordController::init(&db, &live_config, &live_playlist, &live_players).await;
         let live_records = Arc::new(records.clone()) as Arc<dyn LiveRecords>;
 
         let schedule = ScheduleController::init(
@@ -73,6 +100,7 @@ impl Controller {
             &live_queue,
             &live_records,
             &live_config,
+            &live_players,
         )
         .await;
         let live_schedule = Arc::new(schedule.clone()) as Arc<dyn LiveSchedule>;
@@ -80,6 +108,9 @@ impl Controller {
         let race = RaceController::init(&server, &live_players).await;
         let live_race = Arc::new(race.clone()) as Arc<dyn LiveRace>;
 
+        let votes = VoteController::init(&live_config, &live_players);
+        let live_votes = Arc::new(votes.clone()) as Arc<dyn LiveVotes>;
+
         let widget = WidgetController::init(
             &server,
             &db,
@@ -92,32 +123,88 @@ impl Controller {
             &live_prefs,
             &live_queue,
             &live_schedule,
+            &live_votes,
         )
         .await;
 
-        Controller {
+        let controller = Controller {
             server,
             db,
             config,
             playlist,
+            curation,
             players,
+            afk,
+            moderation,
+            announcements,
             prefs,
             queue,
             schedule,
             ranking,
             records,
             race,
+            votes,
             widget,
-        }
+        };
+
+        // Periodically check for idle players.
+        let afk_controller = controller.clone(); // 'controller' with 'static lifetime
+        let _ = tokio::spawn(async move {
+            loop {
+                tokio::time::delay_for(Duration::from_secs(AFK_CHECK_INTERVAL_SECS)).await;
+                afk_controller.check_afk().await;
+            }
+        });
+
+        // Periodically display announcements.
+        let announcement_controller = controller.clone(); // 'controller' with 'static lifetime
+        let _ = tokio::spawn(async move {
+            loop {
+                tokio::time::delay_for(Duration::from_secs(ANNOUNCEMENT_CHECK_INTERVAL_SECS)).await;
+                announcement_controller.announce_due().await;
+            }
+        });
+
+        // Periodically apply changes to the config file.
+        let config_controller = controller.clone(); // 'controller' with 'static lifetime
+        let _ = tokio::spawn(async move {
+            loop {
+                tokio::time::delay_for(Duration::from_secs(CONFIG_RELOAD_INTERVAL_SECS)).await;
+                config_controller.reload_config().await;
+            }
+        });
+
+        // Periodically display the live race ranking.
+        let race_controller = controller.clone(); // 'controller' with 'static lifetime
+        let _ = tokio::spawn(async move {
+            loop {
+                tokio::time::delay_for(Duration::from_millis(LIVE_RANKING_REFRESH_MILLIS)).await;
+                race_controller.refresh_live_ranking().await;
+            }
+        });
+
+        controller
     }
 }
 
-/// Send a message to all players.
-async fn announce(server: &Server, message: ServerMessage<'_>) {
+/// Send a message to all players, in their languages.
+async fn announce(server: &Server, players: &dyn LivePlayers, message: ServerMessage<'_>) {
     let message_str = message.to_string();
     if message_str.is_empty() {
         return;
     }
-    log::debug!("server msg> {}", &message);
-    server.chat_send(&message.to_string()).await;
+    log::debug!("server msg> {}", &message_str);
+
+    let players_state = players.lock().await;
+    let groups = players_state.logins_by_language();
+    if groups.len() <= 1 {
+        let lang = groups.keys().next().copied().unwrap_or(DEFAULT_LANGUAGE);
+        server.chat_send(&message.localize(lang)).await;
+        return;
+    }
+
+    for (lang, logins) in groups {
+        // Assume any fault is due to a target player disconnecting.
+        let _ = server.chat_send_to(&message.localize(lang), logins).await;
+    }
 }
@@ -3,43 +3,82 @@ use std::str::FromStr;
 use futures::future::join_all;
 
 use crate::chat::{CommandOutput, CommandResultOutput};
-use crate::config::TimeAttackConfig;
-use crate::controller::{ActivePreference, Controller};
+use crate::config::{ConfigError, PublicConfig};
+use crate::controller::{ActivePreference, Controller, LiveConfig};
+use crate::database::RoleQueries;
 use crate::event::ControllerEvent;
 use crate::server::PlayerInfo;
-use crate::widget::Action;
+use crate::widget::{Action, PagedList};
 
 impl Controller {
     pub(super) async fn on_action(&self, player: &PlayerInfo, action: Action<'_>) {
         use Action::*;
 
+        let required_cmd = match &action {
+            SetConfig { .. } | ConfirmConfig { .. } => Some("/config"),
+            TurnPage {
+                list: PagedList::Maps { .. },
+                ..
+            } => Some("/maps"),
+            TurnPage {
+                list: PagedList::Players { .. },
+                ..
+            } => Some("/players"),
+            _ => None,
+        };
+        if let Some(cmd_name) = required_cmd {
+            if !self.may_use_command(&player.login, cmd_name).await {
+                log::warn!(
+                    "'{}' tried to use '{}' without permission",
+                    &player.login,
+                    cmd_name
+                );
+                return;
+            }
+        }
+
         match action {
-            SetConfig { toml_config } => match TimeAttackConfig::from_str(&toml_config) {
-                Ok(new_cfg) => {
-                    let changes = self.config.set_mode_config(new_cfg).await;
+            SetConfig { toml_config } => match PublicConfig::from_str(&toml_config) {
+                Ok(new_cfg) => self.review_config(player, new_cfg).await,
+                Err(err) => self.on_invalid_config(player, &toml_config, err).await,
+            },
+
+            ConfirmConfig { toml_config } => match PublicConfig::from_str(&toml_config) {
+                Ok(new_cfg) if self.config.take_reviewed(&player.login, &new_cfg).await => {
+                    let changes = self.config.set_public_config(new_cfg).await;
                     join_all(changes.into_iter().map(|change| async move {
                         let ev = ControllerEvent::NewConfig {
                             change,
-                            from_login: &player.login,
+                            from_login: Some(&player.login),
                         };
                         self.on_controller_event(ev).await;
                     }))
                     .await;
                 }
-                Err(de_err) => {
-                    let err_msg = format!("{:#?}", de_err);
-                    let msg = CommandOutput::Result(CommandResultOutput::InvalidConfig {
-                        tried_repr: &toml_config,
-                        error_msg: &err_msg,
-                    });
-                    self.widget.show_popup(msg, &player.login).await;
-                }
+                // The config was edited after its changes were displayed:
+                // show the changes again instead of applying it.
+                Ok(new_cfg) => self.review_config(player, new_cfg).await,
+                Err(err) => self.on_invalid_config(player, &toml_config, err).await,
             },
 
             ConfirmCommand { cmd } => {
                 self.on_dangerous_cmd(&player, cmd).await;
             }
 
+            TurnPage {
+                list: PagedList::Maps { query },
+                page,
+            } => {
+                self.show_map_list(&player.login, &query, page).await;
+            }
+
+            TurnPage {
+                list: PagedList::Players { query },
+                page,
+            } => {
+                self.show_player_list(&player.login, &query, page).await;
+            }
+
             SetPreference {
                 map_uid,
                 preference,
@@ -61,6 +100,47 @@ impl Controller {
             VoteRestart { vote } => {
                 self.prefs.set_restart_vote(player.uid, vote).await;
             }
+
+            VoteRace { vote } => match self.votes.cast(player.uid, vote).await {
+                Some(outcome) => self.on_race_vote_outcome(outcome).await,
+                None => self.widget.refresh_race_vote().await,
+            },
+        }
+    }
+
+    /// Admins can use admin commands through widgets, f.e. edit the config,
+    /// as well as players with a role that grants the command.
+    async fn may_use_command(&self, login: &str, cmd_name: &str) -> bool {
+        if self.config.is_admin(login).await {
+            return true;
         }
+        self.db
+            .granted_commands(login)
+            .await
+            .expect("failed to load granted commands")
+            .iter()
+            .any(|cmd| cmd == cmd_name)
+    }
+
+    /// Show the admin the changes of a new config, which they can confirm
+    /// to apply it.
+    async fn review_config(&self, player: &PlayerInfo, new_cfg: PublicConfig) {
+        let curr_repr = self.config.public_config().await.to_string();
+        let new_repr = new_cfg.to_string();
+        let msg = CommandOutput::Result(CommandResultOutput::ConfigChanges {
+            curr_repr: &curr_repr,
+            new_repr: &new_repr,
+        });
+        self.config.set_reviewed(&player.login, &new_cfg).await;
+        self.widget.show_popup(msg, &player.login).await;
+    }
+
+    async fn on_invalid_config(&self, player: &PlayerInfo, toml_config: &str, err: ConfigError) {
+        let err_msg = err.to_string();
+        let msg = CommandOutput::Result(CommandResultOutput::InvalidConfig {
+            tried_repr: toml_config,
+            error_msg: &err_msg,
+        });
+        self.widget.show_popup(msg, &player.login).await;
     }
 }
@@ -1,21 +1,34 @@
+use chrono::Utc;
 use semver::Version;
 use tokio::time::Duration;
 
 use crate::chat::{
-    AdminCommand, CommandConfirmOutput, CommandErrorOutput, CommandOutput, CommandResultOutput,
-    DangerousCommand, PlayerCommand, PlaylistCommandError, ServerMessage, SuperAdminCommand,
+    grantable_commands, AdminCommand, CommandConfirmOutput, CommandErrorOutput, CommandOutput,
+    CommandResultOutput, DangerousCommand, ListQuery, Page, PlayerCommand, PlayerMessage,
+    PlayerProfile, PlaylistCommandError, RaceVoteKind, RankSummary, RoleGrantee, ServerMessage,
+    SuperAdminCommand,
 };
-use crate::constants::BLACKLIST_FILE;
 use crate::constants::VERSION;
+use crate::constants::{
+    BLACKLIST_FILE, LIST_PAGE_SIZE, MAX_DISPLAYED_TOP_RECORDS, MAX_DISPLAYED_WHOIS_MATCHES,
+};
 use crate::controller::facade::announce;
-use crate::controller::{Controller, LiveConfig, LivePlayers, LivePlaylist};
-use crate::database::{Map, MapQueries, PlayerQueries};
+use crate::controller::{
+    analyze_sectors, lookup_logins, tell, Controller, LiveConfig, LivePlayers, LivePlaylist,
+    LiveServerRanking,
+};
+use crate::database::{
+    FlaggedRecord, Map, MapQueries, PlayerQueries, PoolQueries, RecordEvidence, RecordQueries,
+    RoleQueries,
+};
 use crate::event::{ControllerEvent, PlaylistDiff};
+use crate::locale::supported_language;
 use crate::network::most_recent_controller_version;
 use crate::server::{Calls, ModeCalls, ModeScript, PlayerInfo, RoundBasedModeCalls};
 
 impl Controller {
-    pub(super) async fn on_cmd(&self, from: &PlayerInfo, cmd: PlayerCommand) {
+    pub(super) async fn on_cmd(&self, from: &PlayerInfo, cmd: PlayerCommand<'_>) {
+        use CommandErrorOutput::*;
         use CommandOutput::*;
         use CommandResultOutput::*;
 
@@ -61,6 +74,165 @@ impl Controller {
                     controller.widget.show_popup(msg, &from_login).await;
                 });
             }
+
+            PersonalBest { map_uid } => {
+                let map = match self.cmd_target_map(map_uid).await {
+                    Some(map) => map,
+                    None if map_uid.is_some() => {
+                        self.widget.show_popup(Error(UnknownMap), &from.login).await;
+                        return;
+                    }
+                    None => return,
+                };
+                let nb_laps = map.nb_laps.unwrap_or(0);
+                let pb = self
+                    .db
+                    .player_record(&map.uid, &from.login, nb_laps)
+                    .await
+                    .expect("failed to load personal best");
+                let top_record = self
+                    .db
+                    .top_record(&map.uid, nb_laps)
+                    .await
+                    .expect("failed to load top record");
+                let map_name = map.name.plain();
+                let msg = Result(PersonalBestReport {
+                    map_name: &map_name,
+                    pb,
+                    top_record,
+                });
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            TopRecords { map_uid } => {
+                let map = match self.cmd_target_map(map_uid).await {
+                    Some(map) => map,
+                    None if map_uid.is_some() => {
+                        self.widget.show_popup(Error(UnknownMap), &from.login).await;
+                        return;
+                    }
+                    None => return,
+                };
+                let records = self
+                    .db
+                    .top_records(
+                        &map.uid,
+                        MAX_DISPLAYED_TOP_RECORDS as i64,
+                        map.nb_laps.unwrap_or(0),
+                    )
+                    .await
+                    .expect("failed to load top records");
+                let map_name = map.name.plain();
+                let msg = if records.is_empty() {
+                    Error(NoRecordsOnMap)
+                } else {
+                    Result(TopRecordList {
+                        map_name: &map_name,
+                        records,
+                        player_login: &from.login,
+                    })
+                };
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            ServerRank => {
+                let summary = |rank: &crate::controller::ServerRank, max_pos: usize| RankSummary {
+                    pos: rank.pos,
+                    max_pos,
+                    nb_wins: rank.nb_wins,
+                    nb_losses: rank.nb_losses,
+                };
+
+                let ranking = self.ranking.lock().await;
+                let server_rank = ranking
+                    .rank_of(&from.login)
+                    .map(|rank| summary(rank, ranking.max_pos()));
+                let season_name = ranking.season().map(|season| season.season.name.clone());
+                let season_rank = ranking.season().and_then(|season| {
+                    season
+                        .rank_of(&from.login)
+                        .map(|rank| summary(rank, season.max_pos()))
+                });
+                drop(ranking);
+
+                let msg = Result(ServerRankReport {
+                    server_rank,
+                    season_rank: season_name.as_deref().map(|name| (name, season_rank)),
+                });
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            MissingRecords => {
+                let unfinished_uids = self
+                    .db
+                    .maps_without_player_record(&from.login)
+                    .await
+                    .expect("failed to load maps without record");
+                let playlist = self.playlist.lock().await;
+                let maps: Vec<&Map> = playlist
+                    .maps
+                    .iter()
+                    .filter(|map| unfinished_uids.contains(&map.uid))
+                    .collect();
+                let msg = Result(MissingRecordList(maps));
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            Sectors => {
+                let map = match self.playlist.current_map().await {
+                    Some(map) => map,
+                    None => return,
+                };
+                let history = self
+                    .db
+                    .record_history(&map.uid, map.nb_laps.unwrap_or(0))
+                    .await
+                    .expect("failed to load record history");
+                let map_name = map.name.plain();
+                let msg = match analyze_sectors(&history, &from.login) {
+                    Some(analysis) => Result(SectorReport {
+                        map_name: &map_name,
+                        analysis,
+                    }),
+                    None => Error(NoRecordsOnMap),
+                };
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            Language { code: None } => {
+                let language = self.players.language(&from.login).await.to_string();
+                let msg = PlayerMessage::LanguageInfo { language };
+                tell(&self.server, &self.players, msg, &from.login).await;
+            }
+
+            Language { code: Some(code) } => {
+                let selected = if code.eq_ignore_ascii_case("auto") {
+                    None
+                } else {
+                    match supported_language(code) {
+                        Some(language) => Some(language),
+                        None => {
+                            let msg = PlayerMessage::UnknownLanguage {
+                                code: code.to_string(),
+                            };
+                            tell(&self.server, &self.players, msg, &from.login).await;
+                            return;
+                        }
+                    }
+                };
+
+                let language = match self.players.set_language(&from.login, selected).await {
+                    Some(language) => language.to_string(),
+                    None => return,
+                };
+                let msg = PlayerMessage::LanguageChanged { language };
+                tell(&self.server, &self.players, msg, &from.login).await;
+                self.widget.refresh_language_for(from).await;
+            }
+
+            VoteSkip => self.start_race_vote(from, RaceVoteKind::Skip).await,
+
+            VoteExtend => self.start_race_vote(from, RaceVoteKind::Extend).await,
         }
     }
 
@@ -88,40 +260,66 @@ impl Controller {
 
         match cmd {
             EditConfig => {
-                let curr_cfg = self.config.mode_config().await;
+                let curr_cfg = self.config.public_config().await;
                 let curr_cfg = curr_cfg.to_string();
                 let msg = Result(CurrentConfig { repr: &curr_cfg });
                 self.widget.show_popup(msg, &from.login).await;
             }
 
-            ListMaps => {
-                let playlist = self.server.playlist().await;
+            ListMaps { query } => {
+                self.show_map_list(&from.login, query.unwrap_or_default(), 0)
+                    .await;
+            }
 
-                let maps = self.db.maps(vec![]).await.expect("failed to load maps");
+            ListPlayers { query } => {
+                self.show_player_list(&from.login, query.unwrap_or_default(), 0)
+                    .await;
+            }
 
-                let mut in_playlist: Vec<&Map> = maps
-                    .iter()
-                    .filter(|m1| playlist.iter().any(|m2| m1.uid == m2.uid))
-                    .collect();
-                let mut not_in_playlist: Vec<&Map> = maps
+            WhoIs { name } => {
+                let aliases = self
+                    .db
+                    .find_player_aliases(&name, MAX_DISPLAYED_WHOIS_MATCHES as i64)
+                    .await
+                    .expect("failed to load player aliases");
+                let plain_names: Vec<String> =
+                    aliases.iter().map(|a| a.display_name.plain()).collect();
+                let names = aliases
                     .iter()
-                    .filter(|m1| !playlist.iter().any(|m2| m1.uid == m2.uid))
-                    .collect();
+                    .zip(plain_names.iter())
+                    .map(|(alias, plain)| (alias.player_login.as_str(), plain.as_str()));
 
-                in_playlist.sort_by_key(|map| map.name.plain());
-                not_in_playlist.sort_by_key(|map| map.name.plain());
+                let logins: Vec<&str> = lookup_logins(&name, names);
+                if logins.is_empty() {
+                    let msg = Error(NoMatchingPlayer);
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
 
-                let msg = Result(MapList {
-                    in_playlist,
-                    not_in_playlist,
-                });
+                let nb_records = self
+                    .db
+                    .nb_records_by_player(logins.clone())
+                    .await
+                    .expect("failed to count player records");
 
-                self.widget.show_popup(msg, &from.login).await;
-            }
+                let profiles = logins
+                    .iter()
+                    .map(|login| PlayerProfile {
+                        login: login.to_string(),
+                        aliases: aliases
+                            .iter()
+                            .filter(|alias| alias.player_login == *login)
+                            .cloned()
+                            .collect(),
+                        nb_records: nb_records
+                            .iter()
+                            .find(|(l, _)| l == login)
+                            .map(|(_, nb)| *nb)
+                            .unwrap_or(0),
+                    })
+                    .collect();
 
-            ListPlayers => {
-                let players_state = self.players.lock().await;
-                let msg = Result(PlayerList(players_state.info_all()));
+                let msg = Result(PlayerProfiles(profiles));
                 self.widget.show_popup(msg, &from.login).await;
             }
 
@@ -133,6 +331,7 @@ impl Controller {
                 if let Some(PlaylistDiff::Append(map)) = diff {
                     announce(
                         &self.server,
+                        &self.players,
                         ServerMessage::AddedMap {
                             admin_name,
                             map: &map,
@@ -157,6 +356,7 @@ impl Controller {
                     if let Some(PlaylistDiff::Append(map)) = diff {
                         announce(
                             &self.server,
+                            &self.players,
                             ServerMessage::AddedMap {
                                 admin_name,
                                 map: &map,
@@ -175,6 +375,7 @@ impl Controller {
                 if let Some(PlaylistDiff::Append(map)) = diff {
                     announce(
                         &self.server,
+                        &self.players,
                         ServerMessage::RemovedMap {
                             admin_name,
                             map: &map,
@@ -184,6 +385,57 @@ impl Controller {
                 }
             }
 
+            PlaylistCurate => {
+                let verdicts = self.curation.report().await;
+                let msg = Result(CurationReport(verdicts));
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            ListPools => {
+                let pools = self.db.pools().await.expect("failed to load map pools");
+                let active_pool = self.playlist.lock().await.pool.clone();
+                let msg = Result(PoolList {
+                    pools: pools.iter().collect(),
+                    active_pool: active_pool.as_deref(),
+                });
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            CreatePool { name } => {
+                self.playlist.create_pool(name).await;
+                announce(
+                    &self.server,
+                    &self.players,
+                    ServerMessage::CreatedPool {
+                        admin_name,
+                        pool_name: name,
+                    },
+                )
+                .await;
+            }
+
+            SwitchPool { name } => match self.playlist.switch_pool(name).await {
+                Ok(diffs) => {
+                    for diff in diffs {
+                        let ev = ControllerEvent::NewPlaylist(diff);
+                        self.on_controller_event(ev).await;
+                    }
+                    announce(
+                        &self.server,
+                        &self.players,
+                        ServerMessage::SwitchedPool {
+                            admin_name,
+                            pool_name: name,
+                        },
+                    )
+                    .await;
+                }
+                Err(err) => {
+                    let msg = Error(InvalidPlaylistCommand(err));
+                    self.widget.show_popup(msg, &from.login).await;
+                }
+            },
+
             ImportMap { id } => {
                 // Download maps in a separate task.
                 let controller = self.clone(); // 'self' with 'static lifetime
@@ -200,6 +452,7 @@ impl Controller {
                     if let Some(PlaylistDiff::Append(map)) = diff {
                         announce(
                             &controller.server,
+                            &controller.players,
                             ServerMessage::NewMap {
                                 admin_name: &admin_name,
                                 map: &map,
@@ -214,6 +467,7 @@ impl Controller {
                 if self.server.end_map().await.is_ok() {
                     announce(
                         &self.server,
+                        &self.players,
                         ServerMessage::CurrentMapSkipped { admin_name },
                     )
                     .await;
@@ -225,7 +479,12 @@ impl Controller {
                     let ev = ControllerEvent::NewQueue(diff);
                     self.on_controller_event(ev).await;
                 }
-                announce(&self.server, ServerMessage::ForceRestart { admin_name }).await;
+                announce(
+                    &self.server,
+                    &self.players,
+                    ServerMessage::ForceRestart { admin_name },
+                )
+                .await;
             }
 
             ForceQueue { uid } => {
@@ -248,6 +507,7 @@ impl Controller {
 
                     announce(
                         &self.server,
+                        &self.players,
                         ServerMessage::ForceQueued {
                             admin_name,
                             map_name: &map.name.formatted,
@@ -273,6 +533,7 @@ impl Controller {
 
                 announce(
                     &self.server,
+                    &self.players,
                     ServerMessage::PlayerBlacklisted {
                         admin_name,
                         player_name: &try_display_name(login.to_string()).await,
@@ -297,6 +558,7 @@ impl Controller {
 
                 announce(
                     &self.server,
+                    &self.players,
                     ServerMessage::PlayerUnblacklisted {
                         admin_name,
                         player_name: &try_display_name(login.to_string()).await,
@@ -316,6 +578,7 @@ impl Controller {
                 for login in blacklist {
                     announce(
                         &self.server,
+                        &self.players,
                         ServerMessage::PlayerUnblacklisted {
                             admin_name,
                             player_name: &try_display_name(login.to_string()).await,
@@ -335,12 +598,12 @@ impl Controller {
                     // case 2: unpause now
                     assert!(self.server.pause().await.active);
                     let msg = ServerMessage::MatchPaused { admin_name };
-                    announce(&self.server, msg).await;
+                    announce(&self.server, &self.players, msg).await;
                 } else {
                     // case 3: pause now
                     assert!(!self.server.pause().await.active);
                     let msg = ServerMessage::MatchUnpaused { admin_name };
-                    announce(&self.server, msg).await;
+                    announce(&self.server, &self.players, msg).await;
                 }
             }
 
@@ -349,7 +612,7 @@ impl Controller {
                 if status.active {
                     self.server.warmup_extend(Duration::from_secs(secs)).await;
                     let msg = ServerMessage::WarmupRoundExtended { admin_name, secs };
-                    announce(&self.server, msg).await;
+                    announce(&self.server, &self.players, msg).await;
                 } else {
                     let msg = Error(NotInWarmup);
                     self.widget.show_popup(msg, &from.login).await;
@@ -361,7 +624,7 @@ impl Controller {
                 if status.active {
                     self.server.force_end_warmup().await;
                     let msg = ServerMessage::WarmupSkipped { admin_name };
-                    announce(&self.server, msg).await;
+                    announce(&self.server, &self.players, msg).await;
                 } else {
                     let msg = Error(NotInWarmup);
                     self.widget.show_popup(msg, &from.login).await;
@@ -371,16 +634,14 @@ impl Controller {
             KickPlayer {
                 login_or_display_name,
             } => {
-                let players_state = self.players.lock().await;
-
-                let maybe_player = players_state
-                    .info(&login_or_display_name)
-                    .or_else(|| players_state.display_name_info(&login_or_display_name));
-
-                let player = match maybe_player {
+                let player = match self
+                    .players
+                    .lookup_unambiguous(&login_or_display_name)
+                    .await
+                {
                     Some(player) => player,
                     None => {
-                        let msg = Error(UnknownPlayer);
+                        let msg = Error(NoMatchingPlayer);
                         self.widget.show_popup(msg, &from.login).await;
                         return;
                     }
@@ -391,26 +652,70 @@ impl Controller {
                         admin_name,
                         player_name: &player.display_name.formatted,
                     };
-                    announce(&self.server, msg).await;
+                    announce(&self.server, &self.players, msg).await;
                 } else {
                     let msg = Error(UnknownPlayer);
                     self.widget.show_popup(msg, &from.login).await;
                 }
             }
 
-            MovePlayerToSpectator {
+            MutePlayer {
                 login_or_display_name,
+                secs,
             } => {
-                let players_state = self.players.lock().await;
+                let player = match self.players.lookup(&login_or_display_name).await {
+                    Some(player) => player,
+                    None => {
+                        let msg = Error(NoMatchingPlayer);
+                        self.widget.show_popup(msg, &from.login).await;
+                        return;
+                    }
+                };
 
-                let maybe_player = players_state
-                    .info(&login_or_display_name)
-                    .or_else(|| players_state.display_name_info(&login_or_display_name));
+                let duration = chrono::Duration::seconds(secs as i64);
+                self.moderation.mute(&player.login, duration).await;
+                let msg = ServerMessage::PlayerMuted {
+                    admin_name,
+                    player_name: &player.display_name.formatted,
+                };
+                announce(&self.server, &self.players, msg).await;
+            }
 
-                let player = match maybe_player {
+            UnmutePlayer {
+                login_or_display_name,
+            } => {
+                let player = match self.players.lookup(&login_or_display_name).await {
                     Some(player) => player,
                     None => {
-                        let msg = Error(UnknownPlayer);
+                        let msg = Error(NoMatchingPlayer);
+                        self.widget.show_popup(msg, &from.login).await;
+                        return;
+                    }
+                };
+
+                if self.moderation.unmute(&player.login).await {
+                    let msg = ServerMessage::PlayerUnmuted {
+                        admin_name,
+                        player_name: &player.display_name.formatted,
+                    };
+                    announce(&self.server, &self.players, msg).await;
+                } else {
+                    let msg = Error(PlayerNotMuted);
+                    self.widget.show_popup(msg, &from.login).await;
+                }
+            }
+
+            MovePlayerToSpectator {
+                login_or_display_name,
+            } => {
+                let player = match self
+                    .players
+                    .lookup_unambiguous(&login_or_display_name)
+                    .await
+                {
+                    Some(player) => player,
+                    None => {
+                        let msg = Error(NoMatchingPlayer);
                         self.widget.show_popup(msg, &from.login).await;
                         return;
                     }
@@ -422,7 +727,7 @@ impl Controller {
                         admin_name,
                         player_name: &player.display_name.formatted,
                     };
-                    announce(&self.server, msg).await;
+                    announce(&self.server, &self.players, msg).await;
                 } else {
                     let msg = Error(UnknownPlayer);
                     self.widget.show_popup(msg, &from.login).await;
@@ -446,6 +751,7 @@ impl Controller {
                         Ok(_) => {
                             announce(
                                 &self.server,
+                                &self.players,
                                 ServerMessage::ModeChanging { admin_name, mode },
                             )
                             .await;
@@ -465,6 +771,7 @@ impl Controller {
                     Ok(_) => {
                         announce(
                             &self.server,
+                            &self.players,
                             ServerMessage::LoadedMatchSettings {
                                 admin_name,
                                 settings_name: &file_name.trim_end_matches(".txt"),
@@ -509,6 +816,7 @@ impl Controller {
                     Ok(_) => {
                         announce(
                             &self.server,
+                            &self.players,
                             ServerMessage::SavedMatchSettings {
                                 admin_name,
                                 settings_name: &file_name.trim_end_matches(".txt"),
@@ -522,13 +830,304 @@ impl Controller {
                     }
                 }
             }
+
+            ReviewRecords => {
+                let since = Utc::now().naive_utc() - chrono::Duration::days(7);
+                let flagged = self
+                    .db
+                    .flagged_records()
+                    .await
+                    .expect("failed to load flagged records");
+                let recent = self
+                    .db
+                    .top_records_since(&since)
+                    .await
+                    .expect("failed to load recent records");
+                let removals = self
+                    .db
+                    .record_removals(10)
+                    .await
+                    .expect("failed to load record removals");
+                let msg = Result(RecordReview {
+                    flagged,
+                    recent,
+                    removals,
+                });
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            ApproveRecord { id } => {
+                let flagged = self
+                    .db
+                    .delete_flagged_record(id)
+                    .await
+                    .expect("failed to delete flagged record");
+                let flagged = match flagged {
+                    Some(flagged) => flagged,
+                    None => {
+                        let msg = Error(UnknownRecord);
+                        self.widget.show_popup(msg, &from.login).await;
+                        return;
+                    }
+                };
+
+                let pb = self
+                    .db
+                    .player_record(&flagged.map_uid, &flagged.player_login, flagged.nb_laps)
+                    .await
+                    .expect("failed to load player record");
+                let is_improvement = pb.map(|pb| flagged.millis < pb.millis).unwrap_or(true);
+                if !is_improvement {
+                    let msg = Error(FlaggedRecordNotImproved);
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
+
+                log::info!(
+                    "{} approved flagged record #{} of '{}' on '{}'",
+                    &from.login,
+                    id,
+                    &flagged.player_login,
+                    &flagged.map_uid
+                );
+                self.on_flagged_record_reviewed(admin_name, &flagged, true)
+                    .await;
+
+                let map_uid = flagged.map_uid.clone();
+                let rec = RecordEvidence::from(flagged);
+                self.db
+                    .upsert_record(&rec)
+                    .await
+                    .expect("failed to store approved record");
+                self.on_records_changed(&map_uid).await;
+            }
+
+            RejectRecord { id } => {
+                let flagged = self
+                    .db
+                    .delete_flagged_record(id)
+                    .await
+                    .expect("failed to delete flagged record");
+                let flagged = match flagged {
+                    Some(flagged) => flagged,
+                    None => {
+                        let msg = Error(UnknownRecord);
+                        self.widget.show_popup(msg, &from.login).await;
+                        return;
+                    }
+                };
+
+                log::info!(
+                    "{} rejected flagged record #{} of '{}' on '{}'",
+                    &from.login,
+                    id,
+                    &flagged.player_login,
+                    &flagged.map_uid
+                );
+                self.on_flagged_record_reviewed(admin_name, &flagged, false)
+                    .await;
+            }
+
+            RemoveRecord {
+                map_uid,
+                login,
+                flying_lap,
+            } => {
+                let map = self.db.map(map_uid).await.expect("failed to load map");
+                let map_nb_laps = map.as_ref().and_then(|map| map.nb_laps);
+
+                // Flying lap records only exist on multi-lap maps.
+                if flying_lap && map_nb_laps.is_none() {
+                    let msg = Error(UnknownRecord);
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
+                let nb_laps = if flying_lap {
+                    0
+                } else {
+                    map_nb_laps.unwrap_or(0)
+                };
+
+                let removal = self
+                    .db
+                    .remove_record(map_uid, login, nb_laps, &from.login)
+                    .await
+                    .expect("failed to remove record");
+                if removal.is_none() {
+                    let msg = Error(UnknownRecord);
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
+
+                self.on_records_changed(map_uid).await;
+
+                let map_name = match map {
+                    Some(map) => map.name.formatted,
+                    None => map_uid.to_string(),
+                };
+                announce(
+                    &self.server,
+                    &self.players,
+                    ServerMessage::RecordRemoved {
+                        admin_name,
+                        player_name: &try_display_name(login.to_string()).await,
+                        map_name: &map_name,
+                    },
+                )
+                .await;
+            }
+
+            ListAnnouncements => {
+                let msg = Result(AnnouncementList(self.announcements.list().await));
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            AddAnnouncement { trigger, message } => {
+                self.announcements.add(message, trigger).await;
+                log::info!("{} added announcement: {}", &from.login, message);
+
+                let msg = Result(AnnouncementList(self.announcements.list().await));
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            SetAnnouncementWindow { id, window } => {
+                if !self.announcements.set_window(id, window).await {
+                    let msg = Error(UnknownAnnouncement);
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
+
+                let msg = Result(AnnouncementList(self.announcements.list().await));
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            RemoveAnnouncement { id } => {
+                if !self.announcements.remove(id).await {
+                    let msg = Error(UnknownAnnouncement);
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
+                log::info!("{} removed announcement #{}", &from.login, id);
+
+                let msg = Result(AnnouncementList(self.announcements.list().await));
+                self.widget.show_popup(msg, &from.login).await;
+            }
         };
     }
 
+    /// Tell all admins that a flagged record was approved or rejected.
+    async fn on_flagged_record_reviewed(
+        &self,
+        admin_name: &str,
+        flagged: &FlaggedRecord,
+        approved: bool,
+    ) {
+        let player_name = self
+            .db
+            .player(&flagged.player_login)
+            .await
+            .expect("failed to load player")
+            .map(|p| p.display_name.formatted)
+            .unwrap_or_else(|| flagged.player_login.clone());
+        let map_name = self
+            .db
+            .map(&flagged.map_uid)
+            .await
+            .expect("failed to load map")
+            .map(|map| map.name.formatted)
+            .unwrap_or_else(|| flagged.map_uid.clone());
+
+        for info in self.players.info_all().await {
+            if !self.config.is_admin(&info.login).await {
+                continue;
+            }
+            let msg = PlayerMessage::FlaggedRecordReviewed {
+                admin_name: admin_name.to_string(),
+                player_name: player_name.clone(),
+                map_name: map_name.clone(),
+                millis: flagged.millis as usize,
+                approved,
+            };
+            tell(&self.server, &self.players, msg, &info.login).await;
+        }
+    }
+
+    /// Reload the records of the given map if it is the current map,
+    /// and re-calculate the server ranking.
+    async fn on_records_changed(&self, map_uid: &str) {
+        if let Some(map) = self.playlist.current_map().await {
+            if map.uid == map_uid {
+                self.records.load_for_map(&map).await;
+            }
+        }
+
+        let controller = self.clone(); // 'self' with 'static lifetime
+        let _ = tokio::spawn(async move {
+            let ranking_change = controller.ranking.update().await;
+            let new_ranking_ev = ControllerEvent::NewServerRanking(ranking_change);
+            controller.on_controller_event(new_ranking_ev).await;
+        });
+    }
+
+    /// Show the page at `page` of the maps that match `query`.
+    /// Playlist maps are listed before other maps.
+    pub(super) async fn show_map_list(&self, login: &str, query: &str, page: usize) {
+        let query = ListQuery::parse(query);
+        let playlist = self.server.playlist().await;
+        let maps = self.db.maps(vec![]).await.expect("failed to load maps");
+
+        let (mut in_playlist, mut not_in_playlist): (Vec<&Map>, Vec<&Map>) = maps
+            .iter()
+            .filter(|map| query.matches_map(map))
+            .partition(|m1| playlist.iter().any(|m2| m1.uid == m2.uid));
+        query.sort_maps(&mut in_playlist);
+        query.sort_maps(&mut not_in_playlist);
+
+        let maps = in_playlist
+            .into_iter()
+            .map(|map| (map, true))
+            .chain(not_in_playlist.into_iter().map(|map| (map, false)))
+            .collect();
+
+        let msg = CommandOutput::Result(CommandResultOutput::MapList {
+            maps: Page::of(maps, page, LIST_PAGE_SIZE),
+            query: &query,
+        });
+        self.widget.show_popup(msg, login).await;
+    }
+
+    /// Show the page at `page` of the connected players that match `query`.
+    pub(super) async fn show_player_list(&self, login: &str, query: &str, page: usize) {
+        let query = ListQuery::parse(query);
+        let players_state = self.players.lock().await;
+
+        let mut players: Vec<&PlayerInfo> = players_state
+            .info_all()
+            .into_iter()
+            .filter(|player| query.matches_player(player))
+            .collect();
+        query.sort_players(&mut players);
+
+        let msg = CommandOutput::Result(CommandResultOutput::PlayerList {
+            players: Page::of(players, page, LIST_PAGE_SIZE),
+            query: &query,
+        });
+        self.widget.show_popup(msg, login).await;
+    }
+
+    /// The map with the given UID, or the current map if no UID is given.
+    async fn cmd_target_map(&self, map_uid: Option<&str>) -> Option<Map> {
+        match map_uid {
+            Some(uid) => self.db.map(uid).await.expect("failed to load map"),
+            None => self.playlist.current_map().await,
+        }
+    }
+
     pub(super) async fn on_super_admin_cmd(&self, from: &PlayerInfo, cmd: SuperAdminCommand<'_>) {
         use CommandConfirmOutput::*;
         use CommandErrorOutput::*;
         use CommandOutput::*;
+        use CommandResultOutput::*;
 
         use DangerousCommand::*;
         use SuperAdminCommand::*;
@@ -570,9 +1169,91 @@ impl Controller {
                 let msg = Confirm(Shutdown, ConfirmShutdown);
                 self.widget.show_popup(msg, &from.login).await;
             }
+
+            ListRoles => {
+                let roles = self.db.roles().await.expect("failed to load roles");
+                let msg = Result(RoleList(roles));
+                self.widget.show_popup(msg, &from.login).await;
+            }
+
+            GrantRole { role_name, grantee } => {
+                self.on_role_cmd(from, role_name, grantee, true).await
+            }
+
+            RevokeRole { role_name, grantee } => {
+                self.on_role_cmd(from, role_name, grantee, false).await
+            }
         }
     }
 
+    /// Grant or revoke a role, or one of its commands, and list the updated roles.
+    async fn on_role_cmd(
+        &self,
+        from: &PlayerInfo,
+        role_name: &str,
+        grantee: RoleGrantee<'_>,
+        grant: bool,
+    ) {
+        use CommandErrorOutput::*;
+        use CommandOutput::*;
+        use CommandResultOutput::*;
+
+        let changed = match grantee {
+            RoleGrantee::Player(login) => {
+                if self
+                    .db
+                    .player(login)
+                    .await
+                    .expect("failed to load player")
+                    .is_none()
+                {
+                    self.widget
+                        .show_popup(Error(UnknownPlayer), &from.login)
+                        .await;
+                    return;
+                }
+                if grant {
+                    self.db.grant_role(login, role_name).await
+                } else {
+                    self.db.revoke_role(login, role_name).await
+                }
+                .expect("failed to update player roles")
+            }
+            RoleGrantee::Command(name) => {
+                let name = name.split_whitespace().collect::<Vec<&str>>().join(" ");
+                let options = grantable_commands();
+                if !options.contains(&name) {
+                    let msg = Error(UnknownCommandName {
+                        tried: &name,
+                        options,
+                    });
+                    self.widget.show_popup(msg, &from.login).await;
+                    return;
+                }
+                if grant {
+                    self.db.grant_command(role_name, &name).await
+                } else {
+                    self.db.revoke_command(role_name, &name).await
+                }
+                .expect("failed to update role commands")
+            }
+        };
+
+        if changed {
+            log::info!(
+                "{} {} role '{}': {:?}",
+                &from.login,
+                if grant { "granted" } else { "revoked" },
+                role_name,
+                grantee
+            );
+        }
+
+        let roles = self.db.roles().await.expect("failed to load roles");
+        let msg = Result(RoleList(roles));
+        self.widget.show_popup(msg, &from.login).await;
+    }
+
     pub(super) async fn on_dangerous_cmd(&self, from: &PlayerInfo, cmd: DangerousCommand<'_>) {
         use CommandErrorOutput::*;
         use CommandOutput::*;
@@ -607,6 +1288,7 @@ impl Controller {
 
                 announce(
                     &self.server,
+                    &self.players,
                     ServerMessage::MapDeleted {
                         admin_name,
                         map_name: &map.name.formatted,
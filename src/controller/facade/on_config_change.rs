@@ -1,24 +1,64 @@
-use crate::chat::ServerMessage;
+use futures::future::join_all;
+
+use crate::chat::{PlayerMessage, ServerMessage};
 use crate::controller::facade::announce;
-use crate::controller::{Controller, LivePlayers};
-use crate::event::ConfigDiff;
+use crate::controller::{tell, Controller, LiveConfig, LivePlayers};
+use crate::event::{ConfigDiff, ControllerEvent};
 
 impl Controller {
-    pub(super) async fn on_config_change(&self, from_login: &str, diff: ConfigDiff) {
-        use ConfigDiff::*;
-
-        let from_display_name = match self.players.display_name(from_login).await {
-            Some(name) => name,
-            None => return,
+    /// Apply changes to the config file, or tell super admins
+    /// why they were rejected.
+    pub(super) async fn reload_config(&self) {
+        let changes = match self.config.reload().await {
+            Ok(changes) => changes,
+            Err(err) => {
+                log::error!("rejected changes to the config file: {}", err);
+                for info in self.players.info_all().await {
+                    if !self.config.is_super_admin(&info.login).await {
+                        continue;
+                    }
+                    let msg = PlayerMessage::ConfigRejected {
+                        reason: err.to_string(),
+                    };
+                    tell(&self.server, &self.players, msg, &info.login).await;
+                }
+                return;
+            }
         };
 
+        if !changes.is_empty() {
+            log::info!("applied changes to the config file: {:?}", &changes);
+        }
+
+        join_all(changes.into_iter().map(|change| async move {
+            let ev = ControllerEvent::NewConfig {
+                change,
+                from_login: None,
+            };
+            self.on_controller_event(ev).await;
+        }))
+        .await;
+    }
+
+    pub(super) async fn on_config_change(&self, from_login: Option<&str>, diff: ConfigDiff) {
+        use ConfigDiff::*;
+
         match diff {
             NewTimeLimit { .. } => {
                 self.schedule.set_time_limit().await;
                 self.widget.refresh_schedule().await;
 
+                let from_login = match from_login {
+                    Some(login) => login,
+                    None => return,
+                };
+                let from_display_name = match self.players.display_name(from_login).await {
+                    Some(name) => name,
+                    None => return,
+                };
                 announce(
                     &self.server,
+                    &self.players,
                     ServerMessage::TimeLimitChanged {
                         admin_name: &from_display_name.formatted,
                     },
@@ -28,6 +68,27 @@ impl Controller {
             NewOutroDuration { .. } => {
                 self.widget.refresh_schedule().await;
             }
+            NewQueueConfig => {
+                if let Some(diff) = self.queue.sort_queue().await {
+                    let ev = ControllerEvent::NewQueue(diff);
+                    self.on_controller_event(ev).await;
+                }
+            }
+            NewSeasonConfig => {
+                self.ranking.update_season().await;
+            }
+            NewPoolCalendar => {
+                self.schedule.load_pool_switch().await;
+                self.widget.refresh_schedule().await;
+            }
+
+            // The server ranking is re-calculated with the new config at the end
+            // of the current map, so that its diffs remain meaningful.
+            NewServerRankingConfig => {}
+
+            // These are looked up in the config whenever they are needed.
+            NewRaceVoteConfig | NewAdmins | NewCurationConfig | NewAfkConfig
+            | NewRecordCheckConfig | NewChatConfig => {}
         }
     }
 }
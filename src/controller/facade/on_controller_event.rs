@@ -1,14 +1,24 @@
 use async_recursion::async_recursion;
+use chrono::Utc;
+use tokio::time::Duration;
 
-use crate::chat::{Command, CommandOutput, CommandResultOutput, ServerMessage, TopRankMessage};
+use crate::chat::{
+    command_spec, Command, CommandErrorOutput, CommandOutput, CommandResultOutput, PlayerMessage,
+    RaceVoteKind, ServerMessage, TopRankMessage,
+};
+use crate::config::CurationAction;
 use crate::constants::{
     MAX_ANNOUNCED_RANK, MAX_ANNOUNCED_RECORD, MAX_ANNOUNCED_RECORD_IMPROVEMENT,
     MAX_NB_ANNOUNCED_RANKS,
 };
 use crate::controller::facade::announce;
-use crate::controller::{Controller, LiveConfig, LivePlayers, LivePlaylist, LiveQueue};
+use crate::controller::{
+    flying_lap_evidence, run_evidence, tell, AfkAction, ChatBlockReason, ChatVerdict, Controller,
+    Implausibility, LiveConfig, LivePlayers, LivePlaylist, LiveQueue, RaceVoteOutcome,
+};
+use crate::database::PlayerQueries;
 use crate::event::{ControllerEvent, PbDiff, PlayerTransition, ServerRankingDiff};
-use crate::server::Calls;
+use crate::server::{Calls, CheckpointEvent, ModeCalls, PlayerInfo};
 
 impl Controller {
     #[async_recursion]
@@ -18,13 +28,17 @@ impl Controller {
         log::debug!("{:#?}", &event);
 
         if let Some(server_msg) = self.message_from_event(&event).await {
-            announce(&self.server, server_msg).await;
+            announce(&self.server, &self.players, server_msg).await;
         }
 
         match event {
             BeginRun { player_login } => {
                 // If this is the first time a player is at the start line,
                 // their intro has just ended.
+                self.afk.touch(&player_login).await;
+                self.records.begin_run(&player_login).await;
+                self.race.begin_run(&player_login).await;
+
                 let is_player_intro_end = self.race.add_contestant(&player_login).await;
                 if is_player_intro_end {
                     let ev = ControllerEvent::EndIntro {
@@ -37,30 +51,86 @@ impl Controller {
             }
 
             ContinueRun(event) => {
+                self.afk.touch(&event.player_login).await;
+
+                // Sending the split widget should not delay the handling of runs;
+                // run in separate task.
+                let controller = self.clone(); // 'self' with 'static lifetime
+                let split_event = event.clone(); // allow data to outlive the current scope
+                let _ = tokio::spawn(async move {
+                    controller.widget.show_checkpoint_split(&split_event).await;
+                });
+
+                self.race.update(&event).await;
+
+                if event.is_lap_finish {
+                    // Storing records involves file IO; run in separate task.
+                    let controller = self.clone(); // 'self' with 'static lifetime
+                    let event = event.clone(); // allow data to outlive the current scope
+                    let _ = tokio::spawn(async move {
+                        controller.on_lap_finish(&event).await;
+                    });
+                }
+
                 if !event.is_finish {
                     return;
                 }
 
-                self.race.update(&event).await;
+                if let Some(map_uid) = self.playlist.current_map_uid().await {
+                    self.schedule
+                        .add_run(&map_uid, event.race_time_millis)
+                        .await;
+                }
 
                 // Storing records involves file IO; run in separate task.
                 let controller = self.clone(); // 'self' with 'static lifetime
                 let _ = tokio::spawn(async move {
-                    if let Some(pb_diff) = controller.records.end_run(&event).await {
-                        let ev = ControllerEvent::FinishRun(pb_diff);
-                        controller.on_controller_event(ev).await;
+                    let evidence = controller
+                        .playlist
+                        .current_map()
+                        .await
+                        .map(|map| run_evidence(&event, &map));
+                    match controller.records.end_run(&event).await {
+                        Ok(Some(pb_diff)) => {
+                            // Only plausible runs count towards the season.
+                            if let Some(evidence) = evidence {
+                                if pb_diff.new_record.is_some()
+                                    || controller.records.is_plausible(&evidence).await
+                                {
+                                    controller.ranking.add_season_run(&evidence).await;
+                                }
+                            }
+                            let ev = ControllerEvent::FinishRun(pb_diff);
+                            controller.on_controller_event(ev).await;
+                        }
+                        Ok(None) => {}
+                        Err(reason) => {
+                            controller
+                                .on_implausible_run(&event.player_login, reason)
+                                .await;
+                        }
                     }
                 });
             }
 
-            DesyncRun { .. } => {
-                // TODO unsure if we have to do something here
-                //  => as a precaution, we could not consider player runs until they give
-                //     up their current run
+            DesyncRun { player_login } => {
+                // Do not consider this run for records, until the player starts a new one.
+                self.records.taint_run(&player_login).await;
+
+                let controller = self.clone(); // 'self' with 'static lifetime
+                let player_login = player_login.to_string(); // allow data to outlive the current scope
+                let _ = tokio::spawn(async move {
+                    controller.on_incoherence(&player_login).await;
+                });
             }
 
             BeginIntro => {
                 self.widget.begin_intro().await;
+
+                for text in self.announcements.on_map_start().await {
+                    let msg = ServerMessage::Announcement { text: &text };
+                    announce(&self.server, &self.players, msg).await;
+                }
             }
 
             EndIntro { .. } => {}
@@ -77,6 +147,8 @@ impl Controller {
             BeginOutro => {
                 self.widget.begin_outro_and_vote().await;
                 let _ = self.race.reset().await;
+                let _ = self.votes.cancel().await;
+                self.afk.pause().await;
 
                 // Spawn a task to re-calculate the server ranking,
                 // which could be expensive, depending on how we do it.
@@ -93,13 +165,18 @@ impl Controller {
 
             EndOutro => {
                 self.race.reset().await;
+                self.votes.reset().await;
+                self.afk.resume().await;
 
                 self.schedule.set_time_limit().await;
+                self.schedule.load_pool_switch().await;
 
                 self.widget.end_outro().await;
             }
 
             ChangeMap => {
+                let prev_map_uid = self.playlist.current_map_uid().await;
+
                 // Update the current map
                 let new_playlist_index = self.server.playlist_next_index().await;
                 let next_map = self.playlist.set_index(new_playlist_index).await;
@@ -112,6 +189,14 @@ impl Controller {
 
                 // Load data for next map
                 self.records.load_for_map(&next_map).await;
+
+                // Check whether the previous map should be removed from the playlist.
+                if let Some(map_uid) = prev_map_uid {
+                    let controller = self.clone(); // 'self' with 'static lifetime
+                    let _ = tokio::spawn(async move {
+                        controller.curate(&map_uid).await;
+                    });
+                }
             }
 
             BeginVote => {
@@ -130,6 +215,9 @@ impl Controller {
             }
 
             EndVote => {
+                // Switch to a scheduled map pool before the next map is decided.
+                self.activate_scheduled_pool().await;
+
                 // Sort the queue, now that all restart votes have been cast.
                 // The next map is now at the top of the queue.
                 if let Some(diff) = self.queue.sort_queue().await {
@@ -145,7 +233,7 @@ impl Controller {
                 self.prefs.reset_restart_votes().await;
 
                 let msg = ServerMessage::NextMap { map: &next_map };
-                announce(&self.server, msg).await;
+                announce(&self.server, &self.players, msg).await;
             }
 
             NewQueue(diff) => {
@@ -153,14 +241,27 @@ impl Controller {
             }
 
             NewPlayerList(diff) => {
+                // If idle players are moved to spectator slots, spectators keep
+                // their player slot until they are idle for too long.
+                let keep_player_slot = self.config.lock().await.afk.enabled;
                 if let PlayerTransition::MoveToSpectator = &diff.transition {
-                    // TODO we might not always want to free up their player slot!
-                    let _ = self.server.force_pure_spectator(&diff.info.login).await;
+                    if !keep_player_slot {
+                        let _ = self.server.force_pure_spectator(&diff.info.login).await;
+                    }
                 }
 
+                self.afk.update_for_player(&diff).await;
                 self.records.update_for_player(&diff).await;
                 self.prefs.update_for_player(&diff).await;
                 self.widget.refresh_for_player(&diff).await;
+
+                use PlayerTransition::*;
+                if matches!(diff.transition, AddPlayer | AddSpectator | AddPureSpectator) {
+                    if let Some(text) = self.announcements.on_join().await {
+                        let msg = PlayerMessage::Announcement { text };
+                        tell(&self.server, &self.players, msg, &diff.info.login).await;
+                    }
+                }
             }
 
             NewPlaylist(playlist_diff) => {
@@ -188,8 +289,11 @@ impl Controller {
                 self.widget.refresh_server_ranking(&change).await;
             }
 
-            IssueCommand(ctxt, Command::Help) => {
-                let msg = CommandOutput::Result(CommandResultOutput::CommandReference(ctxt));
+            IssueCommand(ctxt, Command::Help { topic }) => {
+                let msg = match topic.and_then(command_spec) {
+                    Some(spec) => CommandOutput::Result(CommandResultOutput::CommandHelp(spec)),
+                    None => CommandOutput::Result(CommandResultOutput::CommandReference(ctxt)),
+                };
                 self.widget.show_popup(msg, &ctxt.player.login).await;
             }
 
@@ -221,10 +325,307 @@ impl Controller {
                 if message.is_empty() {
                     return;
                 }
-                self.server
-                    .chat_send_from_to(message, &from.login, vec![])
-                    .await
-                    .expect("failed to forward chat message");
+                let reason = match self.moderation.moderate(&from.login, message).await {
+                    ChatVerdict::Deliver(message) => {
+                        self.server
+                            .chat_send_from_to(&message, &from.login, vec![])
+                            .await
+                            .expect("failed to forward chat message");
+                        return;
+                    }
+                    ChatVerdict::Block(reason) => reason,
+                };
+
+                log::warn!(
+                    "blocked message from {}: '{}' ({:?})",
+                    &from.login,
+                    message,
+                    &reason
+                );
+                let msg = match reason {
+                    ChatBlockReason::Muted { secs_left } => PlayerMessage::ChatMuted { secs_left },
+                    ChatBlockReason::Flooding { mute_secs } => {
+                        PlayerMessage::ChatFlooding { mute_secs }
+                    }
+                    ChatBlockReason::BlockedWord(word) => PlayerMessage::ChatBlockedWord { word },
+                };
+                tell(&self.server, &self.players, msg, &from.login).await;
+            }
+        }
+    }
+
+    /// Replace the playlist with the map pool that is scheduled in the config,
+    /// unless it was already activated, or another pool was activated
+    /// by an admin since.
+    async fn activate_scheduled_pool(&self) {
+        let now = Utc::now().naive_utc();
+        let (at, pool_name) = match self.config.lock().await.scheduled_pool_at(&now) {
+            Some((at, pool_name)) => (at, pool_name.to_string()),
+            None => return,
+        };
+
+        {
+            let playlist_state = self.playlist.lock().await;
+            let is_active = playlist_state.pool.as_deref() == Some(pool_name.as_str());
+            let is_overridden = playlist_state.pool_since.map_or(false, |since| since >= at);
+            if is_active || is_overridden {
+                return;
+            }
+        }
+
+        match self.playlist.switch_pool(&pool_name).await {
+            Ok(diffs) => {
+                for diff in diffs {
+                    let ev = ControllerEvent::NewPlaylist(diff);
+                    self.on_controller_event(ev).await;
+                }
+                let msg = ServerMessage::ScheduledPool {
+                    pool_name: &pool_name,
+                };
+                announce(&self.server, &self.players, msg).await;
+            }
+            Err(_) => log::warn!("failed to activate scheduled map pool '{}'", pool_name),
+        }
+    }
+
+    /// Store a flying lap record, and remember it for the seasonal ranking.
+    async fn on_lap_finish(&self, event: &CheckpointEvent) {
+        let evidence = match self.playlist.current_map().await {
+            Some(map) => flying_lap_evidence(event, &map),
+            None => None,
+        };
+
+        match self.records.end_lap(event).await {
+            Ok(Some(pb_diff)) => {
+                // Only plausible laps count towards the season.
+                if let Some(evidence) = evidence {
+                    if pb_diff.new_record.is_some() || self.records.is_plausible(&evidence).await {
+                        self.ranking.add_season_run(&evidence).await;
+                    }
+                }
+                if let Some(record) = pb_diff.new_record {
+                    let msg = PlayerMessage::FlyingLapPb {
+                        millis: record.millis as usize,
+                        map_rank: record.map_rank as usize,
+                    };
+                    tell(&self.server, &self.players, msg, &event.player_login).await;
+                }
+            }
+            Ok(None) => {}
+            Err(reason) => {
+                self.on_implausible_run(&event.player_login, reason).await;
+            }
+        }
+    }
+
+    /// Tell a player that their run was either rejected, or flagged for review.
+    async fn on_implausible_run(&self, player_login: &str, reason: Implausibility) {
+        let msg = if reason.is_invalid() {
+            PlayerMessage::RunRejected {
+                reason: reason.to_string(),
+            }
+        } else {
+            PlayerMessage::RunFlagged {
+                reason: reason.to_string(),
+            }
+        };
+        tell(&self.server, &self.players, msg, player_login).await;
+    }
+
+    /// Remember an incoherent run of the specified player, and notify
+    /// admins if that player has had too many recently.
+    async fn on_incoherence(&self, player_login: &str) {
+        let map_uid = match self.playlist.current_map_uid().await {
+            Some(map_uid) => map_uid,
+            None => return,
+        };
+        self.db
+            .add_incoherence(player_login, &map_uid)
+            .await
+            .expect("failed to store incoherence");
+
+        let max_per_hour = self.config.lock().await.incoherence.max_per_hour;
+        if max_per_hour == 0 {
+            return;
+        }
+
+        let since = Utc::now().naive_utc() - chrono::Duration::hours(1);
+        let nb_incoherences = self
+            .db
+            .nb_incoherences(player_login, &since)
+            .await
+            .expect("failed to count incoherences");
+
+        // Only notify once, when the player exceeds the limit.
+        if nb_incoherences != max_per_hour as i64 + 1 {
+            return;
+        }
+
+        let player_name = match self.players.display_name(player_login).await {
+            Some(name) => name.formatted,
+            None => return,
+        };
+        for info in self.players.info_all().await {
+            if !self.config.is_admin(&info.login).await {
+                continue;
+            }
+            let msg = PlayerMessage::FrequentIncoherences {
+                player_name: player_name.clone(),
+                player_login: player_login.to_string(),
+                nb_incoherences,
+            };
+            tell(&self.server, &self.players, msg, &info.login).await;
+        }
+    }
+
+    /// Display the live race ranking, if it changed since it was last displayed.
+    pub(super) async fn refresh_live_ranking(&self) {
+        if self.race.take_live_ranking_change().await {
+            self.widget.refresh_live_ranking().await;
+        }
+    }
+
+    /// Warn idle players, or move them to spectator slots
+    /// if they have been idle for too long.
+    pub(super) async fn check_afk(&self) {
+        for (login, action) in self.afk.check().await {
+            match action {
+                AfkAction::Warn { secs_left } => {
+                    let msg = PlayerMessage::AfkWarning { secs_left };
+                    tell(&self.server, &self.players, msg, &login).await;
+                }
+                AfkAction::MoveToSpectator => {
+                    // Fails if the player has disconnected in the meantime.
+                    if self.server.force_pure_spectator(&login).await.is_err() {
+                        continue;
+                    }
+                    log::info!("moved idle player '{}' to spectator", login);
+                    if let Some(name) = self.players.display_name(&login).await {
+                        let msg = ServerMessage::IdlePlayerMovedToSpectator {
+                            player_name: &name.formatted,
+                        };
+                        announce(&self.server, &self.players, msg).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Display the announcements whose interval elapsed.
+    pub(super) async fn announce_due(&self) {
+        for text in self.announcements.due().await {
+            let msg = ServerMessage::Announcement { text: &text };
+            announce(&self.server, &self.players, msg).await;
+        }
+    }
+
+    /// Start a vote to skip the current map, or to extend its time limit,
+    /// and end it once the configured vote duration has passed.
+    pub(super) async fn start_race_vote(&self, from: &PlayerInfo, kind: RaceVoteKind) {
+        let vote_id = match self.votes.start(kind, from.uid).await {
+            Ok(vote_id) => vote_id,
+            Err(err) => {
+                let msg = CommandOutput::Error(CommandErrorOutput::CannotStartVote(err));
+                self.widget.show_popup(msg, &from.login).await;
+                return;
+            }
+        };
+
+        let msg = ServerMessage::RaceVoteStarted {
+            player_name: &from.display_name.formatted,
+            kind,
+        };
+        announce(&self.server, &self.players, msg).await;
+
+        // The player that started the vote is in favour; this can
+        // already be enough votes if there are few players.
+        if let Some(outcome) = self.votes.cast(from.uid, true).await {
+            self.on_race_vote_outcome(outcome).await;
+            return;
+        }
+        self.widget.refresh_race_vote().await;
+
+        let vote_duration = self.config.lock().await.timeattack.votes.vote_duration_secs;
+        let controller = self.clone(); // 'self' with 'static lifetime
+        let _ = tokio::spawn(async move {
+            tokio::time::delay_for(Duration::from_secs(vote_duration as u64)).await;
+            if let Some(outcome) = controller.votes.end(vote_id).await {
+                controller.on_race_vote_outcome(outcome).await;
+            }
+        });
+    }
+
+    /// Skip the current map, or extend its time limit, if players voted to do so.
+    pub(super) async fn on_race_vote_outcome(&self, outcome: RaceVoteOutcome) {
+        self.widget.refresh_race_vote().await;
+
+        if !outcome.passed {
+            let msg = ServerMessage::RaceVoteFailed { kind: outcome.kind };
+            announce(&self.server, &self.players, msg).await;
+            return;
+        }
+
+        let msg = ServerMessage::RaceVotePassed { kind: outcome.kind };
+        announce(&self.server, &self.players, msg).await;
+
+        match outcome.kind {
+            RaceVoteKind::Skip => {
+                let _ = self.server.end_map().await;
+            }
+            RaceVoteKind::Extend => {
+                let extend_secs = self.config.lock().await.timeattack.votes.extend_secs;
+                let extension = chrono::Duration::seconds(extend_secs as i64);
+                self.schedule.extend_time_limit(extension).await;
+                self.widget.refresh_schedule().await;
+            }
+        }
+    }
+
+    /// Remove the specified map from the playlist, or recommend admins to do so,
+    /// if it meets the curation thresholds.
+    ///
+    /// Admins are only told about a recommendation when it is new; they can
+    /// list all of them with `/playlist curate`.
+    async fn curate(&self, map_uid: &str) {
+        let verdict = match self.curation.evaluate(map_uid).await {
+            Some(verdict) => verdict,
+            None => return,
+        };
+        let is_new_recommendation = self.curation.is_new_recommendation(&verdict).await;
+        if !verdict.should_remove {
+            return;
+        }
+
+        match self.curation.action().await {
+            CurationAction::Disabled => {}
+
+            CurationAction::Recommend if !is_new_recommendation => {}
+
+            CurationAction::Recommend => {
+                for info in self.players.info_all().await {
+                    if !self.config.is_admin(&info.login).await {
+                        continue;
+                    }
+                    let msg = PlayerMessage::CurationRecommendation {
+                        map_name: verdict.map.name.formatted.clone(),
+                        map_uid: verdict.map.uid.clone(),
+                        remove_ratio: verdict.remove_ratio(),
+                    };
+                    tell(&self.server, &self.players, msg, &info.login).await;
+                }
+            }
+
+            CurationAction::Remove => {
+                // Fails if this is the last map in the playlist, or if an admin
+                // has removed it in the meantime.
+                if let Ok(diff) = self.playlist.remove(map_uid).await {
+                    log::info!("curated '{}' ({})", verdict.map.name.plain(), map_uid);
+                    let msg = ServerMessage::CuratedMap { map: &verdict.map };
+                    announce(&self.server, &self.players, msg).await;
+
+                    let ev = ControllerEvent::NewPlaylist(diff);
+                    self.on_controller_event(ev).await;
+                }
             }
         }
     }
@@ -1,5 +1,6 @@
 use crate::chat::{Command, CommandContext, CommandErrorOutput, CommandOutput};
 use crate::controller::{Controller, LiveConfig, LivePlayers};
+use crate::database::RoleQueries;
 use crate::event::ControllerEvent;
 use crate::server::{Calls, ModeScriptSectionCallback, PlayloopCallback, ServerEvent};
 use crate::widget::Action;
@@ -82,22 +83,12 @@ impl Controller {
             } => {
                 // FIXME this is only PoC
                 //  => build the context from state
-                let cfg = self.config.lock().await;
                 let player = self.players.info(&from_login).await.unwrap();
                 let mode = self.server.mode().await.script;
                 let warmup = self.server.warmup_status().await;
                 let pause = self.server.pause_status().await;
 
-                let player_role = cfg.role_of(&from_login);
-
-                let ctxt = CommandContext {
-                    cmd: &message,
-                    player: &player,
-                    mode: &mode,
-                    player_role,
-                    warmup: &warmup,
-                    pause: &pause,
-                };
+                let player_role = self.config.lock().await.role_of(&from_login);
 
                 if !message.starts_with('/') {
                     // Message is not a command
@@ -109,6 +100,22 @@ impl Controller {
                     return;
                 }
 
+                let granted_commands = self
+                    .db
+                    .granted_commands(&from_login)
+                    .await
+                    .expect("failed to load granted commands");
+
+                let ctxt = CommandContext {
+                    cmd: &message,
+                    player: &player,
+                    mode: &mode,
+                    player_role,
+                    granted_commands: &granted_commands,
+                    warmup: &warmup,
+                    pause: &pause,
+                };
+
                 match Command::try_from(ctxt) {
                     Ok(cmd) => {
                         let ev = ControllerEvent::IssueCommand(ctxt, cmd);
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+/// Find the logins of players whose login or display names match the given
+/// search term, and sort them from best to worst match.
+///
+/// Names match if they are equal to the search term, start with it, contain it,
+/// or are only a few typos apart from it. Letter case, whitespace and symbols
+/// are ignored.
+///
+/// # Arguments
+/// `names` - Pairs of logins and plain display names, that may contain
+///           several names for each login
+pub fn lookup_logins<'a, I>(term: &str, names: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let term = normalize(term);
+    if term.is_empty() {
+        return vec![];
+    }
+
+    let mut best_scores: HashMap<&'a str, usize> = HashMap::new();
+    for (login, name) in names {
+        let score = [login, name]
+            .iter()
+            .filter_map(|candidate| match_score(&term, &normalize(candidate)))
+            .min();
+        if let Some(score) = score {
+            let best = best_scores.entry(login).or_insert(score);
+            *best = (*best).min(score);
+        }
+    }
+
+    let mut matches: Vec<(&'a str, usize)> = best_scores.into_iter().collect();
+    matches.sort_by(|(a_login, a_score), (b_login, b_score)| {
+        a_score.cmp(b_score).then_with(|| a_login.cmp(b_login))
+    });
+    matches.into_iter().map(|(login, _)| login).collect()
+}
+
+/// Find the login of the player whose login or display name matches the given search
+/// term best, or `None` if no player, or more than one player match equally well.
+pub fn lookup_unique_login<'a, I>(term: &str, names: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let names: Vec<(&'a str, &'a str)> = names.into_iter().collect();
+    let normalized_term = normalize(term);
+    let score_of = |login: &str| {
+        names
+            .iter()
+            .filter(|(l, _)| *l == login)
+            .flat_map(|(l, name)| vec![*l, *name])
+            .filter_map(|candidate| match_score(&normalized_term, &normalize(candidate)))
+            .min()
+    };
+
+    let matches = lookup_logins(term, names.iter().copied());
+    match &matches[..] {
+        [] => None,
+        [only] => Some(*only),
+        [first, second, ..] if score_of(first) < score_of(second) => Some(*first),
+        _ => None,
+    }
+}
+
+/// Find the login of the only player whose login or display name matches the given
+/// search term, or `None` if no player, or more than one player match.
+///
+/// Players whose login or display name is equal to the search term are preferred
+/// over players whose names only resemble it.
+pub fn lookup_single_login<'a, I>(term: &str, names: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let names: Vec<(&'a str, &'a str)> = names.into_iter().collect();
+    let normalized_term = normalize(term);
+
+    let mut exact_matches: Vec<&'a str> = names
+        .iter()
+        .filter(|(login, name)| {
+            normalize(login) == normalized_term || normalize(name) == normalized_term
+        })
+        .map(|(login, _)| *login)
+        .collect();
+    exact_matches.sort_unstable();
+    exact_matches.dedup();
+    match &exact_matches[..] {
+        [] => {}
+        [only] => return Some(*only),
+        _ => return None,
+    }
+
+    match &lookup_logins(term, names)[..] {
+        [only] => Some(*only),
+        _ => None,
+    }
+}
+
+/// How well a name matches the search term. Lower is better,
+/// and `None` means that the name does not match.
+fn match_score(term: &str, name: &str) -> Option<usize> {
+    if name == term {
+        return Some(0);
+    }
+    if name.starts_with(term) {
+        return Some(1);
+    }
+    if name.contains(term) {
+        return Some(2);
+    }
+    let max_typos = (term.chars().count() / 4).max(1);
+    let typos = edit_distance(term, name);
+    if typos <= max_typos {
+        return Some(2 + typos);
+    }
+    None
+}
+
+/// Lower-case a name, and strip everything but letters and digits.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The Levenshtein distance between two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = prev_row[j] + if a_char == *b_char { 0 } else { 1 };
+            let insertion = row[j] + 1;
+            let deletion = prev_row[j + 1] + 1;
+            row.push(substitution.min(insertion).min(deletion));
+        }
+        prev_row = row;
+    }
+    prev_row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn computes_edit_distance() {
+        assert_eq!(0, edit_distance("steward", "steward"));
+        assert_eq!(1, edit_distance("steward", "stewart"));
+        assert_eq!(2, edit_distance("abc", "a"));
+        assert_eq!(3, edit_distance("", "abc"));
+    }
+
+    #[test]
+    fn matches_current_and_past_names() {
+        let names = vec![
+            ("login1", "Speedy Gonzales"),
+            ("login1", "Troublemaker"),
+            ("login2", "speedrunner"),
+            ("login3", "Trouble"),
+        ];
+
+        assert_eq!(
+            vec!["login3", "login1"],
+            lookup_logins("trouble", names.clone())
+        );
+        assert_eq!(
+            vec!["login1", "login2"],
+            lookup_logins("speed", names.clone())
+        );
+        assert_eq!(vec!["login1"], lookup_logins("Trublemaker", names.clone()));
+        assert_eq!(
+            Some(&"login2"),
+            lookup_logins("LOGIN2", names.clone()).first()
+        );
+        assert!(lookup_logins("nobody", names.clone()).is_empty());
+        assert!(lookup_logins("$$$", names).is_empty());
+    }
+
+    #[test]
+    fn requires_unique_best_match() {
+        let names = vec![("login1", "Speedy"), ("login2", "Speedster")];
+        assert_eq!(Some("login1"), lookup_unique_login("speedy", names.clone()));
+        assert_eq!(None, lookup_unique_login("speed", names.clone()));
+        assert_eq!(None, lookup_unique_login("nobody", names));
+    }
+
+    #[test]
+    fn requires_single_match() {
+        let names = vec![
+            ("login1", "Speedy"),
+            ("login2", "Speedster"),
+            ("login3", "Trouble"),
+        ];
+        assert_eq!(Some("login1"), lookup_single_login("speedy", names.clone()));
+        assert_eq!(Some("login3"), lookup_single_login("troub", names.clone()));
+        assert_eq!(None, lookup_single_login("speed", names.clone()));
+        assert_eq!(None, lookup_single_login("nobody", names));
+    }
+}
@@ -1,5 +1,13 @@
+pub(self) use afk::*;
+pub(self) use announcement::*;
 pub(self) use config::*;
+pub use curation::CurationVerdict;
+pub(self) use curation::*;
 pub use facade::Controller;
+pub use lookup::edit_distance;
+pub(self) use lookup::*;
+pub(self) use moderation::*;
+pub(self) use plausibility::*;
 pub(self) use player::*;
 pub(self) use playlist::*;
 pub(self) use preference::*;
@@ -7,14 +15,23 @@ pub(self) use queue::*;
 pub(self) use race::*;
 pub(self) use record::*;
 pub(self) use schedule::*;
+pub use sector::SectorAnalysis;
+pub(self) use sector::*;
 pub(self) use server_rank::*;
+pub(self) use vote::*;
 pub(self) use widget::*;
 
 use crate::chat::PlayerMessage;
 use crate::server::{Calls, Server};
 
+mod afk;
+mod announcement;
 mod config;
+mod curation;
 mod facade;
+mod lookup;
+mod moderation;
+mod plausibility;
 mod player;
 mod playlist;
 mod preference;
@@ -22,18 +39,19 @@ mod queue;
 mod race;
 mod record;
 mod schedule;
+mod sector;
 mod server_rank;
+mod vote;
 mod widget;
 
-async fn tell(server: &Server, message: PlayerMessage, to_login: &str) {
-    let message_str = message.to_string();
+/// Send a message to a player, in their language.
+async fn tell(server: &Server, players: &dyn LivePlayers, message: PlayerMessage, to_login: &str) {
+    let message_str = message.localize(players.language(to_login).await);
     if message_str.is_empty() {
         return;
     }
-    log::debug!("player msg @{}> {}", &to_login, &message_str);
+    log::debug!("player msg @{}> {}", &to_login, &message);
 
     // Assume any fault is due to the target player disconnecting.
-    let _ = server
-        .chat_send_to(&message.to_string(), vec![to_login])
-        .await;
+    let _ = server.chat_send_to(&message_str, vec![to_login]).await;
 }
@@ -0,0 +1,209 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::controller::LiveConfig;
+
+#[derive(Default)]
+struct ModerationState {
+    /// Logins of muted players, and the time until which they are muted.
+    muted_until: HashMap<String, NaiveDateTime>,
+
+    /// The times at which players sent their most recent chat messages,
+    /// within the flood limit's time window.
+    recent_messages: HashMap<String, VecDeque<NaiveDateTime>>,
+}
+
+/// The outcome of moderating a chat message.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChatVerdict {
+    /// Deliver the message, in which censored words may have been replaced.
+    Deliver(String),
+
+    /// Do not deliver the message.
+    Block(ChatBlockReason),
+}
+
+/// The reason why a chat message was not delivered.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ChatBlockReason {
+    /// The player is muted for this number of seconds.
+    Muted { secs_left: i64 },
+
+    /// The player exceeded the flood limit, and was muted for this number of seconds.
+    Flooding { mute_secs: i64 },
+
+    /// The message contained this blocked word.
+    BlockedWord(String),
+}
+
+/// This controller decides which chat messages are delivered,
+/// and keeps track of muted players.
+#[derive(Clone)]
+pub struct ModerationController {
+    state: Arc<RwLock<ModerationState>>,
+    live_config: Arc<dyn LiveConfig>,
+}
+
+impl ModerationController {
+    pub fn init(live_config: &Arc<dyn LiveConfig>) -> Self {
+        ModerationController {
+            state: Arc::new(RwLock::new(ModerationState::default())),
+            live_config: live_config.clone(),
+        }
+    }
+
+    /// Prevent a player from chatting for the given duration,
+    /// replacing any previous mute.
+    pub async fn mute(&self, player_login: &str, duration: Duration) {
+        let until = Utc::now().naive_utc() + duration;
+        let mut moderation_state = self.state.write().await;
+        moderation_state
+            .muted_until
+            .insert(player_login.to_string(), until);
+    }
+
+    /// Allow a muted player to chat again.
+    ///
+    /// Returns `false` if the player was not muted.
+    pub async fn unmute(&self, player_login: &str) -> bool {
+        let now = Utc::now().naive_utc();
+        let mut moderation_state = self.state.write().await;
+        match moderation_state.muted_until.remove(player_login) {
+            Some(until) => until > now,
+            None => false,
+        }
+    }
+
+    /// Decide whether a player's chat message should be delivered,
+    /// and replace censored words.
+    ///
+    /// Players that exceed the flood limit are muted.
+    pub async fn moderate(&self, player_login: &str, message: &str) -> ChatVerdict {
+        use ChatBlockReason::*;
+
+        let config = self.live_config.lock().await.chat.clone();
+        let now = Utc::now().naive_utc();
+        let mut moderation_state = self.state.write().await;
+
+        if let Some(until) = moderation_state.muted_until.get(player_login).copied() {
+            if until > now {
+                let secs_left = (until - now).num_seconds().max(1);
+                return ChatVerdict::Block(Muted { secs_left });
+            }
+            moderation_state.muted_until.remove(player_login);
+        }
+
+        if config.flood_max_messages > 0 {
+            let window_start = now - Duration::seconds(config.flood_window_secs as i64);
+            let recent = moderation_state
+                .recent_messages
+                .entry(player_login.to_string())
+                .or_insert_with(VecDeque::new);
+            recent.retain(|sent_at| *sent_at > window_start);
+            recent.push_back(now);
+
+            if recent.len() > config.flood_max_messages as usize {
+                recent.clear();
+                let mute_secs = config.flood_mute_secs as i64;
+                moderation_state
+                    .muted_until
+                    .insert(player_login.to_string(), now + Duration::seconds(mute_secs));
+                return ChatVerdict::Block(Flooding { mute_secs });
+            }
+        }
+
+        match filter_words(message, &config.censored_words, &config.blocked_words) {
+            Ok(filtered) => ChatVerdict::Deliver(filtered),
+            Err(word) => ChatVerdict::Block(BlockedWord(word)),
+        }
+    }
+}
+
+/// Replace censored words in a message with asterisks, or return the first
+/// blocked word that it contains.
+///
+/// Words only match if they are not part of a longer word.
+/// Letter case is ignored.
+fn filter_words(message: &str, censored: &[String], blocked: &[String]) -> Result<String, String> {
+    let mut chars: Vec<char> = message.chars().collect();
+
+    if let Some(word) = blocked
+        .iter()
+        .find(|word| !word_positions(&chars, word).is_empty())
+    {
+        return Err(word.clone());
+    }
+
+    for word in censored {
+        let len = word.chars().count();
+        for start in word_positions(&chars, word) {
+            chars[start..start + len].iter_mut().for_each(|c| *c = '*');
+        }
+    }
+
+    Ok(chars.into_iter().collect())
+}
+
+/// The indices at which the given word occurs in a text, but not
+/// as part of a longer word.
+fn word_positions(text: &[char], word: &str) -> Vec<usize> {
+    let word: Vec<char> = word.chars().collect();
+    if word.is_empty() || word.len() > text.len() {
+        return vec![];
+    }
+
+    let eq_ignore_case = |a: &char, b: &char| a.to_lowercase().eq(b.to_lowercase());
+    let is_boundary = |c: Option<&char>| c.map(|c| !c.is_alphanumeric()).unwrap_or(true);
+
+    (0..=text.len() - word.len())
+        .filter(|&start| {
+            text[start..start + word.len()]
+                .iter()
+                .zip(word.iter())
+                .all(|(a, b)| eq_ignore_case(a, b))
+        })
+        .filter(|&start| {
+            let before = start.checked_sub(1).and_then(|idx| text.get(idx));
+            let after = text.get(start + word.len());
+            is_boundary(before) && is_boundary(after)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn censors_whole_words() {
+        let censored = words(&["darn"]);
+        assert_eq!(
+            Ok("**** it, DARNIT, ****!".to_string()),
+            filter_words("darn it, DARNIT, DaRn!", &censored, &[])
+        );
+        assert_eq!(
+            Ok("nothing to see".to_string()),
+            filter_words("nothing to see", &censored, &[])
+        );
+    }
+
+    #[test]
+    fn blocks_whole_words() {
+        let blocked = words(&["spam", "scam"]);
+        assert_eq!(
+            Err("scam".to_string()),
+            filter_words("this is a SCAM", &[], &blocked)
+        );
+        assert_eq!(
+            Ok("scammers".to_string()),
+            filter_words("scammers", &[], &blocked)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use thiserror::Error;
+
+use crate::config::RecordCheckConfig;
+use crate::database::{Map, RecordEvidence};
+
+/// Reasons why a run is implausible.
+#[derive(Error, Debug, PartialEq)]
+pub enum Implausibility {
+    /// The checkpoint count in the map header does not always match the
+    /// number of checkpoints that are crossed in a run, which is why
+    /// these runs are reviewed instead of rejected.
+    #[error("expected {expected} checkpoints, but got {actual}")]
+    CheckpointCount { expected: i32, actual: usize },
+
+    #[error("checkpoint times are not increasing")]
+    NonMonotonic,
+
+    #[error("final checkpoint time differs from the run time")]
+    FinishMismatch,
+
+    #[error("{millis}ms faster than the author time")]
+    BeatsAuthorTime { millis: i32 },
+
+    #[error("{millis}ms faster than the top record")]
+    BeatsTopRecord { millis: i32 },
+}
+
+impl Implausibility {
+    /// `True` if the run is certainly invalid, and should be rejected.
+    /// Otherwise, the run should be reviewed by an admin.
+    pub fn is_invalid(&self) -> bool {
+        use Implausibility::*;
+        matches!(self, NonMonotonic | FinishMismatch)
+    }
+}
+
+/// Check whether a run is plausible, and return the first reason why it is not.
+///
+/// # Arguments
+/// `map` - The map the run was set on
+/// `top_record_millis` - The time of the top record on that map, if any
+pub fn check_run(
+    run: &RecordEvidence,
+    map: &Map,
+    top_record_millis: Option<i32>,
+    config: &RecordCheckConfig,
+) -> Result<(), Implausibility> {
+    use Implausibility::*;
+
+    if run.cp_millis.windows(2).any(|w| w[0] > w[1]) {
+        return Err(NonMonotonic);
+    }
+
+    if let Some(last) = run.cp_millis.last() {
+        if *last != run.millis {
+            return Err(FinishMismatch);
+        }
+    }
+
+    if !config.enabled {
+        return Ok(());
+    }
+
+    if let Some(nb_cps) = map.nb_cps.filter(|nb| *nb > 0) {
+        let actual = run.cp_millis.len();
+        if actual == 0 || actual % nb_cps as usize != 0 {
+            return Err(CheckpointCount {
+                expected: nb_cps,
+                actual,
+            });
+        }
+    }
+
+    let max_improvement = |ref_millis: i32, ratio: f32| (ref_millis as f32 * ratio) as i32;
+
+    let author_diff = map.author_millis - run.millis;
+    if map.author_millis > 0
+        && author_diff > max_improvement(map.author_millis, config.max_author_improvement)
+    {
+        return Err(BeatsAuthorTime {
+            millis: author_diff,
+        });
+    }
+
+    if let Some(top_millis) = top_record_millis {
+        let top_diff = top_millis - run.millis;
+        if top_diff > max_improvement(top_millis, config.max_top_record_improvement) {
+            return Err(BeatsTopRecord { millis: top_diff });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+
+    use crate::server::DisplayString;
+
+    use super::*;
+
+    fn map(author_millis: i32, nb_cps: Option<i32>) -> Map {
+        Map {
+            uid: "uid".to_string(),
+            file_name: "".to_string(),
+            name: DisplayString::from("".to_string()),
+            author_login: "".to_string(),
+            author_display_name: DisplayString::from("".to_string()),
+            author_millis,
+            gold_millis: None,
+            silver_millis: None,
+            bronze_millis: None,
+            added_since: Utc::now().naive_utc(),
+            exchange_id: None,
+            nb_cps,
+            nb_laps: None,
+        }
+    }
+
+    fn run(cp_millis: Vec<i32>) -> RecordEvidence {
+        RecordEvidence {
+            player_login: "login".to_string(),
+            map_uid: "uid".to_string(),
+            nb_laps: 0,
+            millis: *cp_millis.last().unwrap(),
+            timestamp: Utc::now().naive_utc(),
+            cp_millis,
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_runs() {
+        let cfg = RecordCheckConfig::default();
+        let map = map(30000, Some(3));
+
+        let err = check_run(&run(vec![10000, 9000, 30000]), &map, None, &cfg).unwrap_err();
+        assert_eq!(Implausibility::NonMonotonic, err);
+        assert!(err.is_invalid());
+
+        assert!(check_run(&run(vec![10000, 20000, 30000]), &map, None, &cfg).is_ok());
+    }
+
+    #[test]
+    fn flags_suspicious_runs() {
+        let cfg = RecordCheckConfig::default();
+        let map = map(30000, None);
+
+        let err = check_run(&run(vec![10000, 20000]), &map, None, &cfg).unwrap_err();
+        assert_eq!(Implausibility::BeatsAuthorTime { millis: 10000 }, err);
+        assert!(!err.is_invalid());
+
+        let err = check_run(&run(vec![10000, 25000]), &map, Some(29000), &cfg).unwrap_err();
+        assert_eq!(Implausibility::BeatsTopRecord { millis: 4000 }, err);
+
+        let map_with_cps = Map {
+            nb_cps: Some(3),
+            ..map.clone()
+        };
+        let err = check_run(&run(vec![10000, 30000]), &map_with_cps, None, &cfg).unwrap_err();
+        assert_eq!(
+            Implausibility::CheckpointCount {
+                expected: 3,
+                actual: 2
+            },
+            err
+        );
+        assert!(!err.is_invalid());
+
+        assert!(check_run(&run(vec![10000, 28000]), &map, Some(29000), &cfg).is_ok());
+    }
+}
@@ -4,8 +4,11 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use tokio::sync::{RwLock, RwLockReadGuard};
 
+use crate::constants::DEFAULT_LANGUAGE;
+use crate::controller::{lookup_single_login, lookup_unique_login};
 use crate::database::{DatabaseClient, PlayerQueries};
 use crate::event::{PlayerDiff, PlayerTransition};
+use crate::locale::supported_language;
 use crate::server::{Calls, DisplayString, PlayerInfo, PlayerSlot, Server};
 
 /// Use to lookup information of connected players.
@@ -58,6 +61,12 @@ pub trait LivePlayers: Send + Sync {
             .info(login)
             .map(|info| info.display_name.clone())
     }
+
+    /// Return the language of the player with the specified login,
+    /// or the `DEFAULT_LANGUAGE` if no such player is connected.
+    async fn language(&self, login: &str) -> &'static str {
+        self.lock().await.language(login)
+    }
 }
 
 pub struct PlayersState {
@@ -75,6 +84,10 @@ pub struct PlayersState {
 
     /// Lists UIDs of players that are spectating, and have *no* player slot.
     pure_spectating: HashSet<i32>,
+
+    /// Maps player UIDs to the language that chat messages and widgets
+    /// are displayed in.
+    uid_to_language: HashMap<i32, &'static str>,
 }
 
 impl PlayersState {
@@ -85,6 +98,7 @@ impl PlayersState {
             playing: HashSet::new(),
             spectating: HashSet::new(),
             pure_spectating: HashSet::new(),
+            uid_to_language: HashMap::new(),
         }
     }
 
@@ -145,6 +159,35 @@ impl PlayersState {
             .map(|info| info.login.as_str())
     }
 
+    /// Return the language of the player with the specified login,
+    /// or the `DEFAULT_LANGUAGE` if no such player is connected.
+    pub fn language(&self, login: &str) -> &'static str {
+        self.uid(login)
+            .map(|uid| self.uid_language(*uid))
+            .unwrap_or(DEFAULT_LANGUAGE)
+    }
+
+    /// Return the language of the player with the specified UID,
+    /// or the `DEFAULT_LANGUAGE` if no such player is connected.
+    pub fn uid_language(&self, player_uid: i32) -> &'static str {
+        self.uid_to_language
+            .get(&player_uid)
+            .copied()
+            .unwrap_or(DEFAULT_LANGUAGE)
+    }
+
+    /// Group the logins of all connected players by their language.
+    pub fn logins_by_language(&self) -> HashMap<&'static str, Vec<&str>> {
+        let mut groups: HashMap<&'static str, Vec<&str>> = HashMap::new();
+        for (uid, info) in self.uid_to_info.iter() {
+            groups
+                .entry(self.uid_language(*uid))
+                .or_default()
+                .push(&info.login);
+        }
+        groups
+    }
+
     pub fn replay_diffs(&self) -> Vec<PlayerDiff> {
         let players = self.playing.iter().map(|uid| PlayerDiff {
             transition: PlayerTransition::AddPlayer,
@@ -168,6 +211,7 @@ impl PlayersState {
 #[derive(Clone)]
 pub struct PlayerController {
     state: Arc<RwLock<PlayersState>>,
+    server: Server,
     db: DatabaseClient,
 }
 
@@ -175,6 +219,7 @@ impl PlayerController {
     pub async fn init(server: &Server, db: &DatabaseClient) -> Self {
         let controller = PlayerController {
             state: Arc::new(RwLock::new(PlayersState::init())),
+            server: server.clone(),
             db: db.clone(),
         };
 
@@ -186,6 +231,62 @@ impl PlayerController {
         controller
     }
 
+    /// Find a connected player by their login, or by their current or past
+    /// display names. Returns `None` if no player, or more than one player
+    /// match the search term equally well.
+    pub async fn lookup(&self, login_or_display_name: &str) -> Option<PlayerInfo> {
+        self.find(login_or_display_name, false).await
+    }
+
+    /// Find a connected player by their login, or by their current or past
+    /// display names. Unlike `lookup`, this returns `None` if more than one
+    /// player matches the search term at all, unless the login matches exactly.
+    pub async fn lookup_unambiguous(&self, login_or_display_name: &str) -> Option<PlayerInfo> {
+        self.find(login_or_display_name, true).await
+    }
+
+    async fn find(&self, login_or_display_name: &str, single_match: bool) -> Option<PlayerInfo> {
+        let players_state = self.state.read().await;
+        if let Some(info) = players_state.info(login_or_display_name) {
+            return Some(info.clone());
+        }
+        if !single_match {
+            if let Some(info) = players_state.display_name_info(login_or_display_name) {
+                return Some(info.clone());
+            }
+        }
+
+        let logins: Vec<&str> = players_state
+            .info_all()
+            .iter()
+            .map(|info| info.login.as_str())
+            .collect();
+        let aliases = self
+            .db
+            .player_aliases(logins)
+            .await
+            .expect("failed to load player aliases");
+
+        let names: Vec<(&str, String)> = players_state
+            .info_all()
+            .into_iter()
+            .map(|info| (info.login.as_str(), info.display_name.plain()))
+            .chain(
+                aliases
+                    .iter()
+                    .map(|alias| (alias.player_login.as_str(), alias.display_name.plain())),
+            )
+            .collect();
+
+        let names = names.iter().map(|(login, name)| (*login, name.as_str()));
+        let login = if single_match {
+            lookup_single_login(login_or_display_name, names)
+        } else {
+            lookup_unique_login(login_or_display_name, names)
+        }?;
+        players_state.info(login).cloned()
+    }
+
     /// Update a player's information.
     pub async fn update_player(&self, info: PlayerInfo) -> Option<PlayerDiff> {
         use PlayerTransition::*;
@@ -199,6 +300,15 @@ impl PlayerController {
             return self.remove_player(&info.login).await;
         }
 
+        // Select the language of joining players, without blocking others
+        // from reading the players state while asking the server.
+        let is_known = self.state.read().await.uid_to_info.contains_key(&info.uid);
+        let language = if is_known {
+            None
+        } else {
+            Some(self.detect_language(&info.login).await)
+        };
+
         let mut players_state = self.state.write().await;
         let uid = info.uid;
 
@@ -227,6 +337,10 @@ impl PlayerController {
                 .expect("failed to upsert player data");
         }
 
+        if let Some(language) = language.filter(|_| is_new) {
+            let _ = players_state.uid_to_language.insert(uid, language);
+        }
+
         let transition = match info.slot() {
             PlayerSlot::None => return None,
             PlayerSlot::Player => {
@@ -267,6 +381,54 @@ impl PlayerController {
         Some(PlayerDiff { transition, info })
     }
 
+    /// Select the language of a connected player, or reset it to the
+    /// language of their game client with `None`. The selection is persisted,
+    /// and is used whenever they join again.
+    ///
+    /// Returns the language they have now, or `None` if no such player
+    /// is connected.
+    pub async fn set_language(
+        &self,
+        login: &str,
+        language: Option<&'static str>,
+    ) -> Option<&'static str> {
+        self.db
+            .set_player_language(login, language)
+            .await
+            .expect("failed to update player language");
+
+        let language = match language {
+            Some(language) => language,
+            None => self.detect_language(login).await,
+        };
+
+        let mut players_state = self.state.write().await;
+        let uid = *players_state.uid(login)?;
+        let _ = players_state.uid_to_language.insert(uid, language);
+        Some(language)
+    }
+
+    /// Find the language of a player: the one they selected, or otherwise
+    /// the language of their game client, if it is supported.
+    async fn detect_language(&self, login: &str) -> &'static str {
+        let selected = self
+            .db
+            .player_language(login)
+            .await
+            .expect("failed to load player language");
+        if let Some(language) = selected.as_deref().and_then(supported_language) {
+            return language;
+        }
+
+        match self.server.player_detailed_info(login).await {
+            Ok(info) => supported_language(&info.language).unwrap_or(DEFAULT_LANGUAGE),
+            Err(err) => {
+                log::warn!("failed to get the language of '{}': {:?}", login, err);
+                DEFAULT_LANGUAGE
+            }
+        }
+    }
+
     /// Remove a player's information.
     pub async fn remove_player(&self, login: &str) -> Option<PlayerDiff> {
         use PlayerTransition::*;
@@ -283,6 +445,8 @@ impl PlayerController {
             None => return None,
         };
 
+        let _ = players_state.uid_to_language.remove(&uid);
+
         let transition = if players_state.playing.remove(&uid) {
             RemovePlayer
         } else if players_state.spectating.remove(&uid) {
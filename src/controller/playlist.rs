@@ -4,14 +4,14 @@ use std::path::Path;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use tokio::sync::{RwLock, RwLockReadGuard};
 
 use gbx::file::parse_map_file;
 
 use crate::chat::PlaylistCommandError;
 use crate::controller::LiveConfig;
-use crate::database::{DatabaseClient, Map, MapQueries};
+use crate::database::{DatabaseClient, Map, MapPool, MapQueries, PoolQueries};
 use crate::event::PlaylistDiff;
 use crate::network::{exchange_map, ExchangeError};
 use crate::server::{Calls, ModeCalls, Server};
@@ -69,6 +69,13 @@ pub struct PlaylistState {
     /// The playlist index of the current map, or `None` if the current map
     /// is not part of the playlist anymore.
     pub current_index: Option<usize>,
+
+    /// The name of the map pool that was most recently activated,
+    /// or `None` if no pool was ever activated.
+    pub pool: Option<String>,
+
+    /// The moment the map pool was most recently activated.
+    pub pool_since: Option<NaiveDateTime>,
 }
 
 impl PlaylistState {
@@ -132,9 +139,16 @@ impl PlaylistController {
             server.end_map().await.expect("failed to end map");
         }
 
+        let active_pool = db
+            .active_pool()
+            .await
+            .expect("failed to load active map pool");
+
         let state = PlaylistState {
             maps: playlist,
             current_index: curr_index,
+            pool: active_pool.as_ref().map(|(name, _)| name.clone()),
+            pool_since: active_pool.map(|(_, since)| since),
         };
 
         PlaylistController {
@@ -233,6 +247,99 @@ impl PlaylistController {
         })
     }
 
+    /// Store the current playlist as a map pool with the given name,
+    /// replacing any pool with the same name.
+    pub async fn create_pool(&self, name: &str) {
+        let mut playlist_state = self.state.write().await;
+        let pool = MapPool {
+            name: name.to_string(),
+            map_uids: playlist_state
+                .maps
+                .iter()
+                .map(|map| map.uid.clone())
+                .collect(),
+        };
+        self.db
+            .upsert_pool(&pool)
+            .await
+            .expect("failed to store map pool");
+
+        let now = Utc::now().naive_utc();
+        self.db
+            .set_active_pool(&pool.name, &now)
+            .await
+            .expect("failed to store active map pool");
+
+        playlist_state.pool = Some(pool.name);
+        playlist_state.pool_since = Some(now);
+    }
+
+    /// Replace the playlist with the maps of the specified pool.
+    ///
+    /// Returns the changes made to the playlist: maps are added first,
+    /// so that the playlist never becomes empty.
+    pub async fn switch_pool(&self, name: &str) -> Result<Vec<PlaylistDiff>, PlaylistCommandError> {
+        use PlaylistCommandError::*;
+
+        let pool = self
+            .db
+            .pool(name)
+            .await
+            .expect("failed to load map pool")
+            .ok_or(UnknownPool)?;
+
+        if pool.map_uids.is_empty() {
+            return Err(EmptyPlaylistDisallowed);
+        }
+
+        let maps = self
+            .db
+            .maps(pool.map_uids.iter().map(String::as_str).collect())
+            .await
+            .expect("failed to load maps");
+        if maps.is_empty() {
+            return Err(EmptyPlaylistDisallowed);
+        }
+
+        let mut diffs = Vec::new();
+
+        for map in maps.iter() {
+            if self.index_of(&map.uid).await.is_none() {
+                if let Ok(diff) = self.add(&map.uid).await {
+                    diffs.push(diff);
+                }
+            }
+        }
+
+        let to_remove: Vec<String> = self
+            .lock()
+            .await
+            .maps
+            .iter()
+            .filter(|m1| !maps.iter().any(|m2| m1.uid == m2.uid))
+            .map(|map| map.uid.clone())
+            .collect();
+        for map_uid in to_remove {
+            if let Ok(diff) = self.remove(&map_uid).await {
+                diffs.push(diff);
+            }
+        }
+
+        log::info!("switched to map pool '{}'", &pool.name);
+
+        let mut playlist_state = self.state.write().await;
+        let now = Utc::now().naive_utc();
+        self.db
+            .set_active_pool(&pool.name, &now)
+            .await
+            .expect("failed to store active map pool");
+
+        playlist_state.pool = Some(pool.name);
+        playlist_state.pool_since = Some(now);
+
+        Ok(diffs)
+    }
+
     /// Download a map from [trackmania.exchange](https://trackmania.exchange/),
     /// and add it to the playlist.
     ///
@@ -294,7 +401,12 @@ impl PlaylistController {
             author_display_name: header.author_display_name,
             added_since: Utc::now().naive_utc(),
             author_millis: header.millis_author,
+            gold_millis: Some(header.millis_gold),
+            silver_millis: Some(header.millis_silver),
+            bronze_millis: Some(header.millis_bronze),
             exchange_id: Some(import_map.metadata.exchange_id),
+            nb_cps: Some(header.nb_cps),
+            nb_laps: header.nb_laps,
         };
 
         self.db
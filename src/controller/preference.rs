@@ -222,14 +222,12 @@ impl PreferenceController {
             }
         }
 
-        tell(
-            &self.server,
-            PlayerMessage::PreferenceReminder {
-                nb_active_preferences: preferences_state.nb_player_prefs(player.uid),
-            },
-            &player.login,
-        )
-        .await;
+        let msg = PlayerMessage::PreferenceReminder {
+            nb_active_preferences: preferences_state.nb_player_prefs(player.uid),
+        };
+        drop(players_state);
+        drop(preferences_state);
+        tell(&self.server, &*self.live_players, msg, &player.login).await;
     }
 
     /// Load a player's data if they enter a player slot,
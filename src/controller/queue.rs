@@ -1,15 +1,18 @@
-use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::cmp::{min, Ordering};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Utc};
 use tokio::sync::{RwLock, RwLockReadGuard};
 
+use crate::config::QueueWeights;
 use crate::constants::{
-    DEFAULT_MIN_RESTART_VOTE_RATIO, MAX_DISPLAYED_IN_QUEUE, MIN_RESTART_VOTE_RATIO_STEP,
+    MAX_DISPLAYED_IN_QUEUE, MAX_QUEUE_MAP_AGE_DAYS, QUEUE_RECORD_ACTIVITY_DAYS,
 };
-use crate::controller::{LivePlayers, LivePlaylist, LivePreferences};
-use crate::database::Map;
+use crate::controller::{LiveConfig, LivePlayers, LivePlaylist, LivePreferences};
+use crate::database::timeattack::History;
+use crate::database::{DatabaseClient, Map, RecordQueries};
 use crate::event::{PlaylistDiff, QueueDiff};
 use crate::server::{Calls, ModeCalls, Server};
 use crate::widget::ActivePreferenceValue;
@@ -49,6 +52,80 @@ pub struct QueueState {
     /// that have to vote in favour of a restart to cause replaying a map.
     /// It is increased for each subsequent restart of the same map.
     pub min_restart_vote_ratio: f32,
+
+    /// Maps UIDs to the number of records that were recently set on that map.
+    record_activity: HashMap<String, usize>,
+}
+
+/// Calculates the score of maps in the queue, which decides the map
+/// that is queued next.
+///
+/// Scores are only calculated for maps that are neither the current map,
+/// nor force-queued.
+pub trait QueueScorer: Send + Sync {
+    /// Returns the score of a map, where higher scores are queued first.
+    fn score(&self, ctxt: &QueueScoreContext) -> i32;
+}
+
+/// The data that can be considered when scoring a map in the queue.
+pub struct QueueScoreContext<'a> {
+    /// The map that is scored.
+    pub map: &'a Map,
+
+    /// The preferences of playing players for this map.
+    pub prefs: Vec<ActivePreferenceValue>,
+
+    /// The histories of playing players for this map.
+    pub histories: Vec<&'a History>,
+
+    /// The number of times this map was not queued since it was last played.
+    pub times_skipped: usize,
+
+    /// The number of records that were recently set on this map.
+    pub nb_recent_records: usize,
+
+    /// The moment the queue is sorted.
+    pub now: NaiveDateTime,
+}
+
+/// Scores maps by summing up configurable weights.
+pub struct WeightedQueueScorer {
+    pub weights: QueueWeights,
+}
+
+impl QueueScorer for WeightedQueueScorer {
+    fn score(&self, ctxt: &QueueScoreContext) -> i32 {
+        use ActivePreferenceValue::*;
+
+        let w = &self.weights;
+
+        let pref_score: i32 = ctxt
+            .prefs
+            .iter()
+            .map(|pv| match pv {
+                Pick => w.pick,
+                Veto => w.veto,
+                Remove => w.remove,
+                AutoPick => w.auto_pick,
+                None => w.no_preference,
+            })
+            .sum();
+
+        let history_score: i32 = ctxt
+            .histories
+            .iter()
+            .map(|h| w.nb_maps_since * h.nb_maps_since as i32)
+            .sum();
+
+        let age_days = (ctxt.now - ctxt.map.added_since).num_days();
+        let age_days = min(MAX_QUEUE_MAP_AGE_DAYS, age_days.max(0)) as i32;
+
+        pref_score
+            + history_score
+            + w.skip_count * ctxt.times_skipped as i32
+            + w.map_age * age_days
+            + w.record_activity * ctxt.nb_recent_records as i32
+    }
 }
 
 /// An entry in the map queue, which assigns a priority to the map in the playlist
@@ -129,12 +206,13 @@ impl PartialOrd for QueuePriority {
 }
 
 impl QueueState {
-    pub fn init(playlist_len: usize) -> Self {
+    pub fn init(playlist_len: usize, min_restart_vote_ratio: f32) -> Self {
         QueueState {
             entries: vec![],
             times_skipped: vec![0; playlist_len],
             force_queue: VecDeque::new(),
-            min_restart_vote_ratio: DEFAULT_MIN_RESTART_VOTE_RATIO,
+            min_restart_vote_ratio,
+            record_activity: HashMap::new(),
         }
     }
 
@@ -174,33 +252,64 @@ impl QueueState {
 pub struct QueueController {
     state: Arc<RwLock<QueueState>>,
     server: Server,
+    db: DatabaseClient,
+    live_config: Arc<dyn LiveConfig>,
     live_players: Arc<dyn LivePlayers>,
     live_playlist: Arc<dyn LivePlaylist>,
     live_prefs: Arc<dyn LivePreferences>,
 }
 
 impl QueueController {
+    #[allow(clippy::too_many_arguments)]
     pub async fn init(
         server: &Server,
+        db: &DatabaseClient,
+        live_config: &Arc<dyn LiveConfig>,
         live_players: &Arc<dyn LivePlayers>,
         live_playlist: &Arc<dyn LivePlaylist>,
         live_prefs: &Arc<dyn LivePreferences>,
     ) -> Self {
+        let min_restart_vote_ratio = live_config.lock().await.timeattack.min_restart_vote_ratio;
         let state = {
             let playlist_state = live_playlist.lock().await;
-            QueueState::init(playlist_state.maps.len())
+            QueueState::init(playlist_state.maps.len(), min_restart_vote_ratio)
         };
         let controller = QueueController {
             state: Arc::new(RwLock::new(state)),
             server: server.clone(),
+            db: db.clone(),
+            live_config: live_config.clone(),
             live_players: live_players.clone(),
             live_playlist: live_playlist.clone(),
             live_prefs: live_prefs.clone(),
         };
+        controller.load_record_activity().await;
         let _ = controller.sort_queue().await;
         controller
     }
 
+    /// Count the records that were recently set on each map.
+    async fn load_record_activity(&self) {
+        let since = Utc::now().naive_utc() - Duration::days(QUEUE_RECORD_ACTIVITY_DAYS);
+        let record_activity = self
+            .db
+            .nb_records_since(&since)
+            .await
+            .expect("failed to load recent records")
+            .into_iter()
+            .map(|(map_uid, nb_records)| (map_uid, nb_records as usize))
+            .collect();
+
+        let mut queue_state = self.state.write().await;
+        queue_state.record_activity = record_activity;
+    }
+
+    /// The scorer that is used to calculate the priority of maps in the queue.
+    async fn scorer(&self) -> Box<dyn QueueScorer> {
+        let weights = self.live_config.lock().await.timeattack.queue;
+        Box::new(WeightedQueueScorer { weights })
+    }
+
     /// Update the queue when maps are added or removed from the playlist,
     /// and re-sort it.
     pub async fn insert_or_remove(&self, diff: &PlaylistDiff) -> QueueDiff {
@@ -260,11 +369,12 @@ impl QueueController {
     }
 
     /// Re-order the queue, and take into consideration:
-    /// - all connected players' map preferences
-    /// - for each map: the number of other maps that have been played
-    ///   since last playing the former
+    /// - the score of each map, as calculated by the `QueueScorer`
+    /// - maps that were force-queued
     /// - the number of players voting for a restart
     pub async fn sort_queue(&self) -> Option<QueueDiff> {
+        let scorer = self.scorer().await;
+
         let mut queue_state = self.state.write().await;
         let preferences_state = self.live_prefs.lock().await;
         let playlist_state = self.live_playlist.lock().await;
@@ -284,22 +394,28 @@ impl QueueController {
             restart_vote_ratio >= queue_state.min_restart_vote_ratio
         };
 
-        let pref_sum = |idx: usize| -> i32 {
-            use ActivePreferenceValue::*;
+        let now = Utc::now().naive_utc();
 
-            let map_uid = &playlist_state
+        let score = |idx: usize, times_skipped: usize| -> i32 {
+            let map = playlist_state
                 .at_index(idx)
-                .expect("no map at this playlist index")
-                .uid;
-            let active_prefs = preferences_state.map_prefs(map_uid);
-            active_prefs
-                .iter()
-                .map(|pv| match pv {
-                    AutoPick => 1,
-                    Pick => 1,
-                    _ => -1,
-                })
-                .sum()
+                .expect("no map at this playlist index");
+            let ctxt = QueueScoreContext {
+                map,
+                prefs: preferences_state.map_prefs(&map.uid),
+                histories: uid_playing
+                    .iter()
+                    .filter_map(|uid| preferences_state.history(*uid, &map.uid))
+                    .collect(),
+                times_skipped,
+                nb_recent_records: queue_state
+                    .record_activity
+                    .get(&map.uid)
+                    .copied()
+                    .unwrap_or(0),
+                now,
+            };
+            scorer.score(&ctxt)
         };
 
         let mut priorities: Vec<(usize, QueuePriority)> = queue_state
@@ -314,7 +430,7 @@ impl QueueController {
                 } else if Some(idx) == maybe_curr_index {
                     QueuePriority::NoRestart
                 } else {
-                    QueuePriority::Score(pref_sum(idx) + *skip_count as i32)
+                    QueuePriority::Score(score(idx, *skip_count))
                 };
                 (idx, prio)
             })
@@ -359,6 +475,14 @@ impl QueueController {
     /// Tell the server to load the map at the top of the queue next,
     /// and returns it. This does *not* re-sort the queue.
     pub async fn pop_front(&self) -> Map {
+        let (default_ratio, ratio_step) = {
+            let config = self.live_config.lock().await;
+            (
+                config.timeattack.min_restart_vote_ratio,
+                config.timeattack.min_restart_vote_ratio_step,
+            )
+        };
+
         let mut queue_state = self.state.write().await;
 
         let maybe_curr_index = self.live_playlist.current_index().await;
@@ -380,12 +504,12 @@ impl QueueController {
         // If restart, increase the needed threshold to make another restart less
         // likely. Otherwise, reset it for the next map.
         if is_restart {
-            queue_state.min_restart_vote_ratio += MIN_RESTART_VOTE_RATIO_STEP;
+            queue_state.min_restart_vote_ratio += ratio_step;
             if queue_state.min_restart_vote_ratio > 1.0 {
                 queue_state.min_restart_vote_ratio = 1.0;
             }
         } else {
-            queue_state.min_restart_vote_ratio = DEFAULT_MIN_RESTART_VOTE_RATIO;
+            queue_state.min_restart_vote_ratio = default_ratio;
         }
 
         // If the next map was force-queued, remove it from the force-queue.
@@ -393,6 +517,11 @@ impl QueueController {
             let _ = queue_state.force_queue.pop_front();
         }
 
+        drop(queue_state);
+
+        // The record activity will be considered the next time the queue is sorted.
+        self.load_record_activity().await;
+
         // Tell server the next map.
         if is_restart {
             self.server.restart_map().await;
@@ -449,3 +578,61 @@ impl LiveQueue for QueueController {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+
+    use crate::database::mock_map;
+
+    use super::*;
+
+    fn context(map: &Map, now: NaiveDateTime) -> QueueScoreContext {
+        QueueScoreContext {
+            map,
+            prefs: vec![],
+            histories: vec![],
+            times_skipped: 0,
+            nb_recent_records: 0,
+            now,
+        }
+    }
+
+    #[test]
+    fn default_weights_score() {
+        use ActivePreferenceValue::*;
+
+        let now = Utc::now().naive_utc();
+        let map = mock_map("uid", now);
+        let scorer = WeightedQueueScorer {
+            weights: QueueWeights::default(),
+        };
+        let ctxt = QueueScoreContext {
+            map: &map,
+            prefs: vec![Pick, AutoPick, Veto, Remove, None],
+            histories: vec![],
+            times_skipped: 3,
+            nb_recent_records: 5,
+            now,
+        };
+        assert_eq!(2, scorer.score(&ctxt));
+    }
+
+    #[test]
+    fn map_age_is_capped() {
+        let now = Utc::now().naive_utc();
+        let old_map = mock_map("uid", now - Duration::days(100));
+        let new_map = mock_map("uid", now - Duration::days(2));
+        let scorer = WeightedQueueScorer {
+            weights: QueueWeights {
+                map_age: -1,
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            -(MAX_QUEUE_MAP_AGE_DAYS as i32),
+            scorer.score(&context(&old_map, now))
+        );
+        assert_eq!(-2, scorer.score(&context(&new_map, now)));
+    }
+}
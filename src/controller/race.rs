@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -16,6 +16,10 @@ pub trait LiveRace: Send + Sync {
     /// The ranking of the specified player in the current race,
     /// or `None` if they have not completed a run.
     async fn rank_of(&self, player_uid: i32) -> Option<usize>;
+
+    /// The ranking of the current race, including players that
+    /// have not completed a run yet.
+    async fn live_ranking(&self) -> Vec<LiveRaceRank>;
 }
 
 #[derive(Default)]
@@ -26,11 +30,68 @@ pub struct RaceState {
     /// The server does not wait for every player to start the race.
     pre_race: HashSet<i32>,
 
+    /// Maps player logins to the checkpoint times of their
+    /// current run, and of their best run.
+    runs: HashMap<String, RunProgress>,
+
+    /// `True` if the live ranking changed since it was last displayed.
+    live_ranking_changed: bool,
+
     pub warmup: bool,
 
     pub paused: bool,
 }
 
+impl RaceState {
+    /// The ranking of the current race, including players that
+    /// have not completed a run yet.
+    pub fn live_ranking(&self) -> Vec<LiveRaceRank> {
+        live_ranking(&self.ranking, &self.runs)
+    }
+}
+
+#[derive(Default)]
+struct RunProgress {
+    /// The checkpoint times of the current run.
+    curr_cp_millis: Vec<i32>,
+
+    /// The checkpoint times of the best completed run,
+    /// or an empty vector if there is none.
+    best_cp_millis: Vec<i32>,
+}
+
+/// A player's progress in the current race.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LiveProgress {
+    /// The player has completed at least one run.
+    Finished { millis: i32 },
+
+    /// The player has not completed a run, but crossed at least one
+    /// checkpoint in their current run.
+    OnTrack { cp_index: usize, millis: i32 },
+
+    /// The player has not crossed a checkpoint yet.
+    NotStarted,
+}
+
+/// A player's rank in the live ranking of the current race.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveRaceRank {
+    /// The player's position, starting at 1.
+    pub pos: usize,
+
+    pub login: String,
+
+    pub display_name: DisplayString,
+
+    pub progress: LiveProgress,
+
+    /// The difference to the leader's time at the same checkpoint,
+    /// or `None` if this player is the leader, or if there is
+    /// no time to compare to.
+    pub gap_millis: Option<i32>,
+}
+
 #[derive(Clone)]
 pub struct RaceController {
     state: Arc<RwLock<RaceState>>,
@@ -93,6 +154,7 @@ impl RaceController {
                 race_state.ranking.retain(|s| s.login != score.login); // remove previous entry
                 race_state.ranking.insert(idx, score);
             });
+        race_state.live_ranking_changed = true;
     }
 
     /// Set whether the current race is paused.
@@ -117,6 +179,8 @@ impl RaceController {
 
         let res = race_state.ranking.drain(..).collect();
         race_state.pre_race.clear();
+        race_state.runs.clear();
+        race_state.live_ranking_changed = true;
 
         self.live_players
             .info_all()
@@ -132,13 +196,20 @@ impl RaceController {
         res
     }
 
-    /// Update the ranking if the finished line was crossed
-    /// and the run improved a player's time.
-    pub async fn update(&self, ev: &CheckpointEvent) {
-        if !ev.is_finish {
-            return;
+    /// Signals that a player has started a new run,
+    /// which resets their progress in the live ranking.
+    pub async fn begin_run(&self, login: &str) {
+        let mut race_state = self.state.write().await;
+        if let Some(progress) = race_state.runs.get_mut(login) {
+            progress.curr_cp_millis.clear();
+            race_state.live_ranking_changed = true;
         }
+    }
 
+    /// Update a player's progress in the live ranking whenever they cross
+    /// a checkpoint. Update the ranking if the finished line was crossed
+    /// and the run improved a player's time.
+    pub async fn update(&self, ev: &CheckpointEvent) {
         let player_info = match self.live_players.info(&ev.player_login).await {
             Some(info) => info,
             None => return,
@@ -149,10 +220,32 @@ impl RaceController {
             return;
         }
 
+        let progress = race_state
+            .runs
+            .entry(player_info.login.clone())
+            .or_default();
+        progress.curr_cp_millis.truncate(ev.race_cp_index as usize);
+        progress.curr_cp_millis.push(ev.race_time_millis);
+        race_state.live_ranking_changed = true;
+
         let prev_idx = race_state
             .ranking
             .iter()
             .position(|lr| lr.login == player_info.login);
+
+        if !ev.is_finish {
+            // Players that joined during the race are added to the ranking
+            // once they cross a checkpoint.
+            if prev_idx.is_none() {
+                race_state.ranking.push(RaceRank {
+                    login: player_info.login,
+                    display_name: player_info.display_name,
+                    millis: None,
+                });
+            }
+            return;
+        }
+
         match prev_idx {
             Some(idx)
                 if race_state.ranking[idx]
@@ -168,6 +261,10 @@ impl RaceController {
             None => {}
         }
 
+        if let Some(progress) = race_state.runs.get_mut(&player_info.login) {
+            progress.best_cp_millis = progress.curr_cp_millis.clone();
+        }
+
         let new_ranking = RaceRank {
             login: player_info.login,
             display_name: player_info.display_name,
@@ -189,6 +286,86 @@ impl RaceController {
             None => race_state.ranking.push(new_ranking),
         }
     }
+
+    /// Returns `true` if the live ranking changed since the last call.
+    pub async fn take_live_ranking_change(&self) -> bool {
+        let mut race_state = self.state.write().await;
+        std::mem::replace(&mut race_state.live_ranking_changed, false)
+    }
+}
+
+/// Rank players that completed a run by their best time, and other players
+/// by their furthest checkpoint in the current run, and their time at that checkpoint.
+fn live_ranking(ranking: &[RaceRank], runs: &HashMap<String, RunProgress>) -> Vec<LiveRaceRank> {
+    let progress_of = |rank: &RaceRank| -> LiveProgress {
+        if let Some(millis) = rank.millis {
+            return LiveProgress::Finished {
+                millis: millis as i32,
+            };
+        }
+        let curr_cp_millis = runs
+            .get(&rank.login)
+            .map(|run| run.curr_cp_millis.as_slice())
+            .unwrap_or_default();
+        match curr_cp_millis.last() {
+            Some(millis) => LiveProgress::OnTrack {
+                cp_index: curr_cp_millis.len() - 1,
+                millis: *millis,
+            },
+            None => LiveProgress::NotStarted,
+        }
+    };
+
+    let mut ranked: Vec<(&RaceRank, LiveProgress)> = ranking
+        .iter()
+        .map(|rank| (rank, progress_of(rank)))
+        .collect();
+
+    // The sort is stable, which keeps the order of players that have not started.
+    ranked.sort_by_key(|(_, progress)| match progress {
+        LiveProgress::Finished { millis } => (0, 0, *millis),
+        LiveProgress::OnTrack { cp_index, millis } => (1, -(*cp_index as i64), *millis),
+        LiveProgress::NotStarted => (2, 0, 0),
+    });
+
+    // The leader's checkpoint times, that all other players are compared to.
+    let leader_cp_millis: &[i32] = match ranked.first() {
+        Some((rank, LiveProgress::Finished { .. })) => runs
+            .get(&rank.login)
+            .map(|run| run.best_cp_millis.as_slice())
+            .unwrap_or_default(),
+        Some((rank, LiveProgress::OnTrack { .. })) => runs
+            .get(&rank.login)
+            .map(|run| run.curr_cp_millis.as_slice())
+            .unwrap_or_default(),
+        _ => &[],
+    };
+    let leader_finish_millis = match ranked.first() {
+        Some((_, LiveProgress::Finished { millis })) => Some(*millis),
+        _ => None,
+    };
+
+    ranked
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (rank, progress))| {
+            let gap_millis = match &progress {
+                _ if idx == 0 => None,
+                LiveProgress::Finished { millis } => leader_finish_millis.map(|lm| millis - lm),
+                LiveProgress::OnTrack { cp_index, millis } => {
+                    leader_cp_millis.get(*cp_index).map(|lm| millis - lm)
+                }
+                LiveProgress::NotStarted => None,
+            };
+            LiveRaceRank {
+                pos: idx + 1,
+                login: rank.login.clone(),
+                display_name: rank.display_name.clone(),
+                progress,
+                gap_millis,
+            }
+        })
+        .collect()
 }
 
 #[async_trait]
@@ -210,4 +387,81 @@ impl LiveRace for RaceController {
             .find(|(_, lr)| lr.login == player_login)
             .map(|(idx, _)| idx + 1)
     }
+
+    async fn live_ranking(&self) -> Vec<LiveRaceRank> {
+        self.lock().await.live_ranking()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rank(login: &str, millis: Option<usize>) -> RaceRank {
+        RaceRank {
+            login: login.to_string(),
+            display_name: DisplayString::from(login.to_string()),
+            millis,
+        }
+    }
+
+    fn run(curr_cp_millis: Vec<i32>, best_cp_millis: Vec<i32>) -> RunProgress {
+        RunProgress {
+            curr_cp_millis,
+            best_cp_millis,
+        }
+    }
+
+    #[test]
+    fn ranks_players_on_track() {
+        let ranking = vec![
+            rank("a", Some(30000)),
+            rank("b", None),
+            rank("c", None),
+            rank("d", None),
+            rank("e", None),
+        ];
+        let mut runs = HashMap::new();
+        runs.insert("a".to_string(), run(vec![], vec![10000, 20000, 30000]));
+        runs.insert("b".to_string(), run(vec![10500, 21000], vec![]));
+        runs.insert("c".to_string(), run(vec![9000], vec![]));
+        runs.insert("d".to_string(), run(vec![10200, 20500], vec![]));
+
+        let live = live_ranking(&ranking, &runs);
+        let summary: Vec<(&str, Option<i32>)> = live
+            .iter()
+            .map(|r| (r.login.as_str(), r.gap_millis))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a", None),
+                ("d", Some(500)),
+                ("b", Some(1000)),
+                ("c", Some(-1000)),
+                ("e", None),
+            ],
+            summary
+        );
+        assert_eq!(
+            LiveProgress::OnTrack {
+                cp_index: 1,
+                millis: 20500
+            },
+            live[1].progress
+        );
+        assert_eq!(LiveProgress::NotStarted, live[4].progress);
+        assert_eq!(5, live[4].pos);
+    }
+
+    #[test]
+    fn compares_to_leader_on_track() {
+        let ranking = vec![rank("a", None), rank("b", None)];
+        let mut runs = HashMap::new();
+        runs.insert("a".to_string(), run(vec![10000, 20000], vec![]));
+        runs.insert("b".to_string(), run(vec![11000], vec![]));
+
+        let live = live_ranking(&ranking, &runs);
+        assert_eq!("a", live[0].login);
+        assert_eq!(Some(1000), live[1].gap_millis);
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -7,7 +7,7 @@ use futures::future::join_all;
 use tokio::sync::{RwLock, RwLockReadGuard};
 
 use crate::constants::MAX_DISPLAYED_MAP_RANKS;
-use crate::controller::{LivePlayers, LivePlaylist};
+use crate::controller::{check_run, Implausibility, LiveConfig, LivePlayers, LivePlaylist};
 use crate::database::{DatabaseClient, Map, Record, RecordEvidence, RecordQueries};
 use crate::event::{PbDiff, PlayerDiff, PlayerTransition};
 use crate::server::{CheckpointEvent, PlayerInfo};
@@ -21,25 +21,93 @@ pub trait LiveRecords: Send + Sync {
 }
 
 pub struct RecordsState {
-    /// The number of players that have set a record on the current map.
+    /// The ranking of full-race records on the current map.
+    pub race: MapRecords,
+
+    /// The ranking of flying lap records on the current map,
+    /// or `None` if the current map is not multi-lap.
+    pub flying_lap: Option<MapRecords>,
+
+    /// The UIDs of players whose current run was reported as incoherent.
+    /// Such runs will not be considered for records.
+    tainted_runs: HashSet<i32>,
+}
+
+impl RecordsState {
+    fn init() -> Self {
+        RecordsState {
+            race: MapRecords::init(0),
+            flying_lap: None,
+            tainted_runs: HashSet::new(),
+        }
+    }
+
+    /// The ranking for records with the given lap count,
+    /// or `None` if there is no such ranking on the current map.
+    fn ranking(&self, nb_laps: i32) -> Option<&MapRecords> {
+        if self.race.nb_laps == nb_laps {
+            return Some(&self.race);
+        }
+        self.flying_lap
+            .as_ref()
+            .filter(|flying_lap| flying_lap.nb_laps == nb_laps)
+    }
+
+    /// The ranking for records with the given lap count,
+    /// or `None` if there is no such ranking on the current map.
+    fn ranking_mut(&mut self, nb_laps: i32) -> Option<&mut MapRecords> {
+        if self.race.nb_laps == nb_laps {
+            return Some(&mut self.race);
+        }
+        self.flying_lap
+            .as_mut()
+            .filter(|flying_lap| flying_lap.nb_laps == nb_laps)
+    }
+
+    /// Compare a player's time at a checkpoint to the time of their personal best,
+    /// and the time of the top record at the same checkpoint.
+    pub fn checkpoint_split(
+        &self,
+        player_uid: i32,
+        cp_index: usize,
+        millis: i32,
+    ) -> CheckpointSplit {
+        let cp_millis_of = |rec: &Record| rec.cp_millis.get(cp_index).copied();
+        CheckpointSplit {
+            cp_index,
+            millis,
+            pb_millis: self.race.pb(player_uid).and_then(cp_millis_of),
+            top_record_millis: self.race.top_record.as_ref().and_then(cp_millis_of),
+        }
+    }
+}
+
+/// The records of a single ranking on the current map.
+pub struct MapRecords {
+    /// The lap count of the records in this ranking.
+    /// This is `0` for flying laps, or if the map is not multi-lap.
+    pub nb_laps: i32,
+
+    /// The number of players that have set a record in this ranking.
     pub nb_records: usize,
 
-    /// The top record on the current map, or `None` if no player
-    /// has set a record on the current map.
+    /// The top record in this ranking, or `None` if no player
+    /// has set a record yet.
     pub top_record: Option<Record>,
 
-    /// A number of top records on the current map. The number is determined
+    /// A number of top records in this ranking. The number is determined
     /// by how many records we want to display in-game. The vector is sorted
     /// from better to worse.
     pub top_records: Vec<Record>,
 
-    /// Maps player UID to their personal best on the current map.
+    /// Maps player UID to their personal best in this ranking.
     pbs: HashMap<i32, Record>,
 }
 
-impl RecordsState {
-    fn init() -> Self {
-        RecordsState {
+impl MapRecords {
+    fn init(nb_laps: i32) -> Self {
+        MapRecords {
+            nb_laps,
             nb_records: 0,
             top_record: None,
             top_records: vec![],
@@ -47,7 +115,7 @@ impl RecordsState {
         }
     }
 
-    /// The personal best of the specified player, on the current map, or
+    /// The personal best of the specified player in this ranking, or
     /// `None` if they have not set a record yet.
     pub fn pb(&self, player_uid: i32) -> Option<&Record> {
         self.pbs.get(&player_uid)
@@ -118,9 +186,29 @@ impl RecordsState {
     }
 }
 
+/// A player's time at a checkpoint, and the times at that checkpoint
+/// they are compared to.
+#[derive(Debug, PartialEq)]
+pub struct CheckpointSplit {
+    /// The index of the checkpoint in the run.
+    pub cp_index: usize,
+
+    /// The player's time at this checkpoint.
+    pub millis: i32,
+
+    /// The time at this checkpoint in the player's personal best,
+    /// or `None` if they have no personal best on this map.
+    pub pb_millis: Option<i32>,
+
+    /// The time at this checkpoint in the map's top record,
+    /// or `None` if there is no record on this map.
+    pub top_record_millis: Option<i32>,
+}
+
 #[derive(Clone)]
 pub struct RecordController {
     db: DatabaseClient,
+    live_config: Arc<dyn LiveConfig>,
     live_playlist: Arc<dyn LivePlaylist>,
     live_players: Arc<dyn LivePlayers>,
     state: Arc<RwLock<RecordsState>>,
@@ -129,11 +217,13 @@ pub struct RecordController {
 impl RecordController {
     pub async fn init(
         db: &DatabaseClient,
+        live_config: &Arc<dyn LiveConfig>,
         live_playlist: &Arc<dyn LivePlaylist>,
         live_players: &Arc<dyn LivePlayers>,
     ) -> Self {
         let controller = RecordController {
             db: db.clone(),
+            live_config: live_config.clone(),
             live_playlist: live_playlist.clone(),
             live_players: live_players.clone(),
             state: Arc::new(RwLock::new(RecordsState::init())),
@@ -156,7 +246,7 @@ impl RecordController {
         controller
     }
 
-    /// Load a player's personal best when they join, or unload it when they leave.
+    /// Load a player's personal bests when they join, or unload them when they leave.
     pub async fn update_for_player(&self, diff: &PlayerDiff) {
         use PlayerTransition::*;
 
@@ -171,39 +261,70 @@ impl RecordController {
             }
             RemovePlayer | RemoveSpectator | RemovePureSpectator => {
                 let mut records_state = self.state.write().await;
-                records_state.pbs.remove(&diff.info.uid);
+                records_state.race.pbs.remove(&diff.info.uid);
+                if let Some(flying_lap) = records_state.flying_lap.as_mut() {
+                    flying_lap.pbs.remove(&diff.info.uid);
+                }
+                records_state.tainted_runs.remove(&diff.info.uid);
             }
             _ => {}
         }
     }
 
     async fn load_for_player(&self, map_uid: &str, info: &PlayerInfo) {
-        // TODO support multi-lap records
-        //  => load all 0 lap records
-        //  => check if there are multiple laps in the current mode,
-        //     and if so, fetch all records matching that lap count as well
+        let (race_laps, flying_lap_laps) = {
+            let records_state = self.state.read().await;
+            let flying_lap_laps = records_state.flying_lap.as_ref().map(|r| r.nb_laps);
+            (records_state.race.nb_laps, flying_lap_laps)
+        };
 
         let pb = self
             .db
-            .player_record(&map_uid, &info.login, 0)
+            .player_record(&map_uid, &info.login, race_laps)
             .await
             .expect("failed to load player PB");
+
+        let flying_lap_pb = match flying_lap_laps {
+            Some(nb_laps) => self
+                .db
+                .player_record(&map_uid, &info.login, nb_laps)
+                .await
+                .expect("failed to load player flying lap PB"),
+            None => None,
+        };
+
+        let mut records_state = self.state.write().await;
         if let Some(pb) = pb {
-            let mut records_state = self.state.write().await;
-            records_state.pbs.insert(info.uid, pb);
+            records_state.race.pbs.insert(info.uid, pb);
+        }
+        if let (Some(pb), Some(flying_lap)) = (flying_lap_pb, records_state.flying_lap.as_mut()) {
+            flying_lap.pbs.insert(info.uid, pb);
         }
     }
 
     /// Load a map's top records, and records of connected players.
+    ///
+    /// On multi-lap maps, the full-race records and the flying lap
+    /// records are loaded separately.
     pub async fn load_for_map(&self, loaded_map: &Map) {
-        // TODO support multi-lap records
-        //  => load all 0 lap records
-        //  => check if there are multiple laps in the current mode,
-        //     and if so, fetch all records matching that lap count as well
+        let race = self
+            .load_map_records(&loaded_map.uid, loaded_map.nb_laps.unwrap_or(0))
+            .await;
+
+        let flying_lap = match loaded_map.nb_laps {
+            Some(_) => Some(self.load_map_records(&loaded_map.uid, 0).await),
+            None => None,
+        };
 
+        let mut records_state = self.state.write().await;
+        records_state.race = race;
+        records_state.flying_lap = flying_lap;
+    }
+
+    async fn load_map_records(&self, map_uid: &str, nb_laps: i32) -> MapRecords {
         let top1 = self
             .db
-            .top_record(&loaded_map.uid, 0)
+            .top_record(map_uid, nb_laps)
             .await
             .expect("failed to load map's top1 record");
 
@@ -214,15 +335,14 @@ impl RecordController {
 
         let top_records = self
             .db
-            .top_records(&loaded_map.uid, MAX_DISPLAYED_MAP_RANKS as i64, 0)
+            .top_records(map_uid, MAX_DISPLAYED_MAP_RANKS as i64, nb_laps)
             .await
             .expect("failed to load map records");
 
-        let mut records_state = self.state.write().await;
-        records_state.top_record = top1;
-        records_state.top_records = top_records;
-        records_state.nb_records = nb_records as usize;
-        records_state.pbs.clear();
+        let mut map_records = MapRecords::init(nb_laps);
+        map_records.top_record = top1;
+        map_records.top_records = top_records;
+        map_records.nb_records = nb_records as usize;
 
         let players_state = self.live_players.lock().await;
         let all_logins = players_state
@@ -233,73 +353,177 @@ impl RecordController {
 
         let pbs = self
             .db
-            .records(vec![&loaded_map.uid], all_logins, 0, None)
+            .records(vec![map_uid], all_logins, nb_laps, None)
             .await
             .expect("failed to load player PBs");
 
         for pb in pbs {
             if let Some(uid) = players_state.uid(&pb.player_login) {
-                records_state.pbs.insert(*uid, pb);
+                map_records.pbs.insert(*uid, pb);
             }
         }
+
+        map_records
+    }
+
+    /// Mark the current run of the specified player as tainted,
+    /// so that it will not be considered for records.
+    pub async fn taint_run(&self, player_login: &str) {
+        if let Some(uid) = self.live_players.uid(player_login).await {
+            self.state.write().await.tainted_runs.insert(uid);
+        }
+    }
+
+    /// Signal that a player has started a new run, which clears
+    /// the taint of their previous run.
+    pub async fn begin_run(&self, player_login: &str) {
+        if let Some(uid) = self.live_players.uid(player_login).await {
+            self.state.write().await.tainted_runs.remove(&uid);
+        }
     }
 
     /// Produce a record at the end of a run.
+    /// Returns `None` if the run was tainted by an incoherence.
+    ///
+    /// New personal bests are checked for plausibility first. If the run is implausible,
+    /// it is either rejected, or stored for review by an admin. In both cases,
+    /// the reason is returned as an error.
     /// If that run is the player's new personal best, update the map records.
     ///
     /// The cached personal best for this player will be updated,
     /// and if it is a top n record, that cached list will also be
     /// updated.
-    pub async fn end_run(&self, finish_ev: &CheckpointEvent) -> Option<PbDiff> {
-        // TODO support multi-lap records
-        //  => for every finished lap, create a 0 lap record
-        //  => check if there are multiple laps in the current mode,
-        //     and if so, create records for the full amount of laps as well
-
+    pub async fn end_run(
+        &self,
+        finish_ev: &CheckpointEvent,
+    ) -> Result<Option<PbDiff>, Implausibility> {
         assert!(finish_ev.is_finish);
 
-        let player = match self.live_players.info(&finish_ev.player_login).await {
-            Some(player_info) => player_info,
-            None => return None,
+        let map = match self.live_playlist.current_map().await {
+            Some(map) => map,
+            None => return Ok(None),
         };
 
-        let map_uid = match self.live_playlist.current_map_uid().await {
-            Some(uid) => uid,
-            None => return None,
+        let evidence = run_evidence(finish_ev, &map);
+        self.end_attempt(evidence, &map).await
+    }
+
+    /// Produce a flying lap record when a player finishes a lap on a multi-lap map.
+    /// Returns `None` if the lap was not a flying lap, or if the run was tainted.
+    ///
+    /// Flying laps are handled like finished runs, with the exception that they
+    /// are compared to the flying lap records instead of the full-race records.
+    pub async fn end_lap(
+        &self,
+        lap_ev: &CheckpointEvent,
+    ) -> Result<Option<PbDiff>, Implausibility> {
+        assert!(lap_ev.is_lap_finish);
+
+        let map = match self.live_playlist.current_map().await {
+            Some(map) => map,
+            None => return Ok(None),
         };
 
+        let evidence = match flying_lap_evidence(lap_ev, &map) {
+            Some(evidence) => evidence,
+            None => return Ok(None),
+        };
+
+        self.end_attempt(evidence, &lap_map(map)).await
+    }
+
+    /// Check whether a finished run or flying lap on the current map is plausible.
+    ///
+    /// New personal bests are already checked when they are stored; use this
+    /// for runs that did not improve a player's record, but might still
+    /// count towards the season.
+    pub async fn is_plausible(&self, evidence: &RecordEvidence) -> bool {
+        let map = match self.live_playlist.current_map().await {
+            Some(map) if map.uid == evidence.map_uid => map,
+            _ => return false, // map was changed in the meantime
+        };
+        let map = if evidence.nb_laps == 0 && map.nb_laps.is_some() {
+            lap_map(map)
+        } else {
+            map
+        };
+
+        let check_config = self.live_config.lock().await.record_checks;
+        let records_state = self.state.read().await;
+        let top_record_millis = records_state
+            .ranking(evidence.nb_laps)
+            .and_then(|ranking| ranking.top_record.as_ref())
+            .map(|rec| rec.millis);
+
+        check_run(evidence, &map, top_record_millis, &check_config).is_ok()
+    }
+
+    async fn end_attempt(
+        &self,
+        evidence: RecordEvidence,
+        map: &Map,
+    ) -> Result<Option<PbDiff>, Implausibility> {
+        let player = match self.live_players.info(&evidence.player_login).await {
+            Some(player_info) => player_info,
+            None => return Ok(None),
+        };
+
+        let check_config = self.live_config.lock().await.record_checks;
+
         let mut records_state = self.state.write().await;
 
-        let prev_pb = records_state.pb(player.uid);
+        if records_state.tainted_runs.contains(&player.uid) {
+            log::warn!(
+                "ignore incoherent run of '{}' on '{}'",
+                &player.login,
+                &evidence.map_uid
+            );
+            return Ok(None);
+        }
+
+        let map_records = match records_state.ranking_mut(evidence.nb_laps) {
+            Some(map_records) => map_records,
+            None => return Ok(None), // map was changed in the meantime
+        };
+
+        let prev_pb = map_records.pb(player.uid);
         let prev_pb_pos = prev_pb.map(|rec| rec.map_rank as usize);
-        let prev_pb_diff = prev_pb.map(|rec| finish_ev.race_time_millis - rec.millis);
+        let prev_pb_diff = prev_pb.map(|rec| evidence.millis - rec.millis);
 
         let is_new_pb = prev_pb_diff.map(|millis| millis < 0).unwrap_or(true);
 
         if !is_new_pb {
-            return Some(PbDiff {
+            return Ok(Some(PbDiff {
                 player_uid: player.uid,
                 millis_diff: prev_pb_diff,
                 prev_pos: prev_pb_pos,
                 new_pos: prev_pb_pos.unwrap(), // no change in position
                 new_record: None,
                 pos_gained: 0,
-            });
+            }));
         }
 
-        let evidence = RecordEvidence {
-            player_login: player.login.clone(),
-            map_uid,
-            millis: finish_ev.race_time_millis,
-            timestamp: Utc::now().naive_utc(),
-            nb_laps: 0,
-            cp_millis: finish_ev.race_cp_millis.iter().copied().collect(),
-        };
+        let top_record_millis = map_records.top_record.as_ref().map(|rec| rec.millis);
+        if let Err(reason) = check_run(&evidence, map, top_record_millis, &check_config) {
+            log::warn!(
+                "implausible run of '{}' on '{}': {}",
+                &evidence.player_login,
+                &evidence.map_uid,
+                &reason
+            );
+            if !reason.is_invalid() {
+                self.db
+                    .flag_record(&evidence, &reason.to_string())
+                    .await
+                    .expect("failed to flag record");
+            }
+            return Err(reason);
+        }
 
         // We already know the rank of the new record if it is better
         // than at least one of the cached records. Otherwise,
         // we have to look it up in the database.
-        let new_pos: usize = match records_state.pos_preview(&evidence) {
+        let new_pos: usize = match map_records.pos_preview(&evidence) {
             Some(pos) => pos,
             None => self
                 .db
@@ -317,7 +541,7 @@ impl RecordController {
         let record = Record {
             map_uid: evidence.map_uid,
             map_rank: new_pos as i64,
-            max_map_rank: records_state.nb_records as i64,
+            max_map_rank: map_records.nb_records as i64,
             player_login: player.login.clone(),
             player_display_name: player.display_name.clone(),
             timestamp: evidence.timestamp,
@@ -327,28 +551,182 @@ impl RecordController {
         };
 
         // Remember record in the cache.
-        records_state.upsert_record(player.uid, &record);
+        map_records.upsert_record(player.uid, &record);
 
         let pos_gained = match prev_pb_pos {
             Some(p) => p - new_pos,
-            None if records_state.nb_records == 1 => 1,
-            None => records_state.nb_records - new_pos,
+            None if map_records.nb_records == 1 => 1,
+            None => map_records.nb_records - new_pos,
         };
 
-        Some(PbDiff {
+        Ok(Some(PbDiff {
             player_uid: player.uid,
             millis_diff: prev_pb_diff,
             new_pos,
             prev_pos: prev_pb_pos,
             pos_gained,
             new_record: Some(record),
-        })
+        }))
+    }
+}
+
+/// The record evidence of a finished run.
+///
+/// On multi-lap maps, the record counts all laps of the race.
+pub fn run_evidence(finish_ev: &CheckpointEvent, map: &Map) -> RecordEvidence {
+    RecordEvidence {
+        player_login: finish_ev.player_login.clone(),
+        map_uid: map.uid.clone(),
+        millis: finish_ev.race_time_millis,
+        timestamp: Utc::now().naive_utc(),
+        nb_laps: map.nb_laps.unwrap_or(0),
+        cp_millis: finish_ev.race_cp_millis.clone(),
     }
 }
 
+/// Flying laps are checked for plausibility against the author time of a single lap.
+fn lap_map(map: Map) -> Map {
+    Map {
+        author_millis: map.author_millis / map.nb_laps.unwrap_or(1).max(1),
+        ..map
+    }
+}
+
+/// The record evidence of a flying lap, or `None` if the map is not
+/// multi-lap, or if the finished lap was the first lap of the run.
+///
+/// Flying laps are stored with a lap count of zero.
+pub fn flying_lap_evidence(lap_ev: &CheckpointEvent, map: &Map) -> Option<RecordEvidence> {
+    let is_first_lap = lap_ev.race_cp_index == lap_ev.lap_cp_index;
+    if map.nb_laps.is_none() || !lap_ev.is_lap_finish || is_first_lap {
+        return None;
+    }
+    Some(RecordEvidence {
+        player_login: lap_ev.player_login.clone(),
+        map_uid: map.uid.clone(),
+        millis: lap_ev.lap_time_millis,
+        timestamp: Utc::now().naive_utc(),
+        nb_laps: 0,
+        cp_millis: lap_ev.lap_cp_millis.clone(),
+    })
+}
+
 #[async_trait]
 impl LiveRecords for RecordController {
     async fn lock(&self) -> RwLockReadGuard<'_, RecordsState> {
         self.state.read().await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+
+    use crate::server::DisplayString;
+
+    use super::*;
+
+    fn record(login: &str, cp_millis: Vec<i32>) -> Record {
+        Record {
+            map_uid: "uid".to_string(),
+            player_login: login.to_string(),
+            nb_laps: 0,
+            map_rank: 1,
+            max_map_rank: 1,
+            player_display_name: DisplayString::from(login.to_string()),
+            millis: *cp_millis.last().unwrap(),
+            cp_millis,
+            timestamp: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn compares_checkpoint_splits() {
+        let mut state = RecordsState::init();
+        assert_eq!(
+            CheckpointSplit {
+                cp_index: 0,
+                millis: 1000,
+                pb_millis: None,
+                top_record_millis: None,
+            },
+            state.checkpoint_split(1, 0, 1000)
+        );
+
+        state.race.upsert_record(1, &record("a", vec![1200, 2500]));
+        state.race.upsert_record(2, &record("b", vec![1100, 2400]));
+        assert_eq!(
+            CheckpointSplit {
+                cp_index: 1,
+                millis: 2450,
+                pb_millis: Some(2500),
+                top_record_millis: Some(2400),
+            },
+            state.checkpoint_split(1, 1, 2450)
+        );
+
+        // Checkpoints beyond those of the records
+        let split = state.checkpoint_split(1, 2, 3000);
+        assert_eq!(None, split.pb_millis);
+        assert_eq!(None, split.top_record_millis);
+    }
+
+    fn lap_event(race_cp_index: i32, lap_cp_millis: Vec<i32>) -> CheckpointEvent {
+        CheckpointEvent {
+            player_login: "login".to_string(),
+            race_time_millis: 0,
+            lap_time_millis: *lap_cp_millis.last().unwrap(),
+            race_cp_index,
+            lap_cp_index: lap_cp_millis.len() as i32 - 1,
+            race_cp_millis: vec![],
+            lap_cp_millis,
+            is_finish: false,
+            is_lap_finish: true,
+            speed: 0.0,
+        }
+    }
+
+    #[test]
+    fn only_flying_laps_are_lap_records() {
+        let mut map = Map {
+            uid: "uid".to_string(),
+            file_name: "".to_string(),
+            name: DisplayString::from("".to_string()),
+            author_login: "".to_string(),
+            author_display_name: DisplayString::from("".to_string()),
+            author_millis: 0,
+            gold_millis: None,
+            silver_millis: None,
+            bronze_millis: None,
+            added_since: Utc::now().naive_utc(),
+            exchange_id: None,
+            nb_cps: Some(2),
+            nb_laps: None,
+        };
+
+        // not a multi-lap map
+        assert!(flying_lap_evidence(&lap_event(3, vec![900, 1800]), &map).is_none());
+
+        map.nb_laps = Some(3);
+
+        // first lap starts from a standing start
+        assert!(flying_lap_evidence(&lap_event(1, vec![1000, 2000]), &map).is_none());
+
+        let evidence = flying_lap_evidence(&lap_event(3, vec![900, 1800]), &map).unwrap();
+        assert_eq!(0, evidence.nb_laps);
+        assert_eq!(1800, evidence.millis);
+        assert_eq!(vec![900, 1800], evidence.cp_millis);
+    }
+
+    #[test]
+    fn separates_race_and_flying_lap_rankings() {
+        let mut state = RecordsState::init();
+        state.race = MapRecords::init(3);
+        assert!(state.ranking_mut(0).is_none());
+
+        state.flying_lap = Some(MapRecords::init(0));
+        assert_eq!(3, state.ranking_mut(3).unwrap().nb_laps);
+        assert_eq!(0, state.ranking_mut(0).unwrap().nb_laps);
+        assert!(state.ranking_mut(2).is_none());
+    }
+}
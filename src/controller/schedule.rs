@@ -1,4 +1,5 @@
 use std::cmp::{max, min};
+use std::collections::{HashMap, VecDeque};
 use std::ops::{Add, Sub};
 use std::sync::Arc;
 
@@ -7,9 +8,10 @@ use chrono::{Duration, NaiveDateTime, Utc};
 use futures::future::join_all;
 use tokio::sync::RwLock;
 
-use crate::config::TimeAttackConfig;
-use crate::controller::{LiveConfig, LivePlaylist, LiveQueue, LiveRecords};
-use crate::database::{DatabaseClient, RecordQueries};
+use crate::config::{TimeAttackConfig, TimeLimitPolicy, TimeLimitReference};
+use crate::constants::TIME_LIMIT_RECENT_RUNS;
+use crate::controller::{LiveConfig, LivePlayers, LivePlaylist, LiveQueue, LiveRecords};
+use crate::database::{DatabaseClient, Map, PoolQueries, RecordQueries};
 use crate::event::PlaylistDiff;
 use crate::server::{Calls, ModeOptions, Server};
 
@@ -19,7 +21,27 @@ pub trait LiveSchedule: Send + Sync {
     /// Returns the current *expected* duration until the specified map is
     /// played on the server, or `None` if that map is not in the playlist.
     /// The default duration (zero) signals that the map is currently being played.
+    ///
+    /// Returns `None` for maps that are not expected to be played before the
+    /// next map pool switch, if they are not part of that pool.
     async fn time_until_played(&self, map_uid: &str) -> Option<Duration>;
+
+    /// Returns the next scheduled map pool switch, or `None`
+    /// if there is no switch within the next week.
+    async fn next_pool_switch(&self) -> Option<PoolSwitch>;
+}
+
+/// An upcoming activation of a map pool, as scheduled in the config.
+#[derive(Clone, Debug)]
+pub struct PoolSwitch {
+    /// The time at which the pool will be activated. The playlist is
+    /// replaced after the map that is played at that time.
+    pub at: NaiveDateTime,
+
+    pub pool_name: String,
+
+    /// The UIDs of the maps in that pool.
+    pub map_uids: Vec<String>,
 }
 
 struct ScheduleState {
@@ -30,6 +52,16 @@ struct ScheduleState {
     /// These times should typically be the top record, or the author time,
     /// if no record was set yet.
     reference_millis: Vec<u64>,
+
+    /// The most recent finish times on playlist maps, including runs that did not
+    /// improve a player's record. These are not kept when the controller restarts.
+    recent_run_millis: HashMap<String, VecDeque<i32>>,
+
+    /// The time limit of the current map.
+    curr_time_limit: Duration,
+
+    /// The next scheduled map pool switch, if any.
+    pool_switch: Option<PoolSwitch>,
 }
 
 #[derive(Clone)]
@@ -41,6 +73,7 @@ pub struct ScheduleController {
     live_queue: Arc<dyn LiveQueue>,
     live_records: Arc<dyn LiveRecords>,
     live_config: Arc<dyn LiveConfig>,
+    live_players: Arc<dyn LivePlayers>,
 }
 
 impl ScheduleController {
@@ -53,20 +86,24 @@ impl ScheduleController {
         live_queue: &Arc<dyn LiveQueue>,
         live_records: &Arc<dyn LiveRecords>,
         live_config: &Arc<dyn LiveConfig>,
+        live_players: &Arc<dyn LivePlayers>,
     ) -> Self {
+        let policy = live_config.lock().await.timeattack.time_limit;
         let playlist_state = live_playlist.lock().await;
-        let reference_millis = join_all(playlist_state.maps.iter().map(|map| async move {
-            let top1 = db
-                .top_record(&map.uid, 0)
-                .await
-                .expect("failed to load top record");
-            top1.map(|rec| rec.millis).unwrap_or(map.author_millis) as u64
-        }))
+        let reference_millis = join_all(
+            playlist_state
+                .maps
+                .iter()
+                .map(|map| load_reference_millis(db, map, &policy)),
+        )
         .await;
 
         let state = ScheduleState {
             map_start_time: Utc::now().naive_utc(), // we don't know the actual time
             reference_millis,
+            recent_run_millis: HashMap::new(),
+            curr_time_limit: Duration::zero(),
+            pool_switch: None,
         };
 
         let controller = ScheduleController {
@@ -77,36 +114,64 @@ impl ScheduleController {
             live_queue: live_queue.clone(),
             live_records: live_records.clone(),
             live_config: live_config.clone(),
+            live_players: live_players.clone(),
         };
 
         controller.set_time_limit().await;
+        controller.load_pool_switch().await;
 
         controller
     }
 
     /// Sets the time limit for the current map.
     ///
-    /// The time limit is based on either the author time, or the top record, if available.
+    /// The time limit is based on either the author time, or the records on the map,
+    /// if available, and adjusted according to the configured time limit policy.
     pub async fn set_time_limit(&self) {
         let idx = match self.live_playlist.current_index().await {
             Some(idx) => idx,
             None => return,
         };
+        let map = match self.live_playlist.at_index(idx).await {
+            Some(map) => map,
+            None => return,
+        };
+
+        let mode_config = self.live_config.lock().await.timeattack;
+        let policy = mode_config.time_limit;
+
+        // Update in case a new record have been set the last time this map was played.
+        let ref_millis = load_reference_millis(&self.db, &map, &policy).await;
 
         let mut schedule_state = self.state.write().await;
         let records_state = self.live_records.lock().await;
 
-        let config = self.live_config.lock().await;
-        let mode_config = config.timeattack;
+        schedule_state.reference_millis[idx] = ref_millis;
 
-        // Update in case a new record have been set the last time this map was played.
-        if let Some(top_record) = &records_state.top_record {
-            let top1_millis = top_record.millis as u64;
-            let _ = std::mem::replace(&mut schedule_state.reference_millis[idx], top1_millis);
-        }
+        let mut recent_millis: Vec<i32> = schedule_state
+            .recent_run_millis
+            .get(&map.uid)
+            .map(|runs| runs.iter().copied().collect())
+            .unwrap_or_default();
+        recent_millis.sort_unstable();
+
+        let nb_unfinished = self
+            .live_players
+            .uid_playing()
+            .await
+            .into_iter()
+            .filter(|uid| records_state.race.pb(*uid).is_none())
+            .count();
+
+        let ctxt = TimeLimitContext {
+            nb_unfinished,
+            recent_median_millis: percentile_millis(&recent_millis, 0.5)
+                .map(|millis| millis as u64),
+        };
 
         // Set the server's time limit
-        let new_time_limit = self.to_limit(schedule_state.reference_millis[idx], &mode_config);
+        let new_time_limit = time_limit(schedule_state.reference_millis[idx], &ctxt, &mode_config);
+        schedule_state.curr_time_limit = new_time_limit;
         let mode_options = self.server.mode_options().await;
         if let ModeOptions::TimeAttack(mut options) = mode_options {
             options.time_limit_secs = new_time_limit.num_seconds() as i32;
@@ -117,6 +182,66 @@ impl ScheduleController {
         }
     }
 
+    /// Remember the finish time of a run on the specified map, which
+    /// is used to adjust the time limit the next time it is played.
+    pub async fn add_run(&self, map_uid: &str, millis: i32) {
+        let mut schedule_state = self.state.write().await;
+        let runs = schedule_state
+            .recent_run_millis
+            .entry(map_uid.to_string())
+            .or_default();
+        if runs.len() == TIME_LIMIT_RECENT_RUNS {
+            let _ = runs.pop_front();
+        }
+        runs.push_back(millis);
+    }
+
+    /// Extends the time limit of the current map by the specified duration.
+    pub async fn extend_time_limit(&self, duration: Duration) {
+        let mut schedule_state = self.state.write().await;
+        schedule_state.curr_time_limit = schedule_state.curr_time_limit + duration;
+
+        let mode_options = self.server.mode_options().await;
+        if let ModeOptions::TimeAttack(mut options) = mode_options {
+            options.time_limit_secs = schedule_state.curr_time_limit.num_seconds() as i32;
+            self.server
+                .set_mode_options(&ModeOptions::TimeAttack(options))
+                .await
+                .expect("failed to set mode options");
+        }
+    }
+
+    /// Look up the next map pool switch in the config.
+    pub async fn load_pool_switch(&self) {
+        let now = Utc::now().naive_utc();
+        let next_switch = self
+            .live_config
+            .lock()
+            .await
+            .next_pool_switch(&now)
+            .map(|(at, pool_name)| (at, pool_name.to_string()));
+
+        let pool_switch = match next_switch {
+            Some((at, pool_name)) => {
+                let map_uids = self
+                    .db
+                    .pool(&pool_name)
+                    .await
+                    .expect("failed to load map pool")
+                    .map(|pool| pool.map_uids)
+                    .unwrap_or_default();
+                Some(PoolSwitch {
+                    at,
+                    pool_name,
+                    map_uids,
+                })
+            }
+            None => None,
+        };
+
+        self.state.write().await.pool_switch = pool_switch;
+    }
+
     /// Update the cached reference times for playlist maps.
     pub async fn insert_or_remove(&self, diff: &PlaylistDiff) {
         let mut schedule_state = self.state.write().await;
@@ -127,38 +252,92 @@ impl ScheduleController {
                     .push(map.author_millis as u64);
             }
             PlaylistDiff::Append(map) => {
-                let top1 = self
-                    .db
-                    .top_record(&map.uid, 0)
-                    .await
-                    .expect("failed to load top record");
-                let millis = top1.map(|rec| rec.millis).unwrap_or(map.author_millis) as u64;
+                let policy = self.live_config.lock().await.timeattack.time_limit;
+                let millis = load_reference_millis(&self.db, map, &policy).await;
                 schedule_state.reference_millis.push(millis);
             }
-            PlaylistDiff::Remove { was_index, .. } => {
+            PlaylistDiff::Remove { was_index, map } => {
                 schedule_state.reference_millis.remove(*was_index);
+                schedule_state.recent_run_millis.remove(&map.uid);
             }
         }
     }
+}
 
-    fn to_limit(&self, ref_millis: u64, ta_config: &TimeAttackConfig) -> Duration {
-        const TIME_LIMIT_DIVIDER: u64 = 30 * 1000; // use steps of 30 seconds
+/// Data that is used to adjust the time limit of the current map.
+#[derive(Debug, Default)]
+struct TimeLimitContext {
+    /// The number of connected players that have not finished the map.
+    nb_unfinished: usize,
 
-        let n = TIME_LIMIT_DIVIDER;
-        let i = ref_millis * ta_config.time_limit_factor as u64;
+    /// The median of the most recent finishes on the map, if any.
+    recent_median_millis: Option<u64>,
+}
 
-        let rem_millis = i % n;
-        let limit_millis = if rem_millis > n / 2 {
-            i + n - rem_millis // round up
-        } else {
-            i - rem_millis // round down
-        };
+/// Load the reference time of the given map, that the time limit factor is applied to.
+/// This is the top record, or the record at the configured percentile, or the author
+/// time if there are no records.
+async fn load_reference_millis(db: &DatabaseClient, map: &Map, policy: &TimeLimitPolicy) -> u64 {
+    let nb_laps = map.nb_laps.unwrap_or(0);
+    let ref_millis = match policy.reference {
+        TimeLimitReference::TopRecord => db
+            .top_record(&map.uid, nb_laps)
+            .await
+            .expect("failed to load top record")
+            .map(|rec| rec.millis),
+        TimeLimitReference::RecordPercentile => {
+            let records = db
+                .records(vec![&map.uid], vec![], nb_laps, None)
+                .await
+                .expect("failed to load records");
+            let mut all_millis: Vec<i32> = records.iter().map(|rec| rec.millis).collect();
+            all_millis.sort_unstable();
+            percentile_millis(&all_millis, policy.percentile)
+        }
+    };
+    ref_millis.unwrap_or(map.author_millis) as u64
+}
+
+/// Calculate a time limit from the given reference time.
+fn time_limit(ref_millis: u64, ctxt: &TimeLimitContext, ta_config: &TimeAttackConfig) -> Duration {
+    const TIME_LIMIT_DIVIDER: u64 = 30 * 1000; // use steps of 30 seconds
+
+    let policy = &ta_config.time_limit;
+
+    let n = TIME_LIMIT_DIVIDER;
+    let i = ref_millis * ta_config.time_limit_factor as u64;
+    let i = (i as f64
+        * (1.0 + policy.scale_per_unfinished_player as f64 * ctxt.nb_unfinished as f64))
+        as u64;
+    let i = match ctxt.recent_median_millis {
+        Some(median) => max(
+            i,
+            (median as f64 * policy.recent_median_factor as f64) as u64,
+        ),
+        None => i,
+    };
 
-        let limit_secs = limit_millis / 1000;
-        let limit_secs = min(ta_config.time_limit_max_secs as u64, limit_secs);
-        let limit_secs = max(ta_config.time_limit_min_secs as u64, limit_secs);
-        Duration::seconds(limit_secs as i64)
+    let rem_millis = i % n;
+    let limit_millis = if rem_millis > n / 2 {
+        i + n - rem_millis // round up
+    } else {
+        i - rem_millis // round down
+    };
+
+    let limit_secs = limit_millis / 1000;
+    let limit_secs = min(ta_config.time_limit_max_secs as u64, limit_secs);
+    let limit_secs = max(ta_config.time_limit_min_secs as u64, limit_secs);
+    Duration::seconds(limit_secs as i64)
+}
+
+/// Returns the time at the given percentile of the sorted times,
+/// or `None` if the list is empty.
+fn percentile_millis(sorted_millis: &[i32], percentile: f32) -> Option<i32> {
+    if sorted_millis.is_empty() {
+        return None;
     }
+    let idx = (percentile * (sorted_millis.len() - 1) as f32).round() as usize;
+    sorted_millis.get(idx).copied()
 }
 
 #[async_trait]
@@ -193,11 +372,10 @@ impl LiveSchedule for ScheduleController {
         let mut result = Duration::zero();
 
         // 1. add time until current map ends
-        if let Some(idx) = curr_playlist_idx {
+        if curr_playlist_idx.is_some() {
             let now = Utc::now().naive_utc();
             let time_since_map_start = now.signed_duration_since(schedule_state.map_start_time);
-            let ref_millis = schedule_state.reference_millis[idx];
-            result = result.add(self.to_limit(ref_millis, &mode_config));
+            result = result.add(schedule_state.curr_time_limit);
             result = result.sub(time_since_map_start);
         }
         result = result.add(duration_between_maps);
@@ -205,10 +383,91 @@ impl LiveSchedule for ScheduleController {
         // 2. add time until the specified map starts
         for entry in entries_ahead {
             let ref_millis = schedule_state.reference_millis[entry.playlist_idx];
-            result = result.add(self.to_limit(ref_millis, &mode_config));
+            result = result.add(time_limit(
+                ref_millis,
+                &TimeLimitContext::default(),
+                &mode_config,
+            ));
             result = result.add(duration_between_maps);
         }
 
+        // 3. the map might not be played if the playlist is replaced before
+        if let Some(switch) = &schedule_state.pool_switch {
+            let now = Utc::now().naive_utc();
+            let is_after_switch = now + result > switch.at;
+            if is_after_switch && !switch.map_uids.iter().any(|uid| uid == map_uid) {
+                return None;
+            }
+        }
+
         Some(result)
     }
+
+    async fn next_pool_switch(&self) -> Option<PoolSwitch> {
+        self.state.read().await.pool_switch.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{QueueWeights, RaceVoteConfig, TimeLimitPolicy};
+
+    use super::*;
+
+    fn config(time_limit: TimeLimitPolicy) -> TimeAttackConfig {
+        TimeAttackConfig {
+            time_limit_factor: 10,
+            time_limit_max_secs: 900,
+            time_limit_min_secs: 60,
+            time_limit,
+            outro_duration_secs: 20,
+            min_restart_vote_ratio: 0.5,
+            min_restart_vote_ratio_step: 0.25,
+            queue: QueueWeights::default(),
+            votes: RaceVoteConfig::default(),
+        }
+    }
+
+    #[test]
+    fn default_time_limit() {
+        let cfg = config(TimeLimitPolicy::default());
+        let ctxt = TimeLimitContext::default();
+        assert_eq!(Duration::seconds(300), time_limit(30_000, &ctxt, &cfg));
+        assert_eq!(Duration::seconds(60), time_limit(1_000, &ctxt, &cfg));
+        assert_eq!(Duration::seconds(900), time_limit(100_000, &ctxt, &cfg));
+    }
+
+    #[test]
+    fn time_limit_scales_with_unfinished_players() {
+        let cfg = config(TimeLimitPolicy {
+            scale_per_unfinished_player: 0.1,
+            ..Default::default()
+        });
+        let ctxt = TimeLimitContext {
+            nb_unfinished: 5,
+            recent_median_millis: None,
+        };
+        assert_eq!(Duration::seconds(450), time_limit(30_000, &ctxt, &cfg));
+    }
+
+    #[test]
+    fn time_limit_floor_from_recent_median() {
+        let cfg = config(TimeLimitPolicy {
+            recent_median_factor: 15.0,
+            ..Default::default()
+        });
+        let ctxt = TimeLimitContext {
+            nb_unfinished: 0,
+            recent_median_millis: Some(40_000),
+        };
+        assert_eq!(Duration::seconds(600), time_limit(30_000, &ctxt, &cfg));
+    }
+
+    #[test]
+    fn record_percentile() {
+        assert_eq!(None, percentile_millis(&[], 0.5));
+        assert_eq!(Some(1), percentile_millis(&[1, 2, 3, 4, 5], 0.0));
+        assert_eq!(Some(3), percentile_millis(&[1, 2, 3, 4, 5], 0.5));
+        assert_eq!(Some(5), percentile_millis(&[1, 2, 3, 4, 5], 1.0));
+    }
 }
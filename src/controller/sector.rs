@@ -0,0 +1,168 @@
+use crate::database::RecordEvidence;
+
+/// Compares a player's sector times on a map to those of the top record,
+/// and to the best sector times of all players.
+///
+/// A sector is the section of a run between two consecutive checkpoints,
+/// or between the start and the first checkpoint.
+#[derive(Debug, PartialEq)]
+pub struct SectorAnalysis {
+    /// The sector times of the map's top record.
+    pub top_record: Vec<i32>,
+
+    /// The best time of any player in each sector. Combined, these
+    /// sector times make up the "ideal run".
+    pub ideal: Vec<i32>,
+
+    /// The sector times of the player's personal best,
+    /// or `None` if they have not set a record on this map.
+    pub player_pb: Option<Vec<i32>>,
+
+    /// The player's best time in each sector, across all of their
+    /// personal bests on this map, or `None` if they have not
+    /// set a record on this map.
+    pub player_best: Option<Vec<i32>>,
+}
+
+impl SectorAnalysis {
+    /// The sum of the best sector times of all players.
+    pub fn ideal_millis(&self) -> i32 {
+        self.ideal.iter().sum()
+    }
+
+    /// The sum of the player's best sector times.
+    pub fn player_best_millis(&self) -> Option<i32> {
+        self.player_best
+            .as_ref()
+            .map(|sectors| sectors.iter().sum())
+    }
+
+    /// The index of the sector in which the player's personal best loses
+    /// the most time against the given sector times, and the time lost,
+    /// or `None` if the player does not lose time in any sector.
+    pub fn max_loss(&self, against: &[i32]) -> Option<(usize, i32)> {
+        self.player_pb
+            .as_ref()?
+            .iter()
+            .zip(against.iter())
+            .map(|(pb, other)| pb - other)
+            .enumerate()
+            .filter(|(_, loss)| *loss > 0)
+            .max_by_key(|(_, loss)| *loss)
+    }
+}
+
+/// Analyse the sector times of the given player on a map.
+///
+/// # Arguments
+/// `history` - Every personal best that was set on the map
+///
+/// Returns `None` if there are no records on the map.
+pub fn analyze_sectors(history: &[RecordEvidence], player_login: &str) -> Option<SectorAnalysis> {
+    let top_record = history.iter().min_by_key(|rec| rec.millis)?;
+    let nb_sectors = top_record.cp_millis.len();
+
+    // Runs with a different number of checkpoints cannot be compared,
+    // f.e. if the map was changed since they were set.
+    let comparable: Vec<&RecordEvidence> = history
+        .iter()
+        .filter(|rec| rec.cp_millis.len() == nb_sectors)
+        .collect();
+
+    let player_runs: Vec<&RecordEvidence> = comparable
+        .iter()
+        .copied()
+        .filter(|rec| rec.player_login == player_login)
+        .collect();
+
+    let player_pb = player_runs
+        .iter()
+        .min_by_key(|rec| rec.millis)
+        .map(|rec| sectors(&rec.cp_millis));
+
+    let player_best = if player_runs.is_empty() {
+        None
+    } else {
+        Some(best_sectors(&player_runs, nb_sectors))
+    };
+
+    Some(SectorAnalysis {
+        top_record: sectors(&top_record.cp_millis),
+        ideal: best_sectors(&comparable, nb_sectors),
+        player_pb,
+        player_best,
+    })
+}
+
+/// Convert checkpoint times to sector times.
+pub fn sectors(cp_millis: &[i32]) -> Vec<i32> {
+    let mut prev = 0;
+    cp_millis
+        .iter()
+        .map(|millis| {
+            let sector = millis - prev;
+            prev = *millis;
+            sector
+        })
+        .collect()
+}
+
+/// The best time in each sector across the given runs.
+fn best_sectors(runs: &[&RecordEvidence], nb_sectors: usize) -> Vec<i32> {
+    let mut best = vec![i32::MAX; nb_sectors];
+    for run in runs {
+        for (best, sector) in best.iter_mut().zip(sectors(&run.cp_millis)) {
+            *best = (*best).min(sector);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn run(login: &str, cp_millis: Vec<i32>) -> RecordEvidence {
+        RecordEvidence {
+            player_login: login.to_string(),
+            map_uid: "uid".to_string(),
+            nb_laps: 0,
+            millis: *cp_millis.last().unwrap(),
+            timestamp: Utc::now().naive_utc(),
+            cp_millis,
+        }
+    }
+
+    #[test]
+    fn converts_checkpoints_to_sectors() {
+        assert_eq!(vec![1000, 1500, 500], sectors(&[1000, 2500, 3000]));
+        assert!(sectors(&[]).is_empty());
+    }
+
+    #[test]
+    fn finds_ideal_and_best_sectors() {
+        let history = vec![
+            run("a", vec![1000, 3000, 4000]),
+            run("a", vec![1200, 2800, 3900]),
+            run("b", vec![1100, 2500, 3700]),
+            run("c", vec![900, 5000]), // not comparable
+        ];
+
+        let analysis = analyze_sectors(&history, "a").unwrap();
+        assert_eq!(vec![1100, 1400, 1200], analysis.top_record);
+        assert_eq!(vec![1000, 1400, 1000], analysis.ideal);
+        assert_eq!(Some(vec![1200, 1600, 1100]), analysis.player_pb);
+        assert_eq!(Some(vec![1000, 1600, 1000]), analysis.player_best);
+        assert_eq!(3400, analysis.ideal_millis());
+        assert_eq!(Some(3600), analysis.player_best_millis());
+        assert_eq!(Some((1, 200)), analysis.max_loss(&analysis.top_record));
+
+        let analysis = analyze_sectors(&history, "d").unwrap();
+        assert_eq!(None, analysis.player_pb);
+        assert_eq!(None, analysis.max_loss(&analysis.ideal));
+
+        assert!(analyze_sectors(&[], "a").is_none());
+    }
+}
@@ -1,17 +1,21 @@
 use std::borrow::Cow;
-use std::cmp::max;
+use std::cmp::{max, Ordering};
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::Utc;
 use indexmap::map::IndexMap;
 use tokio::sync::{RwLock, RwLockReadGuard};
 
+use crate::config::{RankedRecords, RankingStrategy};
 use crate::constants::MAX_DISPLAYED_SERVER_RANKS;
-use crate::controller::LivePlayers;
-use crate::database::timeattack::TimeAttackQueries;
-use crate::database::{DatabaseClient, RecordQueries};
+use crate::controller::{LiveConfig, LivePlayers};
+use crate::database::timeattack::{MapRank, TimeAttackQueries};
+use crate::database::{
+    DatabaseClient, RecordEvidence, RecordQueries, Season, SeasonQueries, SeasonRank,
+};
 use crate::event::{ServerRankDiff, ServerRankingDiff};
 use crate::server::{Calls, DisplayString, Server};
 
@@ -26,6 +30,9 @@ pub trait LiveServerRanking: Send + Sync {
 pub struct ServerRankingState {
     /// A collection of server ranks, sorted from best to worst.
     all_ranks: IndexMap<Cow<'static, str>, ServerRank>,
+
+    /// The ranking of the current season, or `None` if there is no active season.
+    season: Option<SeasonRankingState>,
 }
 
 impl ServerRankingState {
@@ -48,9 +55,41 @@ impl ServerRankingState {
     pub fn max_pos(&self) -> usize {
         self.all_ranks.len()
     }
+
+    /// The ranking of the current season, or `None` if there is no active season.
+    pub fn season(&self) -> Option<&SeasonRankingState> {
+        self.season.as_ref()
+    }
+}
+
+/// A server ranking that only considers records set within a season.
+pub struct SeasonRankingState {
+    pub season: Season,
+
+    /// A collection of seasonal ranks, sorted from best to worst.
+    ranks: IndexMap<Cow<'static, str>, ServerRank>,
+}
+
+impl SeasonRankingState {
+    /// Returns a number of top seasonal ranks, sorted from better to worse.
+    pub fn top_ranks(&self) -> impl Iterator<Item = &ServerRank> {
+        self.ranks.values().take(MAX_DISPLAYED_SERVER_RANKS)
+    }
+
+    /// Returns a the seasonal rank of the specified player, or `None`
+    /// if they haven't set a record this season.
+    pub fn rank_of<'a>(&'a self, player_login: &'a str) -> Option<&'a ServerRank> {
+        let key: Cow<'a, str> = player_login.into();
+        self.ranks.get(&key)
+    }
+
+    /// The number of players that have a seasonal rank.
+    pub fn max_pos(&self) -> usize {
+        self.ranks.len()
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
 pub struct ServerRank {
     pub pos: usize,
     pub player_login: String,
@@ -61,6 +100,51 @@ pub struct ServerRank {
 
     /// The number of records better than this player's summed for every map.
     pub nb_losses: usize,
+
+    /// The value this rank was decided by, which depends on the
+    /// configured `RankingStrategy`:
+    /// - the number of wins for `Wins`
+    /// - the average map rank for `AverageMapRank` (less is better)
+    /// - the sum of points for `PointsPerRank` and `Medals`
+    pub score: f64,
+}
+
+/// Returns the points for a player's map rank, or `None` if the
+/// strategy does not award points.
+fn map_rank_points(strategy: &RankingStrategy, map_rank: &MapRank) -> Option<u32> {
+    match strategy {
+        RankingStrategy::PointsPerRank { points } => {
+            let idx = map_rank.pos as usize - 1;
+            Some(points.get(idx).copied().unwrap_or(0))
+        }
+        RankingStrategy::Medals {
+            author_points,
+            gold_points,
+            silver_points,
+            bronze_points,
+        } => {
+            // Medals with unknown times cannot be achieved.
+            let millis = map_rank.millis;
+            let achieved = |medal_millis: Option<i32>| {
+                medal_millis
+                    .map(|medal_millis| millis <= medal_millis)
+                    .unwrap_or(false)
+            };
+            let points = if millis <= map_rank.author_millis {
+                *author_points
+            } else if achieved(map_rank.gold_millis) {
+                *gold_points
+            } else if achieved(map_rank.silver_millis) {
+                *silver_points
+            } else if achieved(map_rank.bronze_millis) {
+                *bronze_points
+            } else {
+                0
+            };
+            Some(points)
+        }
+        _ => None,
+    }
 }
 
 /// Ranks all players that have set at least one record on this server.
@@ -69,18 +153,26 @@ pub struct ServerRank {
 /// - maps a player's login to their server rank,
 /// - and is iterated from rank 1 to the last in order.
 ///
-/// Players will earn a "win" on each map in the playlist, for every player
-/// that has a worse personal best (or none at all). Maps that are not
-/// in the playlist should not count, since new players cannot set records
-/// on them, making it hard for them to catch up to other players.
+/// Maps that are not in the playlist should not count, since new players
+/// cannot set records on them, making it hard for them to catch up to other players.
 ///
+/// Regardless of the strategy, players will earn a "win" on each map in the playlist,
+/// for every player that has a worse personal best (or none at all).
 /// For example, if a player has the 50th rank on a map, and the
 /// server has had 200 players (with at least one record on any map) in total,
 /// they get `199 max wins - 49 losses = 150 wins` for that map. How many of
 /// those 200 players have actually set a record on that map is irrelevant.
+///
+/// Ties in the strategy's score are broken by the number of wins.
+///
+/// If a season is specified, only the records set within that season are considered.
+/// On multi-lap maps, the ranked records are either full-race or flying lap records.
 async fn calc_server_ranking(
     db: &DatabaseClient,
+    season_id: Option<i32>,
     map_uids: Vec<&str>,
+    strategy: &RankingStrategy,
+    ranked_records: RankedRecords,
 ) -> IndexMap<Cow<'static, str>, ServerRank> {
     // This is a lazy way of calculating the server ranking,
     // which will look at the entire data set of records every time.
@@ -90,50 +182,113 @@ async fn calc_server_ranking(
     // are the only metric used).
 
     // Every player with at least one record will be ranked.
-    let nb_ranked_players =
-        db.nb_players_with_record()
-            .await
-            .expect("failed to load amount of players with at least one record") as usize;
+    let nb_ranked_players = match season_id {
+        Some(season_id) => db.nb_players_with_season_record(season_id).await,
+        None => db.nb_players_with_record().await,
+    }
+    .expect("failed to load amount of players with at least one record")
+        as usize;
+
+    let nb_maps = map_uids.len();
 
     // You can beat (nb_ranked_players - 1) players on every map.
     let max_total_wins = {
         let max_wins_per_map: usize = max(1, nb_ranked_players) - 1;
-        map_uids.len() * max_wins_per_map
+        nb_maps * max_wins_per_map
     };
 
     // Using the map rankings, we can count the number of wins for each player.
     // Note that we cannot count the losses, since you also gain losses by not having
     // a map rank at all.
-    let map_ranks = db
-        .map_rankings(map_uids)
-        .await
-        .expect("failed to load map rankings");
+    let flying_laps = ranked_records == RankedRecords::FlyingLap;
+    let map_ranks = match season_id {
+        Some(season_id) => {
+            db.season_map_rankings(season_id, map_uids, flying_laps)
+                .await
+        }
+        None => db.map_rankings(map_uids, flying_laps).await,
+    }
+    .expect("failed to load map rankings");
 
     let mut nb_wins = IndexMap::<&str, usize>::new(); // player login -> nb of wins
+    let mut sum_pos = HashMap::<&str, usize>::new(); // player login -> sum of map ranks
+    let mut nb_map_ranks = HashMap::<&str, usize>::new(); // player login -> nb of map ranks
+    let mut sum_max_pos = HashMap::<&str, usize>::new(); // player login -> sum of max ranks
+    let mut points = HashMap::<&str, u32>::new(); // player login -> sum of points
+    let mut max_pos = HashMap::<&str, usize>::new(); // map uid -> max map rank
     let mut display_names = HashMap::<&str, DisplayString>::new(); // player login -> display name
 
     for map_rank in map_ranks.iter() {
+        let login = map_rank.player_login.as_str();
+
         let nb_map_wins = nb_ranked_players - map_rank.pos as usize;
-        *nb_wins.entry(&map_rank.player_login).or_insert(0) += nb_map_wins;
+        *nb_wins.entry(login).or_insert(0) += nb_map_wins;
+        *sum_pos.entry(login).or_insert(0) += map_rank.pos as usize;
+        *nb_map_ranks.entry(login).or_insert(0) += 1;
+        *sum_max_pos.entry(login).or_insert(0) += map_rank.max_pos as usize;
+        max_pos.insert(&map_rank.map_uid, map_rank.max_pos as usize);
+
+        if let Some(map_points) = map_rank_points(strategy, map_rank) {
+            *points.entry(login).or_insert(0) += map_points;
+        }
 
-        if !display_names.contains_key(map_rank.player_login.as_str()) {
-            display_names.insert(&map_rank.player_login, map_rank.player_display_name.clone());
+        if !display_names.contains_key(login) {
+            display_names.insert(login, map_rank.player_display_name.clone());
         }
     }
 
-    // More wins is better: put them first.
-    nb_wins.sort_by(|_, a_wins, _, b_wins| b_wins.cmp(a_wins));
+    // Every map rank a player is missing counts as the worst rank on that map plus a penalty.
+    let total_max_pos: usize = max_pos.values().sum();
+    let sum_missing_pos = |login: &str, penalty: u32| -> usize {
+        let nb_missing = nb_maps - nb_map_ranks[login];
+        let sum_max_pos_missing = total_max_pos - sum_max_pos[login];
+        sum_max_pos_missing + nb_missing * penalty as usize
+    };
+
+    let mut scores: IndexMap<&str, f64> = nb_wins
+        .iter()
+        .map(|(login, nb_wins)| {
+            let score = match strategy {
+                RankingStrategy::Wins => *nb_wins as f64,
+                RankingStrategy::AverageMapRank {
+                    missing_rank_penalty,
+                } => {
+                    let total_pos = sum_pos[login] + sum_missing_pos(login, *missing_rank_penalty);
+                    total_pos as f64 / nb_maps as f64
+                }
+                RankingStrategy::PointsPerRank { .. } | RankingStrategy::Medals { .. } => {
+                    points.get(login).copied().unwrap_or(0) as f64
+                }
+            };
+            (*login, score)
+        })
+        .collect();
+
+    // Sort by score first, then by number of wins.
+    let less_is_better = matches!(strategy, RankingStrategy::AverageMapRank { .. });
+    scores.sort_by(|a_login, a_score, b_login, b_score| {
+        let by_score = if less_is_better {
+            a_score.partial_cmp(b_score)
+        } else {
+            b_score.partial_cmp(a_score)
+        };
+        by_score
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| nb_wins[b_login].cmp(&nb_wins[a_login]))
+    });
 
-    nb_wins
+    scores
         .into_iter()
         .enumerate()
-        .map(|(idx, (login, nb_wins))| {
+        .map(|(idx, (login, score))| {
+            let nb_wins = nb_wins[login];
             let rank = ServerRank {
                 pos: idx + 1,
                 player_login: login.to_string(),
                 player_display_name: display_names.remove(login).unwrap(),
                 nb_wins,
                 nb_losses: max_total_wins - nb_wins,
+                score,
             };
             (login.to_string().into(), rank)
         })
@@ -145,6 +300,7 @@ pub struct ServerRankController {
     state: Arc<RwLock<ServerRankingState>>,
     server: Server,
     db: DatabaseClient,
+    live_config: Arc<dyn LiveConfig>,
     live_players: Arc<dyn LivePlayers>,
 }
 
@@ -152,25 +308,133 @@ impl ServerRankController {
     pub async fn init(
         server: &Server,
         db: &DatabaseClient,
+        live_config: &Arc<dyn LiveConfig>,
         live_players: &Arc<dyn LivePlayers>,
     ) -> Self {
         let playlist = server.playlist().await;
         let playlist_uids = playlist.iter().map(|m| m.uid.deref()).collect();
+        let (strategy, ranked_records) = {
+            let config = live_config.lock().await;
+            (config.server_ranking.clone(), config.ranked_records)
+        };
 
         let state = ServerRankingState {
-            all_ranks: calc_server_ranking(db, playlist_uids).await,
+            all_ranks: calc_server_ranking(db, None, playlist_uids, &strategy, ranked_records)
+                .await,
+            season: None,
         };
-        ServerRankController {
+        let controller = ServerRankController {
             state: Arc::new(RwLock::new(state)),
             server: server.clone(),
             db: db.clone(),
+            live_config: live_config.clone(),
             live_players: live_players.clone(),
+        };
+        controller.update_season().await;
+        controller
+    }
+
+    /// Archive the final standings of seasons that have ended, start a new season
+    /// if there is one, and re-calculate the current seasonal ranking.
+    pub async fn update_season(&self) {
+        let now = Utc::now().naive_utc();
+
+        let (strategy, ranked_records, period) = {
+            let config = self.live_config.lock().await;
+            (
+                config.server_ranking.clone(),
+                config.ranked_records,
+                config.seasons.period_at(&now),
+            )
+        };
+
+        let playlist = self.server.playlist().await;
+        let playlist_uids: Vec<&str> = playlist.iter().map(|m| m.uid.deref()).collect();
+
+        let ended_seasons = self
+            .db
+            .seasons_to_archive(&now)
+            .await
+            .expect("failed to load ended seasons");
+
+        for season in ended_seasons {
+            let ranking = calc_server_ranking(
+                &self.db,
+                Some(season.id),
+                playlist_uids.clone(),
+                &strategy,
+                ranked_records,
+            )
+            .await;
+            let max_pos = ranking.len() as i64;
+            let ranks: Vec<SeasonRank> = ranking
+                .into_iter()
+                .map(|(_, rank)| SeasonRank {
+                    season_id: season.id,
+                    player_login: rank.player_login,
+                    player_display_name: rank.player_display_name,
+                    pos: rank.pos as i64,
+                    max_pos,
+                    nb_wins: rank.nb_wins as i64,
+                    score: rank.score,
+                })
+                .collect();
+            self.db
+                .archive_season(season.id, &ranks)
+                .await
+                .expect("failed to archive season");
+            log::info!("archived season '{}'", &season.name);
         }
+
+        let new_season_ranking = match period {
+            Some(period) => {
+                let season = self
+                    .db
+                    .upsert_season(&period.name, &period.start_time(), &period.end_time())
+                    .await
+                    .expect("failed to store season");
+                let ranks = calc_server_ranking(
+                    &self.db,
+                    Some(season.id),
+                    playlist_uids,
+                    &strategy,
+                    ranked_records,
+                )
+                .await;
+                Some(SeasonRankingState { season, ranks })
+            }
+            None => None,
+        };
+
+        let mut server_ranking_state = self.state.write().await;
+        server_ranking_state.season = new_season_ranking;
+    }
+
+    /// Remember a finished run or flying lap for the seasonal ranking,
+    /// if there is an active season.
+    pub async fn add_season_run(&self, evidence: &RecordEvidence) {
+        let server_ranking_state = self.state.read().await;
+        let season = match &server_ranking_state.season {
+            Some(season_ranking) if evidence.timestamp < season_ranking.season.end => {
+                &season_ranking.season
+            }
+            _ => return,
+        };
+
+        self.db
+            .upsert_season_record(season.id, &evidence)
+            .await
+            .expect("failed to update player's season record");
     }
 
     /// Update the server ranking, and return information of changed
     /// ranks for connected players.
+    ///
+    /// The seasonal ranking is updated as well, but no diff is
+    /// produced for it.
     pub async fn update(&self) -> ServerRankingDiff {
+        self.update_season().await;
+
         let mut server_ranking_state = self.state.write().await;
         let players_state = self.live_players.lock().await;
 
@@ -182,7 +446,12 @@ impl ServerRankController {
         // Calculate new ranking from scratch
         let playlist = self.server.playlist().await;
         let playlist_uids = playlist.iter().map(|m| m.uid.deref()).collect();
-        let new_ranking = calc_server_ranking(&self.db, playlist_uids).await;
+        let (strategy, ranked_records) = {
+            let config = self.live_config.lock().await;
+            (config.server_ranking.clone(), config.ranked_records)
+        };
+        let new_ranking =
+            calc_server_ranking(&self.db, None, playlist_uids, &strategy, ranked_records).await;
 
         // List for newly ranked players
         let first_ranks: Vec<(i32, &ServerRank)> = players_state
@@ -249,14 +518,28 @@ mod test {
     #[tokio::test]
     async fn empty_server_ranking() {
         let mut mock_db: DatabaseClient = Default::default();
-        let ranking = calc_server_ranking(&mock_db, vec![]).await;
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec![],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
         assert!(ranking.is_empty());
 
         mock_db.push_player("login1", "nick1");
         mock_db.push_map("uid1");
         mock_db.push_record("login1", "uid1", 10000);
 
-        let ranking = calc_server_ranking(&mock_db, vec![]).await;
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec![],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
         assert!(ranking.is_empty());
     }
 
@@ -267,7 +550,14 @@ mod test {
         mock_db.push_map("uid1");
         mock_db.push_record("login1", "uid1", 10000);
 
-        let ranking = calc_server_ranking(&mock_db, vec!["uid1"]).await;
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1"],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
         assert_eq!(1, ranking.len());
 
         let actual = ranking.values().next().unwrap();
@@ -277,6 +567,7 @@ mod test {
             player_display_name: DisplayString::from("nick1".to_string()),
             nb_wins: 0,
             nb_losses: 0,
+            score: 0.0,
         };
         assert_eq!(actual, &expected);
     }
@@ -292,7 +583,14 @@ mod test {
         mock_db.push_record("login2", "uid1", 20000);
         mock_db.push_record("login3", "uid1", 30000);
 
-        let ranking = calc_server_ranking(&mock_db, vec!["uid1"]).await;
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1"],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
 
         let actual = ranking.values().next().unwrap();
         let expected = ServerRank {
@@ -301,6 +599,7 @@ mod test {
             player_display_name: DisplayString::from("nick1".to_string()),
             nb_wins: 2,
             nb_losses: 0,
+            score: 2.0,
         };
         assert_eq!(actual, &expected);
 
@@ -311,6 +610,7 @@ mod test {
             player_display_name: DisplayString::from("nick2".to_string()),
             nb_wins: 1,
             nb_losses: 1,
+            score: 1.0,
         };
         assert_eq!(actual, &expected);
 
@@ -321,6 +621,7 @@ mod test {
             player_display_name: DisplayString::from("nick3".to_string()),
             nb_wins: 0,
             nb_losses: 2,
+            score: 0.0,
         };
         assert_eq!(actual, &expected);
     }
@@ -340,7 +641,14 @@ mod test {
         mock_db.push_record("login1", "uid3", 20000);
         mock_db.push_record("login2", "uid3", 10000);
 
-        let ranking = calc_server_ranking(&mock_db, vec!["uid1", "uid2", "uid3"]).await;
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1", "uid2", "uid3"],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
 
         let actual = ranking.values().next().unwrap();
         let expected = ServerRank {
@@ -349,6 +657,7 @@ mod test {
             player_display_name: DisplayString::from("nick1".to_string()),
             nb_wins: 2,
             nb_losses: 1,
+            score: 2.0,
         };
         assert_eq!(actual, &expected);
 
@@ -359,6 +668,7 @@ mod test {
             player_display_name: DisplayString::from("nick2".to_string()),
             nb_wins: 1,
             nb_losses: 2,
+            score: 1.0,
         };
         assert_eq!(actual, &expected);
     }
@@ -375,7 +685,14 @@ mod test {
         mock_db.push_record("login1", "uid2", 20000);
         mock_db.push_record("login2", "uid2", 10000);
 
-        let ranking = calc_server_ranking(&mock_db, vec!["uid1"]).await;
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1"],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
 
         let actual = ranking.values().next().unwrap();
         let expected = ServerRank {
@@ -384,6 +701,7 @@ mod test {
             player_display_name: DisplayString::from("nick1".to_string()),
             nb_wins: 1,
             nb_losses: 0,
+            score: 1.0,
         };
         assert_eq!(actual, &expected);
 
@@ -394,7 +712,138 @@ mod test {
             player_display_name: DisplayString::from("nick2".to_string()),
             nb_wins: 0,
             nb_losses: 1,
+            score: 0.0,
         };
         assert_eq!(actual, &expected);
     }
+
+    #[tokio::test]
+    async fn average_map_rank_penalizes_missing_records() {
+        let mut mock_db: DatabaseClient = Default::default();
+        mock_db.push_player("login1", "nick1");
+        mock_db.push_player("login2", "nick2");
+        mock_db.push_map("uid1");
+        mock_db.push_map("uid2");
+        mock_db.push_record("login1", "uid1", 10000);
+        mock_db.push_record("login2", "uid1", 20000);
+        mock_db.push_record("login2", "uid2", 10000);
+
+        let strategy = RankingStrategy::AverageMapRank {
+            missing_rank_penalty: 5,
+        };
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1", "uid2"],
+            &strategy,
+            RankedRecords::Race,
+        )
+        .await;
+
+        let actual = ranking.values().next().unwrap();
+        assert_eq!("login2", actual.player_login);
+        assert_eq!(1.5, actual.score);
+
+        let actual = ranking.values().nth(1).unwrap();
+        assert_eq!("login1", actual.player_login);
+        assert_eq!(3.5, actual.score);
+    }
+
+    #[tokio::test]
+    async fn points_per_rank_server_ranking() {
+        let mut mock_db: DatabaseClient = Default::default();
+        mock_db.push_player("login1", "nick1");
+        mock_db.push_player("login2", "nick2");
+        mock_db.push_player("login3", "nick3");
+        mock_db.push_map("uid1");
+        mock_db.push_map("uid2");
+        mock_db.push_record("login1", "uid1", 10000);
+        mock_db.push_record("login2", "uid1", 20000);
+        mock_db.push_record("login3", "uid1", 30000);
+        mock_db.push_record("login3", "uid2", 10000);
+        mock_db.push_record("login1", "uid2", 20000);
+
+        let strategy = RankingStrategy::PointsPerRank {
+            points: vec![10, 5],
+        };
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1", "uid2"],
+            &strategy,
+            RankedRecords::Race,
+        )
+        .await;
+
+        let scores: Vec<(&str, f64)> = ranking
+            .values()
+            .map(|r| (r.player_login.as_str(), r.score))
+            .collect();
+        assert_eq!(
+            vec![("login1", 15.0), ("login3", 10.0), ("login2", 5.0)],
+            scores
+        );
+    }
+
+    #[tokio::test]
+    async fn medals_server_ranking() {
+        let mut mock_db: DatabaseClient = Default::default();
+        mock_db.push_player("login1", "nick1");
+        mock_db.push_player("login2", "nick2");
+        mock_db.push_player("login3", "nick3");
+        mock_db.push_map_with_medal_times("uid1", 10000, 10400, 12000, 15000);
+        mock_db.push_record("login1", "uid1", 9000); // author
+        mock_db.push_record("login2", "uid1", 10500); // silver
+        mock_db.push_record("login3", "uid1", 14000); // bronze
+
+        let strategy = RankingStrategy::Medals {
+            author_points: 4,
+            gold_points: 3,
+            silver_points: 2,
+            bronze_points: 1,
+        };
+        let ranking =
+            calc_server_ranking(&mock_db, None, vec!["uid1"], &strategy, RankedRecords::Race).await;
+
+        let scores: Vec<(&str, f64)> = ranking
+            .values()
+            .map(|r| (r.player_login.as_str(), r.score))
+            .collect();
+        assert_eq!(
+            vec![("login1", 4.0), ("login2", 2.0), ("login3", 1.0)],
+            scores
+        );
+    }
+
+    #[tokio::test]
+    async fn multi_lap_server_ranking() {
+        let mut mock_db: DatabaseClient = Default::default();
+        mock_db.push_player("login1", "nick1");
+        mock_db.push_player("login2", "nick2");
+        mock_db.push_multi_lap_map("uid1", 3);
+        mock_db.push_lap_record("login1", "uid1", 30000, 3);
+        mock_db.push_lap_record("login2", "uid1", 31000, 3);
+        mock_db.push_lap_record("login1", "uid1", 9500, 0);
+        mock_db.push_lap_record("login2", "uid1", 9000, 0);
+
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1"],
+            &RankingStrategy::Wins,
+            RankedRecords::Race,
+        )
+        .await;
+        assert_eq!("login1", ranking.values().next().unwrap().player_login);
+
+        let ranking = calc_server_ranking(
+            &mock_db,
+            None,
+            vec!["uid1"],
+            &RankingStrategy::Wins,
+            RankedRecords::FlyingLap,
+        )
+        .await;
+        assert_eq!("login2", ranking.values().next().unwrap().player_login);
+    }
 }
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Utc};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::chat::{RaceVoteError, RaceVoteKind};
+use crate::config::RaceVoteConfig;
+use crate::controller::{LiveConfig, LivePlayers};
+
+/// Use to lookup the vote that players can currently participate in.
+#[async_trait]
+pub trait LiveVotes: Send + Sync {
+    /// While holding this guard, the state is read-only, and can be referenced.
+    async fn lock(&self) -> RwLockReadGuard<'_, VoteState>;
+}
+
+#[derive(Default)]
+pub struct VoteState {
+    /// The vote that is currently in progress, if any.
+    pub active: Option<RaceVote>,
+
+    /// The number of votes of each kind that were started on the current map.
+    nb_started: HashMap<RaceVoteKind, u32>,
+
+    /// The moment the last vote ended.
+    last_ended: Option<NaiveDateTime>,
+
+    /// The ID of the next vote that is started.
+    next_id: usize,
+
+    /// `True` if votes can be started, which is the case during the race,
+    /// but not during the outro.
+    in_race: bool,
+}
+
+/// A vote that players can participate in during a race.
+#[derive(Clone, Debug)]
+pub struct RaceVote {
+    /// Identifies this vote, to distinguish it from later votes.
+    pub id: usize,
+
+    pub kind: RaceVoteKind,
+
+    /// The UID of the player that started this vote.
+    pub started_by: i32,
+
+    pub started_at: NaiveDateTime,
+
+    /// The UIDs of players that voted in favour.
+    pub yes: HashSet<i32>,
+
+    /// The UIDs of players that voted against.
+    pub no: HashSet<i32>,
+}
+
+impl RaceVote {
+    /// The number of votes in favour that are required for this
+    /// vote to pass, given the number of non-spectators.
+    pub fn nb_required(&self, nb_playing: usize, config: &RaceVoteConfig) -> usize {
+        let ratio = match self.kind {
+            RaceVoteKind::Skip => config.skip_ratio,
+            RaceVoteKind::Extend => config.extend_ratio,
+        };
+        ((nb_playing as f32 * ratio).ceil() as usize).max(1)
+    }
+
+    /// The number of votes in favour, only counting players that are still playing.
+    pub fn nb_yes(&self, playing: &HashSet<i32>) -> usize {
+        self.yes.intersection(playing).count()
+    }
+
+    /// The number of votes against, only counting players that are still playing.
+    pub fn nb_no(&self, playing: &HashSet<i32>) -> usize {
+        self.no.intersection(playing).count()
+    }
+
+    /// The remaining time until this vote ends.
+    pub fn time_left(&self, config: &RaceVoteConfig) -> Duration {
+        let end = self.started_at + Duration::seconds(config.vote_duration_secs as i64);
+        (end - Utc::now().naive_utc()).max(Duration::zero())
+    }
+}
+
+/// The result of a vote, once it has ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaceVoteOutcome {
+    pub kind: RaceVoteKind,
+    pub passed: bool,
+}
+
+#[derive(Clone)]
+pub struct VoteController {
+    state: Arc<RwLock<VoteState>>,
+    live_config: Arc<dyn LiveConfig>,
+    live_players: Arc<dyn LivePlayers>,
+}
+
+impl VoteController {
+    pub fn init(live_config: &Arc<dyn LiveConfig>, live_players: &Arc<dyn LivePlayers>) -> Self {
+        let mut state = VoteState::default();
+        state.in_race = true;
+
+        VoteController {
+            state: Arc::new(RwLock::new(state)),
+            live_config: live_config.clone(),
+            live_players: live_players.clone(),
+        }
+    }
+
+    /// Start a new vote of the given kind, and return its ID.
+    pub async fn start(&self, kind: RaceVoteKind, player_uid: i32) -> Result<usize, RaceVoteError> {
+        let config = self.live_config.lock().await.timeattack.votes;
+        let is_playing = self.live_players.uid_playing().await.contains(&player_uid);
+
+        let mut vote_state = self.state.write().await;
+        let now = Utc::now().naive_utc();
+
+        check_start(&vote_state, kind, is_playing, &config, now)?;
+
+        let id = vote_state.next_id;
+        vote_state.next_id += 1;
+        *vote_state.nb_started.entry(kind).or_insert(0) += 1;
+        vote_state.active = Some(RaceVote {
+            id,
+            kind,
+            started_by: player_uid,
+            started_at: now,
+            yes: HashSet::new(),
+            no: HashSet::new(),
+        });
+
+        Ok(id)
+    }
+
+    /// Update a player's vote in the ongoing vote. Returns the outcome
+    /// if this vote caused it to pass early.
+    pub async fn cast(&self, player_uid: i32, vote: bool) -> Option<RaceVoteOutcome> {
+        let config = self.live_config.lock().await.timeattack.votes;
+        let playing = self.live_players.uid_playing().await;
+        if !playing.contains(&player_uid) {
+            return None;
+        }
+
+        let mut vote_state = self.state.write().await;
+        let active = vote_state.active.as_mut()?;
+        if vote {
+            active.no.remove(&player_uid);
+            active.yes.insert(player_uid);
+        } else {
+            active.yes.remove(&player_uid);
+            active.no.insert(player_uid);
+        }
+
+        // A vote can pass before it times out,
+        // once enough players have voted in favour.
+        if active.nb_yes(&playing) >= active.nb_required(playing.len(), &config) {
+            let outcome = RaceVoteOutcome {
+                kind: active.kind,
+                passed: true,
+            };
+            vote_state.active = None;
+            vote_state.last_ended = Some(Utc::now().naive_utc());
+            return Some(outcome);
+        }
+        None
+    }
+
+    /// End the vote with the given ID, and return its outcome, or `None`
+    /// if that vote already ended.
+    pub async fn end(&self, vote_id: usize) -> Option<RaceVoteOutcome> {
+        let config = self.live_config.lock().await.timeattack.votes;
+        let playing = self.live_players.uid_playing().await;
+
+        let mut vote_state = self.state.write().await;
+        match &vote_state.active {
+            Some(vote) if vote.id == vote_id => {}
+            _ => return None,
+        }
+        let vote = vote_state.active.take()?;
+        vote_state.last_ended = Some(Utc::now().naive_utc());
+
+        Some(RaceVoteOutcome {
+            kind: vote.kind,
+            passed: vote.nb_yes(&playing) >= vote.nb_required(playing.len(), &config),
+        })
+    }
+
+    /// Cancel any ongoing vote, and disallow new votes until the next map.
+    /// Returns `true` if there was an ongoing vote.
+    pub async fn cancel(&self) -> bool {
+        let mut vote_state = self.state.write().await;
+        vote_state.in_race = false;
+        vote_state.active.take().is_some()
+    }
+
+    /// Allow new votes, and reset vote limits for the next map.
+    pub async fn reset(&self) {
+        let mut vote_state = self.state.write().await;
+        vote_state.active = None;
+        vote_state.nb_started.clear();
+        vote_state.in_race = true;
+    }
+}
+
+/// Check whether a vote of the given kind can be started.
+fn check_start(
+    vote_state: &VoteState,
+    kind: RaceVoteKind,
+    is_playing: bool,
+    config: &RaceVoteConfig,
+    now: NaiveDateTime,
+) -> Result<(), RaceVoteError> {
+    if !vote_state.in_race {
+        return Err(RaceVoteError::NotDuringRace);
+    }
+    if !is_playing {
+        return Err(RaceVoteError::NotPlaying);
+    }
+    if vote_state.active.is_some() {
+        return Err(RaceVoteError::VoteInProgress);
+    }
+    if let Some(last_ended) = vote_state.last_ended {
+        let cooldown_end = last_ended + Duration::seconds(config.cooldown_secs as i64);
+        if cooldown_end > now {
+            return Err(RaceVoteError::OnCooldown {
+                secs_left: (cooldown_end - now).num_seconds().max(1),
+            });
+        }
+    }
+
+    let max_votes = match kind {
+        RaceVoteKind::Skip => config.max_skip_votes_per_map,
+        RaceVoteKind::Extend => config.max_extend_votes_per_map,
+    };
+    let nb_started = vote_state.nb_started.get(&kind).copied().unwrap_or(0);
+    if nb_started >= max_votes {
+        return Err(RaceVoteError::LimitReached);
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl LiveVotes for VoteController {
+    async fn lock(&self) -> RwLockReadGuard<'_, VoteState> {
+        self.state.read().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vote(kind: RaceVoteKind, yes: &[i32]) -> RaceVote {
+        RaceVote {
+            id: 0,
+            kind,
+            started_by: yes[0],
+            started_at: Utc::now().naive_utc(),
+            yes: yes.iter().copied().collect(),
+            no: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn tally_ignores_spectators() {
+        let playing: HashSet<i32> = vec![1, 2, 3, 4].into_iter().collect();
+        let mut vote = vote(RaceVoteKind::Skip, &[1, 2, 5, 6]);
+        vote.no = vec![3, 7].into_iter().collect();
+        assert_eq!(2, vote.nb_yes(&playing));
+        assert_eq!(1, vote.nb_no(&playing));
+        assert_eq!(
+            2,
+            vote.nb_required(playing.len(), &RaceVoteConfig::default())
+        );
+    }
+
+    #[test]
+    fn enforces_vote_limits() {
+        let config = RaceVoteConfig::default();
+        let now = Utc::now().naive_utc();
+
+        let mut state = VoteState::default();
+        state.in_race = true;
+        assert!(check_start(&state, RaceVoteKind::Skip, true, &config, now).is_ok());
+        assert!(check_start(&state, RaceVoteKind::Skip, false, &config, now).is_err());
+
+        state.last_ended = Some(now);
+        assert!(check_start(&state, RaceVoteKind::Skip, true, &config, now).is_err());
+
+        let later = now + Duration::seconds(config.cooldown_secs as i64);
+        assert!(check_start(&state, RaceVoteKind::Skip, true, &config, later).is_ok());
+
+        state
+            .nb_started
+            .insert(RaceVoteKind::Skip, config.max_skip_votes_per_map);
+        assert!(check_start(&state, RaceVoteKind::Skip, true, &config, later).is_err());
+        assert!(check_start(&state, RaceVoteKind::Extend, true, &config, later).is_ok());
+    }
+}
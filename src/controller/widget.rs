@@ -1,21 +1,27 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 use std::sync::Arc;
 
 use askama::Template;
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use futures::future::join_all;
 use tokio::sync::RwLock;
 
 use crate::chat::CommandOutput;
+use crate::config::RankedRecords;
 use crate::constants::{
-    cdn_prefix, MAX_DISPLAYED_IN_QUEUE, MAX_DISPLAYED_RACE_RANKS, START_HIDE_WIDGET_DELAY_MILLIS,
+    cdn_prefix, CHECKPOINT_SPLIT_DISPLAY_MILLIS, DEFAULT_LANGUAGE, MAX_DISPLAYED_ARCHIVED_SEASONS,
+    MAX_DISPLAYED_IN_QUEUE, MAX_DISPLAYED_RACE_RANKS, MAX_DISPLAYED_SERVER_RANKS,
+    START_HIDE_WIDGET_DELAY_MILLIS,
 };
 use crate::controller::*;
 use crate::database::timeattack::{PreferenceValue, TimeAttackQueries};
-use crate::database::{DatabaseClient, PlayerQueries, RecordQueries};
+use crate::database::{
+    DatabaseClient, PlayerQueries, RecordQueries, Season, SeasonQueries, SeasonRank,
+};
 use crate::event::*;
-use crate::server::{Calls, Fault, PlayerInfo, Server};
+use crate::server::{Calls, CheckpointEvent, Fault, PlayerInfo, Server};
 use crate::widget::timeattack::*;
 use crate::widget::*;
 
@@ -35,6 +41,7 @@ pub struct WidgetController {
     live_server_ranking: Arc<dyn LiveServerRanking>,
     live_queue: Arc<dyn LiveQueue>,
     live_schedule: Arc<dyn LiveSchedule>,
+    live_votes: Arc<dyn LiveVotes>,
 }
 
 /// May be used to select the widgets that will be sent to a player
@@ -59,6 +66,7 @@ impl WidgetController {
         live_prefs: &Arc<dyn LivePreferences>,
         live_queue: &Arc<dyn LiveQueue>,
         live_schedule: &Arc<dyn LiveSchedule>,
+        live_votes: &Arc<dyn LiveVotes>,
     ) -> Self {
         let controller = WidgetController {
             state: Arc::new(RwLock::new(WidgetState::Race)),
@@ -73,6 +81,7 @@ impl WidgetController {
             live_prefs: live_prefs.clone(),
             live_queue: live_queue.clone(),
             live_schedule: live_schedule.clone(),
+            live_votes: live_votes.clone(),
         };
 
         for diff in live_players.lock().await.replay_diffs() {
@@ -140,6 +149,86 @@ impl WidgetController {
         self.show_outro_queue(queued_next).await;
     }
 
+    /// Display the ongoing race vote to all players, or remove the
+    /// vote widget if there is no such vote.
+    pub async fn refresh_race_vote(&self) {
+        let config = self.live_config.lock().await.timeattack.votes;
+        let playing = self.live_players.uid_playing().await;
+        let votes_state = self.live_votes.lock().await;
+
+        let vote = match &votes_state.active {
+            Some(vote) => vote,
+            None => {
+                self.hide_singleton::<RaceVoteWidget>().await;
+                return;
+            }
+        };
+
+        let nb_yes = vote.nb_yes(&playing);
+        let nb_no = vote.nb_no(&playing);
+        let nb_required = vote.nb_required(playing.len(), &config);
+        let secs_left = vote.time_left(&config).num_seconds();
+
+        self.show_localized(|lang| RaceVoteWidget {
+            kind: vote.kind,
+            nb_yes,
+            nb_no,
+            nb_required,
+            secs_left,
+            lang,
+        })
+        .await;
+    }
+
+    /// Compare a player's time at a checkpoint to their personal best,
+    /// and the map's top record.
+    pub async fn show_checkpoint_split(&self, event: &CheckpointEvent) {
+        let player_uid = match self.live_players.uid(&event.player_login).await {
+            Some(uid) => uid,
+            None => return,
+        };
+        let split = self.live_records.lock().await.checkpoint_split(
+            player_uid,
+            event.race_cp_index as usize,
+            event.race_time_millis,
+        );
+        let widget = CheckpointSplitWidget {
+            millis: split.millis,
+            pb_millis: split.pb_millis,
+            top_record_millis: split.top_record_millis,
+            display_millis: CHECKPOINT_SPLIT_DISPLAY_MILLIS,
+        };
+        self.show_singleton_for(&widget, player_uid).await;
+    }
+
+    /// Display the live ranking of the current race to all players and spectators.
+    pub async fn refresh_live_ranking(&self) {
+        if *self.state.read().await != WidgetState::Race {
+            return;
+        }
+        let live_ranking = self.live_race.live_ranking().await;
+        let entries = live_ranking
+            .iter()
+            .take(MAX_DISPLAYED_RACE_RANKS)
+            .map(|rank| {
+                let (millis, nb_cps) = match rank.progress {
+                    LiveProgress::Finished { millis } => (millis, 0),
+                    LiveProgress::OnTrack { cp_index, millis } => (millis, cp_index + 1),
+                    LiveProgress::NotStarted => (0, 0),
+                };
+                LiveRankingEntry {
+                    pos: rank.pos,
+                    login: &rank.login,
+                    display_name: &rank.display_name,
+                    millis,
+                    nb_cps,
+                    gap_millis: rank.gap_millis,
+                }
+            })
+            .collect();
+        self.show_singleton(&LiveRankingWidget { entries }).await;
+    }
+
     /// Display appropriate widgets for (new or transitioning) players
     /// and spectators.
     pub async fn refresh_for_player(&self, diff: &PlayerDiff) {
@@ -171,6 +260,16 @@ impl WidgetController {
         }
     }
 
+    /// Re-send the widgets of a player that selected another language.
+    pub async fn refresh_language_for(&self, player: &PlayerInfo) {
+        self.show_menu_for(player).await;
+
+        let is_race = *self.state.read().await == WidgetState::Race;
+        if is_race && self.live_players.uid_playing().await.contains(&player.uid) {
+            self.show_race_widgets_for(player).await;
+        }
+    }
+
     /// Update widgets after a player finishes a run:
     /// - If this is the first completed run by a player, update widgets
     ///   that show their personal best.
@@ -232,7 +331,8 @@ impl WidgetController {
     /// Display a popup message to the specified player.
     pub async fn show_popup(&self, resp: CommandOutput<'_>, for_login: &str) {
         if let Some(uid) = self.live_players.uid(for_login).await {
-            let widget = PopupWidget::from(resp);
+            let lang = self.live_players.language(for_login).await;
+            let widget = PopupWidget::new(resp, lang);
             self.show_singleton_for(&widget, uid).await;
         }
     }
@@ -247,7 +347,60 @@ impl WidgetController {
         self.server.send_manialink(&rendered).await;
     }
 
-    #[allow(dead_code)]
+    /// Display a widget to all players, in their languages.
+    async fn show_localized<T, F>(&self, make_widget: F)
+    where
+        T: SingletonWidget,
+        F: Fn(&'static str) -> T,
+    {
+        let players_state = self.live_players.lock().await;
+        let groups = players_state.logins_by_language();
+        if groups.len() <= 1 {
+            let lang = groups.keys().next().copied().unwrap_or(DEFAULT_LANGUAGE);
+            self.show_singleton(&make_widget(lang)).await;
+            return;
+        }
+
+        for (lang, logins) in groups {
+            self.show_singleton_to_logins(&make_widget(lang), logins)
+                .await;
+        }
+    }
+
+    /// Display a widget to the specified players, in their languages.
+    async fn show_localized_for<T, F>(&self, for_players: &[&PlayerInfo], make_widget: F)
+    where
+        T: SingletonWidget,
+        F: Fn(&'static str) -> T,
+    {
+        let players_state = self.live_players.lock().await;
+        let mut groups: HashMap<&'static str, Vec<&str>> = HashMap::new();
+        for player in for_players {
+            groups
+                .entry(players_state.language(&player.login))
+                .or_default()
+                .push(&player.login);
+        }
+
+        for (lang, logins) in groups {
+            self.show_singleton_to_logins(&make_widget(lang), logins)
+                .await;
+        }
+    }
+
+    async fn show_singleton_to_logins<T>(&self, widget: &T, logins: Vec<&str>)
+    where
+        T: SingletonWidget,
+    {
+        let rendered = render_template(&widget.manialink());
+
+        // Assume any fault is due to a target player disconnecting.
+        let _ = self
+            .server
+            .send_manialink_to_logins(&rendered, logins)
+            .await;
+    }
+
     async fn show_singleton<T>(&self, widget: &T)
     where
         T: SingletonWidget,
@@ -326,13 +479,18 @@ impl WidgetController {
 
     async fn hide_race_widgets_for(&self, for_uid: i32) {
         self.hide_singleton_for::<RunOutroWidget>(for_uid).await;
+        self.hide_singleton_for::<CheckpointSplitWidget>(for_uid)
+            .await;
         self.hide_singleton_for::<TimeAttackHudWidget>(for_uid)
             .await;
     }
 
     async fn hide_race_widgets(&self) {
         self.hide_singleton::<RunOutroWidget>().await;
+        self.hide_singleton::<CheckpointSplitWidget>().await;
+        self.hide_singleton::<LiveRankingWidget>().await;
         self.hide_singleton::<TimeAttackHudWidget>().await;
+        self.hide_singleton::<RaceVoteWidget>().await;
     }
 
     async fn show_outro_widgets(&self) {
@@ -345,7 +503,7 @@ impl WidgetController {
 
         for player in players_state.info_all() {
             let widget = OutroWidget {
-                map_ranking: self.curr_map_ranking(&*records_state, &player).await,
+                map_ranking: self.curr_map_ranking(&records_state.race, &player).await,
                 max_displayed_race_ranks: MAX_DISPLAYED_RACE_RANKS,
                 min_restart_vote_ratio,
                 init_preference: prefs.get(&player.uid).copied(),
@@ -371,6 +529,7 @@ impl WidgetController {
     }
 
     async fn show_menu_for(&self, player: &PlayerInfo) {
+        let lang = self.live_players.language(&player.login).await;
         let server_ranking_state = self.live_server_ranking.lock().await;
         let records_state = self.live_records.lock().await;
 
@@ -378,14 +537,35 @@ impl WidgetController {
             .curr_server_ranking(&*server_ranking_state, &player)
             .await;
 
-        let map_ranking = self.curr_map_ranking(&*records_state, &player).await;
+        let map_ranking = self.curr_map_ranking(&records_state.race, &player).await;
+        let flying_lap_ranking = match &records_state.flying_lap {
+            Some(flying_lap) => Some(self.curr_map_ranking(flying_lap, &player).await),
+            None => None,
+        };
+
+        let season_ranking = server_ranking_state
+            .season()
+            .map(|season_ranking| curr_season_ranking(season_ranking, &player));
+
+        let archived_season_ranks = self.archived_season_ranks(&player).await;
+        let archived_seasons = archived_season_ranks
+            .iter()
+            .map(|(season, ranks, personal_rank)| {
+                archived_season_ranking(season, ranks, personal_rank.as_ref(), &player)
+            })
+            .collect();
 
         let server_ranking_widget = ServerRankingWidget {
             ranking: server_ranking,
+            season_ranking,
+            archived_seasons,
+            lang,
         };
 
         let map_ranking_widget = MapRankingWidget {
             ranking: map_ranking,
+            flying_lap_ranking,
+            lang,
         };
 
         let menu = MenuWidget {};
@@ -424,10 +604,31 @@ impl WidgetController {
     }
 
     async fn show_schedules(&self, for_players: &[&PlayerInfo]) {
-        join_all(for_players.iter().map(|player| async move {
-            let schedule_widget = ScheduleWidget {};
-            self.show_singleton_for(&schedule_widget, player.uid).await;
-        }))
+        let queue_preview = self.live_queue.peek().await;
+        let pool_switch = self.live_schedule.next_pool_switch().await;
+
+        let mut entries = Vec::with_capacity(queue_preview.len());
+        for entry in queue_preview.iter() {
+            if let Some(duration) = self.live_schedule.time_until_played(&entry.map.uid).await {
+                entries.push(ScheduleEntry {
+                    map_name: &entry.map.name,
+                    map_author_display_name: &entry.map.author_display_name,
+                    minutes_until: duration.num_minutes(),
+                });
+            }
+        }
+
+        let now = Utc::now().naive_utc();
+        let pool_switch = pool_switch.as_ref().map(|switch| PoolSwitchEntry {
+            pool_name: &switch.pool_name,
+            minutes_until: switch.at.signed_duration_since(now).num_minutes(),
+        });
+
+        self.show_localized_for(for_players, |lang| ScheduleWidget {
+            entries: entries.clone(),
+            pool_switch: pool_switch.clone(),
+            lang,
+        })
         .await;
     }
 
@@ -477,7 +678,7 @@ impl WidgetController {
 
         let records_state = self.live_records.lock().await;
 
-        let need_update = records_state.playing_pbs().filter_map(|pb| {
+        let need_update = records_state.race.playing_pbs().filter_map(|pb| {
             if pb.map_rank as usize >= max_pos_changed {
                 players_state.info(&pb.player_login)
             } else {
@@ -536,8 +737,8 @@ impl WidgetController {
             let preview = MapPreview {
                 map_name: &next_map.name,
                 map_author_display_name: &next_map_author,
-                player_map_rank: records_state.pb(uid).map(|rec| rec.map_rank as usize),
-                max_map_rank: records_state.nb_records,
+                player_map_rank: records_state.race.pb(uid).map(|rec| rec.map_rank as usize),
+                max_map_rank: records_state.race.nb_records,
                 player_preference: preferences_state.pref(uid, &next_map.uid),
                 preference_counts: next_map_prefs.clone(),
                 last_played: preferences_state
@@ -581,7 +782,7 @@ impl WidgetController {
         let records_state = self.live_records.lock().await;
         let server_ranking_state = self.live_server_ranking.lock().await;
 
-        let maybe_pb = records_state.pb(player.uid);
+        let maybe_pb = records_state.race.pb(player.uid);
 
         let server_rank = players_state
             .login(player.uid)
@@ -591,13 +792,15 @@ impl WidgetController {
         let widget = TimeAttackHudWidget {
             pb_millis: maybe_pb.map(|rec| rec.millis as usize),
             top1_millis: records_state
+                .race
                 .top_record
                 .as_ref()
                 .map(|rec| rec.millis as usize),
             map_rank: maybe_pb.map(|rec| rec.map_rank as usize),
-            max_map_rank: Some(records_state.nb_records).filter(|n| *n > 0),
+            max_map_rank: Some(records_state.race.nb_records).filter(|n| *n > 0),
             server_rank,
             max_server_rank: Some(server_ranking_state.max_pos()).filter(|n| *n > 0),
+            lang: players_state.language(&player.login),
         };
 
         self.show_singleton_for(&widget, player.uid).await;
@@ -608,15 +811,7 @@ impl WidgetController {
         server_ranking: &'a ServerRankingState,
         for_player: &'a PlayerInfo,
     ) -> ServerRanking<'a> {
-        let to_entry = |r: &'a ServerRank| -> ServerRankingEntry {
-            ServerRankingEntry {
-                pos: r.pos,
-                display_name: &r.player_display_name,
-                nb_wins: r.nb_wins,
-                nb_losses: r.nb_losses,
-                is_own: r.player_login == for_player.login,
-            }
-        };
+        let to_entry = |r: &'a ServerRank| server_rank_entry(r, for_player);
 
         let entries = server_ranking.top_ranks().map(to_entry).collect();
 
@@ -629,12 +824,43 @@ impl WidgetController {
         }
     }
 
+    /// Load the final standings of the most recent seasons, including
+    /// the specified player's own rank.
+    async fn archived_season_ranks(
+        &self,
+        for_player: &PlayerInfo,
+    ) -> Vec<(Season, Vec<SeasonRank>, Option<SeasonRank>)> {
+        let seasons = self
+            .db
+            .archived_seasons(MAX_DISPLAYED_ARCHIVED_SEASONS as i64)
+            .await
+            .expect("failed to load archived seasons");
+
+        let mut season_ranks = Vec::with_capacity(seasons.len());
+        for season in seasons {
+            let top_ranks = self
+                .db
+                .season_ranks(season.id, vec![], Some(MAX_DISPLAYED_SERVER_RANKS as i64))
+                .await
+                .expect("failed to load season ranks");
+            let personal_rank = self
+                .db
+                .season_ranks(season.id, vec![&for_player.login], None)
+                .await
+                .expect("failed to load player's season rank")
+                .into_iter()
+                .next();
+            season_ranks.push((season, top_ranks, personal_rank));
+        }
+        season_ranks
+    }
+
     async fn curr_map_ranking<'a>(
         &self,
-        records_state: &'a RecordsState,
+        map_records: &'a MapRecords,
         for_player: &'a PlayerInfo,
     ) -> MapRanking<'a> {
-        let map_ranks = records_state
+        let map_ranks = map_records
             .top_records
             .iter()
             .enumerate()
@@ -647,7 +873,7 @@ impl WidgetController {
             })
             .collect();
 
-        let personal_entry = records_state.pb(for_player.uid).map(|rec| MapRankingEntry {
+        let personal_entry = map_records.pb(for_player.uid).map(|rec| MapRankingEntry {
             pos: rec.map_rank as usize,
             display_name: &rec.player_display_name,
             millis: rec.millis as usize,
@@ -658,7 +884,7 @@ impl WidgetController {
         MapRanking {
             entries: map_ranks,
             personal_entry,
-            max_pos: records_state.nb_records,
+            max_pos: map_records.nb_records,
         }
     }
 
@@ -669,22 +895,38 @@ impl WidgetController {
         pref_state: &'a PreferencesState,
         for_players: &[&'a PlayerInfo],
     ) -> Vec<PlaylistWidget<'a>> {
-        let map_uids = playlist_state
-            .maps
-            .iter()
-            .map(|map| map.uid.deref())
-            .collect();
-        let player_logins = for_players.iter().map(|p| p.login.deref()).collect();
-        let nb_laps = 0; // this is only for TimeAttack
+        let flying_laps = self.live_config.lock().await.ranked_records == RankedRecords::FlyingLap;
+
+        // Records are stored with their number of laps, which is zero for flying laps,
+        // and on maps that are not multi-lap.
+        let mut map_uids_by_laps = HashMap::<i32, Vec<&str>>::new();
+        for map in playlist_state.maps.iter() {
+            let nb_laps = if flying_laps {
+                0
+            } else {
+                map.nb_laps.unwrap_or(0)
+            };
+            map_uids_by_laps
+                .entry(nb_laps)
+                .or_default()
+                .push(map.uid.deref());
+        }
+
+        let player_logins: Vec<&str> = for_players.iter().map(|p| p.login.deref()).collect();
         let limit_per_map = None;
 
-        let records = self
-            .db
-            .records(map_uids, player_logins, nb_laps, limit_per_map)
-            .await
-            .expect("failed to load records");
+        let mut records = Vec::new();
+        for (nb_laps, map_uids) in map_uids_by_laps {
+            let map_records = self
+                .db
+                .records(map_uids, player_logins.clone(), nb_laps, limit_per_map)
+                .await
+                .expect("failed to load records");
+            records.extend(map_records);
+        }
 
         let curr_map_uid = playlist_state.current_map().map(|m| &m.uid);
+        let players_state = self.live_players.lock().await;
 
         for_players
             .iter()
@@ -733,6 +975,7 @@ impl WidgetController {
                 PlaylistWidget {
                     cdn: cdn_prefix(),
                     entries,
+                    lang: players_state.language(&player.login),
                 }
             })
             .collect()
@@ -805,7 +1048,10 @@ handle!(
     "Steward:TimeAttack:Playlist",
     timeattack::PlaylistWidget<'_>
 );
-handle!("Steward:TimeAttack:Schedule", timeattack::ScheduleWidget);
+handle!(
+    "Steward:TimeAttack:Schedule",
+    timeattack::ScheduleWidget<'_>
+);
 handle!(
     "Steward:TimeAttack:ServerRanking",
     timeattack::ServerRankingWidget<'_>
@@ -821,3 +1067,62 @@ handle!(
 );
 handle!("Steward:TimeAttack:Hud", timeattack::TimeAttackHudWidget);
 handle!("Steward:TimeAttack:RunOutro", timeattack::RunOutroWidget);
+handle!("Steward:TimeAttack:RaceVote", timeattack::RaceVoteWidget);
+handle!(
+    "Steward:TimeAttack:CheckpointSplit",
+    timeattack::CheckpointSplitWidget
+);
+handle!(
+    "Steward:TimeAttack:LiveRanking",
+    timeattack::LiveRankingWidget<'_>
+);
+
+fn server_rank_entry<'a>(rank: &'a ServerRank, for_player: &PlayerInfo) -> ServerRankingEntry<'a> {
+    ServerRankingEntry {
+        pos: rank.pos,
+        display_name: &rank.player_display_name,
+        nb_wins: rank.nb_wins,
+        nb_losses: rank.nb_losses,
+        score: rank.score,
+        is_own: rank.player_login == for_player.login,
+    }
+}
+
+fn curr_season_ranking<'a>(
+    season_ranking: &'a SeasonRankingState,
+    for_player: &'a PlayerInfo,
+) -> SeasonRanking<'a> {
+    let to_entry = |r: &'a ServerRank| server_rank_entry(r, for_player);
+    SeasonRanking {
+        name: &season_ranking.season.name,
+        ranking: ServerRanking {
+            entries: season_ranking.top_ranks().map(to_entry).collect(),
+            personal_entry: season_ranking.rank_of(&for_player.login).map(to_entry),
+            max_pos: season_ranking.max_pos(),
+        },
+    }
+}
+
+fn archived_season_ranking<'a>(
+    season: &'a Season,
+    ranks: &'a [SeasonRank],
+    personal_rank: Option<&'a SeasonRank>,
+    for_player: &PlayerInfo,
+) -> SeasonRanking<'a> {
+    let to_entry = |r: &'a SeasonRank| ServerRankingEntry {
+        pos: r.pos as usize,
+        display_name: &r.player_display_name,
+        nb_wins: r.nb_wins as usize,
+        nb_losses: 0,
+        score: r.score,
+        is_own: r.player_login == for_player.login,
+    };
+    SeasonRanking {
+        name: &season.name,
+        ranking: ServerRanking {
+            entries: ranks.iter().map(to_entry).collect(),
+            personal_entry: personal_rank.map(to_entry),
+            max_pos: ranks.first().map(|r| r.max_pos as usize).unwrap_or(0),
+        },
+    }
+}
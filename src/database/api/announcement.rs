@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use chrono::NaiveTime;
+
+use crate::database::Result;
+
+/// A message that is displayed in the chat whenever its trigger occurs.
+///
+/// Announcements that share a trigger take turns, so that f.e.
+/// several tips can be displayed one after another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Announcement {
+    pub id: i32,
+
+    /// The chat message, which can contain formatting.
+    pub message: String,
+
+    pub trigger: AnnouncementTrigger,
+
+    /// The time of day in which this announcement is displayed,
+    /// or `None` if it is displayed at any time.
+    pub window: Option<TimeWindow>,
+}
+
+/// The events that display an announcement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnnouncementTrigger {
+    /// Tell players when they join the server.
+    Join,
+
+    /// Announce when a map starts.
+    MapStart,
+
+    /// Announce at the start of every n-th map.
+    EveryNbMaps { nb_maps: i32 },
+
+    /// Announce every time this number of seconds has passed.
+    Interval { secs: i32 },
+}
+
+/// A daily time window in UTC. If it ends before it starts,
+/// it extends past midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// `True` if the given time of day is within this window.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+#[async_trait]
+pub trait AnnouncementQueries {
+    /// List all announcements, in the order they were added.
+    async fn announcements(&self) -> Result<Vec<Announcement>>;
+
+    /// Store a new announcement, and return its ID.
+    async fn add_announcement(&self, message: &str, trigger: AnnouncementTrigger) -> Result<i32>;
+
+    /// Restrict an announcement to a time window, or remove its window with `None`.
+    ///
+    /// Returns `false` if there is no announcement with that ID.
+    async fn set_announcement_window(&self, id: i32, window: Option<TimeWindow>) -> Result<bool>;
+
+    /// Delete an announcement.
+    ///
+    /// Returns `false` if there is no announcement with that ID.
+    async fn remove_announcement(&self, id: i32) -> Result<bool>;
+}
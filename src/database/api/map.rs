@@ -29,11 +29,28 @@ pub struct Map {
     /// was validated with in the map editor.
     pub author_millis: i32,
 
+    /// The gold medal time in milliseconds, or `None` if it is unknown.
+    pub gold_millis: Option<i32>,
+
+    /// The silver medal time in milliseconds, or `None` if it is unknown.
+    pub silver_millis: Option<i32>,
+
+    /// The bronze medal time in milliseconds, or `None` if it is unknown.
+    pub bronze_millis: Option<i32>,
+
     /// The moment this map was added to the database.
     pub added_since: NaiveDateTime,
 
     /// This map's ID on Trackmania Exchange, or `None` if it is unknown.
     pub exchange_id: Option<i32>,
+
+    /// The number of checkpoints in a lap, including the finish line,
+    /// or `None` if it is unknown.
+    pub nb_cps: Option<i32>,
+
+    /// The number of laps of a multi-lap map, or `None` if the map
+    /// is not multi-lap.
+    pub nb_laps: Option<i32>,
 }
 
 /// A map that is in the database, but was deleted on the file system.
@@ -1,8 +1,16 @@
+pub use announcement::*;
 pub use map::*;
 pub use player::*;
+pub use pool::*;
 pub use record::*;
+pub use role::*;
+pub use season::*;
 
+mod announcement;
 mod map;
 mod player;
+mod pool;
 mod record;
+mod role;
+mod season;
 pub mod timeattack;
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 
 use crate::database::Result;
 use crate::server::{DisplayString, PlayerInfo};
@@ -13,6 +14,22 @@ pub struct Player {
     pub display_name: DisplayString,
 }
 
+/// A display name that a player has joined the server with.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlayerAlias {
+    /// Player login.
+    pub player_login: String,
+
+    /// Formatted display name.
+    pub display_name: DisplayString,
+
+    /// The first time the player joined with this name.
+    pub first_seen: NaiveDateTime,
+
+    /// The last time the player joined with this name.
+    pub last_seen: NaiveDateTime,
+}
+
 #[async_trait]
 pub trait PlayerQueries {
     /// Return the specified player, or `None` if no such player exists in the database.
@@ -23,8 +40,34 @@ pub trait PlayerQueries {
 
     /// Insert a player into the database.
     /// Update their display name if the player already exists.
+    /// In both cases, the display name is added to the player's aliases.
     async fn upsert_player(&self, player: &PlayerInfo) -> Result<()>;
 
+    /// Return every display name that the specified players have joined with,
+    /// or the aliases of all players if no logins are given.
+    /// The aliases of each player are sorted from most to least recent.
+    async fn player_aliases(&self, logins: Vec<&str>) -> Result<Vec<PlayerAlias>>;
+
+    /// Return every display name of up to `limit` players, whose login
+    /// or any unformatted display name contains the given search term,
+    /// ignoring letter case. Players with shorter names are returned first.
+    /// The aliases of each player are sorted from most to least recent.
+    async fn find_player_aliases(&self, name: &str, limit: i64) -> Result<Vec<PlayerAlias>>;
+
+    /// Remember that the server reported an incoherent run for the specified
+    /// player on the specified map.
+    async fn add_incoherence(&self, player_login: &str, map_uid: &str) -> Result<()>;
+
+    /// Count the incoherent runs of the specified player since the given time.
+    async fn nb_incoherences(&self, player_login: &str, since: &NaiveDateTime) -> Result<i64>;
+
+    /// Return the language that the specified player selected, or `None`
+    /// if they did not select one, or if no such player exists.
+    async fn player_language(&self, login: &str) -> Result<Option<String>>;
+
+    /// Set the language of the specified player, or reset it with `None`.
+    async fn set_player_language(&self, login: &str, language: Option<&str>) -> Result<()>;
+
     /// Delete a player, their preferences, and their records.
     /// The data is lost forever.
     async fn delete_player(&self, player_login: &str) -> Result<Option<Player>>;
@@ -0,0 +1,32 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::database::Result;
+
+/// A named selection of maps, that can replace the playlist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapPool {
+    pub name: String,
+
+    /// The UIDs of the maps in this pool.
+    pub map_uids: Vec<String>,
+}
+
+#[async_trait]
+pub trait PoolQueries {
+    /// List all map pools.
+    async fn pools(&self) -> Result<Vec<MapPool>>;
+
+    /// Return the map pool with the given name, or `None` if there is no such pool.
+    async fn pool(&self, name: &str) -> Result<Option<MapPool>>;
+
+    /// Insert a map pool, replacing the maps of any pool with the same name.
+    async fn upsert_pool(&self, pool: &MapPool) -> Result<()>;
+
+    /// Return the name of the active map pool, and the moment it was activated,
+    /// or `None` if no pool is active.
+    async fn active_pool(&self) -> Result<Option<(String, NaiveDateTime)>>;
+
+    /// Mark the map pool with the given name as the active one.
+    async fn set_active_pool(&self, name: &str, since: &NaiveDateTime) -> Result<()>;
+}
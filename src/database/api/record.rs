@@ -50,6 +50,58 @@ pub struct Record {
     pub timestamp: NaiveDateTime,
 }
 
+/// A record that has to be reviewed by an admin, before it
+/// can count as the player's personal best.
+#[derive(Clone, Debug)]
+pub struct FlaggedRecord {
+    /// Identifies this record in the review queue.
+    pub id: i32,
+
+    pub player_login: String,
+    pub map_uid: String,
+    pub nb_laps: i32,
+    pub millis: i32,
+    pub timestamp: NaiveDateTime,
+    pub cp_millis: Vec<i32>,
+
+    /// The reason why this record was flagged.
+    pub reason: String,
+}
+
+impl From<FlaggedRecord> for RecordEvidence {
+    fn from(rec: FlaggedRecord) -> Self {
+        RecordEvidence {
+            player_login: rec.player_login,
+            map_uid: rec.map_uid,
+            nb_laps: rec.nb_laps,
+            millis: rec.millis,
+            timestamp: rec.timestamp,
+            cp_millis: rec.cp_millis,
+        }
+    }
+}
+
+/// An entry in the audit trail of removed records.
+#[derive(Clone, Debug)]
+pub struct RecordRemoval {
+    /// The login of the admin that removed the record.
+    pub admin_login: String,
+
+    pub player_login: String,
+    pub map_uid: String,
+    pub nb_laps: i32,
+
+    /// The time of the removed record.
+    pub millis: i32,
+
+    /// The time of the player's previous personal best, which replaced the
+    /// removed record, or `None` if there was no previous record.
+    pub restored_millis: Option<i32>,
+
+    /// The moment the record was removed.
+    pub removed_at: NaiveDateTime,
+}
+
 #[async_trait]
 pub trait RecordQueries {
     /// Return records on the specified maps, set by the specified players, with the specified
@@ -99,14 +151,70 @@ pub trait RecordQueries {
     /// Return the number of players that have set a record on at least one map.
     async fn nb_players_with_record(&self) -> Result<i64>;
 
+    /// Count the records set on each map since the specified time.
+    /// Returns pairs of map UID and record count. Maps without such
+    /// records are not included.
+    ///
+    /// Since only personal bests are stored, a player improving their
+    /// record several times will only count once.
+    async fn nb_records_since(&self, since: &NaiveDateTime) -> Result<Vec<(String, i64)>>;
+
+    /// Count the maps that each of the specified players has a record on.
+    /// Players without records are not included.
+    async fn nb_records_by_player(&self, player_logins: Vec<&str>) -> Result<Vec<(String, i64)>>;
+
     /// List all map UIDs that the specified player has not completed a run on.
+    /// On multi-lap maps, flying lap records do not count as completed runs.
     async fn maps_without_player_record(&self, player_login: &str) -> Result<Vec<String>>;
 
     /// Without inserting the given record, return the map rank it would achieve,
     /// if it were inserted.
     async fn record_preview(&self, record: &RecordEvidence) -> Result<i64>;
 
-    /// Updates the player's personal best on a map.
+    /// Store a record that has to be reviewed by an admin, before it
+    /// can count as the player's personal best.
+    async fn flag_record(&self, rec: &RecordEvidence, reason: &str) -> Result<()>;
+
+    /// Return all records that have to be reviewed, from oldest to newest.
+    async fn flagged_records(&self) -> Result<Vec<FlaggedRecord>>;
+
+    /// Remove the specified record from the review queue, and return it,
+    /// or `None` if no such record exists.
+    async fn delete_flagged_record(&self, id: i32) -> Result<Option<FlaggedRecord>>;
+
+    /// Return the top records of every map that were set since the given time.
+    ///
+    /// On multi-lap maps, only full-race records are considered.
+    async fn top_records_since(&self, since: &NaiveDateTime) -> Result<Vec<Record>>;
+
+    /// Remove a player's personal best on a map, and replace it with their
+    /// previous personal best, if there is one. The removal is added to the
+    /// audit trail, and returned. Returns `None` if there is no such record.
+    async fn remove_record(
+        &self,
+        map_uid: &str,
+        player_login: &str,
+        nb_laps: i32,
+        admin_login: &str,
+    ) -> Result<Option<RecordRemoval>>;
+
+    /// Assign the given lap count to all records on a map that were stored
+    /// with a lap count of zero, including flagged and removed records.
+    ///
+    /// Records on multi-lap maps used to be stored that way, even though they
+    /// are full-race records. Use this once the lap count of such a map is known,
+    /// and before any flying lap records are stored.
+    async fn convert_multi_lap_records(&self, map_uid: &str, nb_laps: i32) -> Result<()>;
+
+    /// Return every personal best that was set on the given map, including
+    /// those that were improved since.
+    async fn record_history(&self, map_uid: &str, nb_laps: i32) -> Result<Vec<RecordEvidence>>;
+
+    /// Return the most recent record removals.
+    async fn record_removals(&self, limit: i64) -> Result<Vec<RecordRemoval>>;
+
+    /// Updates the player's personal best on a map, and adds it to the
+    /// player's record history.
     ///
     /// # Note
     /// If a previous record exists for that player, this function does not
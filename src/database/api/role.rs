@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+
+use crate::database::Result;
+
+/// A named role, that allows players to execute specific commands.
+///
+/// A role exists as long as it grants at least one command,
+/// or is assigned to at least one player.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Role {
+    pub name: String,
+
+    /// The commands that this role grants, named like their usage
+    /// without arguments, f.e. `/playlist add`.
+    pub commands: Vec<String>,
+
+    /// The logins of players that were assigned this role.
+    pub player_logins: Vec<String>,
+}
+
+#[async_trait]
+pub trait RoleQueries {
+    /// List all roles.
+    async fn roles(&self) -> Result<Vec<Role>>;
+
+    /// List the commands that any of the given player's roles grant.
+    async fn granted_commands(&self, player_login: &str) -> Result<Vec<String>>;
+
+    /// Allow a role to execute a command.
+    ///
+    /// Returns `false` if the role already granted that command.
+    async fn grant_command(&self, role_name: &str, command: &str) -> Result<bool>;
+
+    /// Disallow a role to execute a command.
+    ///
+    /// Returns `false` if the role did not grant that command.
+    async fn revoke_command(&self, role_name: &str, command: &str) -> Result<bool>;
+
+    /// Assign a role to a player.
+    ///
+    /// Returns `false` if the player already had that role.
+    async fn grant_role(&self, player_login: &str, role_name: &str) -> Result<bool>;
+
+    /// Take a role from a player.
+    ///
+    /// Returns `false` if the player did not have that role.
+    async fn revoke_role(&self, player_login: &str, role_name: &str) -> Result<bool>;
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::database::timeattack::MapRank;
+use crate::database::{RecordEvidence, Result};
+use crate::server::DisplayString;
+
+/// A period with its own server ranking.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Season {
+    pub id: i32,
+    pub name: String,
+
+    /// The first moment of this season.
+    pub start: NaiveDateTime,
+
+    /// The first moment after this season.
+    pub end: NaiveDateTime,
+
+    /// `True` if the final standings of this season were stored.
+    pub archived: bool,
+}
+
+/// A player's final standing in a past season.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeasonRank {
+    pub season_id: i32,
+    pub player_login: String,
+    pub player_display_name: DisplayString,
+    pub pos: i64,
+
+    /// The number of players that were ranked in this season.
+    pub max_pos: i64,
+
+    pub nb_wins: i64,
+    pub score: f64,
+}
+
+#[async_trait]
+pub trait SeasonQueries {
+    /// Insert a season, or return the existing one with the same period.
+    /// The name of an existing season is updated.
+    async fn upsert_season(
+        &self,
+        name: &str,
+        start: &NaiveDateTime,
+        end: &NaiveDateTime,
+    ) -> Result<Season>;
+
+    /// Return seasons that ended before the given time, but were not archived yet.
+    async fn seasons_to_archive(&self, before: &NaiveDateTime) -> Result<Vec<Season>>;
+
+    /// Return a number of archived seasons, from most to least recent.
+    async fn archived_seasons(&self, limit: i64) -> Result<Vec<Season>>;
+
+    /// Store the final standings of a season, and mark it as archived.
+    async fn archive_season(&self, season_id: i32, ranks: &[SeasonRank]) -> Result<()>;
+
+    /// Return the final standings of an archived season, sorted from best to worst.
+    ///
+    /// # Arguments
+    /// `player_logins` - A list of player logins to return ranks for. Use an empty list to
+    ///                   select the ranks of any player.
+    /// `limit` - The maximum number of ranks returned.
+    async fn season_ranks(
+        &self,
+        season_id: i32,
+        player_logins: Vec<&str>,
+        limit: Option<i64>,
+    ) -> Result<Vec<SeasonRank>>;
+
+    /// Update a player's best run on a map within a season,
+    /// if the given run is better than the one in the database.
+    async fn upsert_season_record(&self, season_id: i32, rec: &RecordEvidence) -> Result<()>;
+
+    /// Return the number of players that have set a record on at least one map
+    /// within the specified season.
+    async fn nb_players_with_season_record(&self, season_id: i32) -> Result<i64>;
+
+    /// Like `TimeAttackQueries::map_rankings`, but only considers runs
+    /// within the specified season.
+    async fn season_map_rankings(
+        &self,
+        season_id: i32,
+        map_uids: Vec<&str>,
+        flying_laps: bool,
+    ) -> Result<Vec<MapRank>>;
+}
@@ -14,7 +14,7 @@ pub struct Preference {
     pub value: PreferenceValue,
 }
 
-#[derive(Debug, Clone, Copy, ToSql, FromSql, Serialize_repr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ToSql, FromSql, Serialize_repr)]
 #[postgres(name = "pref")]
 #[repr(u8)]
 pub enum PreferenceValue {
@@ -40,7 +40,8 @@ pub struct History {
 
 /// A rank of a player's record on a specific map.
 ///
-/// On multi-lap maps, this is the
+/// On multi-lap maps, this is the rank of either the player's full-race record,
+/// or of their flying lap record.
 #[derive(Debug)]
 pub struct MapRank {
     pub map_uid: String,
@@ -54,6 +55,18 @@ pub struct MapRank {
     /// The maximum map rank; or the number of players that have set a
     /// record on this map.
     pub max_pos: i64,
+
+    /// The player's personal best on this map in milliseconds.
+    pub millis: i32,
+
+    /// The map's author time in milliseconds.
+    pub author_millis: i32,
+
+    /// The map's gold, silver and bronze medal times in milliseconds,
+    /// which are `None` if they are unknown.
+    pub gold_millis: Option<i32>,
+    pub silver_millis: Option<i32>,
+    pub bronze_millis: Option<i32>,
 }
 
 #[async_trait]
@@ -81,16 +94,20 @@ pub trait TimeAttackQueries {
     /// Count the number of times each preference was set by any player, for the specified map.
     async fn count_map_preferences(&self, map_uid: &str) -> Result<Vec<(PreferenceValue, i64)>>;
 
+    /// Count the number of players that have played the specified map.
+    async fn nb_players_played(&self, map_uid: &str) -> Result<i64>;
+
     /// Insert a player's map preference, overwriting any previous preference.
     async fn upsert_preference(&self, pref: &Preference) -> Result<()>;
 
     /// Calculate the map rank of *every* player, for each of the specified maps.
     ///
-    /// For multi-lap maps, the best map rank will have the best flying lap.
+    /// For multi-lap maps, the map ranks are decided by full-race records,
+    /// or by flying lap records if `flying_laps` is `true`.
     ///
     /// # Note
-    /// The length of this collection is equal to the total number of `nb_laps == 0` records
+    /// The length of this collection is equal to the total number of ranked records
     /// stored in the database. This function should only be used when calculating
     /// the server ranking.
-    async fn map_rankings(&self, map_uids: Vec<&str>) -> Result<Vec<MapRank>>;
+    async fn map_rankings(&self, map_uids: Vec<&str>, flying_laps: bool) -> Result<Vec<MapRank>>;
 }
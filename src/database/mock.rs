@@ -49,6 +49,30 @@ impl PlayerQueries for DatabaseClient {
         unimplemented!()
     }
 
+    async fn player_aliases(&self, _logins: Vec<&str>) -> Result<Vec<PlayerAlias>> {
+        unimplemented!()
+    }
+
+    async fn find_player_aliases(&self, _name: &str, _limit: i64) -> Result<Vec<PlayerAlias>> {
+        unimplemented!()
+    }
+
+    async fn add_incoherence(&self, _player_login: &str, _map_uid: &str) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn nb_incoherences(&self, _player_login: &str, _since: &NaiveDateTime) -> Result<i64> {
+        unimplemented!()
+    }
+
+    async fn player_language(&self, _login: &str) -> Result<Option<String>> {
+        unimplemented!()
+    }
+
+    async fn set_player_language(&self, _login: &str, _language: Option<&str>) -> Result<()> {
+        unimplemented!()
+    }
+
     async fn delete_player(&self, _player_login: &str) -> Result<Option<Player>> {
         unimplemented!()
     }
@@ -130,6 +154,14 @@ impl RecordQueries for DatabaseClient {
         Ok(logins.len() as i64)
     }
 
+    async fn nb_records_since(&self, _since: &NaiveDateTime) -> Result<Vec<(String, i64)>> {
+        unimplemented!()
+    }
+
+    async fn nb_records_by_player(&self, _player_logins: Vec<&str>) -> Result<Vec<(String, i64)>> {
+        unimplemented!()
+    }
+
     async fn maps_without_player_record(&self, _player_login: &str) -> Result<Vec<String>> {
         unimplemented!()
     }
@@ -138,11 +170,168 @@ impl RecordQueries for DatabaseClient {
         unimplemented!()
     }
 
+    async fn flag_record(&self, _rec: &RecordEvidence, _reason: &str) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn flagged_records(&self) -> Result<Vec<FlaggedRecord>> {
+        unimplemented!()
+    }
+
+    async fn delete_flagged_record(&self, _id: i32) -> Result<Option<FlaggedRecord>> {
+        unimplemented!()
+    }
+
+    async fn top_records_since(&self, _since: &NaiveDateTime) -> Result<Vec<Record>> {
+        unimplemented!()
+    }
+
+    async fn record_history(&self, _map_uid: &str, _nb_laps: i32) -> Result<Vec<RecordEvidence>> {
+        unimplemented!()
+    }
+
+    async fn remove_record(
+        &self,
+        _map_uid: &str,
+        _player_login: &str,
+        _nb_laps: i32,
+        _admin_login: &str,
+    ) -> Result<Option<RecordRemoval>> {
+        unimplemented!()
+    }
+
+    async fn convert_multi_lap_records(&self, _map_uid: &str, _nb_laps: i32) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn record_removals(&self, _limit: i64) -> Result<Vec<RecordRemoval>> {
+        unimplemented!()
+    }
+
     async fn upsert_record(&self, _rec: &RecordEvidence) -> Result<()> {
         unimplemented!()
     }
 }
 
+#[async_trait]
+impl PoolQueries for DatabaseClient {
+    async fn pools(&self) -> Result<Vec<MapPool>> {
+        unimplemented!()
+    }
+
+    async fn pool(&self, _name: &str) -> Result<Option<MapPool>> {
+        unimplemented!()
+    }
+
+    async fn upsert_pool(&self, _pool: &MapPool) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn active_pool(&self) -> Result<Option<(String, NaiveDateTime)>> {
+        unimplemented!()
+    }
+
+    async fn set_active_pool(&self, _name: &str, _since: &NaiveDateTime) -> Result<()> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl RoleQueries for DatabaseClient {
+    async fn roles(&self) -> Result<Vec<Role>> {
+        unimplemented!()
+    }
+
+    async fn granted_commands(&self, _player_login: &str) -> Result<Vec<String>> {
+        unimplemented!()
+    }
+
+    async fn grant_command(&self, _role_name: &str, _command: &str) -> Result<bool> {
+        unimplemented!()
+    }
+
+    async fn revoke_command(&self, _role_name: &str, _command: &str) -> Result<bool> {
+        unimplemented!()
+    }
+
+    async fn grant_role(&self, _player_login: &str, _role_name: &str) -> Result<bool> {
+        unimplemented!()
+    }
+
+    async fn revoke_role(&self, _player_login: &str, _role_name: &str) -> Result<bool> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl AnnouncementQueries for DatabaseClient {
+    async fn announcements(&self) -> Result<Vec<Announcement>> {
+        unimplemented!()
+    }
+
+    async fn add_announcement(&self, _message: &str, _trigger: AnnouncementTrigger) -> Result<i32> {
+        unimplemented!()
+    }
+
+    async fn set_announcement_window(&self, _id: i32, _window: Option<TimeWindow>) -> Result<bool> {
+        unimplemented!()
+    }
+
+    async fn remove_announcement(&self, _id: i32) -> Result<bool> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl SeasonQueries for DatabaseClient {
+    async fn upsert_season(
+        &self,
+        _name: &str,
+        _start: &NaiveDateTime,
+        _end: &NaiveDateTime,
+    ) -> Result<Season> {
+        unimplemented!()
+    }
+
+    async fn seasons_to_archive(&self, _before: &NaiveDateTime) -> Result<Vec<Season>> {
+        unimplemented!()
+    }
+
+    async fn archived_seasons(&self, _limit: i64) -> Result<Vec<Season>> {
+        unimplemented!()
+    }
+
+    async fn archive_season(&self, _season_id: i32, _ranks: &[SeasonRank]) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn season_ranks(
+        &self,
+        _season_id: i32,
+        _player_logins: Vec<&str>,
+        _limit: Option<i64>,
+    ) -> Result<Vec<SeasonRank>> {
+        unimplemented!()
+    }
+
+    async fn upsert_season_record(&self, _season_id: i32, _rec: &RecordEvidence) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn nb_players_with_season_record(&self, _season_id: i32) -> Result<i64> {
+        unimplemented!()
+    }
+
+    async fn season_map_rankings(
+        &self,
+        _season_id: i32,
+        _map_uids: Vec<&str>,
+        _flying_laps: bool,
+    ) -> Result<Vec<MapRank>> {
+        unimplemented!()
+    }
+}
+
 #[async_trait]
 impl TimeAttackQueries for DatabaseClient {
     async fn add_history(
@@ -166,17 +355,28 @@ impl TimeAttackQueries for DatabaseClient {
         unimplemented!()
     }
 
+    async fn nb_players_played(&self, _map_uid: &str) -> Result<i64> {
+        unimplemented!()
+    }
+
     async fn upsert_preference(&self, _pref: &Preference) -> Result<()> {
         unimplemented!()
     }
 
-    async fn map_rankings(&self, map_uids: Vec<&str>) -> Result<Vec<MapRank>> {
+    async fn map_rankings(&self, map_uids: Vec<&str>, flying_laps: bool) -> Result<Vec<MapRank>> {
         let db = &self.mock;
         let mut grp_by_map = HashMap::<&str, Vec<&RecordEvidence>>::new();
         for rec in db.records.iter() {
             if !map_uids.contains(&rec.map_uid.as_str()) {
                 continue;
             }
+            let ranked_nb_laps = match db.expect_map(&rec.map_uid).nb_laps {
+                Some(nb_laps) if !flying_laps => nb_laps,
+                _ => 0,
+            };
+            if rec.nb_laps != ranked_nb_laps {
+                continue;
+            }
             grp_by_map.entry(&rec.map_uid).or_insert(vec![]).push(&rec);
         }
         for map_recs in grp_by_map.values_mut() {
@@ -189,12 +389,18 @@ impl TimeAttackQueries for DatabaseClient {
                 map_recs.into_iter().enumerate().map(move |(idx, rec)| {
                     let player_display_name =
                         db.expect_player(&rec.player_login).display_name.clone();
+                    let map = db.expect_map(&rec.map_uid);
                     MapRank {
                         map_uid: map_uid.to_string(),
                         player_login: rec.player_login.clone(),
                         player_display_name,
                         pos: idx as i64 + 1,
                         max_pos,
+                        millis: rec.millis,
+                        author_millis: map.author_millis,
+                        gold_millis: map.gold_millis,
+                        silver_millis: map.silver_millis,
+                        bronze_millis: map.bronze_millis,
                     }
                 })
             })
@@ -212,20 +418,40 @@ impl DatabaseClient {
     }
 
     pub fn push_map(&mut self, uid: &str) {
-        let db = &mut self.mock;
-        db.maps.push(Map {
-            uid: uid.to_string(),
-            file_name: "".to_string(),
-            name: DisplayString::from("".to_string()),
-            author_login: "".to_string(),
-            author_display_name: DisplayString::from("".to_string()),
-            added_since: Utc::now().naive_utc(),
-            author_millis: 0,
-            exchange_id: None,
-        });
+        self.push_map_with_author_time(uid, 0);
+    }
+
+    pub fn push_multi_lap_map(&mut self, uid: &str, nb_laps: i32) {
+        self.push_map(uid);
+        self.mock.maps.last_mut().unwrap().nb_laps = Some(nb_laps);
+    }
+
+    pub fn push_map_with_medal_times(
+        &mut self,
+        uid: &str,
+        author_millis: i32,
+        gold_millis: i32,
+        silver_millis: i32,
+        bronze_millis: i32,
+    ) {
+        self.push_map_with_author_time(uid, author_millis);
+        let map = self.mock.maps.last_mut().unwrap();
+        map.gold_millis = Some(gold_millis);
+        map.silver_millis = Some(silver_millis);
+        map.bronze_millis = Some(bronze_millis);
+    }
+
+    pub fn push_map_with_author_time(&mut self, uid: &str, author_millis: i32) {
+        let mut map = mock_map(uid, Utc::now().naive_utc());
+        map.author_millis = author_millis;
+        self.mock.maps.push(map);
     }
 
     pub fn push_record(&mut self, login: &str, uid: &str, millis: i32) {
+        self.push_lap_record(login, uid, millis, 0);
+    }
+
+    pub fn push_lap_record(&mut self, login: &str, uid: &str, millis: i32, nb_laps: i32) {
         let db = &mut self.mock;
         db.records.push(RecordEvidence {
             player_login: login.to_string(),
@@ -233,7 +459,7 @@ impl DatabaseClient {
             millis,
             cp_millis: vec![millis],
             timestamp: Utc::now().naive_utc(),
-            nb_laps: 0,
+            nb_laps,
         });
     }
 }
@@ -254,3 +480,22 @@ impl MockDatabase {
             .expect("map uid not in mock database")
     }
 }
+
+/// A map without any metadata, that was added to the database at the given time.
+pub fn mock_map(uid: &str, added_since: NaiveDateTime) -> Map {
+    Map {
+        uid: uid.to_string(),
+        file_name: "".to_string(),
+        name: DisplayString::from("".to_string()),
+        author_login: "".to_string(),
+        author_display_name: DisplayString::from("".to_string()),
+        author_millis: 0,
+        gold_millis: None,
+        silver_millis: None,
+        bronze_millis: None,
+        added_since,
+        exchange_id: None,
+        nb_cps: None,
+        nb_laps: None,
+    }
+}
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use tokio_postgres::Row;
+
+use crate::database::{
+    Announcement, AnnouncementQueries, AnnouncementTrigger, DatabaseClient, Result, TimeWindow,
+};
+
+#[async_trait]
+impl AnnouncementQueries for DatabaseClient {
+    async fn announcements(&self) -> Result<Vec<Announcement>> {
+        let conn = self.pool.get().await?;
+        let stmt = "SELECT * FROM steward.announcement ORDER BY id";
+        let rows = conn.query(stmt, &[]).await?;
+        Ok(rows.into_iter().map(Announcement::from).collect())
+    }
+
+    async fn add_announcement(&self, message: &str, trigger: AnnouncementTrigger) -> Result<i32> {
+        use AnnouncementTrigger::*;
+
+        let (trigger, every) = match trigger {
+            Join => ("join", None),
+            MapStart => ("map_start", None),
+            EveryNbMaps { nb_maps } => ("every_nb_maps", Some(nb_maps)),
+            Interval { secs } => ("interval", Some(secs)),
+        };
+
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            INSERT INTO steward.announcement
+                (message, trigger, every)
+            VALUES
+                ($1, $2, $3)
+            RETURNING id
+        "#;
+        let row = conn.query_one(stmt, &[&message, &trigger, &every]).await?;
+        Ok(row.get(0))
+    }
+
+    async fn set_announcement_window(&self, id: i32, window: Option<TimeWindow>) -> Result<bool> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            UPDATE steward.announcement
+            SET window_start = $2, window_end = $3
+            WHERE id = $1
+        "#;
+        let start = window.map(|w| w.start);
+        let end = window.map(|w| w.end);
+        let nb_updated = conn.execute(stmt, &[&id, &start, &end]).await?;
+        Ok(nb_updated > 0)
+    }
+
+    async fn remove_announcement(&self, id: i32) -> Result<bool> {
+        let conn = self.pool.get().await?;
+        let stmt = "DELETE FROM steward.announcement WHERE id = $1";
+        let nb_deleted = conn.execute(stmt, &[&id]).await?;
+        Ok(nb_deleted > 0)
+    }
+}
+
+impl From<Row> for Announcement {
+    fn from(row: Row) -> Self {
+        use AnnouncementTrigger::*;
+
+        let trigger: &str = row.get("trigger");
+        let every: Option<i32> = row.get("every");
+        let every = || every.expect("announcement is missing its interval");
+        let trigger = match trigger {
+            "join" => Join,
+            "map_start" => MapStart,
+            "every_nb_maps" => EveryNbMaps { nb_maps: every() },
+            "interval" => Interval { secs: every() },
+            other => panic!("unknown announcement trigger '{}'", other),
+        };
+
+        let window = match (row.get("window_start"), row.get("window_end")) {
+            (Some(start), Some(end)) => Some(TimeWindow { start, end }),
+            _ => None,
+        };
+
+        Announcement {
+            id: row.get("id"),
+            message: row.get("message"),
+            trigger,
+            window,
+        }
+    }
+}
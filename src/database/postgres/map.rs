@@ -71,15 +71,22 @@ impl MapQueries for DatabaseClient {
             INSERT INTO steward.map
                 (uid, file_name, name,
                  author_login, author_display_name, author_millis,
-                 added_since, exchange_id)
+                 gold_millis, silver_millis, bronze_millis,
+                 added_since, exchange_id, nb_cps, nb_laps)
             VALUES
                 ($1, $2, $3,
                  $4, $5, $6,
-                 $7, $8)
+                 $7, $8, $9,
+                 $10, $11, $12, $13)
             ON CONFLICT (uid)
             DO UPDATE SET
                 file_name = excluded.file_name,
-                exchange_id = COALESCE(excluded.exchange_id, steward.map.exchange_id)
+                exchange_id = COALESCE(excluded.exchange_id, steward.map.exchange_id),
+                nb_cps = COALESCE(excluded.nb_cps, steward.map.nb_cps),
+                nb_laps = COALESCE(excluded.nb_laps, steward.map.nb_laps),
+                gold_millis = COALESCE(excluded.gold_millis, steward.map.gold_millis),
+                silver_millis = COALESCE(excluded.silver_millis, steward.map.silver_millis),
+                bronze_millis = COALESCE(excluded.bronze_millis, steward.map.bronze_millis)
         "#;
         let _ = txn
             .execute(
@@ -91,8 +98,13 @@ impl MapQueries for DatabaseClient {
                     &metadata.author_login,
                     &metadata.author_display_name.formatted.trim(),
                     &metadata.author_millis,
+                    &metadata.gold_millis,
+                    &metadata.silver_millis,
+                    &metadata.bronze_millis,
                     &metadata.added_since,
                     &metadata.exchange_id,
+                    &metadata.nb_cps,
+                    &metadata.nb_laps,
                 ],
             )
             .await?;
@@ -119,6 +131,18 @@ impl MapQueries for DatabaseClient {
         let stmt = "DELETE FROM steward.ta_history WHERE map_uid = $1";
         let _ = transaction.execute(stmt, &[&map_uid]).await?;
 
+        let stmt = "DELETE FROM steward.season_record WHERE map_uid = $1";
+        let _ = transaction.execute(stmt, &[&map_uid]).await?;
+
+        let stmt = "DELETE FROM steward.map_pool_map WHERE map_uid = $1";
+        let _ = transaction.execute(stmt, &[&map_uid]).await?;
+
+        let stmt = "DELETE FROM steward.record_history WHERE map_uid = $1";
+        let _ = transaction.execute(stmt, &[&map_uid]).await?;
+
+        let stmt = "DELETE FROM steward.flagged_record WHERE map_uid = $1";
+        let _ = transaction.execute(stmt, &[&map_uid]).await?;
+
         let stmt = "DELETE FROM steward.record WHERE map_uid = $1";
         let _ = transaction.execute(stmt, &[&map_uid]).await?;
 
@@ -155,8 +179,13 @@ impl From<Row> for Map {
             author_login: row.get("author_login"),
             author_display_name: DisplayString::from(row.get("author_display_name")),
             author_millis: row.get("author_millis"),
+            gold_millis: row.get("gold_millis"),
+            silver_millis: row.get("silver_millis"),
+            bronze_millis: row.get("bronze_millis"),
             added_since: row.get("added_since"),
             exchange_id: row.get("exchange_id"),
+            nb_cps: row.get("nb_cps"),
+            nb_laps: row.get("nb_laps"),
         }
     }
 }
@@ -0,0 +1,5 @@
+-- added by 0.1.0
+
+ALTER TABLE steward.player ADD COLUMN language TEXT;
+
+UPDATE steward.meta SET at_migration = 10;
@@ -0,0 +1,14 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.announcement (
+    id            SERIAL,
+    message       TEXT    NOT NULL,
+    trigger       TEXT    NOT NULL, -- 'join', 'map_start', 'every_nb_maps' or 'interval'
+    every         INTEGER,          -- the number of maps, or seconds
+    window_start  TIME,             -- in UTC
+    window_end    TIME,
+
+    PRIMARY KEY (id)
+);
+
+UPDATE steward.meta SET at_migration = 11;
@@ -0,0 +1,7 @@
+-- added by 0.1.0
+
+ALTER TABLE steward.map ADD COLUMN gold_millis   INTEGER DEFAULT NULL; -- NULL if unknown
+ALTER TABLE steward.map ADD COLUMN silver_millis INTEGER DEFAULT NULL; -- NULL if unknown
+ALTER TABLE steward.map ADD COLUMN bronze_millis INTEGER DEFAULT NULL; -- NULL if unknown
+
+UPDATE steward.meta SET at_migration = 12;
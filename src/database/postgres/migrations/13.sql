@@ -0,0 +1,5 @@
+-- added by 0.1.0
+
+ALTER TABLE steward.map_pool ADD COLUMN active_since TIMESTAMP DEFAULT NULL; -- NULL if not the active pool
+
+UPDATE steward.meta SET at_migration = 13;
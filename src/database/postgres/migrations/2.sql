@@ -0,0 +1,41 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.season (
+    id       SERIAL,
+    name     TEXT      NOT NULL,
+    start_at TIMESTAMP NOT NULL,
+    end_at   TIMESTAMP NOT NULL, -- exclusive
+    archived BOOLEAN   NOT NULL DEFAULT FALSE,
+
+    PRIMARY KEY (id),
+    UNIQUE (start_at, end_at),
+
+    CONSTRAINT end_after_start CHECK (end_at > start_at)
+);
+
+CREATE TABLE steward.season_record (
+    season_id    INTEGER,
+    player_login TEXT,
+    map_uid      TEXT,
+    millis       INTEGER   NOT NULL,
+    timestamp    TIMESTAMP NOT NULL,
+
+    PRIMARY KEY (season_id, player_login, map_uid),
+    FOREIGN KEY (season_id)    REFERENCES steward.season (id),
+    FOREIGN KEY (player_login) REFERENCES steward.player (login),
+    FOREIGN KEY (map_uid)      REFERENCES steward.map (uid)
+);
+
+CREATE TABLE steward.season_rank (
+    season_id    INTEGER,
+    player_login TEXT,
+    pos          BIGINT           NOT NULL,
+    nb_wins      BIGINT           NOT NULL,
+    score        DOUBLE PRECISION NOT NULL,
+
+    PRIMARY KEY (season_id, player_login),
+    FOREIGN KEY (season_id)    REFERENCES steward.season (id),
+    FOREIGN KEY (player_login) REFERENCES steward.player (login)
+);
+
+UPDATE steward.meta SET at_migration = 2;
@@ -0,0 +1,18 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.map_pool (
+    name TEXT,
+
+    PRIMARY KEY (name)
+);
+
+CREATE TABLE steward.map_pool_map (
+    pool_name TEXT,
+    map_uid   TEXT,
+
+    PRIMARY KEY (pool_name, map_uid),
+    FOREIGN KEY (pool_name) REFERENCES steward.map_pool (name),
+    FOREIGN KEY (map_uid)   REFERENCES steward.map (uid)
+);
+
+UPDATE steward.meta SET at_migration = 3;
@@ -0,0 +1,13 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.incoherence (
+    player_login TEXT      NOT NULL,
+    map_uid      TEXT      NOT NULL,
+    timestamp    TIMESTAMP NOT NULL,
+
+    FOREIGN KEY (player_login) REFERENCES steward.player (login)
+);
+
+CREATE INDEX incoherence_player_index ON steward.incoherence (player_login, timestamp);
+
+UPDATE steward.meta SET at_migration = 4;
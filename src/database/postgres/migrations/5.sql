@@ -0,0 +1,20 @@
+-- added by 0.1.0
+
+ALTER TABLE steward.map ADD COLUMN nb_cps INTEGER DEFAULT NULL; -- NULL if unknown
+
+CREATE TABLE steward.flagged_record (
+    id           SERIAL,
+    player_login TEXT      NOT NULL,
+    map_uid      TEXT      NOT NULL,
+    millis       INTEGER   NOT NULL,
+    timestamp    TIMESTAMP NOT NULL,
+    nb_laps      INTEGER   NOT NULL,
+    cp_millis    INTEGER[] NOT NULL,
+    reason       TEXT      NOT NULL, -- why this record needs to be reviewed
+
+    PRIMARY KEY (id),
+    FOREIGN KEY (player_login) REFERENCES steward.player (login),
+    FOREIGN KEY (map_uid)      REFERENCES steward.map (uid)
+);
+
+UPDATE steward.meta SET at_migration = 5;
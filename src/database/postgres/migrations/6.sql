@@ -0,0 +1,36 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.record_history (
+    player_login  TEXT      NOT NULL,
+    map_uid       TEXT      NOT NULL,
+    millis        INTEGER   NOT NULL,
+    timestamp     TIMESTAMP NOT NULL,
+    nb_laps       INTEGER   NOT NULL,
+    cp_millis     INTEGER[] NOT NULL,
+
+    FOREIGN KEY (player_login) REFERENCES steward.player (login),
+    FOREIGN KEY (map_uid)      REFERENCES steward.map (uid)
+);
+
+CREATE INDEX record_history_index ON steward.record_history (player_login, map_uid, nb_laps);
+
+-- Current personal bests are the first entries in the history.
+INSERT INTO steward.record_history
+    (player_login, map_uid, millis, timestamp, nb_laps, cp_millis)
+SELECT player_login, map_uid, millis, timestamp, nb_laps, cp_millis
+FROM steward.record;
+
+CREATE TABLE steward.record_removal (
+    id               SERIAL,
+    admin_login      TEXT      NOT NULL,
+    player_login     TEXT      NOT NULL,
+    map_uid          TEXT      NOT NULL,
+    nb_laps          INTEGER   NOT NULL,
+    millis           INTEGER   NOT NULL,
+    restored_millis  INTEGER   DEFAULT NULL, -- the previous personal best, if any
+    removed_at       TIMESTAMP NOT NULL,
+
+    PRIMARY KEY (id)
+);
+
+UPDATE steward.meta SET at_migration = 6;
@@ -0,0 +1,13 @@
+-- added by 0.1.0
+
+ALTER TABLE steward.map ADD COLUMN nb_laps INTEGER DEFAULT NULL; -- NULL if not multi-lap
+
+-- Records on multi-lap maps used to be stored as 0-lap records, even though
+-- they are full-race records. They are converted at startup, once the lap
+-- count of their map was read from its file.
+
+ALTER TABLE steward.season_record ADD COLUMN nb_laps INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE steward.season_record DROP CONSTRAINT season_record_pkey;
+ALTER TABLE steward.season_record ADD PRIMARY KEY (season_id, player_login, map_uid, nb_laps);
+
+UPDATE steward.meta SET at_migration = 7;
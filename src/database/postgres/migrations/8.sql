@@ -0,0 +1,19 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.player_alias (
+    player_login  TEXT      NOT NULL,
+    display_name  TEXT      NOT NULL,
+    first_seen    TIMESTAMP NOT NULL,
+    last_seen     TIMESTAMP NOT NULL,
+
+    PRIMARY KEY (player_login, display_name),
+    FOREIGN KEY (player_login) REFERENCES steward.player (login)
+);
+
+-- Past names of existing players are unknown.
+INSERT INTO steward.player_alias
+    (player_login, display_name, first_seen, last_seen)
+SELECT login, display_name, NOW() AT TIME ZONE 'utc', NOW() AT TIME ZONE 'utc'
+FROM steward.player;
+
+UPDATE steward.meta SET at_migration = 8;
@@ -0,0 +1,18 @@
+-- added by 0.1.0
+
+CREATE TABLE steward.role_command (
+    role_name  TEXT NOT NULL,
+    command    TEXT NOT NULL,
+
+    PRIMARY KEY (role_name, command)
+);
+
+CREATE TABLE steward.player_role (
+    player_login  TEXT NOT NULL,
+    role_name     TEXT NOT NULL,
+
+    PRIMARY KEY (player_login, role_name),
+    FOREIGN KEY (player_login) REFERENCES steward.player (login)
+);
+
+UPDATE steward.meta SET at_migration = 9;
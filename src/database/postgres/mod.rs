@@ -3,9 +3,13 @@ use std::time::Duration;
 
 use include_dir::{include_dir, Dir};
 
+mod announcement;
 mod map;
 mod player;
+mod pool;
 mod record;
+mod role;
+mod season;
 mod timeattack;
 
 /// A connection pool that maintains a set of open connections to the database,
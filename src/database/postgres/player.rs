@@ -1,7 +1,8 @@
 use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
 use tokio_postgres::Row;
 
-use crate::database::api::{Player, PlayerQueries};
+use crate::database::api::{Player, PlayerAlias, PlayerQueries};
 use crate::database::{DatabaseClient, Result};
 use crate::server::{DisplayString, PlayerInfo};
 
@@ -30,7 +31,11 @@ impl PlayerQueries for DatabaseClient {
     }
 
     async fn upsert_player(&self, player: &PlayerInfo) -> Result<()> {
-        let conn = self.pool.get().await?;
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let display_name = player.display_name.formatted.trim();
+
         let stmt = r#"
             INSERT INTO steward.player
                 (login, display_name)
@@ -40,12 +45,115 @@ impl PlayerQueries for DatabaseClient {
             DO UPDATE SET
                 display_name = excluded.display_name
         "#;
-        let _ = conn
-            .execute(
-                stmt,
-                &[&player.login, &player.display_name.formatted.trim()],
-            )
+        let _ = transaction
+            .execute(stmt, &[&player.login, &display_name])
             .await?;
+
+        let stmt = r#"
+            INSERT INTO steward.player_alias
+                (player_login, display_name, first_seen, last_seen)
+            VALUES
+                ($1, $2, $3, $3)
+            ON CONFLICT (player_login, display_name)
+            DO UPDATE SET
+                last_seen = excluded.last_seen
+        "#;
+        let now = Utc::now().naive_utc();
+        let _ = transaction
+            .execute(stmt, &[&player.login, &display_name, &now])
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn player_aliases(&self, logins: Vec<&str>) -> Result<Vec<PlayerAlias>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT *
+            FROM steward.player_alias
+            WHERE CARDINALITY($1::text[]) = 0 OR player_login = ANY($1::text[])
+            ORDER BY player_login, last_seen DESC
+        "#;
+        let rows = conn.query(stmt, &[&logins]).await?;
+        Ok(rows.into_iter().map(PlayerAlias::from).collect())
+    }
+
+    async fn find_player_aliases(&self, name: &str, limit: i64) -> Result<Vec<PlayerAlias>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            WITH plain_alias AS (
+                SELECT
+                    player_login,
+                    regexp_replace(
+                        display_name, '\$([0-9a-f]{1,3}|[lh]\[[^]]*\]|.)', '', 'gi'
+                    ) AS plain_name
+                FROM steward.player_alias
+            ), matching_player AS (
+                SELECT player_login
+                FROM plain_alias
+                WHERE player_login ILIKE $1 OR plain_name ILIKE $1
+                GROUP BY player_login
+                ORDER BY MIN(LENGTH(plain_name)), player_login
+                LIMIT $2
+            )
+            SELECT a.*
+            FROM steward.player_alias a
+            JOIN matching_player m ON m.player_login = a.player_login
+            ORDER BY a.player_login, a.last_seen DESC
+        "#;
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+        let rows = conn.query(stmt, &[&pattern, &limit]).await?;
+        Ok(rows.into_iter().map(PlayerAlias::from).collect())
+    }
+
+    async fn add_incoherence(&self, player_login: &str, map_uid: &str) -> Result<()> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            INSERT INTO steward.incoherence
+                (player_login, map_uid, timestamp)
+            VALUES
+                ($1, $2, $3)
+        "#;
+        let now = Utc::now().naive_utc();
+        let _ = conn.execute(stmt, &[&player_login, &map_uid, &now]).await?;
+        Ok(())
+    }
+
+    async fn nb_incoherences(&self, player_login: &str, since: &NaiveDateTime) -> Result<i64> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT COUNT(*)
+            FROM steward.incoherence
+            WHERE player_login = $1 AND timestamp >= $2
+        "#;
+        let row = conn.query_one(stmt, &[&player_login, &since]).await?;
+        Ok(row.get(0))
+    }
+
+    async fn player_language(&self, login: &str) -> Result<Option<String>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT language
+            FROM steward.player
+            WHERE login = $1
+        "#;
+        let row = conn.query_opt(stmt, &[&login]).await?;
+        Ok(row.and_then(|row| row.get(0)))
+    }
+
+    async fn set_player_language(&self, login: &str, language: Option<&str>) -> Result<()> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            UPDATE steward.player
+            SET language = $2
+            WHERE login = $1
+        "#;
+        let _ = conn.execute(stmt, &[&login, &language]).await?;
         Ok(())
     }
 
@@ -56,6 +164,27 @@ impl PlayerQueries for DatabaseClient {
         let stmt = "DELETE FROM steward.ta_preference WHERE player_login = $1";
         let _ = transaction.execute(stmt, &[&player_login]).await?;
 
+        let stmt = "DELETE FROM steward.season_record WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
+        let stmt = "DELETE FROM steward.season_rank WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
+        let stmt = "DELETE FROM steward.player_role WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
+        let stmt = "DELETE FROM steward.player_alias WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
+        let stmt = "DELETE FROM steward.incoherence WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
+        let stmt = "DELETE FROM steward.record_history WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
+        let stmt = "DELETE FROM steward.flagged_record WHERE player_login = $1";
+        let _ = transaction.execute(stmt, &[&player_login]).await?;
+
         let stmt = "DELETE FROM steward.record WHERE player_login = $1";
         let _ = transaction.execute(stmt, &[&player_login]).await?;
 
@@ -76,3 +205,14 @@ impl From<Row> for Player {
         }
     }
 }
+
+impl From<Row> for PlayerAlias {
+    fn from(row: Row) -> Self {
+        PlayerAlias {
+            player_login: row.get("player_login"),
+            display_name: DisplayString::from(row.get("display_name")),
+            first_seen: row.get("first_seen"),
+            last_seen: row.get("last_seen"),
+        }
+    }
+}
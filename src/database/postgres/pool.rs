@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::database::{DatabaseClient, MapPool, PoolQueries, Result};
+
+#[async_trait]
+impl PoolQueries for DatabaseClient {
+    async fn pools(&self) -> Result<Vec<MapPool>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT p.name, ARRAY_REMOVE(ARRAY_AGG(pm.map_uid), NULL) map_uids
+            FROM steward.map_pool p
+            LEFT JOIN steward.map_pool_map pm ON pm.pool_name = p.name
+            GROUP BY p.name
+            ORDER BY p.name
+        "#;
+        let rows = conn.query(stmt, &[]).await?;
+        let pools = rows
+            .into_iter()
+            .map(|row| MapPool {
+                name: row.get("name"),
+                map_uids: row.get("map_uids"),
+            })
+            .collect();
+        Ok(pools)
+    }
+
+    async fn pool(&self, name: &str) -> Result<Option<MapPool>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT p.name, ARRAY_REMOVE(ARRAY_AGG(pm.map_uid), NULL) map_uids
+            FROM steward.map_pool p
+            LEFT JOIN steward.map_pool_map pm ON pm.pool_name = p.name
+            WHERE p.name = $1
+            GROUP BY p.name
+        "#;
+        let maybe_row = conn.query_opt(stmt, &[&name]).await?;
+        Ok(maybe_row.map(|row| MapPool {
+            name: row.get("name"),
+            map_uids: row.get("map_uids"),
+        }))
+    }
+
+    async fn upsert_pool(&self, pool: &MapPool) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let stmt = r#"
+            INSERT INTO steward.map_pool (name)
+            VALUES ($1)
+            ON CONFLICT DO NOTHING
+        "#;
+        let _ = transaction.execute(stmt, &[&pool.name]).await?;
+
+        let stmt = "DELETE FROM steward.map_pool_map WHERE pool_name = $1";
+        let _ = transaction.execute(stmt, &[&pool.name]).await?;
+
+        let stmt = r#"
+            INSERT INTO steward.map_pool_map (pool_name, map_uid)
+            SELECT $1, UNNEST($2::text[])
+        "#;
+        let _ = transaction
+            .execute(stmt, &[&pool.name, &pool.map_uids])
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn active_pool(&self) -> Result<Option<(String, NaiveDateTime)>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT name, active_since
+            FROM steward.map_pool
+            WHERE active_since IS NOT NULL
+            ORDER BY active_since DESC
+            LIMIT 1
+        "#;
+        let maybe_row = conn.query_opt(stmt, &[]).await?;
+        Ok(maybe_row.map(|row| (row.get("name"), row.get("active_since"))))
+    }
+
+    async fn set_active_pool(&self, name: &str, since: &NaiveDateTime) -> Result<()> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            UPDATE steward.map_pool
+            SET active_since = CASE WHEN name = $1 THEN $2 END
+        "#;
+        let _ = conn.execute(stmt, &[&name, &since]).await?;
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use tokio_postgres::Row;
 
-use crate::database::api::{Record, RecordEvidence, RecordQueries};
+use crate::database::api::{FlaggedRecord, Record, RecordEvidence, RecordQueries, RecordRemoval};
 use crate::database::{DatabaseClient, Result};
 use crate::server::DisplayString;
 
@@ -95,15 +97,39 @@ impl RecordQueries for DatabaseClient {
         Ok(row.get(0))
     }
 
+    async fn nb_records_since(&self, since: &NaiveDateTime) -> Result<Vec<(String, i64)>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT map_uid, COUNT(*)
+            FROM steward.record
+            WHERE timestamp >= $1
+            GROUP BY map_uid
+        "#;
+        let rows = conn.query(stmt, &[&since]).await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    async fn nb_records_by_player(&self, player_logins: Vec<&str>) -> Result<Vec<(String, i64)>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT player_login, COUNT(DISTINCT map_uid)
+            FROM steward.record
+            WHERE player_login = ANY($1::text[])
+            GROUP BY player_login
+        "#;
+        let rows = conn.query(stmt, &[&player_logins]).await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
     async fn maps_without_player_record(&self, player_login: &str) -> Result<Vec<String>> {
         let conn = self.pool.get().await?;
         let stmt = r#"
             SELECT DISTINCT m.uid
             FROM steward.map m
             LEFT JOIN (
-                SELECT map_uid FROM steward.record WHERE player_login = $1
+                SELECT map_uid, nb_laps FROM steward.record WHERE player_login = $1
             ) r
-            ON m.uid = r.map_uid
+            ON m.uid = r.map_uid AND r.nb_laps = COALESCE(m.nb_laps, 0)
             WHERE r.map_uid IS NULL
         "#;
         let rows = conn.query(stmt, &[&player_login]).await?;
@@ -136,8 +162,240 @@ impl RecordQueries for DatabaseClient {
         Ok(1 + row.get::<usize, i64>(0))
     }
 
-    async fn upsert_record(&self, rec: &RecordEvidence) -> Result<()> {
+    async fn flag_record(&self, rec: &RecordEvidence, reason: &str) -> Result<()> {
+        let conn = self.pool.get().await?;
+
+        let stmt = r#"
+            INSERT INTO steward.flagged_record
+                (player_login, map_uid, nb_laps, millis, timestamp, cp_millis, reason)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7)
+        "#;
+
+        let _ = conn
+            .execute(
+                stmt,
+                &[
+                    &rec.player_login,
+                    &rec.map_uid,
+                    &rec.nb_laps,
+                    &rec.millis,
+                    &rec.timestamp,
+                    &rec.cp_millis,
+                    &reason,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn flagged_records(&self) -> Result<Vec<FlaggedRecord>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT *
+            FROM steward.flagged_record
+            ORDER BY timestamp ASC
+        "#;
+        let rows = conn.query(stmt, &[]).await?;
+        Ok(rows.into_iter().map(FlaggedRecord::from).collect())
+    }
+
+    async fn delete_flagged_record(&self, id: i32) -> Result<Option<FlaggedRecord>> {
+        let conn = self.pool.get().await?;
+        let stmt = "DELETE FROM steward.flagged_record WHERE id = $1 RETURNING *";
+        let row = conn.query_opt(stmt, &[&id]).await?;
+        Ok(row.map(FlaggedRecord::from))
+    }
+
+    async fn top_records_since(&self, since: &NaiveDateTime) -> Result<Vec<Record>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT
+                r.map_uid, r.nb_laps, r.pos, r.max_pos, r.millis, r.timestamp, r.cp_millis,
+                p.login, p.display_name
+            FROM (
+                SELECT
+                   rec.*,
+                   RANK () OVER (
+                      PARTITION BY rec.map_uid
+                      ORDER BY rec.millis ASC
+                   ) pos,
+                   COUNT(*) OVER (
+                      PARTITION BY rec.map_uid
+                   ) max_pos
+                FROM steward.record rec
+                INNER JOIN steward.map m ON rec.map_uid = m.uid
+                WHERE rec.nb_laps = COALESCE(m.nb_laps, 0)
+            ) r
+            INNER JOIN steward.player p ON r.player_login = p.login
+            WHERE r.pos = 1 AND r.timestamp >= $1
+            ORDER BY r.timestamp DESC
+        "#;
+        let rows = conn.query(stmt, &[&since]).await?;
+        let records = rows
+            .into_iter()
+            .map(|row| Record {
+                map_uid: row.get("map_uid"),
+                player_login: row.get("login"),
+                nb_laps: row.get("nb_laps"),
+                map_rank: row.get("pos"),
+                max_map_rank: row.get("max_pos"),
+                player_display_name: DisplayString::from(row.get("display_name")),
+                timestamp: row.get("timestamp"),
+                millis: row.get("millis"),
+                cp_millis: row.get("cp_millis"),
+            })
+            .collect();
+        Ok(records)
+    }
+
+    async fn remove_record(
+        &self,
+        map_uid: &str,
+        player_login: &str,
+        nb_laps: i32,
+        admin_login: &str,
+    ) -> Result<Option<RecordRemoval>> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let stmt = r#"
+            DELETE FROM steward.record
+            WHERE map_uid = $1 AND player_login = $2 AND nb_laps = $3
+            RETURNING millis, timestamp
+        "#;
+        let row = transaction
+            .query_opt(stmt, &[&map_uid, &player_login, &nb_laps])
+            .await?;
+        let (millis, timestamp): (i32, NaiveDateTime) = match row {
+            Some(row) => (row.get("millis"), row.get("timestamp")),
+            None => return Ok(None),
+        };
+
+        let stmt = r#"
+            DELETE FROM steward.record_history
+            WHERE map_uid = $1 AND player_login = $2 AND nb_laps = $3 AND timestamp >= $4
+        "#;
+        let _ = transaction
+            .execute(stmt, &[&map_uid, &player_login, &nb_laps, &timestamp])
+            .await?;
+
+        // Restore the best record that was set before the removed one.
+        let stmt = r#"
+            INSERT INTO steward.record
+                (player_login, map_uid, nb_laps, millis, timestamp, cp_millis)
+            SELECT player_login, map_uid, nb_laps, millis, timestamp, cp_millis
+            FROM steward.record_history
+            WHERE map_uid = $1 AND player_login = $2 AND nb_laps = $3
+            ORDER BY millis ASC
+            LIMIT 1
+            RETURNING millis
+        "#;
+        let restored_millis: Option<i32> = transaction
+            .query_opt(stmt, &[&map_uid, &player_login, &nb_laps])
+            .await?
+            .map(|row| row.get("millis"));
+
+        let removal = RecordRemoval {
+            admin_login: admin_login.to_string(),
+            player_login: player_login.to_string(),
+            map_uid: map_uid.to_string(),
+            nb_laps,
+            millis,
+            restored_millis,
+            removed_at: Utc::now().naive_utc(),
+        };
+
+        let stmt = r#"
+            INSERT INTO steward.record_removal
+                (admin_login, player_login, map_uid, nb_laps,
+                 millis, restored_millis, removed_at)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7)
+        "#;
+        let _ = transaction
+            .execute(
+                stmt,
+                &[
+                    &removal.admin_login,
+                    &removal.player_login,
+                    &removal.map_uid,
+                    &removal.nb_laps,
+                    &removal.millis,
+                    &removal.restored_millis,
+                    &removal.removed_at,
+                ],
+            )
+            .await?;
+
+        transaction.commit().await?;
+        Ok(Some(removal))
+    }
+
+    async fn convert_multi_lap_records(&self, map_uid: &str, nb_laps: i32) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let tables = &[
+            "record",
+            "record_history",
+            "season_record",
+            "flagged_record",
+            "record_removal",
+        ];
+        for table in tables {
+            let stmt = format!(
+                "UPDATE steward.{} SET nb_laps = $2 WHERE map_uid = $1 AND nb_laps = 0",
+                table
+            );
+            let _ = transaction
+                .execute(stmt.as_str(), &[&map_uid, &nb_laps])
+                .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn record_history(&self, map_uid: &str, nb_laps: i32) -> Result<Vec<RecordEvidence>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT *
+            FROM steward.record_history
+            WHERE map_uid = $1 AND nb_laps = $2
+            ORDER BY timestamp ASC
+        "#;
+        let rows = conn.query(stmt, &[&map_uid, &nb_laps]).await?;
+        let history = rows
+            .into_iter()
+            .map(|row| RecordEvidence {
+                player_login: row.get("player_login"),
+                map_uid: row.get("map_uid"),
+                nb_laps: row.get("nb_laps"),
+                millis: row.get("millis"),
+                timestamp: row.get("timestamp"),
+                cp_millis: row.get("cp_millis"),
+            })
+            .collect();
+        Ok(history)
+    }
+
+    async fn record_removals(&self, limit: i64) -> Result<Vec<RecordRemoval>> {
         let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT *
+            FROM steward.record_removal
+            ORDER BY removed_at DESC
+            LIMIT $1
+        "#;
+        let rows = conn.query(stmt, &[&limit]).await?;
+        Ok(rows.into_iter().map(RecordRemoval::from).collect())
+    }
+
+    async fn upsert_record(&self, rec: &RecordEvidence) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await?;
 
         let stmt = r#"
             INSERT INTO steward.record
@@ -151,7 +409,28 @@ impl RecordQueries for DatabaseClient {
                 timestamp = excluded.timestamp
         "#;
 
-        let _ = conn
+        let _ = transaction
+            .execute(
+                stmt,
+                &[
+                    &rec.player_login,
+                    &rec.map_uid,
+                    &rec.nb_laps,
+                    &rec.millis,
+                    &rec.timestamp,
+                    &rec.cp_millis,
+                ],
+            )
+            .await?;
+
+        let stmt = r#"
+            INSERT INTO steward.record_history
+                (player_login, map_uid, nb_laps, millis, timestamp, cp_millis)
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+        "#;
+
+        let _ = transaction
             .execute(
                 stmt,
                 &[
@@ -165,6 +444,36 @@ impl RecordQueries for DatabaseClient {
             )
             .await?;
 
+        transaction.commit().await?;
         Ok(())
     }
 }
+
+impl From<Row> for FlaggedRecord {
+    fn from(row: Row) -> Self {
+        FlaggedRecord {
+            id: row.get("id"),
+            player_login: row.get("player_login"),
+            map_uid: row.get("map_uid"),
+            nb_laps: row.get("nb_laps"),
+            millis: row.get("millis"),
+            timestamp: row.get("timestamp"),
+            cp_millis: row.get("cp_millis"),
+            reason: row.get("reason"),
+        }
+    }
+}
+
+impl From<Row> for RecordRemoval {
+    fn from(row: Row) -> Self {
+        RecordRemoval {
+            admin_login: row.get("admin_login"),
+            player_login: row.get("player_login"),
+            map_uid: row.get("map_uid"),
+            nb_laps: row.get("nb_laps"),
+            millis: row.get("millis"),
+            restored_millis: row.get("restored_millis"),
+            removed_at: row.get("removed_at"),
+        }
+    }
+}
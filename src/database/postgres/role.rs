@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+
+use crate::database::{DatabaseClient, Result, Role, RoleQueries};
+
+#[async_trait]
+impl RoleQueries for DatabaseClient {
+    async fn roles(&self) -> Result<Vec<Role>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT
+                r.role_name,
+                ARRAY(
+                    SELECT rc.command
+                    FROM steward.role_command rc
+                    WHERE rc.role_name = r.role_name
+                    ORDER BY rc.command
+                ) commands,
+                ARRAY(
+                    SELECT pr.player_login
+                    FROM steward.player_role pr
+                    WHERE pr.role_name = r.role_name
+                    ORDER BY pr.player_login
+                ) player_logins
+            FROM (
+                SELECT role_name FROM steward.role_command
+                UNION
+                SELECT role_name FROM steward.player_role
+            ) r
+            ORDER BY r.role_name
+        "#;
+        let rows = conn.query(stmt, &[]).await?;
+        let roles = rows
+            .into_iter()
+            .map(|row| Role {
+                name: row.get("role_name"),
+                commands: row.get("commands"),
+                player_logins: row.get("player_logins"),
+            })
+            .collect();
+        Ok(roles)
+    }
+
+    async fn granted_commands(&self, player_login: &str) -> Result<Vec<String>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT DISTINCT rc.command
+            FROM steward.player_role pr
+            JOIN steward.role_command rc ON rc.role_name = pr.role_name
+            WHERE pr.player_login = $1
+            ORDER BY rc.command
+        "#;
+        let rows = conn.query(stmt, &[&player_login]).await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn grant_command(&self, role_name: &str, command: &str) -> Result<bool> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            INSERT INTO steward.role_command (role_name, command)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+        "#;
+        let nb_inserted = conn.execute(stmt, &[&role_name, &command]).await?;
+        Ok(nb_inserted > 0)
+    }
+
+    async fn revoke_command(&self, role_name: &str, command: &str) -> Result<bool> {
+        let conn = self.pool.get().await?;
+        let stmt = "DELETE FROM steward.role_command WHERE role_name = $1 AND command = $2";
+        let nb_deleted = conn.execute(stmt, &[&role_name, &command]).await?;
+        Ok(nb_deleted > 0)
+    }
+
+    async fn grant_role(&self, player_login: &str, role_name: &str) -> Result<bool> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            INSERT INTO steward.player_role (player_login, role_name)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+        "#;
+        let nb_inserted = conn.execute(stmt, &[&player_login, &role_name]).await?;
+        Ok(nb_inserted > 0)
+    }
+
+    async fn revoke_role(&self, player_login: &str, role_name: &str) -> Result<bool> {
+        let conn = self.pool.get().await?;
+        let stmt = "DELETE FROM steward.player_role WHERE player_login = $1 AND role_name = $2";
+        let nb_deleted = conn.execute(stmt, &[&player_login, &role_name]).await?;
+        Ok(nb_deleted > 0)
+    }
+}
@@ -0,0 +1,237 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use tokio_postgres::Row;
+
+use crate::database::timeattack::MapRank;
+use crate::database::{DatabaseClient, RecordEvidence, Result, Season, SeasonQueries, SeasonRank};
+use crate::server::DisplayString;
+
+#[async_trait]
+impl SeasonQueries for DatabaseClient {
+    async fn upsert_season(
+        &self,
+        name: &str,
+        start: &NaiveDateTime,
+        end: &NaiveDateTime,
+    ) -> Result<Season> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            INSERT INTO steward.season
+                (name, start_at, end_at)
+            VALUES
+                ($1, $2, $3)
+            ON CONFLICT (start_at, end_at)
+            DO UPDATE SET
+                name = excluded.name
+            RETURNING *
+        "#;
+        let row = conn.query_one(stmt, &[&name, &start, &end]).await?;
+        Ok(Season::from(row))
+    }
+
+    async fn seasons_to_archive(&self, before: &NaiveDateTime) -> Result<Vec<Season>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT *
+            FROM steward.season
+            WHERE NOT archived AND end_at <= $1
+            ORDER BY end_at ASC
+        "#;
+        let rows = conn.query(stmt, &[&before]).await?;
+        Ok(rows.into_iter().map(Season::from).collect())
+    }
+
+    async fn archived_seasons(&self, limit: i64) -> Result<Vec<Season>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT *
+            FROM steward.season
+            WHERE archived
+            ORDER BY end_at DESC
+            LIMIT $1
+        "#;
+        let rows = conn.query(stmt, &[&limit]).await?;
+        Ok(rows.into_iter().map(Season::from).collect())
+    }
+
+    async fn archive_season(&self, season_id: i32, ranks: &[SeasonRank]) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let stmt = r#"
+            INSERT INTO steward.season_rank
+                (season_id, player_login, pos, nb_wins, score)
+            VALUES
+                ($1, $2, $3, $4, $5)
+            ON CONFLICT (season_id, player_login)
+            DO UPDATE SET
+                pos = excluded.pos,
+                nb_wins = excluded.nb_wins,
+                score = excluded.score
+        "#;
+        for rank in ranks {
+            let _ = transaction
+                .execute(
+                    stmt,
+                    &[
+                        &season_id,
+                        &rank.player_login,
+                        &rank.pos,
+                        &rank.nb_wins,
+                        &rank.score,
+                    ],
+                )
+                .await?;
+        }
+
+        let stmt = "UPDATE steward.season SET archived = TRUE WHERE id = $1";
+        let _ = transaction.execute(stmt, &[&season_id]).await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn season_ranks(
+        &self,
+        season_id: i32,
+        player_logins: Vec<&str>,
+        limit: Option<i64>,
+    ) -> Result<Vec<SeasonRank>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT r.*, p.display_name
+            FROM (
+                SELECT
+                    *,
+                    COUNT(*) OVER () max_pos
+                FROM steward.season_rank
+                WHERE season_id = $1
+            ) r
+            INNER JOIN steward.player p ON
+                r.player_login = p.login
+                AND (CARDINALITY($2::text[]) = 0 OR p.login = ANY($2::text[]))
+            ORDER BY r.pos ASC
+            LIMIT $3
+        "#;
+        let rows = conn
+            .query(stmt, &[&season_id, &player_logins, &limit])
+            .await?;
+        Ok(rows.into_iter().map(SeasonRank::from).collect())
+    }
+
+    async fn upsert_season_record(&self, season_id: i32, rec: &RecordEvidence) -> Result<()> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            INSERT INTO steward.season_record
+                (season_id, player_login, map_uid, nb_laps, millis, timestamp)
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (season_id, player_login, map_uid, nb_laps)
+            DO UPDATE SET
+                millis = excluded.millis,
+                timestamp = excluded.timestamp
+            WHERE excluded.millis < steward.season_record.millis
+        "#;
+        let _ = conn
+            .execute(
+                stmt,
+                &[
+                    &season_id,
+                    &rec.player_login,
+                    &rec.map_uid,
+                    &rec.nb_laps,
+                    &rec.millis,
+                    &rec.timestamp,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn nb_players_with_season_record(&self, season_id: i32) -> Result<i64> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT COUNT(DISTINCT player_login)
+            FROM steward.season_record
+            WHERE season_id = $1
+        "#;
+        let row = conn.query_one(stmt, &[&season_id]).await?;
+        Ok(row.get(0))
+    }
+
+    async fn season_map_rankings(
+        &self,
+        season_id: i32,
+        map_uids: Vec<&str>,
+        flying_laps: bool,
+    ) -> Result<Vec<MapRank>> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT
+                r.map_uid,
+                p.login,
+                p.display_name,
+                RANK () OVER (
+                    PARTITION BY r.map_uid
+                    ORDER BY r.millis ASC
+                ) pos,
+                COUNT(*) OVER (PARTITION BY r.map_uid) max_pos,
+                r.millis,
+                m.author_millis,
+                m.gold_millis,
+                m.silver_millis,
+                m.bronze_millis
+            FROM steward.season_record r
+            INNER JOIN steward.player p ON r.player_login = p.login
+            INNER JOIN steward.map m ON
+                r.map_uid = m.uid
+                AND (CARDINALITY($2::text[]) = 0 OR r.map_uid = ANY($2::text[]))
+            WHERE r.season_id = $1
+              AND r.nb_laps = CASE WHEN $3 THEN 0 ELSE COALESCE(m.nb_laps, 0) END
+        "#;
+        let rows = conn
+            .query(stmt, &[&season_id, &map_uids, &flying_laps])
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| MapRank {
+                map_uid: row.get("map_uid"),
+                player_login: row.get("login"),
+                player_display_name: DisplayString::from(row.get("display_name")),
+                pos: row.get("pos"),
+                max_pos: row.get("max_pos"),
+                millis: row.get("millis"),
+                author_millis: row.get("author_millis"),
+                gold_millis: row.get("gold_millis"),
+                silver_millis: row.get("silver_millis"),
+                bronze_millis: row.get("bronze_millis"),
+            })
+            .collect())
+    }
+}
+
+impl From<Row> for Season {
+    fn from(row: Row) -> Self {
+        Season {
+            id: row.get("id"),
+            name: row.get("name"),
+            start: row.get("start_at"),
+            end: row.get("end_at"),
+            archived: row.get("archived"),
+        }
+    }
+}
+
+impl From<Row> for SeasonRank {
+    fn from(row: Row) -> Self {
+        SeasonRank {
+            season_id: row.get("season_id"),
+            player_login: row.get("player_login"),
+            player_display_name: DisplayString::from(row.get("display_name")),
+            pos: row.get("pos"),
+            max_pos: row.get("max_pos"),
+            nb_wins: row.get("nb_wins"),
+            score: row.get("score"),
+        }
+    }
+}
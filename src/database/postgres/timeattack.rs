@@ -95,6 +95,16 @@ impl TimeAttackQueries for DatabaseClient {
         Ok(counts)
     }
 
+    async fn nb_players_played(&self, map_uid: &str) -> Result<i64> {
+        let conn = self.pool.get().await?;
+        let stmt = r#"
+            SELECT COUNT(*) FROM steward.ta_history
+            WHERE map_uid = $1
+        "#;
+        let row = conn.query_one(stmt, &[&map_uid]).await?;
+        Ok(row.get(0))
+    }
+
     async fn upsert_preference(&self, pref: &Preference) -> Result<()> {
         let conn = self.pool.get().await?;
         let stmt = r#"
@@ -109,7 +119,7 @@ impl TimeAttackQueries for DatabaseClient {
         Ok(())
     }
 
-    async fn map_rankings(&self, map_uids: Vec<&str>) -> Result<Vec<MapRank>> {
+    async fn map_rankings(&self, map_uids: Vec<&str>, flying_laps: bool) -> Result<Vec<MapRank>> {
         let conn = self.pool.get().await?;
         let stmt = r#"
             SELECT
@@ -120,15 +130,20 @@ impl TimeAttackQueries for DatabaseClient {
                     PARTITION BY r.map_uid
                     ORDER BY r.millis ASC
                 ) pos,
-                COUNT(*) OVER (PARTITION BY r.map_uid) max_pos
+                COUNT(*) OVER (PARTITION BY r.map_uid) max_pos,
+                r.millis,
+                m.author_millis,
+                m.gold_millis,
+                m.silver_millis,
+                m.bronze_millis
             FROM steward.record r
             INNER JOIN steward.player p ON r.player_login = p.login
             INNER JOIN steward.map m ON
                 r.map_uid = m.uid
                 AND (CARDINALITY($1::text[]) = 0 OR r.map_uid = ANY($1::text[]))
-            WHERE r.nb_laps = 0
+            WHERE r.nb_laps = CASE WHEN $2 THEN 0 ELSE COALESCE(m.nb_laps, 0) END
         "#;
-        let rows = conn.query(stmt, &[&map_uids]).await?;
+        let rows = conn.query(stmt, &[&map_uids, &flying_laps]).await?;
         Ok(rows
             .iter()
             .map(|row| MapRank {
@@ -137,6 +152,11 @@ impl TimeAttackQueries for DatabaseClient {
                 player_display_name: DisplayString::from(row.get("display_name")),
                 pos: row.get("pos"),
                 max_pos: row.get("max_pos"),
+                millis: row.get("millis"),
+                author_millis: row.get("author_millis"),
+                gold_millis: row.get("gold_millis"),
+                silver_millis: row.get("silver_millis"),
+                bronze_millis: row.get("bronze_millis"),
             })
             .collect())
     }
@@ -65,9 +65,10 @@ pub enum ControllerEvent<'a> {
     /// Signals that the server ranking has been updated.
     NewServerRanking(ServerRankingDiff),
 
-    /// Signals that an admin edited the controller config.
+    /// Signals that the controller config was changed, either by an admin
+    /// in-game, or by editing the config file (in which case `from_login` is `None`).
     NewConfig {
-        from_login: &'a str,
+        from_login: Option<&'a str>,
         change: ConfigDiff,
     },
 
@@ -228,4 +229,35 @@ pub enum ConfigDiff {
 
     /// The duration of the outro after a race has changed.
     NewOutroDuration { secs: u32 },
+
+    /// The weights that decide the queue priority of maps, or the
+    /// thresholds for restart votes have changed.
+    NewQueueConfig,
+
+    /// The thresholds, limits or durations of skip & extend votes have changed.
+    NewRaceVoteConfig,
+
+    /// The super admin or admin whitelist has changed.
+    NewAdmins,
+
+    /// The ranking strategy, or the records that are ranked have changed.
+    NewServerRankingConfig,
+
+    /// The periods of seasonal rankings have changed.
+    NewSeasonConfig,
+
+    /// The thresholds for removing maps from the playlist have changed.
+    NewCurationConfig,
+
+    /// The thresholds for moving idle players to spectator slots have changed.
+    NewAfkConfig,
+
+    /// The limit of incoherent runs, or the checks for implausible records have changed.
+    NewRecordCheckConfig,
+
+    /// The times at which map pools are activated have changed.
+    NewPoolCalendar,
+
+    /// The flood limits or word filters of the chat have changed.
+    NewChatConfig,
 }
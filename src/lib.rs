@@ -4,6 +4,7 @@ pub mod constants;
 pub mod controller;
 pub mod database;
 pub mod event;
+pub mod locale;
 pub mod network;
 pub mod server;
 pub mod startup;
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use include_dir::{include_dir, Dir};
+use lazy_static::lazy_static;
+use toml::Value;
+
+use crate::constants::DEFAULT_LANGUAGE;
+
+/// Translation catalogs for chat messages and widgets.
+///
+/// Each catalog in `src/locale/catalogs` is a TOML file named after the
+/// language code, f.e. `de.toml`. Texts are looked up with keys like
+/// `chat.joining`, where `chat` is a section of the catalog.
+/// Texts that are missing in a catalog are taken from the catalog
+/// of the `DEFAULT_LANGUAGE`, which has to contain every text.
+static CATALOG_DIR: Dir = include_dir!("src/locale/catalogs");
+
+lazy_static! {
+    static ref CATALOGS: HashMap<&'static str, Catalog> = CATALOG_DIR
+        .files()
+        .iter()
+        .map(|file| {
+            let code = file
+                .path()
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .expect("invalid catalog file name");
+            let contents = file
+                .contents_utf8()
+                .unwrap_or_else(|| panic!("catalog '{}' is not UTF-8", code));
+            (code, Catalog::parse(contents))
+        })
+        .collect();
+}
+
+/// The texts of one language.
+struct Catalog {
+    /// The name of the language, in that language, f.e. "Deutsch".
+    name: String,
+
+    /// Maps keys like `chat.joining` to texts.
+    texts: HashMap<String, Text>,
+}
+
+/// A text of a catalog.
+enum Text {
+    Single(String),
+
+    /// A text that depends on an amount, which replaces `{count}`.
+    /// The `zero` form is optional, and `other` is used in its stead.
+    Plural {
+        zero: Option<String>,
+        one: String,
+        other: String,
+    },
+}
+
+impl Catalog {
+    /// # Panics
+    /// Panics if the catalog is not a valid TOML file, or if it is missing
+    /// the `name` of the language.
+    fn parse(contents: &str) -> Catalog {
+        let root: Value = toml::from_str(contents).expect("failed to parse catalog");
+        let name = root
+            .get("name")
+            .and_then(Value::as_str)
+            .expect("catalog is missing the language name")
+            .to_string();
+
+        let mut texts = HashMap::new();
+        if let Value::Table(sections) = root {
+            for (section, entries) in sections {
+                if let Value::Table(entries) = entries {
+                    for (key, value) in entries {
+                        let key = format!("{}.{}", section, key);
+                        let text = Text::from_value(&value)
+                            .unwrap_or_else(|| panic!("invalid text for '{}'", key));
+                        let _ = texts.insert(key, text);
+                    }
+                }
+            }
+        }
+        Catalog { name, texts }
+    }
+}
+
+impl Text {
+    fn from_value(value: &Value) -> Option<Text> {
+        if let Some(text) = value.as_str() {
+            return Some(Text::Single(text.to_string()));
+        }
+        let form = |name: &str| value.get(name).and_then(Value::as_str).map(String::from);
+        Some(Text::Plural {
+            zero: form("zero"),
+            one: form("one")?,
+            other: form("other")?,
+        })
+    }
+
+    fn select(&self, count: Option<usize>) -> &str {
+        match (self, count) {
+            (Text::Single(text), _) => text,
+            (
+                Text::Plural {
+                    zero: Some(zero), ..
+                },
+                Some(0),
+            ) => zero,
+            (Text::Plural { one, .. }, Some(1)) => one,
+            (Text::Plural { other, .. }, _) => other,
+        }
+    }
+}
+
+/// The codes of all languages that have a catalog, in alphabetical order.
+pub fn supported_languages() -> Vec<&'static str> {
+    let mut codes: Vec<&'static str> = CATALOGS.keys().copied().collect();
+    codes.sort_unstable();
+    codes
+}
+
+/// The name of a supported language, in that language, f.e. "Deutsch" for "de".
+pub fn language_name(code: &str) -> Option<&'static str> {
+    CATALOGS.get(code).map(|catalog| catalog.name.as_str())
+}
+
+/// Find the supported language for a language code, which can also be
+/// that of a game client, f.e. `de` for `de-DE`. Returns `None` if there
+/// is no catalog for that language.
+pub fn supported_language(code: &str) -> Option<&'static str> {
+    let code = code.trim().to_lowercase();
+    let code = code
+        .split(|c| c == '-' || c == '_')
+        .next()
+        .unwrap_or_default();
+    CATALOGS.get_key_value(code).map(|(code, _)| *code)
+}
+
+/// Look up a text in the catalog of the given language, or in that of the
+/// `DEFAULT_LANGUAGE` if it is missing, and replace its `{placeholders}`
+/// with the given arguments.
+pub fn translate(lang: &str, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    lookup(lang, key, None, args)
+}
+
+/// Like `translate`, but selects the form of the text that fits the given
+/// amount, which also replaces the `{count}` placeholder.
+pub fn translate_count(lang: &str, key: &str, count: usize) -> String {
+    lookup(lang, key, Some(count), &[("count", &count)])
+}
+
+fn lookup(lang: &str, key: &str, count: Option<usize>, args: &[(&str, &dyn Display)]) -> String {
+    let text = CATALOGS
+        .get(lang)
+        .and_then(|catalog| catalog.texts.get(key))
+        .or_else(|| CATALOGS[DEFAULT_LANGUAGE].texts.get(key));
+
+    let text = match text {
+        Some(text) => text.select(count),
+        None => {
+            log::warn!("missing text '{}'", key);
+            return key.to_string();
+        }
+    };
+
+    args.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Lists the `{placeholders}` of a text.
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split('}').next())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn catalogs_match_default_language() {
+        let default = &CATALOGS[DEFAULT_LANGUAGE];
+        for (code, catalog) in CATALOGS.iter() {
+            for (key, text) in catalog.texts.iter() {
+                let default_text = default
+                    .texts
+                    .get(key)
+                    .unwrap_or_else(|| panic!("'{}' has unknown text '{}'", code, key));
+
+                match (text, default_text) {
+                    (Text::Single(text), Text::Single(default_text)) => assert_eq!(
+                        placeholders(default_text),
+                        placeholders(text),
+                        "'{}' has other placeholders for '{}'",
+                        code,
+                        key
+                    ),
+                    (Text::Plural { .. }, Text::Plural { .. }) => {}
+                    _ => panic!("'{}' has a text of another kind for '{}'", code, key),
+                }
+
+                if key.starts_with("widget.") {
+                    let text = text.select(None);
+                    assert!(
+                        !text.contains(|c| "\"'<>&{}".contains(c)),
+                        "'{}' has a widget text that cannot be used in XML or scripts: '{}'",
+                        code,
+                        key
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_default_language() {
+        assert_eq!(
+            "Admin A kicked B.",
+            translate(
+                "xx",
+                "chat.player_kicked",
+                &[("admin", &"A"), ("name", &"B")]
+            )
+        );
+        assert_eq!("chat.unknown", translate("en", "chat.unknown", &[]));
+    }
+
+    #[test]
+    fn selects_plural_forms() {
+        assert_eq!("no seconds", translate_count("en", "units.second", 0));
+        assert_eq!("one second", translate_count("en", "units.second", 1));
+        assert_eq!("42 seconds", translate_count("en", "units.second", 42));
+    }
+
+    #[test]
+    fn finds_supported_languages() {
+        assert_eq!(Some("de"), supported_language("de-DE"));
+        assert_eq!(Some("fr"), supported_language(" FR_ca"));
+        assert_eq!(Some("en"), supported_language("en"));
+        assert_eq!(None, supported_language("xx"));
+        assert_eq!(None, supported_language(""));
+        assert!(supported_languages().contains(&DEFAULT_LANGUAGE));
+        assert_eq!(Some("English"), language_name("en"));
+    }
+}
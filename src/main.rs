@@ -4,6 +4,7 @@ mod constants;
 mod controller;
 mod database;
 mod event;
+mod locale;
 mod network;
 mod server;
 mod startup;
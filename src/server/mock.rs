@@ -72,6 +72,10 @@ impl Calls for Server {
         unimplemented!()
     }
 
+    async fn player_detailed_info(&self, _login: &str) -> Result<PlayerDetailedInfo> {
+        unimplemented!()
+    }
+
     async fn map(&self, _file_name: &str) -> Result<MapInfo> {
         unimplemented!()
     }
@@ -136,6 +140,10 @@ impl Calls for Server {
         unimplemented!()
     }
 
+    async fn send_manialink_to_logins(&self, _ml: &str, _logins: Vec<&str>) -> Result<()> {
+        unimplemented!()
+    }
+
     async fn force_spectator(&self, _player_login: &str) -> Result<()> {
         unimplemented!()
     }
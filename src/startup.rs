@@ -10,7 +10,7 @@ use gbx::file::parse_map_file;
 
 use crate::config::Config;
 use crate::constants::{BLACKLIST_FILE, VERSION};
-use crate::database::{DatabaseClient, Map, MapQueries};
+use crate::database::{DatabaseClient, Map, MapQueries, RecordQueries};
 use crate::network::exchange_id;
 use crate::server::{
     Calls, Server, ServerBuildInfo, ServerOptions, SetupCalls, SCRIPT_API_VERSION,
@@ -80,9 +80,9 @@ fn add_server_option_constraints(options: &mut ServerOptions) {
     // New players will be announced in the chat instead.
     options.disable_service_announces = true;
 
-    // Let players keep their slots when switching to spectator,
-    // but implement a mechanism that removes spectator's player slots,
-    // if they spectate too long.
+    // Let players keep their slots when switching to spectator;
+    // spectators that are idle for too long are moved to spectator slots
+    // once the server is full.
     options.keep_player_slots = true;
 }
 
@@ -214,7 +214,12 @@ async fn upsert_map(db: &DatabaseClient, map_file: &PathBuf, map_file_name: &str
         author_display_name: header.author_display_name,
         added_since: Utc::now().naive_utc(),
         author_millis: header.millis_author,
+        gold_millis: Some(header.millis_gold),
+        silver_millis: Some(header.millis_silver),
+        bronze_millis: Some(header.millis_bronze),
         exchange_id: None,
+        nb_cps: Some(header.nb_cps),
+        nb_laps: header.nb_laps,
     };
 
     let fs_map_data = read_to_bytes(&map_file).expect("failed to read map file");
@@ -223,8 +228,37 @@ async fn upsert_map(db: &DatabaseClient, map_file: &PathBuf, map_file_name: &str
 
     let is_new_map = maybe_db_map.is_none();
 
+    // Records on multi-lap maps that were added before laps were counted
+    // were stored with a lap count of zero, and have to be converted.
+    let had_nb_laps = maybe_db_map
+        .as_ref()
+        .map(|map| map.nb_laps.is_some())
+        .unwrap_or(true);
+
+    let nb_cps = fs_map.nb_cps;
+    let nb_laps = fs_map.nb_laps;
+    let gold_millis = fs_map.gold_millis;
+    let silver_millis = fs_map.silver_millis;
+    let bronze_millis = fs_map.bronze_millis;
     let mut new_db_map = maybe_db_map.unwrap_or(fs_map);
 
+    // Maps added before checkpoints were counted are missing that count.
+    if new_db_map.nb_cps.is_none() {
+        new_db_map.nb_cps = nb_cps;
+    }
+
+    // Maps added before laps were counted are missing that count.
+    if new_db_map.nb_laps.is_none() {
+        new_db_map.nb_laps = nb_laps;
+    }
+
+    // Maps added before medal times were stored are missing them.
+    if new_db_map.gold_millis.is_none() {
+        new_db_map.gold_millis = gold_millis;
+        new_db_map.silver_millis = silver_millis;
+        new_db_map.bronze_millis = bronze_millis;
+    }
+
     // Try to find exchange ID
     if new_db_map.exchange_id.is_none() {
         if let Ok(id) = exchange_id(&new_db_map.uid).await {
@@ -236,6 +270,20 @@ async fn upsert_map(db: &DatabaseClient, map_file: &PathBuf, map_file_name: &str
         .await
         .expect("failed to upsert map");
 
+    match new_db_map.nb_laps {
+        Some(nb_laps) if !had_nb_laps => {
+            db.convert_multi_lap_records(&new_db_map.uid, nb_laps)
+                .await
+                .expect("failed to convert multi-lap records");
+            log::info!(
+                "converted records on '{}' to {}-lap records",
+                &new_db_map.uid,
+                nb_laps
+            );
+        }
+        _ => {}
+    }
+
     if is_new_map {
         log::info!("found new map: {:#?}", &new_db_map);
     }
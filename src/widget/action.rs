@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::chat::DangerousCommand;
@@ -29,15 +29,45 @@ pub enum Action<'a> {
     /// of the current map.
     VoteRestart { vote: bool },
 
+    /// Update whether a player is for or against the ongoing
+    /// skip or extend vote.
+    VoteRace { vote: bool },
+
     /// Confirm the execution of a pending, dangerous command.
     ConfirmCommand { cmd: DangerousCommand<'a> },
 
+    /// Show another page of a list in the command output popup.
+    /// The page index is zero-based.
+    TurnPage { list: PagedList, page: usize },
+
     /// Update the config, which is textually represented here.
     ///
     /// For this, we use a single text entry in a widget, so a config will
     /// have some format, and parsed from `repr`.
     #[serde(skip_serializing)]
     SetConfig { toml_config: String },
+
+    /// Apply a config that was submitted with `SetConfig` before,
+    /// after its changes were reviewed. If the config was edited
+    /// since, its changes are displayed again instead.
+    #[serde(skip_serializing)]
+    ConfirmConfig { toml_config: String },
+}
+
+/// Lists that are displayed page by page in the command output popup.
+///
+/// Since only the query of a list is included in `TurnPage` actions,
+/// the page is built from the current data, which might have changed
+/// in the meantime. Queries should be short enough to stay within
+/// the limits of `TriggerPageAction`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "list")]
+pub enum PagedList {
+    /// The output of `/maps [query]`.
+    Maps { query: String },
+
+    /// The output of `/players [query]`.
+    Players { query: String },
 }
 
 /// "Private" actions that are converted to an `Action` variant that
@@ -47,6 +77,7 @@ pub enum Action<'a> {
 #[serde(tag = "action")]
 enum InputAction {
     SetConfig { entry_name: String },
+    ConfirmConfig { entry_name: String },
 }
 
 impl Action<'_> {
@@ -74,6 +105,16 @@ impl Action<'_> {
 
                 Action::SetConfig { toml_config: repr }
             }
+            InputAction::ConfirmConfig {
+                entry_name: entry_id,
+            } => {
+                let repr = answer
+                    .entries
+                    .remove(&entry_id)
+                    .expect("missing config_input");
+
+                Action::ConfirmConfig { toml_config: repr }
+            }
         }
     }
 }
@@ -1,7 +1,7 @@
 use askama::Template;
 
 use crate::chat::{CommandOutput, CommandResultOutput, DangerousCommand};
-use crate::widget::filters;
+use crate::widget::{filters, PagedList};
 
 /// A widget that can be used for the outputs of chat commands.
 /// Such outputs are not ideal in the chat, since you cannot
@@ -11,10 +11,11 @@ use crate::widget::filters;
 pub struct PopupWidget<'a> {
     output: String,
     variant: PopupVariant<'a>,
+    lang: &'static str,
 }
 
 impl PopupWidget<'_> {
-    pub fn from(response: CommandOutput) -> PopupWidget {
+    pub fn new(response: CommandOutput, lang: &'static str) -> PopupWidget {
         use CommandOutput::*;
         use CommandResultOutput::*;
 
@@ -25,9 +26,25 @@ impl PopupWidget<'_> {
                 Result(CurrentConfig { .. }) | Result(InvalidConfig { .. }) => {
                     PopupVariant::ConfigEditor
                 }
+                Result(ConfigChanges { .. }) => PopupVariant::ConfigReview,
+                Result(MapList { maps, query }) => PopupVariant::Paged {
+                    list: PagedList::Maps {
+                        query: query.to_string(),
+                    },
+                    prev_page: maps.prev_index(),
+                    next_page: maps.next_index(),
+                },
+                Result(PlayerList { players, query }) => PopupVariant::Paged {
+                    list: PagedList::Players {
+                        query: query.to_string(),
+                    },
+                    prev_page: players.prev_index(),
+                    next_page: players.next_index(),
+                },
                 Confirm(cmd, _) => PopupVariant::Confirm { cmd },
                 _ => PopupVariant::Default,
             },
+            lang,
         }
     }
 }
@@ -44,4 +61,16 @@ enum PopupVariant<'a> {
     /// Use only for the `/config` command. Display the config, and
     /// offer to 'cancel', or 'submit'.
     ConfigEditor,
+
+    /// Use only for the `/config` command, after submitting a config.
+    /// Display its changes, and offer to 'cancel', or 'apply'.
+    ConfigReview,
+
+    /// Use for lists that are split into pages. Offer to 'close',
+    /// and to go to the previous or next page, if there is one.
+    Paged {
+        list: PagedList,
+        prev_page: Option<usize>,
+        next_page: Option<usize>,
+    },
 }
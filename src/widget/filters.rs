@@ -4,6 +4,7 @@ use askama::{Error, Result};
 use chrono::{NaiveDateTime, Utc};
 use serde::Serialize;
 
+use crate::locale::translate;
 use crate::server::DisplayString;
 use crate::widget::timeattack::QueueEntryAnnotation;
 
@@ -36,6 +37,11 @@ where
     }
 }
 
+/// Look up a widget text, f.e. `{{ "widget.yes"|tr(lang) }}`.
+pub(super) fn tr(key: &str, lang: &str) -> Result<String> {
+    Ok(translate(lang, key, &[]))
+}
+
 /// Remove `$o` and `$w` formatting.
 pub(super) fn narrow(s: &DisplayString) -> Result<String> {
     Ok(s.formatted.replace("$o", "").replace("$w", ""))
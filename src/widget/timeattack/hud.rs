@@ -1,5 +1,7 @@
 use askama::Template;
 
+use crate::widget::filters;
+
 /// A widget that is displayed during a race to let players know their
 /// - current server rank
 /// - personal best on this map
@@ -39,4 +41,7 @@ pub struct TimeAttackHudWidget {
 
     /// The number of players that have a server rank.
     pub max_server_rank: Option<usize>,
+
+    /// The language of the widget's texts.
+    pub lang: &'static str,
 }
@@ -13,7 +13,15 @@ use crate::widget::filters;
 #[derive(Template, Debug)]
 #[template(path = "timeattack/menu_map_ranking.xml")]
 pub struct MapRankingWidget<'a> {
+    /// The ranking of full-race records.
     pub ranking: MapRanking<'a>,
+
+    /// The ranking of flying lap records, or `None` if the
+    /// current map is not multi-lap.
+    pub flying_lap_ranking: Option<MapRanking<'a>>,
+
+    /// The language of the widget's texts.
+    pub lang: &'static str,
 }
 
 #[derive(Debug)]
@@ -23,6 +23,9 @@ pub struct PlaylistWidget<'a> {
     /// missing personal records are higher up. The first entry is
     /// the current map.
     pub entries: Vec<PlaylistWidgetEntry<'a>>,
+
+    /// The language of the widget's texts.
+    pub lang: &'static str,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -1,5 +1,8 @@
 use askama::Template;
 
+use crate::server::DisplayString;
+use crate::widget::filters;
+
 /// A widget that displays the schedule, with the maps that are currently
 /// at the top of the queue.
 ///
@@ -8,9 +11,37 @@ use askama::Template;
 /// - Has to be re-sent whenever the top of the queue changes.
 #[derive(Template, Debug)]
 #[template(path = "timeattack/menu_schedule.xml")]
-pub struct ScheduleWidget {
+pub struct ScheduleWidget<'a> {
+    /// The next maps that are expected to be played, the first item
+    /// being the current map.
+    pub entries: Vec<ScheduleEntry<'a>>,
+
+    /// The next scheduled map pool switch, if any.
+    pub pool_switch: Option<PoolSwitchEntry<'a>>,
+
+    /// The language of the widget's texts.
+    pub lang: &'static str,
     // TODO add schedule widget details
-//  - map name, author
-//  - personal preferences
-//  - minutes until played
+    //  - personal preferences
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleEntry<'a> {
+    /// The formatted map name.
+    pub map_name: &'a DisplayString,
+
+    /// The map author's display name.
+    pub map_author_display_name: &'a DisplayString,
+
+    /// The expected number of minutes until this map is played.
+    pub minutes_until: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolSwitchEntry<'a> {
+    /// The name of the map pool that will replace the playlist.
+    pub pool_name: &'a str,
+
+    /// The number of minutes until the switch.
+    pub minutes_until: i64,
 }
@@ -3,7 +3,8 @@ use askama::Template;
 use crate::server::DisplayString;
 use crate::widget::filters;
 
-/// A widget that displays the top server ranks.
+/// A widget that displays the top server ranks, and the standings
+/// of the current and past seasons, one at a time.
 ///
 /// # Sending
 /// - Send this widget to a player after the intro.
@@ -12,6 +13,41 @@ use crate::widget::filters;
 #[template(path = "timeattack/menu_server_ranking.xml")]
 pub struct ServerRankingWidget<'a> {
     pub ranking: ServerRanking<'a>,
+
+    /// The ranking of the current season, or `None` if there is no active season.
+    pub season_ranking: Option<SeasonRanking<'a>>,
+
+    /// The final standings of past seasons, from most to least recent.
+    pub archived_seasons: Vec<SeasonRanking<'a>>,
+
+    /// The language of the widget's texts.
+    pub lang: &'static str,
+}
+
+impl ServerRankingWidget<'_> {
+    /// The number of rows needed to display the longest ranking,
+    /// including the player's own rank.
+    fn nb_rows(&self) -> usize {
+        let seasons = self
+            .season_ranking
+            .iter()
+            .chain(self.archived_seasons.iter());
+        seasons
+            .map(|season| season.ranking.entries.len())
+            .chain(std::iter::once(self.ranking.entries.len()))
+            .max()
+            .unwrap_or(0)
+            + 1
+    }
+}
+
+#[derive(Debug)]
+pub struct SeasonRanking<'a> {
+    /// The name of the season.
+    pub name: &'a str,
+
+    /// The ranking that only considers records set within this season.
+    pub ranking: ServerRanking<'a>,
 }
 
 #[derive(Debug)]
@@ -41,6 +77,10 @@ pub struct ServerRankingEntry<'a> {
     /// The number of records better than this player's, summed up for every map.
     pub nb_losses: usize,
 
+    /// The value this rank was decided by, which depends on the
+    /// configured ranking strategy.
+    pub score: f64,
+
     /// `True` if this is the player's own rank.
     pub is_own: bool,
 }
@@ -7,7 +7,10 @@ pub use menu_server_ranking::*;
 pub use outro::*;
 pub use outro_queue::*;
 pub use outro_server_ranking::*;
+pub use race_live_ranking::*;
 pub use race_run_outro::*;
+pub use race_split::*;
+pub use race_vote::*;
 
 mod hud;
 mod menu;
@@ -18,4 +21,7 @@ mod menu_server_ranking;
 mod outro;
 mod outro_queue;
 mod outro_server_ranking;
+mod race_live_ranking;
 mod race_run_outro;
+mod race_split;
+mod race_vote;
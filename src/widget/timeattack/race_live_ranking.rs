@@ -0,0 +1,41 @@
+use askama::Template;
+
+use crate::server::DisplayString;
+use crate::widget::filters;
+
+/// A widget that displays the ranking of the current race, including
+/// players that are still on track, for players and spectators alike.
+///
+/// # Sending
+/// - Send this widget to all players when the live ranking changed.
+/// - Remove this widget when the race ends.
+#[derive(Template, Debug)]
+#[template(path = "timeattack/race_live_ranking.xml")]
+pub struct LiveRankingWidget<'a> {
+    /// A selection of top live ranks.
+    pub entries: Vec<LiveRankingEntry<'a>>,
+}
+
+#[derive(Debug)]
+pub struct LiveRankingEntry<'a> {
+    /// The live rank.
+    pub pos: usize,
+
+    /// The player's login, used to highlight their own entry.
+    pub login: &'a str,
+
+    /// The player's formatted display name.
+    pub display_name: &'a DisplayString,
+
+    /// The player's best time if they have completed a run,
+    /// or their time at the furthest checkpoint otherwise.
+    /// This is `0` if the player has not crossed a checkpoint.
+    pub millis: i32,
+
+    /// The number of checkpoints the player has crossed in their
+    /// current run, or `0` if they have completed a run.
+    pub nb_cps: usize,
+
+    /// The difference to the leader's time at the same checkpoint.
+    pub gap_millis: Option<i32>,
+}
@@ -0,0 +1,26 @@
+use askama::Template;
+
+/// A widget that compares a player's time at a checkpoint to their
+/// personal best, and to the map's top record.
+///
+/// # Sending
+/// - Send this widget whenever the player crosses a checkpoint.
+/// - It hides itself after a few seconds, but should be removed
+///   when the race ends.
+#[derive(Template, Debug)]
+#[template(path = "timeattack/race_split.xml")]
+pub struct CheckpointSplitWidget {
+    /// The player's time at this checkpoint.
+    pub millis: i32,
+
+    /// The time at this checkpoint in the player's personal best,
+    /// or `None` if they have no personal best.
+    pub pb_millis: Option<i32>,
+
+    /// The time at this checkpoint in the map's top record,
+    /// or `None` if there is no record on this map.
+    pub top_record_millis: Option<i32>,
+
+    /// The milliseconds after which this widget hides itself.
+    pub display_millis: i64,
+}
@@ -0,0 +1,32 @@
+use askama::Template;
+
+use crate::chat::RaceVoteKind;
+use crate::widget::filters;
+
+/// A widget that lets players vote in favour of, or against
+/// skipping the current map, or extending its time limit.
+///
+/// # Sending
+/// - Send this widget when a player starts a vote, and whenever
+///   the number of votes changes.
+/// - Remove this widget when the vote ends, or when the race ends.
+#[derive(Template, Debug)]
+#[template(path = "timeattack/race_vote.xml")]
+pub struct RaceVoteWidget {
+    pub kind: RaceVoteKind,
+
+    /// The number of non-spectators that voted in favour.
+    pub nb_yes: usize,
+
+    /// The number of non-spectators that voted against.
+    pub nb_no: usize,
+
+    /// The number of votes in favour that are required for this vote to pass.
+    pub nb_required: usize,
+
+    /// The remaining number of seconds until the vote ends.
+    pub secs_left: i64,
+
+    /// The language of the widget's texts.
+    pub lang: &'static str,
+}
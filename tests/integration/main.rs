@@ -1,7 +1,7 @@
 use std::ops::Sub;
 
 use anyhow::Result;
-use chrono::{Duration, NaiveDateTime, SubsecRound, Utc};
+use chrono::{Duration, NaiveDateTime, NaiveTime, SubsecRound, Utc};
 use testcontainers::*;
 
 use steward::database::timeattack::*;
@@ -137,6 +137,147 @@ async fn test_player_update() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_player_aliases() -> Result<()> {
+    let db = clean_db().await?;
+    db.upsert_player(&player_info("login", "nickname")).await?;
+    db.upsert_player(&player_info("login", "new nickname"))
+        .await?;
+    db.upsert_player(&player_info("other", "other nickname"))
+        .await?;
+
+    let aliases = db.player_aliases(vec!["login"]).await?;
+    let names: Vec<&str> = aliases
+        .iter()
+        .map(|alias| alias.display_name.formatted.as_str())
+        .collect();
+    assert_eq!(vec!["new nickname", "nickname"], names);
+    assert!(aliases.iter().all(|alias| alias.player_login == "login"));
+
+    let all_aliases = db.player_aliases(vec![]).await?;
+    assert_eq!(3, all_aliases.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_find_player_aliases() -> Result<()> {
+    let db = clean_db().await?;
+    db.upsert_player(&player_info("login1", "$f00Speedy"))
+        .await?;
+    db.upsert_player(&player_info("login1", "Turtle")).await?;
+    db.upsert_player(&player_info("login2", "$oSpeedster"))
+        .await?;
+    db.upsert_player(&player_info("login3", "100%")).await?;
+
+    let aliases = db.find_player_aliases("SPEED", 5).await?;
+    let logins: Vec<&str> = aliases
+        .iter()
+        .map(|alias| alias.player_login.as_str())
+        .collect();
+    assert_eq!(vec!["login1", "login1", "login2"], logins);
+
+    let aliases = db.find_player_aliases("speed", 1).await?;
+    assert_eq!(2, aliases.len());
+    assert!(aliases.iter().all(|alias| alias.player_login == "login1"));
+
+    assert!(db.find_player_aliases("f00", 5).await?.is_empty());
+    assert!(db.find_player_aliases("_", 5).await?.is_empty());
+    assert_eq!(1, db.find_player_aliases("0%", 5).await?.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_player_language() -> Result<()> {
+    let db = clean_db().await?;
+    db.upsert_player(&player_info("login", "nickname")).await?;
+    assert_eq!(None, db.player_language("login").await?);
+
+    db.set_player_language("login", Some("de")).await?;
+    db.upsert_player(&player_info("login", "new nickname"))
+        .await?;
+    assert_eq!(Some("de".to_string()), db.player_language("login").await?);
+
+    db.set_player_language("login", None).await?;
+    assert_eq!(None, db.player_language("login").await?);
+    assert_eq!(None, db.player_language("unknown").await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_roles() -> Result<()> {
+    let db = clean_db().await?;
+    db.upsert_player(&player_info("login1", "nickname1"))
+        .await?;
+    db.upsert_player(&player_info("login2", "nickname2"))
+        .await?;
+
+    assert!(db.grant_command("moderator", "/kick").await?);
+    assert!(db.grant_command("moderator", "/bounce").await?);
+    assert!(!db.grant_command("moderator", "/kick").await?);
+    assert!(db.grant_command("mapper", "/playlist add").await?);
+    assert!(db.grant_role("login1", "moderator").await?);
+    assert!(db.grant_role("login1", "mapper").await?);
+    assert!(db.grant_role("login2", "moderator").await?);
+
+    assert_eq!(
+        vec!["/bounce", "/kick", "/playlist add"],
+        db.granted_commands("login1").await?
+    );
+    assert_eq!(
+        vec!["/bounce", "/kick"],
+        db.granted_commands("login2").await?
+    );
+
+    assert!(db.revoke_command("moderator", "/kick").await?);
+    assert!(!db.revoke_command("moderator", "/kick").await?);
+    assert!(db.revoke_role("login1", "mapper").await?);
+    assert_eq!(vec!["/bounce"], db.granted_commands("login1").await?);
+
+    let expected = vec![
+        Role {
+            name: "mapper".to_string(),
+            commands: vec!["/playlist add".to_string()],
+            player_logins: vec![],
+        },
+        Role {
+            name: "moderator".to_string(),
+            commands: vec!["/bounce".to_string()],
+            player_logins: vec!["login1".to_string(), "login2".to_string()],
+        },
+    ];
+    assert_eq!(expected, db.roles().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_announcements() -> Result<()> {
+    let db = clean_db().await?;
+    let join_id = db
+        .add_announcement("Welcome!", AnnouncementTrigger::Join)
+        .await?;
+    let tip_id = db
+        .add_announcement("Tip", AnnouncementTrigger::Interval { secs: 600 })
+        .await?;
+
+    let window = TimeWindow {
+        start: NaiveTime::from_hms(22, 0, 0),
+        end: NaiveTime::from_hms(2, 0, 0),
+    };
+    assert!(db.set_announcement_window(tip_id, Some(window)).await?);
+    assert!(db.remove_announcement(join_id).await?);
+    assert!(!db.remove_announcement(join_id).await?);
+    assert!(!db.set_announcement_window(join_id, None).await?);
+
+    let expected = vec![Announcement {
+        id: tip_id,
+        message: "Tip".to_string(),
+        trigger: AnnouncementTrigger::Interval { secs: 600 },
+        window: Some(window),
+    }];
+    assert_eq!(expected, db.announcements().await?);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_player_record_some() -> Result<()> {
     let db = clean_db().await?;
@@ -157,6 +298,83 @@ async fn test_player_record_some() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_remove_record_restores_previous() -> Result<()> {
+    let db = clean_db().await?;
+
+    let player = player_info("login", "nickname");
+    let map = map("uid1", "file1");
+    let mut rec1 = record_evidence("login", "uid1", 10000);
+    let rec2 = record_evidence("login", "uid1", 5000);
+    rec1.timestamp = rec1.timestamp.sub(Duration::seconds(1));
+    db.upsert_player(&player).await?;
+    db.upsert_map(&map, vec![]).await?;
+    db.upsert_record(&rec1).await?;
+    db.upsert_record(&rec2).await?;
+
+    let removal = db.remove_record("uid1", "login", 0, "admin").await?;
+    let removal = removal.expect("expected a removal");
+    assert_eq!(5000, removal.millis);
+    assert_eq!(Some(10000), removal.restored_millis);
+
+    let expected = Some(record(1, 1, "nickname", rec1));
+    let actual = db.player_record("uid1", "login", 0).await?;
+    assert_eq!(expected, actual);
+
+    let removals = db.record_removals(10).await?;
+    assert_eq!(1, removals.len());
+    assert_eq!("admin", removals[0].admin_login);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_record_history() -> Result<()> {
+    let db = clean_db().await?;
+
+    let player = player_info("login", "nickname");
+    let map = map("uid1", "file1");
+    let mut rec1 = record_evidence("login", "uid1", 10000);
+    let rec2 = record_evidence("login", "uid1", 5000);
+    rec1.timestamp = rec1.timestamp.sub(Duration::seconds(1));
+    db.upsert_player(&player).await?;
+    db.upsert_map(&map, vec![]).await?;
+    db.upsert_record(&rec1).await?;
+    db.upsert_record(&rec2).await?;
+
+    let history = db.record_history("uid1", 0).await?;
+    let actual: Vec<i32> = history.iter().map(|rec| rec.millis).collect();
+    assert_eq!(vec![rec1.millis, rec2.millis], actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_convert_multi_lap_records() -> Result<()> {
+    let db = clean_db().await?;
+
+    // Multi-lap maps that were added before laps were counted
+    // have their full-race records stored with zero laps.
+    let player = player_info("login", "nickname");
+    let mut map = map("uid1", "file1");
+    let rec = record_evidence("login", "uid1", 10000);
+    db.upsert_player(&player).await?;
+    db.upsert_map(&map, vec![]).await?;
+    db.upsert_record(&rec).await?;
+
+    map.nb_laps = Some(3);
+    db.upsert_map(&map, vec![]).await?;
+    db.convert_multi_lap_records("uid1", 3).await?;
+
+    assert_eq!(None, db.player_record("uid1", "login", 0).await?);
+    let actual = db.player_record("uid1", "login", 3).await?;
+    let actual = actual.expect("expected a converted record");
+    assert_eq!(10000, actual.millis);
+    assert_eq!(3, actual.nb_laps);
+    assert_eq!(1, db.record_history("uid1", 3).await?.len());
+    assert!(db.record_history("uid1", 0).await?.is_empty());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_records_single() -> Result<()> {
     let db = clean_db().await?;
@@ -450,16 +668,33 @@ async fn test_maps_without_player_record() -> Result<()> {
     let player2 = player_info("login2", "nickname2");
     let map1 = map("uid1", "file1");
     let map2 = map("uid2", "file2");
+    let map3 = Map {
+        nb_laps: Some(3),
+        ..map("uid3", "file3")
+    };
     let rec1 = record_evidence("login1", "uid1", 10000);
+    let lap_rec = record_evidence("login1", "uid3", 10000);
     db.upsert_player(&player1).await?;
     db.upsert_player(&player2).await?;
     db.upsert_map(&map1, vec![]).await?;
     db.upsert_map(&map2, vec![]).await?;
+    db.upsert_map(&map3, vec![]).await?;
     db.upsert_record(&rec1).await?;
+    db.upsert_record(&lap_rec).await?;
+
+    // A flying lap does not count as a finished multi-lap race.
+    let mut actual = db.maps_without_player_record("login1").await?;
+    actual.sort();
+    assert_eq!(vec!["uid2".to_string(), "uid3".to_string()], actual);
+
+    let race_rec = RecordEvidence {
+        nb_laps: 3,
+        ..record_evidence("login1", "uid3", 30000)
+    };
+    db.upsert_record(&race_rec).await?;
 
-    let expected = vec!["uid2".to_string()];
     let actual = db.maps_without_player_record("login1").await?;
-    assert_eq!(expected, actual);
+    assert_eq!(vec!["uid2".to_string()], actual);
 
     Ok(())
 }
@@ -544,8 +779,13 @@ fn map(uid: &str, file_name: &str) -> Map {
         author_login: "".to_string(),
         author_display_name: DisplayString::from("".to_string()),
         author_millis: 0,
+        gold_millis: None,
+        silver_millis: None,
+        bronze_millis: None,
         added_since: now(),
         exchange_id: None,
+        nb_cps: None,
+        nb_laps: None,
     }
 }
 